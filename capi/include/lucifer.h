@@ -0,0 +1,104 @@
+/*
+ * C bindings for the lucifer renderer.
+ *
+ * Functions report failure with a negative result or a null pointer,
+ * and lucifer_last_error() then describes the error.
+ */
+
+#ifndef LUCIFER_H
+#define LUCIFER_H
+
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Kinds of primitives. */
+#define LUCIFER_SPHERE 0     /* center, radius */
+#define LUCIFER_CUBE 1       /* center, dimensions */
+#define LUCIFER_PLANE 2      /* normal, distance */
+#define LUCIFER_DISC 3       /* center, normal, radius */
+
+/* Kinds of materials. */
+#define LUCIFER_LAMBERT 0    /* color */
+#define LUCIFER_PHONG 1      /* emission, color, specular, shininess */
+#define LUCIFER_DIELECTRIC 2 /* color, ior */
+#define LUCIFER_BLACKBODY 3  /* emission */
+
+typedef struct LuciferScene LuciferScene;
+
+typedef struct {
+    uint32_t kind;
+    float center[3];
+    float dimensions[3];
+    float normal[3];
+    float radius;
+    float distance;
+} LuciferPrimitive;
+
+typedef struct {
+    uint32_t kind;
+    float color[3];
+    float emission[3];
+    float specular[3];
+    float shininess;
+    float ior;
+} LuciferMaterial;
+
+/* A perspective camera with a vertical field of view in degrees. */
+typedef struct {
+    float position[3];
+    float look_at[3];
+    float up[3];
+    float fov;
+} LuciferCamera;
+
+/* With threads of 0 or 1, the image is rendered on the calling thread. */
+typedef struct {
+    uint32_t width;
+    uint32_t height;
+    uint32_t samples;
+    uint32_t depth_limit;
+    uint32_t threads;
+    float contribution_limit;
+    uint64_t seed;
+} LuciferSettings;
+
+/* Creates an empty scene with a uniform background. */
+LuciferScene *lucifer_scene_new(float r, float g, float b);
+
+/* Releases a scene created by lucifer_scene_new(). */
+void lucifer_scene_free(LuciferScene *scene);
+
+/*
+ * Adds an object to the scene, placed by a column-major 4x4 matrix,
+ * or the identity if transform is NULL.  Returns the index of the
+ * object, or -1.
+ */
+int64_t lucifer_scene_add(LuciferScene *scene,
+                          const LuciferPrimitive *primitive,
+                          const LuciferMaterial *material,
+                          const float *transform);
+
+/*
+ * Renders the scene into pixels, which must have room for
+ * 3 * width * height floats of linear RGB, row by row from the top.
+ * Returns 0, or -1.
+ */
+int lucifer_render(const LuciferScene *scene,
+                   const LuciferCamera *camera,
+                   const LuciferSettings *settings,
+                   float *pixels);
+
+/*
+ * Describes the last error on this thread, or returns NULL.  The
+ * message remains valid until the next error on the same thread.
+ */
+const char *lucifer_last_error(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,419 @@
+//! C bindings for lucifer.
+//!
+//! Scenes are built from plain descriptors of primitives and
+//! materials, and rendered with the path tracer into a buffer of
+//! linear RGB floats owned by the caller.  `include/lucifer.h`
+//! declares the functions and types for C and C++.
+//!
+//! Functions report failure with a negative result or a null
+//! pointer, and `lucifer_last_error` then describes the error.  This
+//! includes panics, which never unwind into the caller.
+//!
+//! # Examples
+//! ```
+//! use std::ptr;
+//!
+//! use lucifer_capi::*;
+//!
+//! unsafe {
+//!     let scene = lucifer_scene_new(0.5, 0.5, 0.5);
+//!
+//!     let sphere = LuciferPrimitive {
+//!         kind: LUCIFER_SPHERE,
+//!         radius: 1.0,
+//!         ..Default::default()
+//!     };
+//!     let gray = LuciferMaterial {
+//!         kind: LUCIFER_LAMBERT,
+//!         color: [0.8, 0.8, 0.8],
+//!         ..Default::default()
+//!     };
+//!     assert_eq!(lucifer_scene_add(scene, &sphere, &gray, ptr::null()), 0);
+//!
+//!     let unknown = LuciferPrimitive {
+//!         kind: 42,
+//!         ..Default::default()
+//!     };
+//!     assert_eq!(lucifer_scene_add(scene, &unknown, &gray, ptr::null()), -1);
+//!     assert!(!lucifer_last_error().is_null());
+//!
+//!     let camera = LuciferCamera {
+//!         position: [0.0, 0.0, 5.0],
+//!         look_at: [0.0, 0.0, 0.0],
+//!         up: [0.0, 1.0, 0.0],
+//!         fov: 40.0,
+//!     };
+//!     let settings = LuciferSettings {
+//!         width: 8,
+//!         height: 8,
+//!         samples: 4,
+//!         depth_limit: 4,
+//!         threads: 1,
+//!         contribution_limit: 0.01,
+//!         seed: 0,
+//!     };
+//!     let mut pixels = vec![0.0; 8 * 8 * 3];
+//!     assert_eq!(lucifer_render(scene, &camera, &settings, pixels.as_mut_ptr()), 0);
+//!
+//!     // The corners show the background, the center the gray sphere.
+//!     assert_eq!(&pixels[..3], &[0.5, 0.5, 0.5]);
+//!     let center = (4 * 8 + 4) * 3;
+//!     assert!(pixels[center] > 0.0 && pixels[center] < 0.5);
+//!
+//!     lucifer_scene_free(scene);
+//! }
+//! ```
+
+extern crate lucifer;
+
+use std::cell::RefCell;
+use std::ffi::CString;
+use std::os::raw::c_char;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::slice;
+use std::sync::Arc;
+
+use lucifer::camera::{AffineTransformCamera, Resolution, Target};
+use lucifer::cgmath::{Deg, Matrix4, PerspectiveFov, Rad, SquareMatrix, Vector3, Vector4};
+use lucifer::geometry::{Cube, Disc, Geometry, Plane, Point, Sphere, Vector};
+use lucifer::lighting::{Albedo, Blackbody, Dielectric, Ior, Lambert, Material, Phong, Radiance};
+use lucifer::render::{Context, Film, PathTracer, TiledRenderer};
+use lucifer::scene::{Object, Scene};
+use lucifer::{Error, Float, Result};
+
+/// A sphere around `center` with `radius`.
+pub const LUCIFER_SPHERE: u32 = 0;
+/// An axis-aligned box around `center` with edges of `dimensions`.
+pub const LUCIFER_CUBE: u32 = 1;
+/// An infinite plane with `normal`, at `distance` from the origin.
+pub const LUCIFER_PLANE: u32 = 2;
+/// A disc around `center` facing `normal`, with `radius`.
+pub const LUCIFER_DISC: u32 = 3;
+
+/// A diffuse material reflecting `color`.
+pub const LUCIFER_LAMBERT: u32 = 0;
+/// A material emitting `emission`, and diffusely reflecting `color`
+/// and specularly `specular` with exponent `shininess`.
+pub const LUCIFER_PHONG: u32 = 1;
+/// A transparent material of `color` with index of refraction `ior`.
+pub const LUCIFER_DIELECTRIC: u32 = 2;
+/// A black body emitting `emission`.
+pub const LUCIFER_BLACKBODY: u32 = 3;
+
+/// Describes the geometry of an object.  Which fields are used
+/// depends on `kind`, one of the `LUCIFER_SPHERE`, `LUCIFER_CUBE`,
+/// `LUCIFER_PLANE`, or `LUCIFER_DISC` constants.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LuciferPrimitive {
+    pub kind: u32,
+    pub center: [f32; 3],
+    pub dimensions: [f32; 3],
+    pub normal: [f32; 3],
+    pub radius: f32,
+    pub distance: f32,
+}
+
+/// Describes the material of an object.  Which fields are used
+/// depends on `kind`, one of the `LUCIFER_LAMBERT`, `LUCIFER_PHONG`,
+/// `LUCIFER_DIELECTRIC`, or `LUCIFER_BLACKBODY` constants.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LuciferMaterial {
+    pub kind: u32,
+    pub color: [f32; 3],
+    pub emission: [f32; 3],
+    pub specular: [f32; 3],
+    pub shininess: f32,
+    pub ior: f32,
+}
+
+/// A perspective camera at `position`, looking at `look_at`, with a
+/// vertical field of view of `fov` degrees.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LuciferCamera {
+    pub position: [f32; 3],
+    pub look_at: [f32; 3],
+    pub up: [f32; 3],
+    pub fov: f32,
+}
+
+/// The settings of the path tracer.  With `threads` of `0` or `1`,
+/// the image is rendered on the calling thread.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LuciferSettings {
+    pub width: u32,
+    pub height: u32,
+    pub samples: u32,
+    pub depth_limit: u32,
+    pub threads: u32,
+    pub contribution_limit: f32,
+    pub seed: u64,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Runs `body` and returns its result, or records its error or panic
+/// for `lucifer_last_error` and returns `failure`.  Every function
+/// called from C runs within, as panics must not unwind into C.
+fn guard<T, F>(failure: T, body: F) -> T
+where
+    F: FnOnce() -> Result<T>,
+{
+    let message = match panic::catch_unwind(AssertUnwindSafe(body)) {
+        Ok(Ok(value)) => return value,
+        Ok(Err(e)) => e.to_string(),
+        Err(payload) => {
+            let reason = match payload.downcast_ref::<&str>() {
+                Some(reason) => reason.to_string(),
+                None => payload
+                    .downcast_ref::<String>()
+                    .cloned()
+                    .unwrap_or_else(|| "unknown cause".to_string()),
+            };
+            format!("internal error: {}", reason)
+        }
+    };
+    let message = CString::new(message).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+    failure
+}
+
+fn null(name: &str) -> Error {
+    Error::InvalidSettings(format!("{} is null", name))
+}
+
+fn float(value: f32) -> Float {
+    Float::from(value)
+}
+
+#[allow(clippy::unnecessary_cast)]
+fn single(value: Float) -> f32 {
+    value as f32
+}
+
+fn point(v: [f32; 3]) -> Point {
+    Point::new(float(v[0]), float(v[1]), float(v[2]))
+}
+
+fn vector(v: [f32; 3]) -> Vector {
+    Vector::new(float(v[0]), float(v[1]), float(v[2]))
+}
+
+fn albedo(v: [f32; 3]) -> Albedo {
+    Albedo::new(float(v[0]), float(v[1]), float(v[2]))
+}
+
+fn radiance(v: [f32; 3]) -> Radiance {
+    Radiance::new(float(v[0]), float(v[1]), float(v[2]))
+}
+
+/// Reads a column-major matrix, as used by OpenGL.
+fn matrix(m: &[f32]) -> Matrix4<Float> {
+    let column = |i: usize| {
+        Vector4::new(
+            float(m[4 * i]),
+            float(m[4 * i + 1]),
+            float(m[4 * i + 2]),
+            float(m[4 * i + 3]),
+        )
+    };
+    Matrix4::from_cols(column(0), column(1), column(2), column(3))
+}
+
+fn geometry(primitive: &LuciferPrimitive) -> Result<Arc<dyn Geometry>> {
+    Ok(match primitive.kind {
+        LUCIFER_SPHERE => Arc::new(Sphere::new(
+            point(primitive.center),
+            float(primitive.radius),
+        )),
+        LUCIFER_CUBE => Arc::new(Cube::new(
+            point(primitive.center),
+            vector(primitive.dimensions),
+        )),
+        LUCIFER_PLANE => Arc::new(Plane::new(
+            vector(primitive.normal),
+            float(primitive.distance),
+        )),
+        LUCIFER_DISC => Arc::new(Disc::new(
+            point(primitive.center),
+            vector(primitive.normal),
+            float(primitive.radius),
+        )),
+        kind => return Err(Error::Unsupported(format!("primitive kind {}", kind))),
+    })
+}
+
+fn material(material: &LuciferMaterial) -> Result<Arc<dyn Material>> {
+    Ok(match material.kind {
+        LUCIFER_LAMBERT => Arc::new(Lambert::new(albedo(material.color))),
+        LUCIFER_PHONG => Arc::new(
+            Phong::new()
+                .glow(radiance(material.emission))
+                .color(albedo(material.color))
+                .highlight(albedo(material.specular), float(material.shininess)),
+        ),
+        LUCIFER_DIELECTRIC => Arc::new(Dielectric::new(
+            albedo(material.color),
+            Ior(float(material.ior)),
+        )),
+        LUCIFER_BLACKBODY => Arc::new(Blackbody::new(radiance(material.emission))),
+        kind => return Err(Error::Unsupported(format!("material kind {}", kind))),
+    })
+}
+
+fn render(scene: &Scene, camera: &LuciferCamera, settings: &LuciferSettings) -> Result<Film> {
+    let resolution = Resolution::try_new(settings.width, settings.height)?;
+    let depth_limit = if settings.depth_limit <= u32::from(u8::MAX) {
+        settings.depth_limit as u8
+    } else {
+        return Err(Error::InvalidSettings(format!(
+            "depth limit {} above {}",
+            settings.depth_limit,
+            u8::MAX
+        )));
+    };
+    let renderer = PathTracer::try_new(
+        float(settings.contribution_limit),
+        depth_limit,
+        settings.samples,
+    )?;
+
+    let projection = PerspectiveFov {
+        fovy: Rad::from(Deg(float(camera.fov))),
+        aspect: settings.width as Float / settings.height as Float,
+        near: 1.0,
+        far: 100.0,
+    };
+    let camera = AffineTransformCamera::try_look_at(
+        point(camera.position),
+        point(camera.look_at),
+        vector(camera.up),
+        projection,
+    )?;
+
+    let tiler = TiledRenderer::new(16, 1).threads(settings.threads as usize);
+    let mut context = Context::seeded(settings.seed, 0);
+    let mut film = Film::new(resolution);
+    tiler.render(&renderer, &mut context, scene, &camera, &mut film);
+    Ok(film)
+}
+
+/// Creates an empty scene with a uniform background, which must be
+/// released with `lucifer_scene_free`.
+#[no_mangle]
+pub extern "C" fn lucifer_scene_new(r: f32, g: f32, b: f32) -> *mut Scene {
+    guard(ptr::null_mut(), || {
+        Ok(Box::into_raw(Box::new(Scene::new(radiance([r, g, b])))))
+    })
+}
+
+/// Releases a scene created by `lucifer_scene_new`.
+///
+/// # Safety
+///
+/// `scene` must be null or returned by `lucifer_scene_new`, and not
+/// be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn lucifer_scene_free(scene: *mut Scene) {
+    guard((), || {
+        if !scene.is_null() {
+            drop(Box::from_raw(scene));
+        }
+        Ok(())
+    })
+}
+
+/// Adds an object of `primitive` and `material` to `scene`, placed
+/// by the column-major 4x4 matrix `transform`, or the identity if
+/// `transform` is null.  Returns the index of the object, or `-1` if
+/// a descriptor or the transformation is invalid.
+///
+/// # Safety
+///
+/// `scene` must be returned by `lucifer_scene_new`, `primitive` and
+/// `material` must point to descriptors, and `transform` must be null
+/// or point to 16 floats.
+#[no_mangle]
+pub unsafe extern "C" fn lucifer_scene_add(
+    scene: *mut Scene,
+    primitive: *const LuciferPrimitive,
+    material: *const LuciferMaterial,
+    transform: *const f32,
+) -> i64 {
+    guard(-1, || {
+        let transform = if transform.is_null() {
+            Matrix4::identity()
+        } else {
+            matrix(slice::from_raw_parts(transform, 16))
+        };
+
+        match (scene.as_mut(), primitive.as_ref(), material.as_ref()) {
+            (None, _, _) => Err(null("scene")),
+            (_, None, _) => Err(null("primitive")),
+            (_, _, None) => Err(null("material")),
+            (Some(scene), Some(p), Some(m)) => geometry(p)
+                .and_then(|g| Ok(Object::try_shared(g, self::material(m)?, transform)?))
+                .map(|object| scene.add(object) as i64),
+        }
+    })
+}
+
+/// Renders `scene` into `pixels`, which receives `width * height`
+/// linear RGB triples, row by row from the top.  Returns `0`, or
+/// `-1` if the settings or the camera are invalid.
+///
+/// # Safety
+///
+/// `scene` must be returned by `lucifer_scene_new`, `camera` and
+/// `settings` must point to descriptors, and `pixels` must have room
+/// for `3 * width * height` floats.
+#[no_mangle]
+pub unsafe extern "C" fn lucifer_render(
+    scene: *const Scene,
+    camera: *const LuciferCamera,
+    settings: *const LuciferSettings,
+    pixels: *mut f32,
+) -> i32 {
+    guard(-1, || {
+        let film = match (scene.as_ref(), camera.as_ref(), settings.as_ref()) {
+            (None, _, _) => Err(null("scene")),
+            (_, None, _) => Err(null("camera")),
+            (_, _, None) => Err(null("settings")),
+            (_, _, _) if pixels.is_null() => Err(null("pixels")),
+            (Some(scene), Some(camera), Some(settings)) => render(scene, camera, settings),
+        }?;
+
+        let resolution = film.resolution();
+        let pixels = slice::from_raw_parts_mut(
+            pixels,
+            3 * resolution.width as usize * resolution.height as usize,
+        );
+        for y in 0..resolution.height {
+            for x in 0..resolution.width {
+                let radiance = Vector3::from(film.get(Target::new(x, y)));
+                let offset = 3 * (y * resolution.width + x) as usize;
+                pixels[offset] = single(radiance.x);
+                pixels[offset + 1] = single(radiance.y);
+                pixels[offset + 2] = single(radiance.z);
+            }
+        }
+        Ok(0)
+    })
+}
+
+/// Describes the last error of a function called on this thread, or
+/// returns null if none failed yet.  The message remains valid until
+/// the next error on the same thread.
+#[no_mangle]
+pub extern "C" fn lucifer_last_error() -> *const c_char {
+    panic::catch_unwind(|| {
+        LAST_ERROR.with(|last| last.borrow().as_ref().map_or(ptr::null(), |m| m.as_ptr()))
+    })
+    .unwrap_or(ptr::null())
+}
@@ -0,0 +1,1524 @@
+extern crate cgmath;
+#[macro_use]
+extern crate clap;
+extern crate image;
+#[cfg(feature = "scene-file")]
+extern crate ron;
+#[cfg(feature = "scene-file")]
+#[macro_use]
+extern crate serde_derive;
+#[cfg(feature = "config")]
+extern crate toml;
+
+extern crate lucifer;
+
+use cgmath::num_traits::clamp;
+use cgmath::prelude::*;
+use cgmath::{Deg, PerspectiveFov, Rad, Vector3};
+use clap::{App, Arg, ArgMatches};
+use image::{Rgb, RgbImage};
+#[cfg(feature = "config")]
+use std::env;
+#[cfg(feature = "config")]
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, IsTerminal, Write};
+#[cfg(feature = "config")]
+use std::iter;
+use std::net::TcpListener;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use lucifer::camera::*;
+use lucifer::geometry::*;
+use lucifer::lighting::*;
+use lucifer::render::lpe::LightPathExpression;
+use lucifer::render::ray::Light;
+use lucifer::render::*;
+#[cfg(feature = "scene-file")]
+use lucifer::scene::file::{CameraDescription, RenderSettings, SceneFile};
+use lucifer::scene::*;
+use lucifer::Float;
+
+fn to_rgb(color: Vector3<Float>) -> Rgb<u8> {
+    Rgb(color
+        .map(|c| (clamp(c, 0.0, 1.0) * 255.0).round() as u8)
+        .into())
+}
+fn to_pixel(radiance: Radiance, exposure: Float, tonemap: &Tonemap) -> Rgb<u8> {
+    let v: Vector = radiance.into();
+    to_rgb(v.map(|c| tonemap.apply(c * exposure)))
+}
+
+/// Writes `film` to `path`, in the format given by its extension.
+///
+/// The image is written to a temporary file first, which then
+/// replaces `path`, so that an interrupted write never leaves a
+/// broken image behind.
+fn save(film: &Film, path: &Path, exposure: Float, tonemap: &Tonemap) -> io::Result<()> {
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+    let temporary = path.with_file_name(format!(".{}.partial.{}", stem, extension));
+
+    match extension {
+        // High dynamic range formats store the unexposed radiance.
+        "hdr" | "pfm" => {
+            let mut writer = BufWriter::new(File::create(&temporary)?);
+            if extension == "hdr" {
+                film.write_hdr(&mut writer)?;
+            } else {
+                film.write_pfm(&mut writer)?;
+            }
+            writer.flush()?;
+        }
+        _ => {
+            let res = film.resolution();
+            let mut img = RgbImage::new(res.width, res.height);
+            for y in 0..res.height {
+                for x in 0..res.width {
+                    let radiance = film.get(Target::new(x, y));
+                    img.put_pixel(x, y, to_pixel(radiance, exposure, tonemap));
+                }
+            }
+            img.save(&temporary)?;
+        }
+    }
+
+    fs::rename(&temporary, path)
+}
+
+/// Writes the complete state of `film` to `path`, for `--resume`.
+fn save_checkpoint(film: &Film, path: &Path) -> io::Result<()> {
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".partial");
+
+    let mut writer = BufWriter::new(File::create(&temporary)?);
+    film.write_checkpoint(&mut writer)?;
+    writer.flush()?;
+
+    fs::rename(&temporary, path)
+}
+
+/// Loads the film checkpoint at `path`, which must match `resolution`.
+fn load_checkpoint(path: &str, resolution: Resolution) -> Film {
+    let film = File::open(path)
+        .map(BufReader::new)
+        .and_then(|mut reader| Film::read_checkpoint(&mut reader))
+        .unwrap_or_else(|e| {
+            eprintln!("error: could not resume from {}: {}", path, e);
+            process::exit(1)
+        });
+
+    if film.resolution() != resolution {
+        eprintln!(
+            "error: could not resume from {}: resolution is {}x{}, not {}x{}",
+            path,
+            film.resolution().width,
+            film.resolution().height,
+            resolution.width,
+            resolution.height
+        );
+        process::exit(1)
+    }
+
+    film
+}
+
+/// The render parameters, taken from the scene file, if any, and
+/// overridden on the command line.
+struct Settings {
+    width: u32,
+    height: u32,
+    samples: u32,
+    depth_limit: u8,
+    contribution_limit: Float,
+    exposure: Float,
+    /// The number of animation frames per second.
+    fps: Float,
+    /// The radius of the camera's lens, `0` for a pinhole camera.
+    aperture: Float,
+    /// The distance in focus, if known.
+    focus: Option<Float>,
+    /// The number of blades forming the aperture, `0` for a circle.
+    blades: u32,
+    /// The rotation of the blades, in degrees.
+    blade_rotation: Float,
+    /// How much the aperture is squashed at the corners of the image.
+    cat_eye: Float,
+    /// The shutter angle, in degrees of a frame, `0` for no motion
+    /// blur.
+    shutter: Float,
+    /// The point lights illuminating the scene for the ray tracer.
+    lights: Vec<Light>,
+    /// The lights each pixel draws for ReSTIR.
+    candidates: u32,
+    /// The neighbors each pixel shares its light with for ReSTIR.
+    spatial_samples: u32,
+    /// Whether ReSTIR reuses the lights of one pass in the next.
+    temporal: bool,
+    /// Whether the path tracer traces light of single wavelengths.
+    spectral: bool,
+}
+
+/// The camera at a point in time, in seconds.
+type CameraRig = Box<dyn Fn(Float) -> AffineTransformCamera>;
+
+/// The placement of a perspective camera.
+#[derive(Clone, Copy)]
+struct View {
+    position: Point,
+    look_at: Point,
+    up: Vector,
+    /// The vertical field of view, in degrees.
+    fov: Float,
+}
+
+impl Default for Settings {
+    fn default() -> Settings {
+        Settings {
+            width: 256,
+            height: 256,
+            samples: 512,
+            depth_limit: 8,
+            contribution_limit: 0.01,
+            exposure: 1.0,
+            fps: 24.0,
+            aperture: 0.0,
+            focus: None,
+            blades: 0,
+            blade_rotation: 0.0,
+            cat_eye: 0.0,
+            shutter: 0.0,
+            lights: Vec::new(),
+            candidates: 32,
+            spatial_samples: 4,
+            temporal: false,
+            spectral: false,
+        }
+    }
+}
+
+#[cfg(feature = "scene-file")]
+impl From<RenderSettings> for Settings {
+    fn from(settings: RenderSettings) -> Settings {
+        Settings {
+            width: settings.width,
+            height: settings.height,
+            samples: settings.samples,
+            depth_limit: settings.depth_limit,
+            contribution_limit: settings.contribution_limit,
+            exposure: settings.exposure,
+            fps: settings.fps,
+            aperture: 0.0,
+            focus: None,
+            blades: 0,
+            blade_rotation: 0.0,
+            cat_eye: 0.0,
+            shutter: 0.0,
+            lights: Vec::new(),
+            candidates: 32,
+            spatial_samples: 4,
+            temporal: false,
+            spectral: false,
+        }
+    }
+}
+
+/// Overrides `settings` with the values given on the command line.
+fn apply_arguments(settings: &mut Settings, matches: &ArgMatches) {
+    if matches.is_present("width") {
+        settings.width = value_t!(matches, "width", u32).unwrap_or_else(|e| e.exit());
+    }
+    if matches.is_present("height") {
+        settings.height = value_t!(matches, "height", u32).unwrap_or_else(|e| e.exit());
+    }
+    if matches.is_present("spp") {
+        settings.samples = value_t!(matches, "spp", u32).unwrap_or_else(|e| e.exit());
+    }
+    if matches.is_present("max-depth") {
+        settings.depth_limit = value_t!(matches, "max-depth", u8).unwrap_or_else(|e| e.exit());
+    }
+    if matches.is_present("exposure") {
+        settings.exposure = value_t!(matches, "exposure", Float).unwrap_or_else(|e| e.exit());
+    }
+    if matches.is_present("contribution-limit") {
+        settings.contribution_limit =
+            value_t!(matches, "contribution-limit", Float).unwrap_or_else(|e| e.exit());
+    }
+    if matches.is_present("aperture") {
+        settings.aperture = value_t!(matches, "aperture", Float).unwrap_or_else(|e| e.exit());
+    }
+    if matches.is_present("focus") {
+        settings.focus = Some(value_t!(matches, "focus", Float).unwrap_or_else(|e| e.exit()));
+    }
+    if matches.is_present("blades") {
+        settings.blades = value_t!(matches, "blades", u32).unwrap_or_else(|e| e.exit());
+    }
+    if matches.is_present("blade-rotation") {
+        settings.blade_rotation =
+            value_t!(matches, "blade-rotation", Float).unwrap_or_else(|e| e.exit());
+    }
+    if matches.is_present("cat-eye") {
+        settings.cat_eye = value_t!(matches, "cat-eye", Float).unwrap_or_else(|e| e.exit());
+    }
+    if matches.is_present("shutter") {
+        settings.shutter = value_t!(matches, "shutter", Float).unwrap_or_else(|e| e.exit());
+    }
+    if matches.is_present("candidates") {
+        settings.candidates = value_t!(matches, "candidates", u32).unwrap_or_else(|e| e.exit());
+    }
+    if matches.is_present("spatial-samples") {
+        settings.spatial_samples =
+            value_t!(matches, "spatial-samples", u32).unwrap_or_else(|e| e.exit());
+    }
+    if matches.is_present("temporal") {
+        settings.temporal = true;
+    }
+    if matches.is_present("spectral") {
+        settings.spectral = true;
+    }
+
+    if let Err(e) = Resolution::try_new(settings.width, settings.height) {
+        eprintln!("error: {}", e);
+        process::exit(1);
+    }
+}
+
+/// Parses the point `x,y,z` given as argument `name`, if any.
+fn point_argument(matches: &ArgMatches, name: &str) -> Option<Point> {
+    let value = matches.value_of(name)?;
+    let coordinates: Vec<Float> = value
+        .split(',')
+        .map(|c| c.trim().parse())
+        .collect::<Result<_, _>>()
+        .unwrap_or_default();
+    if coordinates.len() != 3 {
+        eprintln!(
+            "error: invalid point {} for --{}, expected x,y,z",
+            value, name
+        );
+        process::exit(1);
+    }
+    Some(Point::new(coordinates[0], coordinates[1], coordinates[2]))
+}
+
+/// Overrides `view` with the camera placement given on the command
+/// line, returning `None` if none is given.  Without a `view` to
+/// start from, the position, target, and field of view must all be
+/// given.
+fn override_view(view: Option<View>, matches: &ArgMatches) -> Option<View> {
+    let position = point_argument(matches, "camera-pos");
+    let look_at = point_argument(matches, "look-at");
+    let fov = if matches.is_present("fov") {
+        Some(value_t!(matches, "fov", Float).unwrap_or_else(|e| e.exit()))
+    } else {
+        None
+    };
+    if position.is_none() && look_at.is_none() && fov.is_none() {
+        return None;
+    }
+
+    let view = view.or_else(|| {
+        Some(View {
+            position: position?,
+            look_at: look_at?,
+            up: Vector::unit_y(),
+            fov: fov?,
+        })
+    });
+    match view {
+        Some(view) => Some(View {
+            position: position.unwrap_or(view.position),
+            look_at: look_at.unwrap_or(view.look_at),
+            up: view.up,
+            fov: fov.unwrap_or(view.fov),
+        }),
+        None => {
+            eprintln!(
+                "error: the scene's camera needs --camera-pos, --look-at, and --fov to override"
+            );
+            process::exit(1)
+        }
+    }
+}
+
+/// The tonemapping operator chosen on the command line.
+fn tonemap(matches: &ArgMatches) -> Tonemap {
+    match matches.value_of("tonemap").unwrap() {
+        "linear" => Tonemap::Linear,
+        "gamma" => Tonemap::Gamma(2.2),
+        "reinhard" => Tonemap::Reinhard(2.2),
+        "aces" => Tonemap::Aces,
+        _ => Tonemap::Filmic,
+    }
+}
+
+/// The ReSTIR integrator, with the point lights and reuse of
+/// `settings`.
+fn restir(settings: &Settings) -> Restir {
+    let mut restir = Restir::new(settings.lights.clone(), settings.candidates);
+    restir.spatial_samples = settings.spatial_samples;
+    restir.temporal = settings.temporal;
+    restir
+}
+
+/// The integrator called `name`, showing `mode` if it is the debug
+/// integrator.  ReSTIR only reuses lights between pixels when
+/// rendering whole frames; see `Backend::restir`.
+fn integrator(settings: &Settings, name: &str, mode: &str) -> Box<dyn Renderer + Sync> {
+    match name {
+        "restir" => Box::new(restir(settings)),
+        "ray" => match settings.lights.first() {
+            Some(light) => Box::new(RayTracer::new(light.clone())),
+            None => {
+                eprintln!("error: the ray integrator requires a point light in the scene");
+                process::exit(1)
+            }
+        },
+        "debug" => {
+            let mode = match mode {
+                "ids" => DebugMode::ObjectIds,
+                _ => DebugMode::Normals,
+            };
+            Box::new(DebugRenderer::with_mode(mode))
+        }
+        _ => match PathTracer::try_new(
+            settings.contribution_limit,
+            settings.depth_limit,
+            settings.samples,
+        ) {
+            Ok(renderer) => Box::new(renderer.with_spectral(settings.spectral)),
+            Err(e) => {
+                eprintln!("error: {}", e);
+                process::exit(1)
+            }
+        },
+    }
+}
+
+/// Loads the scene file at `path`, returning the scene, its camera
+/// and the render settings, with those given on the command line
+/// taking precedence.
+#[cfg(feature = "scene-file")]
+fn load_scene(path: &str, matches: &ArgMatches) -> (Scene, CameraRig, Settings) {
+    let (mut file, scene) = SceneFile::load_scene(path).unwrap_or_else(|e| {
+        eprintln!("error: could not load {}: {}", path, e);
+        process::exit(1)
+    });
+
+    let mut settings = Settings::from(file.settings.clone());
+    settings.lights = file.lights();
+    apply_arguments(&mut settings, matches);
+
+    // The camera's aspect ratio follows the final resolution.
+    file.settings.width = settings.width;
+    file.settings.height = settings.height;
+
+    let view = match file.camera {
+        CameraDescription::Perspective {
+            position,
+            look_at,
+            up,
+            fov,
+        } => Some(View {
+            position: Point::from(position),
+            look_at: Point::from(look_at),
+            up: Vector::from(up),
+            fov,
+        }),
+        CameraDescription::Matrix(_) => None,
+    };
+    if let Some(view) = override_view(view, matches) {
+        file.camera = CameraDescription::Perspective {
+            position: view.position.into(),
+            look_at: view.look_at.into(),
+            up: view.up.into(),
+            fov: view.fov,
+        };
+        file.camera_keyframes.clear();
+    }
+
+    // Focus on the point looked at, unless the camera moves.
+    if let CameraDescription::Perspective {
+        position, look_at, ..
+    } = file.camera
+    {
+        if file.camera_keyframes.is_empty() {
+            let distance = Point::from(position).distance(Point::from(look_at));
+            settings.focus = settings.focus.or(Some(distance));
+        }
+    }
+
+    let path = path.to_string();
+    let camera = move |time| {
+        file.camera_at(time).unwrap_or_else(|e| {
+            eprintln!("error: {}: {}", path, e);
+            process::exit(1)
+        })
+    };
+    (scene, Box::new(camera), settings)
+}
+
+#[cfg(not(feature = "scene-file"))]
+fn load_scene(path: &str, _: &ArgMatches) -> (Scene, CameraRig, Settings) {
+    eprintln!(
+        "error: could not load {}: built without scene file support",
+        path
+    );
+    process::exit(1)
+}
+
+/// The built-in scene called `name`, rendered when no scene file is
+/// given.
+fn builtin_scene(name: &str, matches: &ArgMatches) -> (Scene, CameraRig, Settings) {
+    let builtin = Builtin::by_name(name).unwrap_or_else(|| {
+        eprintln!("error: there is no built-in scene {}", name);
+        process::exit(1)
+    });
+
+    let mut settings = Settings {
+        lights: builtin.lights,
+        ..Settings::default()
+    };
+    apply_arguments(&mut settings, matches);
+
+    let view = View {
+        position: builtin.position,
+        look_at: builtin.look_at,
+        up: Vector::new(0.0, 1.0, 0.0),
+        fov: builtin.fov,
+    };
+    let view = override_view(Some(view), matches).unwrap_or(view);
+    settings.focus = settings
+        .focus
+        .or(Some(view.position.distance(view.look_at)));
+
+    let projection = PerspectiveFov {
+        fovy: Rad::from(Deg(view.fov)),
+        aspect: settings.width as Float / settings.height as Float,
+        near: 1.0,
+        far: 100.0,
+    };
+
+    let camera = AffineTransformCamera::look_at(view.position, view.look_at, view.up, projection);
+
+    (builtin.scene, Box::new(move |_| camera.clone()), settings)
+}
+
+/// What the workers of `--serve` render: everything needed to
+/// rebuild the coordinator's scene, camera, and integrator.
+#[cfg(feature = "scene-file")]
+#[derive(Serialize, Deserialize)]
+struct Job {
+    source: Source,
+    camera: ThinLensCamera,
+    width: u32,
+    height: u32,
+    samples: u32,
+    depth_limit: u8,
+    contribution_limit: Float,
+    integrator: String,
+    debug_mode: String,
+    candidates: u32,
+    spectral: bool,
+}
+
+/// Where the scene of a `Job` comes from.
+#[cfg(feature = "scene-file")]
+#[derive(Serialize, Deserialize)]
+enum Source {
+    /// A scene description, with the files it includes relative to
+    /// `base`.  Workers read included files from the same paths.
+    File {
+        scene: Box<SceneFile>,
+        base: PathBuf,
+    },
+    Builtin(String),
+}
+
+/// Encodes the job for the workers of `--serve`, rendering the image
+/// of `camera` with `settings`.
+#[cfg(feature = "scene-file")]
+fn serve_job(matches: &ArgMatches, settings: &Settings, camera: ThinLensCamera) -> Vec<u8> {
+    let source = match matches.value_of("scene") {
+        Some(path) => {
+            let scene = SceneFile::load(path).unwrap_or_else(|e| {
+                eprintln!("error: could not load {}: {}", path, e);
+                process::exit(1)
+            });
+            let base = Path::new(path)
+                .parent()
+                .and_then(|parent| parent.canonicalize().ok())
+                .unwrap_or_default();
+            Source::File {
+                scene: Box::new(scene),
+                base,
+            }
+        }
+        None => Source::Builtin(matches.value_of("builtin").unwrap_or("cornell").to_string()),
+    };
+
+    let job = Job {
+        source,
+        camera,
+        width: settings.width,
+        height: settings.height,
+        samples: settings.samples,
+        depth_limit: settings.depth_limit,
+        contribution_limit: settings.contribution_limit,
+        integrator: matches.value_of("integrator").unwrap().to_string(),
+        debug_mode: matches.value_of("debug-mode").unwrap().to_string(),
+        candidates: settings.candidates,
+        spectral: settings.spectral,
+    };
+    ron::to_string(&job)
+        .unwrap_or_else(|e| {
+            eprintln!("error: could not encode the job: {}", e);
+            process::exit(1)
+        })
+        .into_bytes()
+}
+
+#[cfg(not(feature = "scene-file"))]
+fn serve_job(_: &ArgMatches, _: &Settings, _: ThinLensCamera) -> Vec<u8> {
+    eprintln!("error: could not serve: built without scene file support");
+    process::exit(1)
+}
+
+/// Renders tiles for the coordinator at `address` with `threads`
+/// threads, until it is done.
+#[cfg(feature = "scene-file")]
+fn work(address: &str, threads: usize) {
+    let fail = |e: &dyn std::fmt::Display| -> ! {
+        eprintln!("error: could not work for {}: {}", address, e);
+        process::exit(1)
+    };
+
+    let worker = Worker::connect(address, threads).unwrap_or_else(|e| fail(&e));
+    let job: Job = ron::de::from_bytes(&worker.job).unwrap_or_else(|e| fail(&e));
+
+    let (mut scene, lights) = match job.source {
+        Source::File { scene, base } => {
+            let lights = scene.lights();
+            (scene.resolve(base).unwrap_or_else(|e| fail(&e)), lights)
+        }
+        Source::Builtin(name) => match Builtin::by_name(&name) {
+            Some(builtin) => (builtin.scene, builtin.lights),
+            None => fail(&format!("there is no built-in scene {}", name)),
+        },
+    };
+    if let Err(e) = scene.set_time(0.0) {
+        eprintln!("warning: {}", e);
+    }
+
+    let settings = Settings {
+        width: job.width,
+        height: job.height,
+        samples: job.samples,
+        depth_limit: job.depth_limit,
+        contribution_limit: job.contribution_limit,
+        lights,
+        candidates: job.candidates,
+        spectral: job.spectral,
+        ..Settings::default()
+    };
+    let renderer = integrator(&settings, &job.integrator, &job.debug_mode);
+    let res = Resolution::new(settings.width, settings.height);
+
+    eprintln!("rendering for {}", address);
+    let statistics = worker
+        .work(&*renderer, &scene, &job.camera, res)
+        .unwrap_or_else(|e| fail(&e));
+    eprintln!(
+        "rendered {} paths, {} rays",
+        statistics.paths, statistics.rays
+    );
+}
+
+#[cfg(not(feature = "scene-file"))]
+fn work(address: &str, _: usize) {
+    eprintln!(
+        "error: could not work for {}: built without scene file support",
+        address
+    );
+    process::exit(1)
+}
+
+/// Where and how rendered images are written.
+#[derive(Clone, Copy)]
+struct Output<'a> {
+    path: &'a Path,
+    exposure: Float,
+    tonemap: &'a Tonemap,
+    /// Seconds between writes of the partial image, `0` to disable.
+    save_interval: u64,
+    checkpoint: Option<&'a Path>,
+    /// The auxiliary outputs written next to the image.
+    aovs: &'a [Aov],
+    /// Whether to also write a denoised image, next to the image.
+    denoise: bool,
+    /// The renderers of the light path passes written next to the
+    /// image.
+    light_paths: &'a [FilteredPathTracer],
+    /// The percentile of luma mapped to one, scaling `exposure`.
+    auto_exposure: Option<Float>,
+    /// Whether to report statistics of the final image.
+    verbose: bool,
+    /// The image to compare the final image with, writing a heatmap
+    /// of their difference next to the image.
+    reference: Option<&'a Film>,
+}
+
+impl<'a> Output<'a> {
+    /// The exposure of `film`, adjusted to the image if requested.
+    fn exposure_of(&self, film: &Film) -> Float {
+        match self.auto_exposure {
+            Some(p) => self.exposure * film.auto_exposure(p),
+            None => self.exposure,
+        }
+    }
+}
+
+/// Renders the tiles of images, on the threads of this process, or
+/// on the workers of a coordinator.
+struct Backend {
+    tiler: TiledRenderer,
+    /// The coordinator and the job it hands to its workers, when
+    /// serving.  The auxiliary outputs are still rendered locally.
+    remote: Option<(Coordinator, Vec<u8>)>,
+    /// The ReSTIR integrator and the number of frames it renders per
+    /// image, when chosen, instead of the tiles of the renderer.
+    restir: Option<(Restir, u32)>,
+}
+
+/// Renders `film`, showing the progress and saving the partial image
+/// regularly, then saves the final image and its auxiliary outputs.
+fn render(
+    backend: &mut Backend,
+    renderer: &(dyn Renderer + Sync),
+    context: &mut Context,
+    scene: &Scene,
+    camera: &dyn Camera,
+    film: &mut Film,
+    output: &Output,
+) -> Report {
+    let mut saved = Instant::now();
+
+    // Redraw the progress line at most ten times a second, and only
+    // when somebody is watching.
+    let interactive = io::stderr().is_terminal();
+    let mut redrawn: Option<Instant> = None;
+    let mut on_progress = |progress: &Progress, film: &Film| {
+        if output.save_interval > 0
+            && progress.done < progress.total
+            && saved.elapsed() >= Duration::from_secs(output.save_interval)
+        {
+            let exposure = output.exposure_of(film);
+            if let Err(e) = save(film, output.path, exposure, output.tonemap) {
+                eprintln!("\nwarning: could not save intermediate image: {}", e);
+            }
+            if let Some(Err(e)) = output.checkpoint.map(|c| save_checkpoint(film, c)) {
+                eprintln!("\nwarning: could not save checkpoint: {}", e);
+            }
+            saved = Instant::now();
+        }
+
+        let due = redrawn.is_none_or(|t| t.elapsed() >= Duration::from_millis(100));
+        if interactive && (due || progress.done == progress.total) {
+            eprint!("\r{}", progress);
+            redrawn = Some(Instant::now());
+        }
+    };
+    let tiler = &backend.tiler;
+    let report = match (&backend.remote, &mut backend.restir) {
+        (Some((coordinator, job)), _) => coordinator
+            .render_with_progress(job, context, film, &mut on_progress)
+            .unwrap_or_else(|e| {
+                eprintln!("\nerror: could not serve workers: {}", e);
+                process::exit(1)
+            }),
+        (None, Some((restir, frames))) => {
+            restir.render_with_progress(*frames, context, scene, camera, film, &mut on_progress)
+        }
+        (None, None) => {
+            tiler.render_with_progress(renderer, context, scene, camera, film, &mut on_progress)
+        }
+    };
+    if interactive {
+        eprintln!();
+    }
+
+    let exposure = output.exposure_of(film);
+    save(film, output.path, exposure, output.tonemap).expect("Could not save to file");
+    if let Some(checkpoint) = output.checkpoint {
+        save_checkpoint(film, checkpoint).expect("Could not save checkpoint");
+    }
+
+    let mut passes = Vec::new();
+    for &aov in output.aovs {
+        let pass = match aov {
+            Aov::Variance => film.variance(),
+            _ => {
+                let mut pass = Film::new(film.resolution());
+                let single = TiledRenderer {
+                    passes: 1,
+                    convergence: None,
+                    ..*tiler
+                };
+                let renderer = AovRenderer::new(aov);
+                single.render(
+                    &renderer,
+                    &mut Context::seeded(0, 0),
+                    scene,
+                    camera,
+                    &mut pass,
+                );
+                pass
+            }
+        };
+        save_aov(&pass, &suffixed(output.path, aov.name()), aov).expect("Could not save to file");
+        passes.push((aov, pass));
+    }
+
+    for (i, renderer) in output.light_paths.iter().enumerate() {
+        let mut pass = Film::new(film.resolution());
+        tiler.render(
+            renderer,
+            &mut Context::seeded(0, 0),
+            scene,
+            camera,
+            &mut pass,
+        );
+        let path = suffixed(output.path, &format!("lpe{}", i + 1));
+        save(&pass, &path, exposure, output.tonemap).expect("Could not save to file");
+    }
+
+    if output.denoise {
+        let guide = |aov| passes.iter().find(|p| p.0 == aov).map(|p| &p.1);
+        let denoised = Denoiser::new().denoise(film, guide(Aov::Normal), guide(Aov::Albedo));
+        let path = suffixed(output.path, "denoised");
+        save(&denoised, &path, exposure, output.tonemap).expect("Could not save to file");
+    }
+
+    if let Some(reference) = output.reference {
+        if reference.resolution() == film.resolution() {
+            eprintln!("{}", compare::compare(film, reference));
+            // Show differences relative to the bright parts of the
+            // reference.
+            let heatmap = compare::heatmap(film, reference, reference.auto_exposure(0.99));
+            let path = suffixed(output.path, "diff");
+            save(&heatmap, &path, 1.0, &Tonemap::Linear).expect("Could not save to file");
+        } else {
+            eprintln!("warning: the reference image has a different resolution");
+        }
+    }
+
+    if output.verbose {
+        eprintln!("{}", film.statistics());
+        eprintln!(
+            "luma:              median {:.4} / 99th percentile {:.4}",
+            film.percentile(0.5),
+            film.percentile(0.99)
+        );
+        eprintln!(
+            "clipped:           {} pixels at exposure {:.4}",
+            film.clipped(exposure),
+            exposure
+        );
+    }
+
+    report
+}
+
+/// The output path with a `suffix` before the extension, e.g.
+/// `image.depth.png` for `image.png` and `depth`.
+fn suffixed(path: &Path, suffix: &str) -> PathBuf {
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+    match path.extension().and_then(|e| e.to_str()) {
+        Some(extension) => path.with_file_name(format!("{}.{}.{}", stem, suffix, extension)),
+        None => path.with_file_name(format!("{}.{}", stem, suffix)),
+    }
+}
+
+/// Writes the auxiliary output `film` without tonemapping.  Depth
+/// and variance are scaled to fit low dynamic range formats.
+fn save_aov(film: &Film, path: &Path, aov: Aov) -> io::Result<()> {
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+    let scaled =
+        (aov == Aov::Depth || aov == Aov::Variance) && extension != "hdr" && extension != "pfm";
+
+    let mut exposure = 1.0;
+    if scaled {
+        let res = film.resolution();
+        let max = (0..res.height)
+            .flat_map(|y| (0..res.width).map(move |x| Target::new(x, y)))
+            .map(|target| film.get(target).luma())
+            .fold(0.0, Float::max);
+        if max > 0.0 {
+            exposure = 1.0 / max;
+        }
+    }
+    save(film, path, exposure, &Tonemap::Linear)
+}
+
+/// A camera orbiting `bounds` once over `frames` frames, framing the
+/// whole of `bounds` in every frame, and its distance to the center of
+/// `bounds`.
+fn turntable(
+    bounds: Bounds,
+    frames: u32,
+    fps: Float,
+    fov: Float,
+    aspect: Float,
+) -> (CameraRig, Float) {
+    let fov = Deg(fov);
+    let elevation = Rad::from(Deg(20.0));
+
+    // Keep the bounding sphere within the narrower field of view.
+    let radius = (bounds.diagonal().magnitude() * 0.5).max(1e-3);
+    let half = Rad::from(fov) * 0.5;
+    let narrowest = if aspect < 1.0 {
+        Rad((half.tan() * aspect).atan())
+    } else {
+        half
+    };
+    let distance = radius / narrowest.sin();
+    let center = bounds.center();
+    let projection = PerspectiveFov {
+        fovy: Rad::from(fov),
+        aspect,
+        near: (distance - radius) * 0.5,
+        far: distance + radius * 2.0,
+    };
+
+    let rig = Box::new(move |time| {
+        let angle = Rad::full_turn() * (time * fps / frames as Float);
+        let eye = center
+            + distance
+                * Vector::new(
+                    angle.sin() * elevation.cos(),
+                    elevation.sin(),
+                    angle.cos() * elevation.cos(),
+                );
+        AffineTransformCamera::look_at(eye, center, Vector::unit_y(), projection)
+    });
+    (rig, distance)
+}
+
+/// A camera baking the lightmap of the object with index `object`.
+fn lightmap(scene: &Scene, object: &str) -> LightmapCamera {
+    let object = match object
+        .parse::<usize>()
+        .ok()
+        .and_then(|i| scene.objects().get(i))
+    {
+        Some(object) => object,
+        None => {
+            eprintln!(
+                "error: no object {} to bake, the scene has {} objects",
+                object,
+                scene.objects().len()
+            );
+            process::exit(1)
+        }
+    };
+    LightmapCamera::try_new(object).unwrap_or_else(|e| {
+        eprintln!("error: cannot bake object: {}", e);
+        process::exit(1)
+    })
+}
+
+/// Parses a range of frames, `start..end` (excluding `end`).
+fn parse_frames(frames: &str) -> Option<Range<u32>> {
+    let (start, end) = frames.split_once("..")?;
+    Some(start.trim().parse().ok()?..end.trim().parse().ok()?)
+}
+
+/// The output path for `frame`, e.g. `image_0007.png` for `image.png`.
+fn frame_path(path: &Path, frame: u32) -> PathBuf {
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+    match path.extension().and_then(|e| e.to_str()) {
+        Some(extension) => path.with_file_name(format!("{}_{:04}.{}", stem, frame, extension)),
+        None => path.with_file_name(format!("{}_{:04}", stem, frame)),
+    }
+}
+
+/// The options `lucifer.toml` may set defaults for, besides `format`.
+#[cfg(feature = "config")]
+const CONFIG_OPTIONS: &[&str] = &[
+    "width",
+    "height",
+    "spp",
+    "max-depth",
+    "exposure",
+    "auto-exposure",
+    "tonemap",
+    "integrator",
+    "threads",
+    "save-interval",
+];
+
+/// Reads `lucifer.toml` from the current directory, or else
+/// `.lucifer.toml` from the home directory, and returns the options it
+/// sets that are missing from `matches`, as command line arguments.
+/// `format` sets the extension of the default output file.
+#[cfg(feature = "config")]
+fn config_arguments(matches: &ArgMatches) -> Vec<String> {
+    let home = env::var_os("HOME").map(|home| Path::new(&home).join(".lucifer.toml"));
+    let path = match iter::once(PathBuf::from("lucifer.toml"))
+        .chain(home)
+        .find(|path| path.is_file())
+    {
+        Some(path) => path,
+        None => return Vec::new(),
+    };
+
+    let fail = |message: String| -> ! {
+        eprintln!("error: {}: {}", path.display(), message);
+        process::exit(1)
+    };
+    let text = fs::read_to_string(&path).unwrap_or_else(|e| fail(e.to_string()));
+    let table: toml::value::Table = toml::from_str(&text).unwrap_or_else(|e| fail(e.to_string()));
+
+    let mut arguments = Vec::new();
+    for (key, value) in table {
+        let value = match value {
+            toml::Value::String(s) => s,
+            toml::Value::Integer(i) => i.to_string(),
+            toml::Value::Float(f) => f.to_string(),
+            _ => fail(format!("{} must be a string or a number", key)),
+        };
+        let (option, value) = match key.as_str() {
+            "format" => ("output", format!("lucifer.{}", value)),
+            option if CONFIG_OPTIONS.contains(&option) => (option, value),
+            _ => fail(format!("unknown option {}", key)),
+        };
+        if matches.occurrences_of(option) == 0 {
+            arguments.push(format!("--{}", option));
+            arguments.push(value);
+        }
+    }
+    arguments
+}
+
+fn main() {
+    let app = App::new(env!("CARGO_BIN_NAME"))
+        .version(env!("CARGO_PKG_VERSION"))
+        .about(env!("CARGO_PKG_DESCRIPTION"))
+        .author(env!("CARGO_PKG_AUTHORS"))
+        .arg(
+            Arg::with_name("scene")
+                .help("Scene file (.ron, .json, .usda, .usdz), renders the --builtin scene if omitted")
+                .value_name("scene")
+                .required(false),
+        )
+        .arg(
+            Arg::with_name("builtin")
+                .long("builtin")
+                .help("Built-in scene to render without a scene file [default: cornell]")
+                .value_name("name")
+                .possible_values(&Builtin::NAMES)
+                .conflicts_with("scene"),
+        )
+        .arg(
+            Arg::with_name("output")
+                .short("o")
+                .long("output")
+                .help("Output file name")
+                .value_name("filename")
+                .default_value("lucifer.png"),
+        )
+        .arg(
+            Arg::with_name("width")
+                .long("width")
+                .help("Image width in pixels [default: 256]")
+                .value_name("pixels"),
+        )
+        .arg(
+            Arg::with_name("height")
+                .long("height")
+                .help("Image height in pixels [default: 256]")
+                .value_name("pixels"),
+        )
+        .arg(
+            Arg::with_name("spp")
+                .long("spp")
+                .help("Samples per pixel [default: 512]")
+                .value_name("samples"),
+        )
+        .arg(
+            Arg::with_name("max-depth")
+                .long("max-depth")
+                .help("Maximum number of path vertices [default: 8]")
+                .value_name("depth"),
+        )
+        .arg(
+            Arg::with_name("contribution-limit")
+                .long("contribution-limit")
+                .help("Contribution below which paths are terminated [default: 0.01]")
+                .value_name("limit"),
+        )
+        .arg(
+            Arg::with_name("exposure")
+                .long("exposure")
+                .help("Factor applied to the radiance before tonemapping [default: 1]")
+                .value_name("factor"),
+        )
+        .arg(
+            Arg::with_name("auto-exposure")
+                .long("auto-exposure")
+                .help(
+                    "Scales the exposure to map the luma at the given percentile of the \
+                     pixels to white, e.g. 99",
+                )
+                .value_name("percentile"),
+        )
+        .arg(
+            Arg::with_name("tonemap")
+                .long("tonemap")
+                .help("Tonemapping operator for low dynamic range output")
+                .value_name("operator")
+                .possible_values(&["linear", "gamma", "reinhard", "filmic", "aces"])
+                .default_value("filmic"),
+        )
+        .arg(
+            Arg::with_name("save-interval")
+                .long("save-interval")
+                .help("Seconds between writes of the partial image, 0 to disable")
+                .value_name("seconds")
+                .default_value("60"),
+        )
+        .arg(
+            Arg::with_name("checkpoint")
+                .long("checkpoint")
+                .help("File to save the film to, with the partial image and at the end")
+                .value_name("filename"),
+        )
+        .arg(
+            Arg::with_name("resume")
+                .long("resume")
+                .help("Film checkpoint to continue accumulating samples into")
+                .value_name("filename"),
+        )
+        .arg(
+            Arg::with_name("frames")
+                .long("frames")
+                .help("Renders the animation frames start..end (excluding end) to numbered files")
+                .value_name("range")
+                .conflicts_with_all(&["resume", "checkpoint"]),
+        )
+        .arg(
+            Arg::with_name("camera-pos")
+                .long("camera-pos")
+                .help("Places the camera at x,y,z, overriding the scene's camera")
+                .value_name("point")
+                .allow_hyphen_values(true),
+        )
+        .arg(
+            Arg::with_name("look-at")
+                .long("look-at")
+                .help("Points the camera at x,y,z, overriding the scene's camera")
+                .value_name("point")
+                .allow_hyphen_values(true),
+        )
+        .arg(
+            Arg::with_name("fov")
+                .long("fov")
+                .help("Vertical field of view, overriding the scene's camera")
+                .value_name("degrees"),
+        )
+        .arg(
+            Arg::with_name("aperture")
+                .long("aperture")
+                .help("Radius of the camera's lens, for depth of field [default: 0]")
+                .value_name("radius"),
+        )
+        .arg(
+            Arg::with_name("focus")
+                .long("focus")
+                .help("Distance in focus [default: distance to the point looked at]")
+                .value_name("distance"),
+        )
+        .arg(
+            Arg::with_name("blades")
+                .long("blades")
+                .help("Number of blades forming a polygonal aperture [default: circular]")
+                .value_name("count"),
+        )
+        .arg(
+            Arg::with_name("blade-rotation")
+                .long("blade-rotation")
+                .help("Rotation of the aperture blades [default: 0]")
+                .value_name("degrees"),
+        )
+        .arg(
+            Arg::with_name("cat-eye")
+                .long("cat-eye")
+                .help("Squashes the aperture at the corners of the image, from 0 to 1 [default: 0]")
+                .value_name("amount"),
+        )
+        .arg(
+            Arg::with_name("shutter")
+                .long("shutter")
+                .help("Shutter angle in degrees, blurring motion over that part of a frame [default: 0]")
+                .value_name("degrees"),
+        )
+        .arg(
+            Arg::with_name("turntable")
+                .long("turntable")
+                .help("Orbits the camera around the scene over the given number of frames")
+                .value_name("frames")
+                .conflicts_with_all(&["resume", "checkpoint"]),
+        )
+        .arg(
+            Arg::with_name("bake")
+                .long("bake")
+                .help(
+                    "Renders the light arriving at the surface of the object with the given \
+                     index into a lightmap, instead of viewing the scene",
+                )
+                .value_name("object")
+                .conflicts_with_all(&["frames", "turntable", "serve"]),
+        )
+        .arg(
+            Arg::with_name("serve")
+                .long("serve")
+                .help("Renders on the workers connecting to the given address, e.g. 0.0.0.0:7878")
+                .value_name("address")
+                .conflicts_with_all(&["frames", "turntable"]),
+        )
+        .arg(
+            Arg::with_name("worker")
+                .long("worker")
+                .help("Renders tiles for the coordinator started with --serve at the given address")
+                .value_name("address")
+                .conflicts_with_all(&["scene", "builtin", "serve"]),
+        )
+        .arg(
+            Arg::with_name("threads")
+                .long("threads")
+                .short("j")
+                .help("Number of rendering threads [default: all cores]")
+                .value_name("count"),
+        )
+        .arg(
+            Arg::with_name("reference")
+                .long("reference")
+                .help(
+                    "Compares the image with a reference image, and writes a heatmap of \
+                     their difference next to the image",
+                )
+                .value_name("image")
+                .conflicts_with_all(&["frames", "turntable"]),
+        )
+        .arg(
+            Arg::with_name("verbose")
+                .long("verbose")
+                .short("v")
+                .help("Reports statistics of the final image"),
+        )
+        .arg(
+            Arg::with_name("seed")
+                .long("seed")
+                .help(
+                    "Seed of the random numbers; the same seed, scene, and options \
+                     reproduce the same image, regardless of the number of threads",
+                )
+                .value_name("number")
+                .default_value("0"),
+        )
+        .arg(
+            Arg::with_name("aov")
+                .long("aov")
+                .help("Auxiliary outputs to write next to the image, e.g. image.depth.png")
+                .value_name("outputs")
+                .use_delimiter(true)
+                .possible_values(&["normal", "depth", "albedo", "variance", "id"]),
+        )
+        .arg(
+            Arg::with_name("lpe")
+                .long("lpe")
+                .help(
+                    "Also writes the light of the paths matching a light path expression, \
+                     e.g. 'C<RD>L' for direct diffuse light, to image.lpe1.png, and so on",
+                )
+                .value_name("expression")
+                .multiple(true)
+                .number_of_values(1),
+        )
+        .arg(Arg::with_name("denoise").long("denoise").help(
+            "Also writes a denoised image, e.g. image.denoised.png, guided by \
+                     the normal and albedo outputs, if selected",
+        ))
+        .arg(
+            Arg::with_name("integrator")
+                .long("integrator")
+                .help("Rendering algorithm")
+                .value_name("name")
+                .possible_values(&["path", "ray", "restir", "debug"])
+                .default_value("path"),
+        )
+        .arg(
+            Arg::with_name("candidates")
+                .long("candidates")
+                .help("Lights each pixel draws for the restir integrator [default: 32]")
+                .value_name("count"),
+        )
+        .arg(
+            Arg::with_name("spatial-samples")
+                .long("spatial-samples")
+                .help(
+                    "Neighbors each pixel shares its light with for the restir integrator \
+                     [default: 4]",
+                )
+                .value_name("count"),
+        )
+        .arg(Arg::with_name("temporal").long("temporal").help(
+            "Reuses the lights of one sample in the next for the restir integrator",
+        ))
+        .arg(Arg::with_name("spectral").long("spectral").help(
+            "Traces light of single wavelengths with the path integrator, upsampling \
+                     the colors of the scene to spectra",
+        ))
+        .arg(
+            Arg::with_name("debug-mode")
+                .long("debug-mode")
+                .help("Quantity shown by the debug integrator")
+                .value_name("mode")
+                .possible_values(&["normals", "ids"])
+                .default_value("normals"),
+        );
+    let matches = app.clone().get_matches();
+
+    // Defaults from the config file, as if given before all other
+    // arguments.
+    #[cfg(feature = "config")]
+    let matches = {
+        let defaults = config_arguments(&matches);
+        if defaults.is_empty() {
+            matches
+        } else {
+            let mut arguments: Vec<OsString> = env::args_os().collect();
+            let at = 1.min(arguments.len());
+            arguments.splice(at..at, defaults.into_iter().map(OsString::from));
+            app.get_matches_from(arguments)
+        }
+    };
+
+    let threads = if matches.is_present("threads") {
+        value_t!(matches, "threads", usize).unwrap_or_else(|e| e.exit())
+    } else {
+        thread::available_parallelism().map_or(1, |n| n.get())
+    };
+
+    if let Some(address) = matches.value_of("worker") {
+        work(address, threads);
+        return;
+    }
+
+    let output = matches.value_of("output").unwrap();
+
+    let (mut scene, mut pinhole, mut settings) = match matches.value_of("scene") {
+        Some(path) => load_scene(path, &matches),
+        None => builtin_scene(matches.value_of("builtin").unwrap_or("cornell"), &matches),
+    };
+
+    let turntable_frames = if matches.is_present("turntable") {
+        Some(value_t!(matches, "turntable", u32).unwrap_or_else(|e| e.exit()))
+    } else {
+        None
+    };
+    if let Some(frames) = turntable_frames {
+        let bounds = scene.bounds();
+        if bounds.is_empty() {
+            eprintln!("error: the scene has no finite objects to orbit");
+            process::exit(1);
+        }
+        let fov = if matches.is_present("fov") {
+            value_t!(matches, "fov", Float).unwrap_or_else(|e| e.exit())
+        } else {
+            40.0
+        };
+        let aspect = settings.width as Float / settings.height as Float;
+        let (rig, distance) = turntable(bounds, frames.max(1), settings.fps, fov, aspect);
+        pinhole = rig;
+        if !matches.is_present("focus") {
+            settings.focus = Some(distance);
+        }
+    }
+
+    let aperture = settings.aperture;
+    let focus = match settings.focus {
+        Some(focus) => focus,
+        None if aperture > 0.0 => {
+            eprintln!("error: --aperture needs --focus for a moving or matrix camera");
+            process::exit(1)
+        }
+        None => 1.0,
+    };
+    let blades = (settings.blades, Rad::from(Deg(settings.blade_rotation)).0);
+    let cat_eye = settings.cat_eye;
+    let exposure = settings.shutter / 360.0 / settings.fps;
+    let camera = move |time| {
+        let lens = ThinLensCamera::new(pinhole(time), aperture, focus)
+            .blades(blades.0, blades.1)
+            .cat_eye(cat_eye);
+        ShutterCamera::new(lens, 0.0, exposure)
+    };
+
+    let aovs: Vec<Aov> = matches.values_of("aov").map_or(Vec::new(), |names| {
+        names.filter_map(Aov::from_name).collect()
+    });
+
+    // The variance needs several estimates per pixel, so split the
+    // samples over passes.
+    let passes = if aovs.contains(&Aov::Variance) {
+        settings.samples.clamp(1, 16)
+    } else {
+        1
+    };
+    settings.samples = settings.samples.div_ceil(passes);
+
+    let renderer = integrator(
+        &settings,
+        matches.value_of("integrator").unwrap(),
+        matches.value_of("debug-mode").unwrap(),
+    );
+    let light_paths: Vec<FilteredPathTracer> =
+        matches.values_of("lpe").map_or(Vec::new(), |expressions| {
+            let tracer = PathTracer::new(
+                settings.contribution_limit,
+                settings.depth_limit,
+                settings.samples,
+            )
+            .with_spectral(settings.spectral);
+            expressions
+                .map(|e| match LightPathExpression::parse(e) {
+                    Ok(expression) => FilteredPathTracer::new(tracer, expression),
+                    Err(error) => {
+                        eprintln!("error: {}", error);
+                        process::exit(1)
+                    }
+                })
+                .collect()
+        });
+    let res = Resolution::new(settings.width, settings.height);
+    let tiler = TiledRenderer::new(16, passes).threads(threads);
+    let remote = matches.value_of("serve").map(|address| {
+        let listener = TcpListener::bind(address).unwrap_or_else(|e| {
+            eprintln!("error: could not listen on {}: {}", address, e);
+            process::exit(1)
+        });
+        eprintln!("waiting for workers on {}", address);
+        let job = serve_job(&matches, &settings, camera(0.0).camera);
+        (Coordinator::new(listener, tiler), job)
+    });
+    let restir = match matches.value_of("integrator") {
+        Some("restir") => Some((restir(&settings), settings.samples * passes)),
+        _ => None,
+    };
+    let mut backend = Backend {
+        tiler,
+        remote,
+        restir,
+    };
+    let tonemap = tonemap(&matches);
+    let reference = matches.value_of("reference").map(|path| {
+        compare::load(path).unwrap_or_else(|e| {
+            eprintln!("error: could not load reference {}: {}", path, e);
+            process::exit(1)
+        })
+    });
+    let output = Output {
+        path: Path::new(output),
+        exposure: settings.exposure,
+        tonemap: &tonemap,
+        save_interval: value_t!(matches, "save-interval", u64).unwrap_or_else(|e| e.exit()),
+        checkpoint: matches.value_of("checkpoint").map(Path::new),
+        aovs: &aovs,
+        light_paths: &light_paths,
+        denoise: matches.is_present("denoise"),
+        auto_exposure: matches.value_of("auto-exposure").map(|_| {
+            value_t!(matches, "auto-exposure", Float).unwrap_or_else(|e| e.exit()) / 100.0
+        }),
+        verbose: matches.is_present("verbose"),
+        reference: reference.as_ref(),
+    };
+
+    let frames = match matches.value_of("frames") {
+        Some(frames) => Some(parse_frames(frames).unwrap_or_else(|| {
+            eprintln!("error: invalid frame range {}, expected start..end", frames);
+            process::exit(1)
+        })),
+        None => turntable_frames.map(|frames| 0..frames),
+    };
+
+    let seed = value_t!(matches, "seed", u64).unwrap_or_else(|e| e.exit());
+
+    match frames {
+        None => {
+            let (mut film, mut context) = match matches.value_of("resume") {
+                Some(path) => {
+                    // Continue with random numbers not used by earlier runs.
+                    let film = load_checkpoint(path, res);
+                    let done: u64 = film.sample_counts().iter().map(|&c| u64::from(c)).sum();
+                    (film, Context::seeded(seed, done))
+                }
+                None => (Film::new(res), Context::seeded(seed, 0)),
+            };
+
+            if let Err(e) = scene.set_time(0.0) {
+                eprintln!("warning: {}", e);
+            }
+            scene.set_shutter(0.0, exposure);
+            let view: Box<dyn Camera> = match matches.value_of("bake") {
+                Some(object) => Box::new(lightmap(&scene, object)),
+                None => Box::new(camera(0.0)),
+            };
+            let report = render(
+                &mut backend,
+                &*renderer,
+                &mut context,
+                &scene,
+                &*view,
+                &mut film,
+                &output,
+            );
+            eprintln!("{}", report);
+        }
+        Some(frames) => {
+            for frame in frames {
+                let time = frame as Float / settings.fps;
+                if let Err(e) = scene.set_time(time) {
+                    eprintln!("warning: frame {}: {}", frame, e);
+                }
+                scene.set_shutter(0.0, exposure);
+
+                // Every frame has its own random numbers, independent of
+                // the other frames rendered.
+                let mut context = Context::seeded(seed, u64::from(frame));
+                let mut film = Film::new(res);
+                let path = frame_path(output.path, frame);
+                let output = Output {
+                    path: &path,
+                    ..output
+                };
+
+                eprintln!("frame {}: {}", frame, path.display());
+                let report = render(
+                    &mut backend,
+                    &*renderer,
+                    &mut context,
+                    &scene,
+                    &camera(time),
+                    &mut film,
+                    &output,
+                );
+                eprintln!("{}", report);
+            }
+        }
+    }
+}
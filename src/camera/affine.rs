@@ -1,27 +1,143 @@
 use cgmath::prelude::*;
-use cgmath::Matrix4;
+use cgmath::{Matrix4, PerspectiveFov};
 
 use camera::{Camera, Resolution, Target};
 use geometry::{Point, Ray, Vector};
+use scene::invert;
+use Error;
+use Float;
 
 /// A camera model defined by an affine transformation matrix.
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub struct AffineTransformCamera {
     /// The transformation matrix.
-    pub transform: Matrix4<f32>,
+    pub transform: Matrix4<Float>,
 }
 
 impl AffineTransformCamera {
     /// Creates a new `AffineTransformCamera` with a given
     /// transformation matrix.
-    pub fn new(transform: Matrix4<f32>) -> Self {
+    pub fn new(transform: Matrix4<Float>) -> Self {
         AffineTransformCamera { transform }
     }
+
+    /// Creates a perspective camera at `eye`, looking at `center`,
+    /// with the given projection.
+    ///
+    /// # Panics
+    /// Panics if the view is degenerate; see `try_look_at`.
+    ///
+    /// # Examples
+    /// ```
+    /// # extern crate cgmath;
+    /// # extern crate lucifer;
+    /// use cgmath::{Deg, PerspectiveFov, Rad};
+    /// use lucifer::camera::{AffineTransformCamera, Camera, Resolution, Target};
+    /// use lucifer::geometry::{Point, Vector};
+    ///
+    /// # fn main() {
+    /// let projection = PerspectiveFov {
+    ///     fovy: Rad::from(Deg(40.0)),
+    ///     aspect: 1.0,
+    ///     near: 1.0,
+    ///     far: 100.0,
+    /// };
+    /// let camera = AffineTransformCamera::look_at(
+    ///     Point::new(0.0, 0.0, 5.0),
+    ///     Point::new(0.0, 0.0, 0.0),
+    ///     Vector::new(0.0, 1.0, 0.0),
+    ///     projection,
+    /// );
+    ///
+    /// // The center of the image looks straight at `center`.
+    /// let ray = camera.primary(Resolution::new(1, 1), Target::new(0, 0));
+    /// assert!((ray.origin.z - 4.0).abs() < 1e-4);
+    /// assert!(ray.direction.x.abs() < 1e-4 && ray.direction.z < 0.0);
+    /// # }
+    /// ```
+    pub fn look_at(
+        eye: Point,
+        center: Point,
+        up: Vector,
+        projection: PerspectiveFov<Float>,
+    ) -> Self {
+        AffineTransformCamera::try_look_at(eye, center, up, projection)
+            .unwrap_or_else(|e| panic!("invalid camera: {}", e))
+    }
+
+    /// Creates a perspective camera at `eye`, looking at `center`,
+    /// with the given projection, failing if the view is degenerate,
+    /// e.g. because `eye` and `center` coincide.
+    ///
+    /// # Examples
+    /// ```
+    /// # extern crate cgmath;
+    /// # extern crate lucifer;
+    /// use cgmath::{Deg, PerspectiveFov, Rad};
+    /// use lucifer::camera::AffineTransformCamera;
+    /// use lucifer::geometry::{Point, Vector};
+    ///
+    /// # fn main() {
+    /// let projection = PerspectiveFov {
+    ///     fovy: Rad::from(Deg(40.0)),
+    ///     aspect: 1.0,
+    ///     near: 1.0,
+    ///     far: 100.0,
+    /// };
+    /// let eye = Point::new(0.0, 0.0, 5.0);
+    /// let up = Vector::new(0.0, 1.0, 0.0);
+    ///
+    /// assert!(AffineTransformCamera::try_look_at(eye, eye, up, projection).is_err());
+    /// # }
+    /// ```
+    pub fn try_look_at(
+        eye: Point,
+        center: Point,
+        up: Vector,
+        projection: PerspectiveFov<Float>,
+    ) -> Result<Self, Error> {
+        let proj = Matrix4::from(projection);
+        let view = Matrix4::look_at(eye, center, up);
+
+        Ok(AffineTransformCamera::new(invert(proj.concat(&view))?))
+    }
+
+    /// The cameras of the left and right eye of a parallel stereo rig,
+    /// moved apart by `separation` along the horizontal axis of the
+    /// image, e.g. `0.065` for human eyes in meters.
+    ///
+    /// # Examples
+    /// ```
+    /// # extern crate cgmath;
+    /// # extern crate lucifer;
+    /// use cgmath::{Matrix4, SquareMatrix};
+    /// use lucifer::camera::{AffineTransformCamera, Camera, Resolution, Target};
+    ///
+    /// # fn main() {
+    /// let camera = AffineTransformCamera::new(Matrix4::identity());
+    /// let (left, right) = camera.stereo(0.5);
+    ///
+    /// let (res, target) = (Resolution::new(1, 1), Target::new(0, 0));
+    /// assert!((left.primary(res, target).origin.x + 0.25).abs() < 1e-6);
+    /// assert!((right.primary(res, target).origin.x - 0.25).abs() < 1e-6);
+    /// # }
+    /// ```
+    pub fn stereo(&self, separation: Float) -> (Self, Self) {
+        let at = |x: Float| self.transform.transform_point(Point::new(x, 0.0, -1.0));
+        let right = (at(1.0) - at(0.0)).normalize() * (separation / 2.0);
+
+        let moved = |offset: Vector| {
+            AffineTransformCamera::new(Matrix4::from_translation(offset) * self.transform)
+        };
+        (moved(-right), moved(right))
+    }
 }
 
-impl Camera for AffineTransformCamera {
-    fn primary(&self, resolution: Resolution, target: Target) -> Ray {
-        let (fx, fy) = target.normalized(resolution);
+impl AffineTransformCamera {
+    /// The ray through the point `(fx, fy)` of the image, in
+    /// normalized device coordinates.
+    fn through(&self, (fx, fy): (Float, Float)) -> Ray {
         let o = Point::new(fx, fy, -1.0);
         let t = o + Vector::new(0.0, 0.0, 2.0);
 
@@ -32,3 +148,19 @@ impl Camera for AffineTransformCamera {
         Ray::new(origin, direction)
     }
 }
+
+impl Camera for AffineTransformCamera {
+    fn primary(&self, resolution: Resolution, target: Target) -> Ray {
+        self.through(target.normalized(resolution))
+    }
+
+    fn sample_pixel(
+        &self,
+        resolution: Resolution,
+        target: Target,
+        pixel: (Float, Float),
+        _lens: (Float, Float),
+    ) -> Ray {
+        self.through(target.normalized_at(resolution, pixel))
+    }
+}
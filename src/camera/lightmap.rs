@@ -0,0 +1,195 @@
+use cgmath::prelude::*;
+use cgmath::vec3;
+
+use camera::{Camera, Resolution, Target};
+use geometry::{Intersection, Point, Ray, Vector};
+use montecarlo::warp::concentric_disk;
+use scene::Object;
+use Error;
+use Float;
+
+/// A camera gathering the light arriving at the surface of an object,
+/// for baking lightmaps.
+///
+/// Every pixel is a texel of the surface coordinates of the object's
+/// geometry: `u` runs along the columns, and `v` along the rows from
+/// the top.  Geometry that maps surface coordinates back onto its
+/// surface, like meshes with per-vertex coordinates, is rasterized
+/// through `Geometry::surface_at`: a texel gathers at its center if a
+/// triangle covers it, or else at a corner, which pads the charts of
+/// the layout by a texel.  Texels no triangle touches only see the
+/// environment, and can be masked out with `covers`.  Other geometry
+/// is laid out by `Geometry::sample_surface`.
+///
+/// Rays leave the surface in cosine-weighted directions around the
+/// outward normal, so a `PathTracer` converges to the irradiance at
+/// the texel divided by `π`: the radiance a white, diffuse surface
+/// would reflect there.  The object itself is part of the scene, and
+/// shadows, reflects, and lights as usual.
+///
+/// # Examples
+/// ```
+/// # extern crate cgmath;
+/// # extern crate lucifer;
+/// use cgmath::{InnerSpace, Matrix4, SquareMatrix};
+/// use lucifer::camera::{Camera, LightmapCamera, Resolution, Target};
+/// use lucifer::geometry::{Mesh, Plane, Point, Sphere, Vector};
+/// use lucifer::lighting::{Albedo, Lambert, Radiance};
+/// use lucifer::render::{Context, PathTracer, Renderer};
+/// use lucifer::scene::{Object, Scene};
+///
+/// # fn main() {
+/// // A sphere in a white furnace receives the same light everywhere.
+/// let mut scene = Scene::new(Radiance::gray(1.0));
+/// let index = scene.add(Object::new(
+///     Sphere::new(Point::new(0.0, 0.0, 0.0), 1.0),
+///     Lambert::new(Albedo::white()),
+///     Matrix4::identity(),
+/// ));
+///
+/// let camera = LightmapCamera::new(&scene.objects()[index]);
+/// let tracer = PathTracer::new(0.0, 4, 16);
+/// let mut context = Context::seeded(0, 0);
+///
+/// let texel = tracer.render(
+///     &mut context,
+///     &scene,
+///     &camera,
+///     Resolution::new(4, 4),
+///     Target::new(1, 2),
+/// );
+/// assert_eq!(texel, Radiance::gray(1.0));
+///
+/// // A square mesh, mapped onto the left half of the lightmap.
+/// let square = Mesh::new(
+///     vec![
+///         Point::new(0.0, 0.0, 0.0),
+///         Point::new(1.0, 0.0, 0.0),
+///         Point::new(1.0, 1.0, 0.0),
+///         Point::new(0.0, 1.0, 0.0),
+///     ],
+///     None,
+///     vec![[0, 1, 2], [0, 2, 3]],
+/// )
+/// .with_uvs(vec![(0.0, 1.0), (0.5, 1.0), (0.5, 0.0), (0.0, 0.0)]);
+/// let index = scene.add(Object::new(square, Lambert::new(Albedo::white()), Matrix4::identity()));
+///
+/// let camera = LightmapCamera::new(&scene.objects()[index]);
+/// let resolution = Resolution::new(8, 4);
+/// assert!(camera.covers(resolution, Target::new(3, 0)));
+/// assert!(camera.covers(resolution, Target::new(4, 0)));
+/// assert!(!camera.covers(resolution, Target::new(5, 0)));
+///
+/// // The top left texel lies at the top left corner of the square.
+/// let ray = camera.primary(resolution, Target::new(0, 0));
+/// assert!((ray.origin - Point::new(0.125, 0.875, 0.0)).magnitude() < 1e-3);
+/// assert!(ray.direction.z > 0.99);
+///
+/// // Planes are infinite, and cannot be mapped onto an image.
+/// let floor = Object::new(
+///     Plane::new(Vector::new(0.0, 1.0, 0.0), 0.0),
+///     Lambert::new(Albedo::white()),
+///     Matrix4::identity(),
+/// );
+/// assert!(LightmapCamera::try_new(&floor).is_err());
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct LightmapCamera {
+    object: Object,
+    /// Whether the geometry maps surface coordinates back onto its
+    /// surface.
+    mapped: bool,
+}
+
+impl LightmapCamera {
+    /// Creates a new `LightmapCamera` for the surface of `object`.
+    ///
+    /// # Panics
+    /// Panics if the geometry of `object` has no surface
+    /// parameterization; see `try_new`.
+    pub fn new(object: &Object) -> Self {
+        LightmapCamera::try_new(object).unwrap_or_else(|e| panic!("invalid lightmap: {}", e))
+    }
+
+    /// Creates a new `LightmapCamera` for the surface of `object`,
+    /// failing if its geometry has no surface parameterization.
+    pub fn try_new(object: &Object) -> Result<Self, Error> {
+        let mapped = object.surface_at(0.5, 0.5).is_ok();
+        if !mapped && object.sample_surface(0.5, 0.5).is_none() {
+            return Err(Error::Unsupported(
+                "lightmaps of geometry without a surface parameterization".to_string(),
+            ));
+        }
+
+        Ok(LightmapCamera {
+            object: object.clone(),
+            mapped,
+        })
+    }
+
+    /// Whether the texel at `target` covers any of the surface.
+    pub fn covers(&self, resolution: Resolution, target: Target) -> bool {
+        self.texel(resolution, target).is_some()
+    }
+
+    /// The position and outward normal the texel at `target` gathers
+    /// light at, if it covers any of the surface.
+    fn texel(&self, resolution: Resolution, target: Target) -> Option<(Point, Vector)> {
+        let (width, height) = (resolution.width as Float, resolution.height as Float);
+        let u = (target.x as Float + 0.5) / width;
+        let v = (target.y as Float + 0.5) / height;
+        if !self.mapped {
+            return self.object.sample_surface(u, v);
+        }
+
+        let (du, dv) = (0.5 / width, 0.5 / height);
+        [(0.0, 0.0), (-du, -dv), (du, -dv), (-du, dv), (du, dv)]
+            .iter()
+            .find_map(|&(du, dv)| self.object.surface_at(u + du, v + dv).ok().flatten())
+    }
+}
+
+impl Camera for LightmapCamera {
+    fn primary(&self, resolution: Resolution, target: Target) -> Ray {
+        self.sample(resolution, target, (0.5, 0.5))
+    }
+
+    fn sample(&self, resolution: Resolution, target: Target, lens: (Float, Float)) -> Ray {
+        let (position, normal) = match self.texel(resolution, target) {
+            Some(texel) => texel,
+            None => {
+                // Nothing to gather at, so only the environment is seen.
+                return Ray {
+                    origin: self.object.transform.transform_point(Point::origin()),
+                    direction: Vector::unit_z(),
+                    length: 0.0,
+                    time: 0.0,
+                };
+            }
+        };
+
+        // Project the unit disk onto the hemisphere, which distributes
+        // directions by the cosine to the normal.
+        let (p, _) = concentric_disk(lens.0, lens.1);
+        let z = (1.0 - p.x * p.x - p.y * p.y).max(0.0).sqrt();
+
+        let tangent = if normal[0].abs() > normal[1].abs() {
+            vec3(normal[2], 0.0, -normal[0]).normalize()
+        } else {
+            vec3(0.0, normal[2], -normal[1]).normalize()
+        };
+        let bitangent = normal.cross(tangent);
+        let direction: Vector = (tangent * p.x + bitangent * p.y + normal * z).normalize();
+
+        let surface = Intersection {
+            position,
+            normal,
+            lambda: 0.0,
+            inside: false,
+            primitive: 0,
+            uv: (0.0, 0.0),
+        };
+        surface.spawn(direction)
+    }
+}
@@ -1,15 +1,28 @@
 //! Camera types and view mapping
 
-use geometry::Ray;
+use geometry::{Ray, Vector};
+use Error;
 
 pub mod affine;
+pub mod lightmap;
+pub mod polarizer;
+pub mod probe;
+pub mod shutter;
+pub mod thinlens;
 pub mod tonemap;
 
 pub use self::affine::AffineTransformCamera;
+pub use self::lightmap::LightmapCamera;
+pub use self::polarizer::PolarizerCamera;
+pub use self::probe::ProbeCamera;
+pub use self::shutter::ShutterCamera;
+pub use self::thinlens::ThinLensCamera;
 pub use self::tonemap::Tonemap;
+use Float;
 
 /// A pair of `u32` representing the resolution of an image.
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub struct Resolution {
     /// The horizontal resolution.
     pub width: u32,
@@ -32,10 +45,31 @@ impl Resolution {
     pub fn new(width: u32, height: u32) -> Self {
         Resolution { width, height }
     }
+
+    /// Creates a new `Resolution`, failing if the image would be
+    /// empty.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::camera::Resolution;
+    ///
+    /// assert!(Resolution::try_new(1024, 768).is_ok());
+    /// assert!(Resolution::try_new(1024, 0).is_err());
+    /// ```
+    pub fn try_new(width: u32, height: u32) -> Result<Self, Error> {
+        if width == 0 || height == 0 {
+            return Err(Error::InvalidSettings(format!(
+                "empty image of {}x{} pixels",
+                width, height
+            )));
+        }
+        Ok(Resolution::new(width, height))
+    }
 }
 
 /// A pixel-position within an image buffer.
 #[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub struct Target {
     /// The horizontal position.
     pub x: u32,
@@ -71,21 +105,87 @@ impl Target {
     /// let res = Resolution::new(2, 2);
     /// let t = Target::new(1, 0);
     ///
-    /// assert_eq!(t.normalized(&res), (0.5, 0.5))
+    /// assert_eq!(t.normalized(res), (0.5, 0.5))
     /// ```
-    pub fn normalized(self, resolution: Resolution) -> (f32, f32) {
-        let step_x = 2.0 / (resolution.width as f32);
-        let step_y = 2.0 / (resolution.height as f32);
-        let fx = (self.x as f32) * step_x;
-        let fy = (self.y as f32) * step_y;
+    pub fn normalized(self, resolution: Resolution) -> (Float, Float) {
+        let step_x = 2.0 / (resolution.width as Float);
+        let step_y = 2.0 / (resolution.height as Float);
+        let fx = (self.x as Float) * step_x;
+        let fy = (self.y as Float) * step_y;
 
         (fx - 1.0 + 0.5 * step_x, 1.0 - fy - 0.5 * step_y)
     }
+
+    /// Map the point `pixel`, given in the unit square, within the
+    /// pixel to the unit square of the image like `normalized`, which
+    /// maps the center `(0.5, 0.5)`.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::camera::{Resolution, Target};
+    ///
+    /// let res = Resolution::new(2, 2);
+    /// let t = Target::new(1, 0);
+    ///
+    /// assert_eq!(t.normalized_at(res, (0.5, 0.5)), t.normalized(res));
+    /// assert_eq!(t.normalized_at(res, (0.0, 0.0)), (0.0, 1.0));
+    /// ```
+    pub fn normalized_at(self, resolution: Resolution, pixel: (Float, Float)) -> (Float, Float) {
+        let step_x = 2.0 / (resolution.width as Float);
+        let step_y = 2.0 / (resolution.height as Float);
+        let fx = (self.x as Float + pixel.0) * step_x;
+        let fy = (self.y as Float + pixel.1) * step_y;
+
+        (fx - 1.0, 1.0 - fy)
+    }
 }
 
 /// A Trait describing a camera.
-pub trait Camera {
+///
+/// Cameras are shared between rendering threads, so they must be
+/// `Send` and `Sync`.
+pub trait Camera: Send + Sync {
     /// Construct a `Ray` to compute the light reaching a given
     /// `Target` in a render buffer of a given `Resolution`.
     fn primary(&self, resolution: Resolution, target: Target) -> Ray;
+
+    /// Construct a `Ray` like `primary`, but passing through the point
+    /// `lens`, given in the unit square, of the camera's aperture.
+    /// Cameras without an aperture ignore `lens`.
+    fn sample(&self, resolution: Resolution, target: Target, lens: (Float, Float)) -> Ray {
+        let _ = lens;
+        self.primary(resolution, target)
+    }
+
+    /// Construct a `Ray` like `sample`, but through the point `pixel`,
+    /// given in the unit square, of the `target` pixel instead of its
+    /// center, to antialias the image.  Cameras ignore `pixel` by
+    /// default.
+    fn sample_pixel(
+        &self,
+        resolution: Resolution,
+        target: Target,
+        pixel: (Float, Float),
+        lens: (Float, Float),
+    ) -> Ray {
+        let _ = pixel;
+        self.sample(resolution, target, lens)
+    }
+
+    /// The interval of time, relative to the time of the scene, the
+    /// shutter is open for.  Renderers spread the `time` of the rays
+    /// over it to blur motion; see `Scene::set_shutter`.  The default
+    /// is an instant.
+    fn shutter(&self) -> (Float, Float) {
+        (0.0, 0.0)
+    }
+
+    /// The axis, perpendicular to `ray`, of a linear polarizer in
+    /// front of the camera, passing the light polarized along it.
+    /// Only renderers tracing polarization heed it; the default is no
+    /// polarizer.
+    fn polarizer(&self, ray: &Ray) -> Option<Vector> {
+        let _ = ray;
+        None
+    }
 }
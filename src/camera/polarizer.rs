@@ -0,0 +1,91 @@
+use cgmath::prelude::*;
+
+use camera::{Camera, Resolution, Target};
+use geometry::{Ray, Vector};
+use Float;
+
+/// A camera behind a linear polarizing filter, e.g. to remove the
+/// glare of water or glass.
+///
+/// The camera forms its rays with another camera, and only adds the
+/// filter, which passes the light polarized along `axis`.  Rays not
+/// perpendicular to the axis see its projection onto the plane
+/// perpendicular to them.  Only a `PathTracer` with polarization
+/// enabled heeds the filter; see `PathTracer::with_polarization`.
+///
+/// # Examples
+/// ```
+/// # extern crate cgmath;
+/// # extern crate lucifer;
+/// use cgmath::InnerSpace;
+/// use lucifer::camera::{Camera, PolarizerCamera, ProbeCamera};
+/// use lucifer::geometry::{Point, Ray, Vector};
+///
+/// # fn main() {
+/// let probe = ProbeCamera::new(Point::new(0.0, 0.0, 0.0));
+/// assert_eq!(probe.polarizer(&Ray::new(probe.position, Vector::unit_x())), None);
+///
+/// // A vertical filter, as in polarized sunglasses.
+/// let camera = PolarizerCamera::new(probe, Vector::unit_z());
+/// let tilted = Ray::new(probe.position, Vector::new(1.0, 0.0, 1.0));
+/// let axis = camera.polarizer(&tilted).unwrap();
+///
+/// assert!((axis - Vector::new(-0.5, 0.0, 0.5).normalize()).magnitude() < 1e-6);
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct PolarizerCamera<C> {
+    /// The camera forming the rays.
+    pub camera: C,
+    /// The direction of the polarization passed by the filter.
+    pub axis: Vector,
+}
+
+impl<C: Camera> PolarizerCamera<C> {
+    /// Creates a new `PolarizerCamera` around `camera`, passing the
+    /// light polarized along `axis`.
+    pub fn new(camera: C, axis: Vector) -> Self {
+        PolarizerCamera { camera, axis }
+    }
+}
+
+impl<C: Camera> Camera for PolarizerCamera<C> {
+    fn primary(&self, resolution: Resolution, target: Target) -> Ray {
+        self.camera.primary(resolution, target)
+    }
+
+    fn sample(&self, resolution: Resolution, target: Target, lens: (Float, Float)) -> Ray {
+        self.camera.sample(resolution, target, lens)
+    }
+
+    fn sample_pixel(
+        &self,
+        resolution: Resolution,
+        target: Target,
+        pixel: (Float, Float),
+        lens: (Float, Float),
+    ) -> Ray {
+        self.camera.sample_pixel(resolution, target, pixel, lens)
+    }
+
+    fn shutter(&self) -> (Float, Float) {
+        self.camera.shutter()
+    }
+
+    fn polarizer(&self, ray: &Ray) -> Option<Vector> {
+        let direction = ray.direction.normalize();
+        let axis = self.axis - direction * direction.dot(self.axis);
+        if axis.magnitude2() > 1e-12 {
+            return Some(axis.normalize());
+        }
+
+        // Looking along the axis, the filter is turned arbitrarily.
+        let other = if direction.x.abs() < 0.5 {
+            Vector::unit_x()
+        } else {
+            Vector::unit_y()
+        };
+        Some(direction.cross(other).normalize())
+    }
+}
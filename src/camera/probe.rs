@@ -0,0 +1,62 @@
+use camera::{Camera, Resolution, Target};
+use geometry::{Point, Ray, Vector};
+use montecarlo::warp::uniform_sphere;
+use Float;
+
+/// A camera seeing all directions around a point, for probing the
+/// light arriving there.
+///
+/// The image maps the unit sphere onto equal areas: rows run from
+/// `+z` at the top to `-z` at the bottom, evenly spaced in `z`, and
+/// columns go once around the `z` axis.  Every pixel thus covers the
+/// same solid angle, `4π / (width * height)`, and a renderer computes
+/// the mean radiance arriving from it.  Samples are spread over the
+/// solid angle of the pixel by the lens position.
+///
+/// # Examples
+/// ```
+/// use lucifer::camera::{Camera, ProbeCamera, Resolution, Target};
+/// use lucifer::geometry::Point;
+///
+/// let camera = ProbeCamera::new(Point::new(1.0, 2.0, 3.0));
+/// let res = Resolution::new(4, 4);
+///
+/// let up = camera.sample(res, Target::new(0, 0), (0.0, 0.0));
+/// assert_eq!(up.origin, Point::new(1.0, 2.0, 3.0));
+/// assert_eq!(up.direction.z, 1.0);
+///
+/// let down = camera.sample(res, Target::new(3, 3), (1.0, 1.0));
+/// assert_eq!(down.direction.z, -1.0);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct ProbeCamera {
+    /// The point probed.
+    pub position: Point,
+}
+
+impl ProbeCamera {
+    /// Creates a new `ProbeCamera` at `position`.
+    pub fn new(position: Point) -> Self {
+        ProbeCamera { position }
+    }
+
+    /// The direction to the center of `target`.
+    pub fn direction(&self, resolution: Resolution, target: Target) -> Vector {
+        self.primary(resolution, target).direction
+    }
+}
+
+impl Camera for ProbeCamera {
+    fn primary(&self, resolution: Resolution, target: Target) -> Ray {
+        self.sample(resolution, target, (0.5, 0.5))
+    }
+
+    fn sample(&self, resolution: Resolution, target: Target, lens: (Float, Float)) -> Ray {
+        let u = (target.y as Float + lens.1) / resolution.height as Float;
+        let v = (target.x as Float + lens.0) / resolution.width as Float;
+        let (direction, _) = uniform_sphere(u, v);
+
+        Ray::new(self.position, direction)
+    }
+}
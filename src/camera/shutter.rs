@@ -0,0 +1,74 @@
+use camera::{Camera, Resolution, Target};
+use geometry::{Ray, Vector};
+use Float;
+
+/// A camera whose shutter stays open for an interval of time, giving
+/// moving objects motion blur.
+///
+/// The camera forms its rays with another camera, and only adds the
+/// shutter.  The scene must be given the same interval with
+/// `Scene::set_shutter`.
+///
+/// # Examples
+/// ```
+/// use lucifer::camera::{Camera, ProbeCamera, ShutterCamera};
+/// use lucifer::geometry::Point;
+///
+/// let probe = ProbeCamera::new(Point::new(0.0, 0.0, 0.0));
+/// assert_eq!(probe.shutter(), (0.0, 0.0));
+///
+/// // A 180 degree shutter at 24 frames per second.
+/// let camera = ShutterCamera::new(probe, 0.0, 1.0 / 48.0);
+/// assert_eq!(camera.shutter(), (0.0, 1.0 / 48.0));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct ShutterCamera<C> {
+    /// The camera forming the rays.
+    pub camera: C,
+    /// The time the shutter opens.
+    pub open: Float,
+    /// The time the shutter closes.
+    pub close: Float,
+}
+
+impl<C: Camera> ShutterCamera<C> {
+    /// Creates a new `ShutterCamera` around `camera`, open from
+    /// `open` to `close`.  A shutter closing before it opens stays
+    /// closed.
+    pub fn new(camera: C, open: Float, close: Float) -> Self {
+        ShutterCamera {
+            camera,
+            open,
+            close,
+        }
+    }
+}
+
+impl<C: Camera> Camera for ShutterCamera<C> {
+    fn primary(&self, resolution: Resolution, target: Target) -> Ray {
+        self.camera.primary(resolution, target)
+    }
+
+    fn sample(&self, resolution: Resolution, target: Target, lens: (Float, Float)) -> Ray {
+        self.camera.sample(resolution, target, lens)
+    }
+
+    fn sample_pixel(
+        &self,
+        resolution: Resolution,
+        target: Target,
+        pixel: (Float, Float),
+        lens: (Float, Float),
+    ) -> Ray {
+        self.camera.sample_pixel(resolution, target, pixel, lens)
+    }
+
+    fn shutter(&self) -> (Float, Float) {
+        (self.open, self.close)
+    }
+
+    fn polarizer(&self, ray: &Ray) -> Option<Vector> {
+        self.camera.polarizer(ray)
+    }
+}
@@ -0,0 +1,218 @@
+use cgmath::prelude::*;
+use cgmath::{vec2, Point3, Vector2, Vector4};
+
+use camera::{AffineTransformCamera, Camera, Resolution, Target};
+use consts::{PI, SQRT_2};
+use geometry::{Point, Ray, Vector};
+use montecarlo::warp::{concentric_disk, uniform_triangle};
+use Float;
+
+/// A perspective camera with a lens, giving depth of field.
+///
+/// Only objects at the focus distance are sharp; the larger the
+/// aperture, the blurrier everything closer or farther away.  Out of
+/// focus highlights take the shape of the aperture: a circle, or the
+/// polygon formed by the blades of a diaphragm.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct ThinLensCamera {
+    /// The pinhole camera through the center of the lens.
+    pub pinhole: AffineTransformCamera,
+    /// The radius of the lens.
+    pub aperture: Float,
+    /// The distance of the plane in focus, along the viewing
+    /// direction.
+    pub focus: Float,
+    /// The number of straight blades forming the aperture, or `0`
+    /// for a circular aperture.
+    pub blades: u32,
+    /// The rotation of the blades, in radians.
+    pub rotation: Float,
+    /// How much the aperture is squashed towards the center of the
+    /// image at its corners, from `0` to `1`, giving the cat's-eye
+    /// highlights of optical vignetting.
+    pub cat_eye: Float,
+    eye: Point,
+    forward: Vector,
+    right: Vector,
+    up: Vector,
+}
+
+impl ThinLensCamera {
+    /// Creates a new `ThinLensCamera` around the perspective camera
+    /// `pinhole`, with a lens of radius `aperture` focused at
+    /// distance `focus`.
+    ///
+    /// # Examples
+    /// ```
+    /// # extern crate cgmath;
+    /// # extern crate lucifer;
+    /// use cgmath::{Deg, PerspectiveFov, Rad};
+    /// use lucifer::camera::{AffineTransformCamera, Camera, Resolution, Target, ThinLensCamera};
+    /// use lucifer::geometry::{Point, Vector};
+    ///
+    /// # fn main() {
+    /// let pinhole = AffineTransformCamera::look_at(
+    ///     Point::new(0.0, 0.0, 5.0),
+    ///     Point::new(0.0, 0.0, 0.0),
+    ///     Vector::new(0.0, 1.0, 0.0),
+    ///     PerspectiveFov {
+    ///         fovy: Rad::from(Deg(40.0)),
+    ///         aspect: 1.0,
+    ///         near: 1.0,
+    ///         far: 100.0,
+    ///     },
+    /// );
+    /// let camera = ThinLensCamera::new(pinhole, 0.5, 5.0);
+    ///
+    /// // Rays leave from the edge of the lens, and meet in focus.
+    /// let (res, target) = (Resolution::new(1, 1), Target::new(0, 0));
+    /// let ray = camera.sample(res, target, (1.0, 0.5));
+    /// assert!((ray.origin.x - 0.5).abs() < 1e-4);
+    ///
+    /// let focus = ray.origin + ray.direction * (5.0 / -ray.direction.z);
+    /// assert!(focus.x.abs() < 1e-4 && focus.z.abs() < 1e-4);
+    /// # }
+    /// ```
+    pub fn new(pinhole: AffineTransformCamera, aperture: Float, focus: Float) -> Self {
+        let transform = pinhole.transform;
+        let at = |x: Float, y: Float, z: Float| transform.transform_point(Point::new(x, y, z));
+
+        // The eye is where the clip space's point at infinity along
+        // the depth axis ends up.
+        let eye = transform * Vector4::new(0.0, 0.0, 1.0, 0.0);
+        let eye = Point::from_homogeneous(eye);
+
+        let center = at(0.0, 0.0, -1.0);
+        ThinLensCamera {
+            pinhole,
+            aperture,
+            focus,
+            blades: 0,
+            rotation: 0.0,
+            cat_eye: 0.0,
+            eye,
+            forward: (at(0.0, 0.0, 1.0) - center).normalize(),
+            right: (at(1.0, 0.0, -1.0) - center).normalize(),
+            up: (at(0.0, 1.0, -1.0) - center).normalize(),
+        }
+    }
+
+    /// Forms the aperture from `blades` straight blades, rotated by
+    /// `rotation` radians, inscribed in the circle of the lens.  Fewer
+    /// than three blades leave the aperture circular.
+    ///
+    /// # Examples
+    /// ```
+    /// # extern crate cgmath;
+    /// # extern crate lucifer;
+    /// use cgmath::{Deg, PerspectiveFov, Rad};
+    /// use lucifer::camera::{AffineTransformCamera, Camera, Resolution, Target, ThinLensCamera};
+    /// use lucifer::geometry::{Point, Vector};
+    /// use lucifer::Float;
+    ///
+    /// # fn main() {
+    /// let pinhole = AffineTransformCamera::look_at(
+    ///     Point::new(0.0, 0.0, 5.0),
+    ///     Point::new(0.0, 0.0, 0.0),
+    ///     Vector::new(0.0, 1.0, 0.0),
+    ///     PerspectiveFov {
+    ///         fovy: Rad::from(Deg(40.0)),
+    ///         aspect: 1.0,
+    ///         near: 1.0,
+    ///         far: 100.0,
+    ///     },
+    /// );
+    /// let camera = ThinLensCamera::new(pinhole, 0.5, 5.0).blades(4, 0.0);
+    ///
+    /// // Four blades make a square with its corners on the axes.
+    /// let (res, target) = (Resolution::new(1, 1), Target::new(0, 0));
+    /// for i in 0..100 {
+    ///     let lens = ((i % 10) as Float / 10.0, (i / 10) as Float / 10.0);
+    ///     let origin = camera.sample(res, target, lens).origin;
+    ///     assert!(origin.x.abs() + origin.y.abs() <= 0.5 + 1e-4);
+    /// }
+    /// # }
+    /// ```
+    pub fn blades(self, blades: u32, rotation: Float) -> Self {
+        let mut camera = self;
+        camera.blades = blades;
+        camera.rotation = rotation;
+        camera
+    }
+
+    /// Squashes the aperture towards the center of the image, by
+    /// `cat_eye` at the corners, giving cat's-eye shaped highlights.
+    pub fn cat_eye(self, cat_eye: Float) -> Self {
+        let mut camera = self;
+        camera.cat_eye = cat_eye;
+        camera
+    }
+
+    /// Maps `lens`, given in the unit square, onto the aperture of
+    /// radius one for the pixel at `position`, in normalized device
+    /// coordinates.
+    fn aperture_point(&self, lens: (Float, Float), position: (Float, Float)) -> Vector2<Float> {
+        let point = if self.blades >= 3 {
+            // Pick a triangle between the center and a side of the
+            // polygon, reusing the remainder of `u` within it.
+            let n = self.blades as Float;
+            let k = (lens.0 * n).floor().min(n - 1.0);
+            let corner = |k: Float| {
+                let angle = self.rotation + 2.0 * PI * k / n;
+                Point3::new(angle.cos(), angle.sin(), 0.0)
+            };
+            let (p, _) = uniform_triangle(
+                Point3::new(0.0, 0.0, 0.0),
+                corner(k),
+                corner(k + 1.0),
+                lens.0 * n - k,
+                lens.1,
+            );
+            vec2(p.x, p.y)
+        } else {
+            concentric_disk(lens.0, lens.1).0
+        };
+
+        let radial = vec2(position.0, position.1);
+        let distance = radial.magnitude();
+        if self.cat_eye <= 0.0 || distance == 0.0 {
+            return point;
+        }
+
+        // Compress the aperture along the direction to the center,
+        // the more the farther the pixel is from it.
+        let direction = radial / distance;
+        let squash = self.cat_eye * (distance / SQRT_2).min(1.0);
+        point - direction * (point.dot(direction) * squash)
+    }
+}
+
+impl Camera for ThinLensCamera {
+    fn primary(&self, resolution: Resolution, target: Target) -> Ray {
+        self.pinhole.primary(resolution, target)
+    }
+
+    fn sample(&self, resolution: Resolution, target: Target, lens: (Float, Float)) -> Ray {
+        self.sample_pixel(resolution, target, (0.5, 0.5), lens)
+    }
+
+    fn sample_pixel(
+        &self,
+        resolution: Resolution,
+        target: Target,
+        pixel: (Float, Float),
+        lens: (Float, Float),
+    ) -> Ray {
+        let ray = self.pinhole.sample_pixel(resolution, target, pixel, lens);
+        if self.aperture <= 0.0 {
+            return ray;
+        }
+
+        let focus = self.eye + ray.direction * (self.focus / ray.direction.dot(self.forward));
+        let point = self.aperture_point(lens, target.normalized_at(resolution, pixel));
+        let origin = self.eye + (self.right * point.x + self.up * point.y) * self.aperture;
+
+        Ray::new(origin, focus - origin)
+    }
+}
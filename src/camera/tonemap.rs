@@ -1,12 +1,18 @@
+use Float;
+
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub enum Tonemap {
     Linear,
-    Gamma(f32),
-    Reinhard(f32),
+    Gamma(Float),
+    Reinhard(Float),
     Filmic,
+    /// Narkowicz's fit of the ACES filmic curve, including the
+    /// display gamma.
+    Aces,
 }
 
 impl Tonemap {
-    pub fn apply(&self, c: f32) -> f32 {
+    pub fn apply(&self, c: Float) -> Float {
         match *self {
             Tonemap::Linear => c,
             Tonemap::Gamma(g) => c.powf(1.0 / g),
@@ -15,6 +21,11 @@ impl Tonemap {
                 let x = (c - 0.004).max(0.0);
                 (x * (6.2 * x + 0.5)) / (x * (6.2 * x + 1.7) + 0.06)
             }
+            Tonemap::Aces => {
+                let x = c.max(0.0) * 0.6;
+                let y = (x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14);
+                y.clamp(0.0, 1.0).powf(1.0 / 2.2)
+            }
         }
     }
 }
@@ -0,0 +1,126 @@
+use std::error;
+use std::fmt;
+use std::io;
+use std::result;
+
+use scene::{ObjectError, SceneError};
+
+#[cfg(feature = "scene-file")]
+use scene::file::LoadError;
+#[cfg(feature = "usd")]
+use scene::usd::UsdError;
+
+/// Any error reported by lucifer.
+///
+/// The more specific errors of the individual modules, like
+/// `ObjectError` or `LoadError`, convert into `Error`, so applications
+/// can handle all of them with `?` and a single error type.
+///
+/// # Examples
+/// ```
+/// use lucifer::cgmath::Matrix4;
+/// use lucifer::geometry::{Point, Sphere};
+/// use lucifer::lighting::{Albedo, Lambert, Radiance};
+/// use lucifer::scene::{Object, ObjectError, Scene};
+/// use lucifer::{Error, Float};
+///
+/// fn build(scale: Float) -> lucifer::Result<Scene> {
+///     let sphere = Sphere::new(Point::new(0.0, 0.0, 0.0), 1.0);
+///     let material = Lambert::new(Albedo::gray(0.8));
+///
+///     let mut scene = Scene::new(Radiance::none());
+///     scene.add(Object::try_new(sphere, material, Matrix4::from_scale(scale))?);
+///     Ok(scene)
+/// }
+///
+/// assert!(build(2.0).is_ok());
+/// match build(0.0) {
+///     Err(Error::InvalidTransform(e)) => assert_eq!(e, ObjectError::SingularTransform),
+///     _ => panic!("expected an invalid transform"),
+/// }
+/// ```
+#[derive(Debug)]
+pub enum Error {
+    /// A transformation is not finite, or cannot be inverted.
+    InvalidTransform(ObjectError),
+    /// Some objects of a scene are invalid.
+    InvalidScene(SceneError),
+    /// Settings that cannot be rendered, e.g. an empty image.
+    InvalidSettings(String),
+    /// A file uses a format or feature that is not supported.
+    Unsupported(String),
+    /// Reading or writing a file failed.
+    Io(io::Error),
+    /// A file could not be parsed, or describes an impossible scene.
+    Parse(String),
+}
+
+/// The result of fallible operations of lucifer.
+pub type Result<T> = result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::InvalidTransform(ref e) => write!(f, "invalid transformation: {}", e),
+            Error::InvalidScene(ref e) => write!(f, "{}", e),
+            Error::InvalidSettings(ref message) => write!(f, "invalid settings: {}", message),
+            Error::Unsupported(ref message) => write!(f, "unsupported: {}", message),
+            Error::Io(ref e) => write!(f, "{}", e),
+            Error::Parse(ref message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            Error::InvalidTransform(ref e) => Some(e),
+            Error::InvalidScene(ref e) => Some(e),
+            Error::Io(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<ObjectError> for Error {
+    fn from(e: ObjectError) -> Error {
+        Error::InvalidTransform(e)
+    }
+}
+
+impl From<SceneError> for Error {
+    fn from(e: SceneError) -> Error {
+        Error::InvalidScene(e)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Error {
+        Error::Io(e)
+    }
+}
+
+#[cfg(feature = "scene-file")]
+impl From<LoadError> for Error {
+    fn from(e: LoadError) -> Error {
+        match e {
+            LoadError::Io(e) => Error::Io(e),
+            LoadError::Scene(e) => Error::InvalidScene(e),
+            LoadError::UnknownFormat(_) => Error::Unsupported(e.to_string()),
+            #[cfg(feature = "usd")]
+            LoadError::Usd(e) => Error::from(e),
+            _ => Error::Parse(e.to_string()),
+        }
+    }
+}
+
+#[cfg(feature = "usd")]
+impl From<UsdError> for Error {
+    fn from(e: UsdError) -> Error {
+        match e {
+            UsdError::Io(e) => Error::Io(e),
+            UsdError::Unsupported(_) => Error::Unsupported(e.to_string()),
+            UsdError::Parse(..) => Error::Parse(e.to_string()),
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use cgmath::{Matrix4, Transform};
+
+use geometry::{Point, Vector};
+use Float;
+
+/// An axis-aligned bounding box.
+///
+/// # Examples
+/// ```
+/// use lucifer::geometry::{Bounds, Point};
+///
+/// let a = Bounds::new(Point::new(0.0, 0.0, 0.0), Point::new(1.0, 1.0, 1.0));
+/// let b = Bounds::new(Point::new(2.0, -1.0, 0.0), Point::new(3.0, 0.0, 1.0));
+///
+/// let both = a.union(&b);
+/// assert_eq!(both.min, Point::new(0.0, -1.0, 0.0));
+/// assert_eq!(both.max, Point::new(3.0, 1.0, 1.0));
+/// assert!(Bounds::empty().union(&a) == a);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct Bounds {
+    pub min: Point,
+    pub max: Point,
+}
+
+impl Bounds {
+    pub fn new(min: Point, max: Point) -> Bounds {
+        Bounds { min, max }
+    }
+
+    /// A box containing nothing, the neutral element of `union`.
+    pub fn empty() -> Bounds {
+        Bounds {
+            min: Point::new(Float::INFINITY, Float::INFINITY, Float::INFINITY),
+            max: Point::new(
+                Float::NEG_INFINITY,
+                Float::NEG_INFINITY,
+                Float::NEG_INFINITY,
+            ),
+        }
+    }
+
+    /// A box containing everything, for unbounded geometry.
+    pub fn infinite() -> Bounds {
+        Bounds {
+            min: Point::new(
+                Float::NEG_INFINITY,
+                Float::NEG_INFINITY,
+                Float::NEG_INFINITY,
+            ),
+            max: Point::new(Float::INFINITY, Float::INFINITY, Float::INFINITY),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        (0..3).any(|i| self.min[i] > self.max[i])
+    }
+
+    pub fn is_finite(&self) -> bool {
+        (0..3).all(|i| self.min[i].is_finite() && self.max[i].is_finite())
+    }
+
+    /// The smallest box containing both `self` and `other`.
+    pub fn union(&self, other: &Bounds) -> Bounds {
+        Bounds {
+            min: Point::new(
+                self.min[0].min(other.min[0]),
+                self.min[1].min(other.min[1]),
+                self.min[2].min(other.min[2]),
+            ),
+            max: Point::new(
+                self.max[0].max(other.max[0]),
+                self.max[1].max(other.max[1]),
+                self.max[2].max(other.max[2]),
+            ),
+        }
+    }
+
+    /// The smallest box containing both `self` and `point`.
+    pub fn include(&self, point: Point) -> Bounds {
+        self.union(&Bounds::new(point, point))
+    }
+
+    pub fn center(&self) -> Point {
+        self.min + self.diagonal() * 0.5
+    }
+
+    pub fn diagonal(&self) -> Vector {
+        self.max - self.min
+    }
+
+    /// The eight corners of the box.
+    pub fn corners(&self) -> [Point; 8] {
+        let mut corners = [self.min; 8];
+        for (i, corner) in corners.iter_mut().enumerate() {
+            *corner = Point::new(
+                if i & 1 == 0 { self.min[0] } else { self.max[0] },
+                if i & 2 == 0 { self.min[1] } else { self.max[1] },
+                if i & 4 == 0 { self.min[2] } else { self.max[2] },
+            );
+        }
+        corners
+    }
+
+    /// The bounds of the transformed box.
+    pub fn transform(&self, transform: &Matrix4<Float>) -> Bounds {
+        if self.is_empty() || !self.is_finite() {
+            return *self;
+        }
+
+        self.corners()
+            .iter()
+            .fold(Bounds::empty(), |bounds, &corner| {
+                bounds.include(transform.transform_point(corner))
+            })
+    }
+}
@@ -0,0 +1,637 @@
+use std::mem;
+
+use smallvec::SmallVec;
+
+use geometry::{Bounds, Point, Ray, Vector};
+use trace::Span;
+use Float;
+
+/// The maximum number of items in a leaf node.
+const LEAF_SIZE: usize = 4;
+
+/// The number of nodes traversal keeps on the call stack.  Median
+/// splits keep the hierarchy balanced, so it is never deeper than
+/// the number of bits of an item index, and below `SAH_DEPTH`, all
+/// splits are median splits.
+const STACK_SIZE: usize = 64;
+
+/// The number of rays `Bvh::traverse_batch` walks the hierarchy with
+/// at once, one per bit of a mask.
+const BATCH: usize = 64;
+
+/// The depth below which the surface area heuristic gives way to
+/// median splits, bounding the depth of lopsided hierarchies.
+const SAH_DEPTH: usize = 32;
+
+/// How a `Bvh` divides the items of a node between its two children.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum Split {
+    /// At the median of the items' centers along the axis they spread
+    /// the most: the fastest to build, but oblivious to empty space
+    /// and overlapping items.
+    Median,
+    /// At the plane the surface area heuristic expects to be cheapest
+    /// to traverse, among `bins - 1` evenly spaced planes per axis.
+    /// More bins take longer to build, but find better planes.
+    Sah { bins: usize },
+}
+
+impl Default for Split {
+    fn default() -> Split {
+        Split::Sah { bins: 12 }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Node {
+    bounds: Bounds,
+    /// For leaves, the position of the first item; for inner nodes,
+    /// the index of the second child (the first child directly
+    /// follows its parent).
+    offset: usize,
+    /// The number of items of a leaf, `0` for inner nodes.
+    count: usize,
+    /// The axis along which the children of an inner node are split.
+    axis: usize,
+}
+
+/// A bounding volume hierarchy over a set of items, given by their
+/// bounding boxes.
+///
+/// The hierarchy only stores the bounds; the items themselves are
+/// tested by the caller during traversal.  Items with infinite bounds
+/// (e.g. a `Plane`) are kept aside and always tested, items with
+/// empty bounds are never tested.
+///
+/// The nodes live in a single allocation of exactly their size, made
+/// once the items are partitioned and before the nodes are built, and
+/// traversal keeps its stack on the call stack, so neither building
+/// nor tracing rays strains the global allocator, however large the
+/// scene.
+///
+/// # Examples
+/// ```
+/// use lucifer::geometry::{Bounds, Bvh, Point, Ray, Vector};
+/// use lucifer::Float;
+///
+/// let boxes: Vec<Bounds> = (0..100)
+///     .map(|i| {
+///         let x = i as Float * 2.0;
+///         Bounds::new(Point::new(x, -1.0, -1.0), Point::new(x + 1.0, 1.0, 1.0))
+///     })
+///     .collect();
+/// let bvh = Bvh::new(&boxes);
+///
+/// // Only the leaf containing the box hit by the ray is tested.
+/// let ray = Ray::new(Point::new(21.5, 0.0, 5.0), Vector::new(0.0, 0.0, -1.0));
+/// let mut tested = Vec::new();
+/// bvh.traverse(&ray, |i| {
+///     tested.push(i);
+///     None
+/// });
+///
+/// assert!(tested.contains(&10));
+/// assert!(tested.len() <= 4);
+/// assert_eq!(bvh.bounds().max, Point::new(199.0, 1.0, 1.0));
+/// assert!(bvh.memory() > 100 * std::mem::size_of::<usize>());
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Bvh {
+    nodes: Vec<Node>,
+    items: Vec<usize>,
+    unbounded: Vec<usize>,
+}
+
+impl Bvh {
+    /// Builds a `Bvh` over items with the given `bounds`, splitting
+    /// by the surface area heuristic; see `Split`.
+    pub fn new(bounds: &[Bounds]) -> Bvh {
+        Bvh::with_split(bounds, Split::default())
+    }
+
+    /// Builds a `Bvh` over items with the given `bounds`, splitting
+    /// nodes by `split`, to trade the time to build the hierarchy for
+    /// the time to trace rays through it.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::geometry::bvh::Split;
+    /// use lucifer::geometry::{Bounds, Bvh, Point, Ray, Vector};
+    /// use lucifer::Float;
+    ///
+    /// // A dense cluster next to a sparse row of boxes.
+    /// let cube = |x: Float| Bounds::new(Point::new(x, -0.5, -0.5), Point::new(x + 1.0, 0.5, 0.5));
+    /// let boxes: Vec<Bounds> = (0..64)
+    ///     .map(|i| cube(i as Float * 0.01))
+    ///     .chain((0..8).map(|i| cube(100.0 + i as Float * 100.0)))
+    ///     .collect();
+    ///
+    /// let ray = Ray::new(Point::new(-5.0, 0.0, 0.0), Vector::new(1.0, 0.0, 0.0));
+    /// let tested = |bvh: &Bvh| {
+    ///     let mut tested = Vec::new();
+    ///     bvh.traverse(&ray, |i| {
+    ///         tested.push(i);
+    ///         None
+    ///     });
+    ///     tested.sort();
+    ///     tested
+    /// };
+    ///
+    /// let fast = Bvh::with_split(&boxes, Split::Median);
+    /// let good = Bvh::with_split(&boxes, Split::Sah { bins: 16 });
+    /// assert_eq!(tested(&fast), tested(&good));
+    /// assert_eq!(good.bounds(), fast.bounds());
+    /// ```
+    pub fn with_split(bounds: &[Bounds], split: Split) -> Bvh {
+        let _span = Span::enter("build BVH");
+        let mut bvh = Bvh::default();
+        let mut items = Vec::new();
+
+        for (i, b) in bounds.iter().enumerate() {
+            if b.is_empty() {
+                continue;
+            } else if b.is_finite() {
+                items.push((i, *b, b.center()));
+            } else {
+                bvh.unbounded.push(i);
+            }
+        }
+
+        if !items.is_empty() {
+            // Every inner node has two children, so the splits give
+            // the exact number of nodes to allocate.  There are fewer
+            // inner nodes than items.
+            let mut splits = Vec::with_capacity(items.len() - 1);
+            partition(&mut items, split, 0, &mut splits);
+            bvh.nodes.reserve_exact(2 * splits.len() + 1);
+            bvh.items.reserve_exact(items.len());
+            bvh.build(&items, &mut splits.into_iter());
+        }
+
+        debug!(
+            "BVH of {} items has {} nodes, {} items are unbounded",
+            items.len(),
+            bvh.nodes.len(),
+            bvh.unbounded.len()
+        );
+        bvh
+    }
+
+    /// Creates the nodes over `items`, ordered by `partition`, taking
+    /// the axis and the number of items of the first child of every
+    /// inner node from `splits`, in the same order.
+    fn build<I>(&mut self, items: &[(usize, Bounds, Point)], splits: &mut I)
+    where
+        I: Iterator<Item = (usize, usize)>,
+    {
+        let bounds = items.iter().fold(Bounds::empty(), |acc, i| acc.union(&i.1));
+        let index = self.nodes.len();
+        self.nodes.push(Node {
+            bounds,
+            offset: self.items.len(),
+            count: items.len(),
+            axis: 0,
+        });
+
+        if items.len() <= LEAF_SIZE {
+            self.items.extend(items.iter().map(|i| i.0));
+            return;
+        }
+
+        let (axis, middle) = splits.next().expect("a split for every inner node");
+        let (left, right) = items.split_at(middle);
+        self.build(left, splits);
+        let second = self.nodes.len();
+        self.build(right, splits);
+
+        let node = &mut self.nodes[index];
+        node.offset = second;
+        node.count = 0;
+        node.axis = axis;
+    }
+
+    /// Updates the bounds of the nodes to the new `bounds` of the same
+    /// items, e.g. after objects moved, keeping the structure of the
+    /// hierarchy.  Much faster than building a new one, but the more
+    /// the items move, the less the grouping fits them and the slower
+    /// the traversal.
+    ///
+    /// Returns `false`, leaving the hierarchy unchanged, if the number
+    /// of items changed or any item became bounded, unbounded, or
+    /// empty, which takes a new build.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::geometry::{Bounds, Bvh, Point, Ray, Vector};
+    /// use lucifer::Float;
+    ///
+    /// let cube = |x: Float| Bounds::new(Point::new(x, -1.0, -1.0), Point::new(x + 1.0, 1.0, 1.0));
+    /// let mut boxes: Vec<Bounds> = (0..100).map(|i| cube(i as Float * 2.0)).collect();
+    /// let mut bvh = Bvh::new(&boxes);
+    ///
+    /// // Move the first box to the other end of the row.
+    /// boxes[0] = cube(300.0);
+    /// assert!(bvh.refit(&boxes));
+    /// assert_eq!(bvh.bounds().max, Point::new(301.0, 1.0, 1.0));
+    ///
+    /// let ray = Ray::new(Point::new(300.5, 0.0, 5.0), Vector::new(0.0, 0.0, -1.0));
+    /// let mut tested = Vec::new();
+    /// bvh.traverse(&ray, |i| {
+    ///     tested.push(i);
+    ///     None
+    /// });
+    /// assert!(tested.contains(&0));
+    ///
+    /// boxes[0] = Bounds::infinite();
+    /// assert!(!bvh.refit(&boxes));
+    /// ```
+    pub fn refit(&mut self, bounds: &[Bounds]) -> bool {
+        let _span = Span::enter("refit BVH");
+        let empty = bounds.iter().filter(|b| b.is_empty()).count();
+        let unchanged = self.items.len() + self.unbounded.len() + empty == bounds.len()
+            && self
+                .items
+                .iter()
+                .all(|&i| i < bounds.len() && !bounds[i].is_empty() && bounds[i].is_finite())
+            && self
+                .unbounded
+                .iter()
+                .all(|&i| i < bounds.len() && !bounds[i].is_empty() && !bounds[i].is_finite());
+        if !unchanged {
+            return false;
+        }
+
+        // Children follow their parents, so in reverse, they are
+        // refit first.
+        for index in (0..self.nodes.len()).rev() {
+            let node = &self.nodes[index];
+            let refit = if node.count > 0 {
+                self.items[node.offset..node.offset + node.count]
+                    .iter()
+                    .fold(Bounds::empty(), |acc, &i| acc.union(&bounds[i]))
+            } else {
+                self.nodes[index + 1]
+                    .bounds
+                    .union(&self.nodes[node.offset].bounds)
+            };
+            self.nodes[index].bounds = refit;
+        }
+
+        true
+    }
+
+    /// The memory used by the hierarchy, in bytes.
+    pub fn memory(&self) -> usize {
+        mem::size_of::<Bvh>()
+            + self.nodes.capacity() * mem::size_of::<Node>()
+            + (self.items.capacity() + self.unbounded.capacity()) * mem::size_of::<usize>()
+    }
+
+    /// The bounds of all items.
+    pub fn bounds(&self) -> Bounds {
+        if !self.unbounded.is_empty() {
+            Bounds::infinite()
+        } else {
+            self.nodes.first().map_or(Bounds::empty(), |n| n.bounds)
+        }
+    }
+
+    /// Calls `visit` for every item whose bounds `ray` passes through,
+    /// roughly front to back.  `visit` returns the distance to the
+    /// item's intersection, if any, and items beyond the nearest
+    /// intersection found so far are skipped.
+    pub fn traverse<F>(&self, ray: &Ray, mut visit: F)
+    where
+        F: FnMut(usize) -> Option<Float>,
+    {
+        let mut max = ray.length;
+        for &item in &self.unbounded {
+            if let Some(lambda) = visit(item) {
+                max = max.min(lambda);
+            }
+        }
+
+        self.walk(ray, max, |item, max| {
+            if let Some(lambda) = visit(item) {
+                *max = max.min(lambda);
+            }
+            false
+        });
+    }
+
+    /// Like `traverse`, but for a batch of `rays` at once, e.g. the
+    /// camera rays of several views of a scene.  Nodes are visited
+    /// once for all the rays passing through them, and `visit` is
+    /// called with the index of the ray and of the item.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::geometry::{Bounds, Bvh, Point, Ray, Vector};
+    /// use lucifer::Float;
+    ///
+    /// let boxes: Vec<Bounds> = (0..100)
+    ///     .map(|i| {
+    ///         let x = i as Float * 2.0;
+    ///         Bounds::new(Point::new(x, -1.0, -1.0), Point::new(x + 1.0, 1.0, 1.0))
+    ///     })
+    ///     .collect();
+    /// let bvh = Bvh::new(&boxes);
+    ///
+    /// let rays = [
+    ///     Ray::new(Point::new(21.5, 0.0, 5.0), Vector::new(0.0, 0.0, -1.0)),
+    ///     Ray::new(Point::new(21.6, 0.0, 5.0), Vector::new(0.0, 0.0, -1.0)),
+    ///     Ray::new(Point::new(65.5, 0.0, 5.0), Vector::new(0.0, 0.0, -1.0)),
+    /// ];
+    /// let mut tested = vec![Vec::new(); rays.len()];
+    /// bvh.traverse_batch(&rays, |ray, i| {
+    ///     tested[ray].push(i);
+    ///     None
+    /// });
+    ///
+    /// // The same items as traversing the hierarchy ray by ray.
+    /// for (ray, tested) in rays.iter().zip(&mut tested) {
+    ///     let mut alone = Vec::new();
+    ///     bvh.traverse(ray, |i| {
+    ///         alone.push(i);
+    ///         None
+    ///     });
+    ///     tested.sort();
+    ///     alone.sort();
+    ///     assert_eq!(*tested, alone);
+    /// }
+    /// assert!(tested[2].contains(&32));
+    /// ```
+    pub fn traverse_batch<F>(&self, rays: &[Ray], mut visit: F)
+    where
+        F: FnMut(usize, usize) -> Option<Float>,
+    {
+        for (chunk, rays) in rays.chunks(BATCH).enumerate() {
+            let first = chunk * BATCH;
+            let mut max: SmallVec<[Float; BATCH]> = rays.iter().map(|ray| ray.length).collect();
+            for &item in &self.unbounded {
+                for (i, max) in max.iter_mut().enumerate() {
+                    if let Some(lambda) = visit(first + i, item) {
+                        *max = max.min(lambda);
+                    }
+                }
+            }
+            if self.nodes.is_empty() {
+                continue;
+            }
+
+            let inv_directions: SmallVec<[Vector; BATCH]> = rays
+                .iter()
+                .map(|ray| {
+                    Vector::new(
+                        1.0 / ray.direction[0],
+                        1.0 / ray.direction[1],
+                        1.0 / ray.direction[2],
+                    )
+                })
+                .collect();
+
+            // Every node on the stack comes with the mask of the rays
+            // that passed through its parent.
+            let mut stack: SmallVec<[(usize, u64); STACK_SIZE]> = SmallVec::new();
+            stack.push((0, u64::MAX >> (BATCH - rays.len())));
+            while let Some((index, mask)) = stack.pop() {
+                let node = &self.nodes[index];
+                let active = bits(mask)
+                    .filter(|&i| hits(&node.bounds, rays[i].origin, inv_directions[i], max[i]))
+                    .fold(0, |active, i| active | 1 << i);
+                if active == 0 {
+                    continue;
+                }
+
+                if node.count > 0 {
+                    for &item in &self.items[node.offset..node.offset + node.count] {
+                        for i in bits(active) {
+                            if let Some(lambda) = visit(first + i, item) {
+                                max[i] = max[i].min(lambda);
+                            }
+                        }
+                    }
+                } else if rays[active.trailing_zeros() as usize].direction[node.axis] < 0.0 {
+                    stack.push((index + 1, active));
+                    stack.push((node.offset, active));
+                } else {
+                    stack.push((node.offset, active));
+                    stack.push((index + 1, active));
+                }
+            }
+        }
+    }
+
+    /// Whether `test` returns `true` for any item whose bounds `ray`
+    /// passes through.
+    pub fn any<F>(&self, ray: &Ray, mut test: F) -> bool
+    where
+        F: FnMut(usize) -> bool,
+    {
+        self.unbounded.iter().any(|&item| test(item))
+            || self.walk(ray, ray.length, |item, _| test(item))
+    }
+
+    /// Walks the nodes hit by `ray` within `max`, until `visit`
+    /// returns `true`.  `visit` may shorten `max`.
+    fn walk<F>(&self, ray: &Ray, max: Float, mut visit: F) -> bool
+    where
+        F: FnMut(usize, &mut Float) -> bool,
+    {
+        if self.nodes.is_empty() {
+            return false;
+        }
+
+        let inv_direction = Vector::new(
+            1.0 / ray.direction[0],
+            1.0 / ray.direction[1],
+            1.0 / ray.direction[2],
+        );
+
+        let mut max = max;
+        let mut stack: SmallVec<[usize; STACK_SIZE]> = SmallVec::new();
+        stack.push(0);
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            if !hits(&node.bounds, ray.origin, inv_direction, max) {
+                continue;
+            }
+
+            if node.count > 0 {
+                for &item in &self.items[node.offset..node.offset + node.count] {
+                    if visit(item, &mut max) {
+                        return true;
+                    }
+                }
+            } else if ray.direction[node.axis] < 0.0 {
+                stack.push(index + 1);
+                stack.push(node.offset);
+            } else {
+                stack.push(node.offset);
+                stack.push(index + 1);
+            }
+        }
+
+        false
+    }
+}
+
+/// Orders `items` for `Bvh::build`, dividing every node of more than
+/// `LEAF_SIZE` items by `split`, and pushes the axis and the number of
+/// items of the first child of every such inner node onto `splits`,
+/// depth first.
+fn partition(
+    items: &mut [(usize, Bounds, Point)],
+    split: Split,
+    depth: usize,
+    splits: &mut Vec<(usize, usize)>,
+) {
+    if items.len() <= LEAF_SIZE {
+        return;
+    }
+
+    let centers = items
+        .iter()
+        .fold(Bounds::empty(), |acc, i| acc.include(i.2));
+    let sah = match split {
+        Split::Sah { bins } if depth < SAH_DEPTH => sah_split(items, &centers, bins),
+        _ => None,
+    };
+    let (axis, middle) = match sah {
+        Some(sah) => sah,
+        None => median_split(items, &centers),
+    };
+    splits.push((axis, middle));
+
+    let (left, right) = items.split_at_mut(middle);
+    partition(left, split, depth + 1, splits);
+    partition(right, split, depth + 1, splits);
+}
+
+/// Splits `items` at the median of their `centers` along the axis
+/// they spread the most, returning the axis and the number of items
+/// of the first child.
+fn median_split(items: &mut [(usize, Bounds, Point)], centers: &Bounds) -> (usize, usize) {
+    let extent = centers.diagonal();
+    let axis = if extent[0] > extent[1] && extent[0] > extent[2] {
+        0
+    } else if extent[1] > extent[2] {
+        1
+    } else {
+        2
+    };
+
+    let middle = items.len() / 2;
+    items.select_nth_unstable_by(middle, |a, b| a.2[axis].total_cmp(&b.2[axis]));
+    (axis, middle)
+}
+
+/// Splits `items` by the surface area heuristic, sorting their
+/// `centers` into `bins` along each axis and choosing the bin
+/// boundary where the items on either side, weighted by the area of
+/// their bounds, are fewest.  Returns the axis and the number of
+/// items of the first child, or `None` if the centers coincide.
+fn sah_split(
+    items: &mut [(usize, Bounds, Point)],
+    centers: &Bounds,
+    bins: usize,
+) -> Option<(usize, usize)> {
+    let bins = bins.max(2);
+    let extent = centers.diagonal();
+    let bin = |center: Point, axis: usize| {
+        let x = (center[axis] - centers.min[axis]) / extent[axis] * bins as Float;
+        (x as usize).min(bins - 1)
+    };
+
+    let mut best: Option<(Float, usize, usize)> = None;
+    for axis in 0..3 {
+        if extent[axis] <= 0.0 {
+            continue;
+        }
+
+        let mut counts: Bins<usize> = SmallVec::from_elem(0, bins);
+        let mut bounds: Bins<Bounds> = SmallVec::from_elem(Bounds::empty(), bins);
+        for item in items.iter() {
+            let b = bin(item.2, axis);
+            counts[b] += 1;
+            bounds[b] = bounds[b].union(&item.1);
+        }
+
+        // The cost of all items up to each boundary, then of the rest.
+        let mut below: Bins<Float> = SmallVec::from_elem(0.0, bins - 1);
+        let (mut count, mut acc) = (0, Bounds::empty());
+        for b in 0..bins - 1 {
+            count += counts[b];
+            acc = acc.union(&bounds[b]);
+            below[b] = count as Float * surface_area(&acc);
+        }
+        let (mut count, mut acc) = (0, Bounds::empty());
+        for b in (1..bins).rev() {
+            count += counts[b];
+            acc = acc.union(&bounds[b]);
+            let cost = below[b - 1] + count as Float * surface_area(&acc);
+            if best.is_none_or(|best| cost < best.0) {
+                best = Some((cost, axis, b));
+            }
+        }
+    }
+
+    // Partition the items below the boundary to the front.
+    let (_, axis, boundary) = best?;
+    let mut middle = 0;
+    for i in 0..items.len() {
+        if bin(items[i].2, axis) < boundary {
+            items.swap(i, middle);
+            middle += 1;
+        }
+    }
+
+    if middle == 0 || middle == items.len() {
+        None
+    } else {
+        Some((axis, middle))
+    }
+}
+
+/// Per-bin values of `sah_split`, on the call stack for up to 32 bins.
+type Bins<T> = SmallVec<[T; 32]>;
+
+/// The surface area of `bounds`, `0` if empty.
+fn surface_area(bounds: &Bounds) -> Float {
+    if bounds.is_empty() {
+        return 0.0;
+    }
+    let d = bounds.diagonal();
+    2.0 * (d[0] * d[1] + d[1] * d[2] + d[2] * d[0])
+}
+
+/// The positions of the set bits of `mask`, lowest first.
+fn bits(mask: u64) -> impl Iterator<Item = usize> {
+    let mut mask = mask;
+    ::std::iter::from_fn(move || {
+        if mask == 0 {
+            return None;
+        }
+        let bit = mask.trailing_zeros() as usize;
+        mask &= mask - 1;
+        Some(bit)
+    })
+}
+
+/// Whether a ray enters `bounds` within `max` (the slab test).
+fn hits(bounds: &Bounds, origin: Point, inv_direction: Vector, max: Float) -> bool {
+    let mut near: Float = 0.0;
+    let mut far = max;
+
+    for i in 0..3 {
+        let t0 = (bounds.min[i] - origin[i]) * inv_direction[i];
+        let t1 = (bounds.max[i] - origin[i]) * inv_direction[i];
+        // `min` and `max` ignore the NaN of rays parallel to a slab
+        // and starting on its boundary.
+        near = near.max(t0.min(t1));
+        far = far.min(t0.max(t1));
+    }
+
+    near <= far
+}
@@ -0,0 +1,197 @@
+use consts::PI;
+
+use cgmath::prelude::*;
+
+use geometry::{tangents, Bounds, Geometry, Intersection, Point, Ray, Vector};
+use montecarlo::warp::concentric_disk;
+use Float;
+
+/// A solid cone, closed by a disc at its base.
+///
+/// Like a `Cylinder`, the cone encloses a volume: rays leaving it hit
+/// the `inside` of its surface.
+///
+/// On the side, the surface coordinates are the angle around the axis
+/// and the distance from the apex, relative to the height; on the
+/// base, they span the square around it, like on a `Disc`.  All range
+/// from `0` to `1`.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct Cone {
+    /// The tip of the cone.
+    pub apex: Point,
+    /// The direction from the apex to the base (normalized).
+    pub axis: Vector,
+    /// The angle between the axis and the side, in radians.
+    pub angle: Float,
+    /// The distance between the apex and the base.
+    pub height: Float,
+}
+
+impl Cone {
+    /// Creates a `Cone` opening by `angle` radians around `axis`,
+    /// from `apex` to a base at `height`.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::cgmath::InnerSpace;
+    /// use lucifer::consts::FRAC_PI_4;
+    /// use lucifer::geometry::cone::Cone;
+    /// use lucifer::geometry::{Geometry, Point, Ray, Vector};
+    ///
+    /// // Tip up, with a base of radius 1 in the `xz` plane.
+    /// let apex = Point::new(0.0, 1.0, 0.0);
+    /// let cone = Cone::new(apex, Vector::new(0.0, -1.0, 0.0), FRAC_PI_4, 1.0);
+    /// assert!((cone.radius() - 1.0).abs() < 1e-6);
+    ///
+    /// // Through the side, from outside and from within.
+    /// let ray = Ray::new(Point::new(2.0, 0.5, 0.0), Vector::new(-1.0, 0.0, 0.0));
+    /// let hit = cone.intersect(&ray).unwrap();
+    /// assert!((hit.lambda - 1.5).abs() < 1e-6);
+    /// let normal = Vector::new(1.0, 1.0, 0.0).normalize();
+    /// assert!((hit.normal - normal).magnitude() < 1e-6);
+    /// assert!(!hit.inside);
+    ///
+    /// let ray = Ray::new(Point::new(0.0, 0.5, 0.0), Vector::new(0.0, -1.0, 0.0));
+    /// let hit = cone.intersect(&ray).unwrap();
+    /// assert_eq!(hit.normal, Vector::new(0.0, 1.0, 0.0));
+    /// assert!(hit.inside);
+    ///
+    /// // Past the apex.
+    /// let ray = Ray::new(Point::new(2.0, 1.5, 0.0), Vector::new(-1.0, 0.0, 0.0));
+    /// assert!(cone.intersect(&ray).is_none());
+    /// ```
+    pub fn new(apex: Point, axis: Vector, angle: Float, height: Float) -> Cone {
+        Cone {
+            apex,
+            axis: axis.normalize(),
+            angle,
+            height,
+        }
+    }
+
+    /// The radius of the base.
+    pub fn radius(&self) -> Float {
+        self.height * self.angle.tan()
+    }
+}
+
+impl Geometry for Cone {
+    fn bounds(&self) -> Bounds {
+        // The base extends `radius * sin(angle to axis)` along an axis.
+        let a = self.axis;
+        let base = self.apex + a * self.height;
+        let extent = |a: Float| self.radius() * (1.0 - a * a).max(0.0).sqrt();
+        let r = Vector::new(extent(a[0]), extent(a[1]), extent(a[2]));
+        Bounds::new(base - r, base + r).include(self.apex)
+    }
+
+    fn intersect(&self, ray: &Ray) -> Option<Intersection> {
+        let a = self.axis;
+        let cos2 = self.angle.cos().powi(2);
+
+        // Heights along the axis, measured from the apex.
+        let offset = ray.origin - self.apex;
+        let (ho, hd) = (offset.dot(a), ray.direction.dot(a));
+
+        // The nearest of the (up to three) crossings of the surface,
+        // with the outward normal there.
+        let mut nearest: Option<(Float, Vector)> = None;
+        let mut consider = |lambda: Float, normal: Vector| {
+            if lambda > 0.0 && lambda <= ray.length && nearest.is_none_or(|n| lambda < n.0) {
+                nearest = Some((lambda, normal));
+            }
+        };
+
+        // The side lies on the double cone `(q . a)^2 = cos^2 * |q|^2`,
+        // of which only the half between apex and base counts.
+        let mut side = |lambda: Float| {
+            let q = offset + ray.direction * lambda;
+            let h = ho + lambda * hd;
+            if (0.0..=self.height).contains(&h) {
+                consider(lambda, (q * cos2 - a * h).normalize());
+            }
+        };
+        let qa = hd * hd - cos2 * ray.direction.magnitude2();
+        let qb = ho * hd - cos2 * offset.dot(ray.direction);
+        let qc = ho * ho - cos2 * offset.magnitude2();
+        if qa != 0.0 {
+            let discriminant = qb * qb - qa * qc;
+            if discriminant >= 0.0 {
+                let root = discriminant.sqrt();
+                side((-qb - root) / qa);
+                side((-qb + root) / qa);
+            }
+        } else if qb != 0.0 {
+            // Parallel to the side, crossing it once.
+            side(-qc / (2.0 * qb));
+        }
+
+        if hd != 0.0 {
+            let lambda = (self.height - ho) / hd;
+            let q = offset + ray.direction * lambda - a * self.height;
+            if q.magnitude2() <= self.radius().powi(2) {
+                consider(lambda, a);
+            }
+        }
+
+        let (lambda, mut normal) = nearest?;
+        let position = ray.origin + lambda * ray.direction;
+
+        let (tangent, bitangent) = tangents(a);
+        let local = position - self.apex;
+        let (x, y) = (local.dot(tangent), local.dot(bitangent));
+        let uv = if normal == a {
+            let r = self.radius();
+            (0.5 + 0.5 * x / r, 0.5 + 0.5 * y / r)
+        } else {
+            let angle = y.atan2(x) / (2.0 * PI);
+            (angle - angle.floor(), local.dot(a) / self.height)
+        };
+
+        let inside = normal.dot(ray.direction) > 0.0;
+        if inside {
+            normal = -normal
+        }
+
+        Some(Intersection {
+            position,
+            normal,
+            lambda,
+            inside,
+            primitive: 0,
+            uv,
+        })
+    }
+
+    fn area(&self) -> Float {
+        let r = self.radius();
+        PI * r * (r + (r * r + self.height * self.height).sqrt())
+    }
+
+    fn sample_surface(&self, u: Float, v: Float) -> Option<(Point, Vector)> {
+        let (tangent, bitangent) = tangents(self.axis);
+        let r = self.radius();
+        let side = (r * r + self.height * self.height).sqrt();
+
+        // Pick the side or the base proportional to its area, reusing
+        // the remainder of `u` to position the sample on it.
+        let x = u * (side + r);
+        if x < side {
+            // The circumference grows linearly with the distance from
+            // the apex.
+            let s = (x / side).sqrt();
+            let phi = 2.0 * PI * v;
+            let radial = tangent * phi.cos() + bitangent * phi.sin();
+            let position = self.apex + (self.axis * self.height + radial * r) * s;
+            let normal = radial * self.angle.cos() - self.axis * self.angle.sin();
+            Some((position, normal))
+        } else {
+            let s = ((x - side) / r).min(1.0);
+            let (p, _) = concentric_disk(s, v);
+            let position =
+                self.apex + self.axis * self.height + (tangent * p.x + bitangent * p.y) * r;
+            Some((position, self.axis))
+        }
+    }
+}
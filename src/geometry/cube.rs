@@ -1,11 +1,17 @@
-use std;
+use std::array;
 
 use cgmath::prelude::*;
 
-use geometry::{Geometry, Intersection, Point, Ray, Vector};
+use geometry::{Bounds, Geometry, Intersection, Point, Ray, Vector};
+use Float;
 
 /// An axis-aligned cube.
+///
+/// The surface coordinates of each face span it from `0` to `1`, along
+/// the axes following the face's normal axis, e.g. `y` and `z` on the
+/// faces facing along `x`.
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub struct Cube {
     /// The cube's center point.
     pub center: Point,
@@ -33,15 +39,115 @@ impl Cube {
         let radius = dimensions / 2.0;
         Cube { center, radius }
     }
+
+    /// Intersects `N` rays at once with the slab test, like
+    /// `Sphere::intersect_packet`: the distances to the slabs are
+    /// computed axis by axis for all rays side by side, and only the
+    /// rays passing the test find the faces they cross.  The results
+    /// match those of `intersect`.
+    fn intersect_packet<const N: usize>(&self, rays: &[Ray; N]) -> [Option<Intersection>; N] {
+        let mut t_in = [Float::NEG_INFINITY; N];
+        let mut t_out = [Float::INFINITY; N];
+        let mut lows = [[0.0; N]; 3];
+        let mut highs = [[0.0; N]; 3];
+        for d in 0..3 {
+            let (low, high) = (
+                self.center[d] - self.radius[d],
+                self.center[d] + self.radius[d],
+            );
+            let o: [Float; N] = array::from_fn(|i| rays[i].origin[d]);
+            let dir: [Float; N] = array::from_fn(|i| rays[i].direction[d]);
+            for i in 0..N {
+                let (a, b) = ((low - o[i]) / dir[i], (high - o[i]) / dir[i]);
+                lows[d][i] = a;
+                highs[d][i] = b;
+                let enter = if a < b { a } else { b };
+                let leave = if a > b { a } else { b };
+                t_in[i] = if enter > t_in[i] { enter } else { t_in[i] };
+                t_out[i] = if leave < t_out[i] { leave } else { t_out[i] };
+            }
+        }
+
+        array::from_fn(|i| {
+            if t_out[i] < t_in[i] {
+                return None;
+            }
+
+            // The first axis reaching the distances, as in `intersect`.
+            let ray = &rays[i];
+            let (mut lin, mut lout) = ((t_in[i], 0.0, 0), (t_out[i], 0.0, 0));
+            for d in (0..3).rev() {
+                let (low, high) = (lows[d][i], highs[d][i]);
+                let (enter, leave) = if low < high {
+                    ((low, -1.0), (high, 1.0))
+                } else {
+                    ((high, 1.0), (low, if low > high { -1.0 } else { 1.0 }))
+                };
+                if enter.0 == lin.0 {
+                    lin = (enter.0, enter.1, d);
+                }
+                if leave.0 == lout.0 {
+                    lout = (leave.0, leave.1, d);
+                }
+            }
+            self.hit(ray, lin, lout)
+        })
+    }
+
+    /// The intersection with `ray`, given where it enters and leaves
+    /// the slabs, if it hits the cube.
+    fn hit(
+        &self,
+        ray: &Ray,
+        lin: (Float, Float, usize),
+        lout: (Float, Float, usize),
+    ) -> Option<Intersection> {
+        if lout.0 < lin.0 {
+            return None;
+        }
+
+        let inside = lin.0 <= 0.0;
+        let (lambda, n, dim) = if inside { lout } else { lin };
+
+        if !(lambda > 0.0 && lambda <= ray.length) {
+            return None;
+        }
+
+        let position = ray.origin + lambda * ray.direction;
+        let mut normal = Vector::zero();
+        normal[dim] = n;
+        let face = |i: usize| {
+            let i = (dim + i) % 3;
+            0.5 + 0.5 * (position[i] - self.center[i]) / self.radius[i]
+        };
+        let uv = (face(1), face(2));
+
+        if inside {
+            normal = -normal
+        }
+
+        Some(Intersection {
+            position,
+            normal,
+            lambda,
+            inside,
+            primitive: 0,
+            uv,
+        })
+    }
 }
 
 impl Geometry for Cube {
+    fn bounds(&self) -> Bounds {
+        Bounds::new(self.center - self.radius, self.center + self.radius)
+    }
+
     fn intersect(&self, ray: &Ray) -> Option<Intersection> {
-        let vmin: [f32; 3] = (self.center - self.radius - ray.origin)
+        let vmin: [Float; 3] = (self.center - self.radius - ray.origin)
             .div_element_wise(ray.direction)
             .into();
 
-        let vmax: [f32; 3] = (self.center + self.radius - ray.origin)
+        let vmax: [Float; 3] = (self.center + self.radius - ray.origin)
             .div_element_wise(ray.direction)
             .into();
 
@@ -50,7 +156,7 @@ impl Geometry for Cube {
             .zip(vmax.iter())
             .map(|(a, b)| if a < b { (a, -1.0) } else { (b, 1.0) })
             .enumerate()
-            .fold((std::f32::NEG_INFINITY, 0.0, 0), |acc, (d, x)| {
+            .fold((Float::NEG_INFINITY, 0.0, 0), |acc, (d, x)| {
                 if *x.0 > acc.0 {
                     (*x.0, x.1, d)
                 } else {
@@ -63,7 +169,7 @@ impl Geometry for Cube {
             .zip(vmax.iter())
             .map(|(a, b)| if a > b { (a, -1.0) } else { (b, 1.0) })
             .enumerate()
-            .fold((std::f32::INFINITY, 0.0, 0), |acc, (d, x)| {
+            .fold((Float::INFINITY, 0.0, 0), |acc, (d, x)| {
                 if *x.0 < acc.0 {
                     (*x.0, x.1, d)
                 } else {
@@ -71,30 +177,48 @@ impl Geometry for Cube {
                 }
             });
 
-        if lout.0 < lin.0 {
-            return None;
-        }
+        self.hit(ray, lin, lout)
+    }
 
-        let inside = lin.0 <= 0.0;
-        let (lambda, n, dim) = if inside { lout } else { lin };
+    fn intersect4(&self, rays: &[Ray; 4]) -> [Option<Intersection>; 4] {
+        self.intersect_packet(rays)
+    }
 
-        if lambda <= 0.0 {
-            return None;
+    fn intersect8(&self, rays: &[Ray; 8]) -> [Option<Intersection>; 8] {
+        self.intersect_packet(rays)
+    }
+
+    fn area(&self) -> Float {
+        let r = self.radius;
+        8.0 * (r[0] * r[1] + r[1] * r[2] + r[2] * r[0])
+    }
+
+    fn sample_surface(&self, u: Float, v: Float) -> Option<(Point, Vector)> {
+        let r = self.radius;
+        let faces = [r[1] * r[2], r[2] * r[0], r[0] * r[1]];
+        let total: Float = faces.iter().sum();
+
+        // Pick one of the six faces proportional to its area, reusing
+        // the remainder of `u` to position the sample on the face.
+        let mut x = u * 2.0 * total;
+        let mut face = 0;
+        while face < 5 && x >= faces[face / 2] {
+            x -= faces[face / 2];
+            face += 1;
         }
 
-        let position = ray.origin + lambda * ray.direction;
+        let dim = face / 2;
+        let sign = if face % 2 == 0 { -1.0 } else { 1.0 };
+        let s = (x / faces[dim]).min(1.0);
+
         let mut normal = Vector::zero();
-        normal[dim] = n;
+        normal[dim] = sign;
 
-        if inside {
-            normal = -normal
-        }
+        let mut offset = Vector::zero();
+        offset[dim] = sign * r[dim];
+        offset[(dim + 1) % 3] = (2.0 * s - 1.0) * r[(dim + 1) % 3];
+        offset[(dim + 2) % 3] = (2.0 * v - 1.0) * r[(dim + 2) % 3];
 
-        Some(Intersection {
-            position,
-            normal,
-            lambda,
-            inside,
-        })
+        Some((self.center + offset, normal))
     }
 }
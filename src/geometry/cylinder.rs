@@ -0,0 +1,305 @@
+use consts::PI;
+
+use cgmath::prelude::*;
+
+use geometry::{tangents, Bounds, Geometry, Intersection, Point, Ray, Vector};
+use montecarlo::warp::concentric_disk;
+use Float;
+
+/// A solid cylinder, closed by a disc at either end; see
+/// `InfiniteCylinder` for an open-ended one.
+///
+/// Like a `Sphere`, the cylinder encloses a volume: rays leaving it
+/// hit the `inside` of its surface, so it can be filled with glass or
+/// a participating medium.
+///
+/// On the side, the surface coordinates are the angle around the axis
+/// and the height, from the bottom to the top; on the caps, they span
+/// the square around the cap, like on a `Disc`.  All range from `0` to
+/// `1`.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct Cylinder {
+    /// The center point, halfway between the caps.
+    pub center: Point,
+    /// The direction from the center to the top cap (normalized).
+    pub axis: Vector,
+    /// The cylinder's radius.
+    pub radius: Float,
+    /// The distance between the caps.
+    pub height: Float,
+}
+
+impl Cylinder {
+    /// Creates a `Cylinder` of `radius` and `height` along `axis`,
+    /// centered on `center`.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::geometry::cylinder::Cylinder;
+    /// use lucifer::geometry::{Geometry, Point, Ray, Vector};
+    ///
+    /// let center = Point::new(0.0, 0.0, 0.0);
+    /// let axis = Vector::new(0.0, 2.0, 0.0);
+    /// let cylinder = Cylinder::new(center, axis, 1.0, 2.0);
+    /// assert_eq!(cylinder.axis, Vector::new(0.0, 1.0, 0.0));
+    ///
+    /// // Through the side, from outside and from within.
+    /// let ray = Ray::new(Point::new(3.0, 0.5, 0.0), Vector::new(-1.0, 0.0, 0.0));
+    /// let hit = cylinder.intersect(&ray).unwrap();
+    /// assert_eq!(hit.lambda, 2.0);
+    /// assert_eq!(hit.normal, Vector::new(1.0, 0.0, 0.0));
+    /// assert!(!hit.inside);
+    ///
+    /// let ray = Ray::new(Point::new(0.0, 0.5, 0.0), Vector::new(-1.0, 0.0, 0.0));
+    /// let hit = cylinder.intersect(&ray).unwrap();
+    /// assert_eq!(hit.normal, Vector::new(1.0, 0.0, 0.0));
+    /// assert!(hit.inside);
+    ///
+    /// // Through the top cap.
+    /// let ray = Ray::new(Point::new(0.5, 3.0, 0.0), Vector::new(0.0, -1.0, 0.0));
+    /// let hit = cylinder.intersect(&ray).unwrap();
+    /// assert_eq!(hit.lambda, 2.0);
+    /// assert_eq!(hit.normal, Vector::new(0.0, 1.0, 0.0));
+    ///
+    /// // Past the top cap.
+    /// let ray = Ray::new(Point::new(3.0, 1.5, 0.0), Vector::new(-1.0, 0.0, 0.0));
+    /// assert!(cylinder.intersect(&ray).is_none());
+    /// ```
+    pub fn new(center: Point, axis: Vector, radius: Float, height: Float) -> Cylinder {
+        Cylinder {
+            center,
+            axis: axis.normalize(),
+            radius,
+            height,
+        }
+    }
+}
+
+impl Geometry for Cylinder {
+    fn bounds(&self) -> Bounds {
+        // Each cap extends `radius * sin(angle to axis)` along an axis.
+        let a = self.axis;
+        let half = 0.5 * self.height;
+        let extent = |a: Float| half * a.abs() + self.radius * (1.0 - a * a).max(0.0).sqrt();
+        let r = Vector::new(extent(a[0]), extent(a[1]), extent(a[2]));
+        Bounds::new(self.center - r, self.center + r)
+    }
+
+    fn intersect(&self, ray: &Ray) -> Option<Intersection> {
+        let a = self.axis;
+        let half = 0.5 * self.height;
+
+        // The ray, split into its parts along and across the axis.
+        let offset = ray.origin - self.center;
+        let (ho, hd) = (offset.dot(a), ray.direction.dot(a));
+        let (ro, rd) = (offset - a * ho, ray.direction - a * hd);
+
+        // The nearest of the (up to four) crossings of the surface,
+        // with the outward normal there.
+        let mut nearest: Option<(Float, Vector)> = None;
+        let mut consider = |lambda: Float, normal: Vector| {
+            if lambda > 0.0 && lambda <= ray.length && nearest.is_none_or(|n| lambda < n.0) {
+                nearest = Some((lambda, normal));
+            }
+        };
+
+        for &lambda in side_crossings(ro, rd, self.radius).iter().flatten() {
+            if (ho + lambda * hd).abs() <= half {
+                consider(lambda, (ro + rd * lambda).normalize());
+            }
+        }
+
+        if hd != 0.0 {
+            for &side in &[-1.0, 1.0] {
+                let lambda = (side * half - ho) / hd;
+                if (ro + rd * lambda).magnitude2() <= self.radius.powi(2) {
+                    consider(lambda, a * side);
+                }
+            }
+        }
+
+        let (lambda, mut normal) = nearest?;
+        let position = ray.origin + lambda * ray.direction;
+
+        let (tangent, bitangent) = tangents(a);
+        let local = position - self.center;
+        let (x, y) = (local.dot(tangent), local.dot(bitangent));
+        let uv = if normal.dot(a).abs() > 0.5 {
+            (0.5 + 0.5 * x / self.radius, 0.5 + 0.5 * y / self.radius)
+        } else {
+            (turn(x, y), 0.5 + local.dot(a) / self.height)
+        };
+
+        let inside = normal.dot(ray.direction) > 0.0;
+        if inside {
+            normal = -normal
+        }
+
+        Some(Intersection {
+            position,
+            normal,
+            lambda,
+            inside,
+            primitive: 0,
+            uv,
+        })
+    }
+
+    fn area(&self) -> Float {
+        2.0 * PI * self.radius * (self.radius + self.height)
+    }
+
+    fn sample_surface(&self, u: Float, v: Float) -> Option<(Point, Vector)> {
+        let (tangent, bitangent) = tangents(self.axis);
+        let side = self.height;
+        let cap = 0.5 * self.radius;
+
+        // Pick the side or a cap proportional to its area, reusing the
+        // remainder of `u` to position the sample on it.
+        let x = u * (side + 2.0 * cap);
+        if x < side {
+            let phi = 2.0 * PI * v;
+            let normal = tangent * phi.cos() + bitangent * phi.sin();
+            let along = (x / side - 0.5) * self.height;
+            let position = self.center + self.axis * along + normal * self.radius;
+            Some((position, normal))
+        } else {
+            let x = x - side;
+            let (sign, s) = if x < cap {
+                (-1.0, x / cap)
+            } else {
+                (1.0, ((x - cap) / cap).min(1.0))
+            };
+            let (p, _) = concentric_disk(s, v);
+            let normal = self.axis * sign;
+            let position = self.center
+                + normal * (0.5 * self.height)
+                + (tangent * p.x + bitangent * p.y) * self.radius;
+            Some((position, normal))
+        }
+    }
+}
+
+/// A cylinder without caps, extending infinitely along its axis, e.g.
+/// to drill holes or cut rods with CSG operations.
+///
+/// Like the capped `Cylinder`, it encloses a volume, the points within
+/// `radius` of the axis, so rays leaving that volume hit the `inside`
+/// of its surface.  Its surface is infinite, so it cannot emit light.
+///
+/// The surface coordinates are the angle around the axis, from `0` to
+/// `1`, and the distance along the axis from `origin`.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct InfiniteCylinder {
+    /// A point on the axis.
+    pub origin: Point,
+    /// The direction of the axis (normalized).
+    pub axis: Vector,
+    /// The cylinder's radius.
+    pub radius: Float,
+}
+
+impl InfiniteCylinder {
+    /// Creates an `InfiniteCylinder` of `radius` around the axis
+    /// through `origin` along `axis`.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::geometry::cylinder::InfiniteCylinder;
+    /// use lucifer::geometry::{Geometry, Point, Ray, Vector};
+    ///
+    /// let origin = Point::new(0.0, 0.0, 0.0);
+    /// let axis = Vector::new(0.0, 2.0, 0.0);
+    /// let cylinder = InfiniteCylinder::new(origin, axis, 1.0);
+    /// assert_eq!(cylinder.axis, Vector::new(0.0, 1.0, 0.0));
+    ///
+    /// // The side goes on forever.
+    /// let ray = Ray::new(Point::new(3.0, 1000.0, 0.0), Vector::new(-1.0, 0.0, 0.0));
+    /// let hit = cylinder.intersect(&ray).unwrap();
+    /// assert_eq!(hit.lambda, 2.0);
+    /// assert_eq!(hit.normal, Vector::new(1.0, 0.0, 0.0));
+    /// assert_eq!(hit.uv.1, 1000.0);
+    /// assert!(!hit.inside);
+    ///
+    /// let ray = Ray::new(Point::new(0.0, -5.0, 0.0), Vector::new(0.0, -1.0, 1.0));
+    /// let hit = cylinder.intersect(&ray).unwrap();
+    /// assert_eq!(hit.position.z, 1.0);
+    /// assert!(hit.inside);
+    ///
+    /// // Rays along the axis never reach the side.
+    /// let ray = Ray::new(Point::new(0.5, 3.0, 0.0), Vector::new(0.0, -1.0, 0.0));
+    /// assert!(cylinder.intersect(&ray).is_none());
+    /// ```
+    pub fn new(origin: Point, axis: Vector, radius: Float) -> InfiniteCylinder {
+        InfiniteCylinder {
+            origin,
+            axis: axis.normalize(),
+            radius,
+        }
+    }
+}
+
+impl Geometry for InfiniteCylinder {
+    fn bounds(&self) -> Bounds {
+        Bounds::infinite()
+    }
+
+    fn intersect(&self, ray: &Ray) -> Option<Intersection> {
+        let a = self.axis;
+
+        let offset = ray.origin - self.origin;
+        let (ho, hd) = (offset.dot(a), ray.direction.dot(a));
+        let (ro, rd) = (offset - a * ho, ray.direction - a * hd);
+
+        let lambda = side_crossings(ro, rd, self.radius)?
+            .iter()
+            .cloned()
+            .find(|&lambda| lambda > 0.0 && lambda <= ray.length)?;
+        let position = ray.origin + lambda * ray.direction;
+        let mut normal = (ro + rd * lambda).normalize();
+
+        let (tangent, bitangent) = tangents(a);
+        let local = position - self.origin;
+        let uv = (turn(local.dot(tangent), local.dot(bitangent)), local.dot(a));
+
+        let inside = normal.dot(ray.direction) > 0.0;
+        if inside {
+            normal = -normal
+        }
+
+        Some(Intersection {
+            position,
+            normal,
+            lambda,
+            inside,
+            primitive: 0,
+            uv,
+        })
+    }
+}
+
+/// The distances along a ray to where it crosses the side of a
+/// cylinder of `radius`, nearest first, given the parts of the ray's
+/// origin (relative to the axis) and direction across the axis.  Rays
+/// parallel to the axis never cross it.
+fn side_crossings(ro: Vector, rd: Vector, radius: Float) -> Option<[Float; 2]> {
+    let qa = rd.magnitude2();
+    let qb = ro.dot(rd);
+    let qc = ro.magnitude2() - radius.powi(2);
+    let discriminant = qb * qb - qa * qc;
+    if qa > 0.0 && discriminant >= 0.0 {
+        let root = discriminant.sqrt();
+        Some([(-qb - root) / qa, (-qb + root) / qa])
+    } else {
+        None
+    }
+}
+
+/// The angle of the point `(x, y)` around the origin, as a fraction of
+/// a full turn in `[0, 1)`.
+fn turn(x: Float, y: Float) -> Float {
+    let angle = y.atan2(x) / (2.0 * PI);
+    angle - angle.floor()
+}
@@ -1,16 +1,24 @@
+use consts::PI;
+
 use cgmath::prelude::*;
 
-use geometry::{Geometry, Intersection, Point, Ray, Vector};
+use geometry::{tangents, Bounds, Geometry, Intersection, Point, Ray, Vector};
+use montecarlo::warp::concentric_disk;
+use Float;
 
 /// A two-dimensional disc.
+///
+/// The surface coordinates span the square around the disc, from `0`
+/// to `1` across its diameter.
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub struct Disc {
     /// The disc's center point.
     pub center: Point,
     /// The disc's normal.
     pub normal: Vector,
     /// The disc's radius.
-    pub radius: f32,
+    pub radius: Float,
 }
 
 impl Disc {
@@ -31,7 +39,7 @@ impl Disc {
     /// assert_eq!(disc.normal, normal);
     /// assert_eq!(disc.radius, radius);
     /// ```
-    pub fn new(center: Point, normal: Vector, radius: f32) -> Disc {
+    pub fn new(center: Point, normal: Vector, radius: Float) -> Disc {
         let normal = normal.normalize();
         Disc {
             center,
@@ -42,6 +50,14 @@ impl Disc {
 }
 
 impl Geometry for Disc {
+    fn bounds(&self) -> Bounds {
+        // The extent along each axis is `radius * sin(angle to normal)`.
+        let n = self.normal;
+        let extent = |a: Float| self.radius * (1.0 - a * a).max(0.0).sqrt();
+        let r = Vector::new(extent(n[0]), extent(n[1]), extent(n[2]));
+        Bounds::new(self.center - r, self.center + r)
+    }
+
     fn intersect(&self, ray: &Ray) -> Option<Intersection> {
         let lo = self.normal.dot(ray.origin - self.center);
         let ld = self.normal.dot(ray.direction);
@@ -49,16 +65,22 @@ impl Geometry for Disc {
         let lambda = -lo / ld;
         let inside = ld > 0.0;
 
-        if lambda <= 0.0 {
+        if !(lambda > 0.0 && lambda <= ray.length) {
             return None;
         }
 
         let position = ray.origin + lambda * ray.direction;
         let mut normal = self.normal;
 
-        if (position - self.center).magnitude2() > self.radius.powi(2) {
+        let offset = position - self.center;
+        if offset.magnitude2() > self.radius.powi(2) {
             return None;
         }
+        let (tangent, bitangent) = tangents(self.normal);
+        let uv = (
+            0.5 + 0.5 * offset.dot(tangent) / self.radius,
+            0.5 + 0.5 * offset.dot(bitangent) / self.radius,
+        );
 
         if inside {
             normal = -normal
@@ -69,6 +91,21 @@ impl Geometry for Disc {
             normal,
             lambda,
             inside,
+            primitive: 0,
+            uv,
         })
     }
+
+    fn area(&self) -> Float {
+        PI * self.radius.powi(2)
+    }
+
+    fn sample_surface(&self, u: Float, v: Float) -> Option<(Point, Vector)> {
+        let (tangent, bitangent) = tangents(self.normal);
+
+        let (p, _) = concentric_disk(u, v);
+        let position = self.center + (tangent * p.x + bitangent * p.y) * self.radius;
+
+        Some((position, self.normal))
+    }
 }
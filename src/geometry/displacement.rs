@@ -0,0 +1,72 @@
+//! Displacement mapping, moving the surface of a mesh along its normal
+//! to add detail such as the mortar between bricks or the relief of a
+//! terrain.
+//!
+//! The mesh is first split into many small triangles, which are then
+//! displaced by a height function.  This happens once, when the scene
+//! is built: the result is an ordinary `Mesh`, with a bounding volume
+//! hierarchy over the displaced triangles.
+
+use geometry::subdivision::{refine, smooth_normals};
+use geometry::{Mesh, Point};
+use Float;
+
+impl Mesh {
+    /// Splits every triangle into `4^levels` smaller ones, and moves
+    /// each vertex along the normal by `height` at its position.
+    ///
+    /// The vertices move along the normal averaged over the adjacent
+    /// triangles, so that triangles sharing a vertex across a crease
+    /// stay connected; the result is shaded smoothly with normals of
+    /// the displaced triangles.  Vertex normals of `self` are ignored.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::consts::PI;
+    /// use lucifer::geometry::{Geometry, Mesh, Point, Ray, Vector};
+    ///
+    /// // A square of terrain, facing up, with ridges along `z`.
+    /// let square = Mesh::new(
+    ///     vec![
+    ///         Point::new(-1.0, 0.0, 1.0),
+    ///         Point::new(1.0, 0.0, 1.0),
+    ///         Point::new(1.0, 0.0, -1.0),
+    ///         Point::new(-1.0, 0.0, -1.0),
+    ///     ],
+    ///     None,
+    ///     vec![[0, 1, 2], [0, 2, 3]],
+    /// );
+    /// let terrain = square.displace(4, |p| 0.05 * (1.0 + (4.0 * PI * p.x).cos()));
+    /// assert_eq!(terrain.triangle_count(), 2 * 4 * 4 * 4 * 4);
+    /// assert!(terrain.bounds().max.y > 0.09);
+    ///
+    /// // On a ridge, and in the valley beside it.
+    /// let down = Vector::new(0.0, -1.0, 0.0);
+    /// let ridge = Point::new(0.01, 1.0, 0.4);
+    /// let ridge = terrain.intersect(&Ray::new(ridge, down));
+    /// assert!(ridge.unwrap().lambda < 0.91);
+    /// let valley = Point::new(0.26, 1.0, 0.4);
+    /// let valley = terrain.intersect(&Ray::new(valley, down));
+    /// assert!(valley.unwrap().lambda > 0.99);
+    /// ```
+    pub fn displace<F>(&self, levels: usize, height: F) -> Mesh
+    where
+        F: Fn(Point) -> Float,
+    {
+        let mut positions = self.positions().to_vec();
+        let mut indices = self.indices().to_vec();
+        for _ in 0..levels {
+            let (p, i) = refine(&positions, &indices, false);
+            positions = p;
+            indices = i;
+        }
+
+        let normals = smooth_normals(&positions, &indices);
+        for (position, normal) in positions.iter_mut().zip(normals) {
+            *position += normal * height(*position);
+        }
+
+        let normals = smooth_normals(&positions, &indices);
+        Mesh::new(positions, Some(normals), indices)
+    }
+}
@@ -0,0 +1,365 @@
+use std::mem;
+
+use geometry::{Bounds, Geometry, Intersection, Point, Ray, Vector};
+use trace::Span;
+use Float;
+
+/// The most cells along any axis.
+const MAX_RESOLUTION: usize = 64;
+
+/// A uniform grid over a list of primitives, itself a `Geometry`.
+///
+/// The grid divides the bounds of the primitives into equal cells,
+/// each listing the primitives overlapping it, and rays step from cell
+/// to cell with a 3D-DDA, front to back, until they hit something.
+/// Building takes a single pass over the primitives, much cheaper than
+/// building a `Bvh`, which makes the grid the better choice for many
+/// small, evenly spread primitives like the particles of a dust cloud
+/// or a snow storm.  Clustered primitives leave most cells empty and
+/// crowd a few, so a `Bvh` serves them better.
+///
+/// Hits report the index of the primitive in the list as their
+/// `primitive`.  Unbounded primitives (e.g. a `Plane`) are kept aside
+/// and tested by every ray.
+///
+/// # Examples
+/// ```
+/// use lucifer::geometry::{Geometry, Grid, Point, Ray, Sphere, Vector};
+/// use lucifer::Float;
+///
+/// // A cloud of a thousand particles on a lattice.
+/// let particles: Vec<Sphere> = (0..1000)
+///     .map(|i| {
+///         let (x, y, z) = (i % 10, i / 10 % 10, i / 100);
+///         let center = Point::new(x as Float, y as Float, z as Float);
+///         Sphere::new(center, 0.2)
+///     })
+///     .collect();
+/// let cloud = Grid::new(particles);
+/// assert_eq!(cloud.bounds().max, Point::new(9.2, 9.2, 9.2));
+///
+/// let ray = Ray::new(Point::new(-5.0, 3.0, 4.0), Vector::new(1.0, 0.0, 0.0));
+/// let hit = cloud.intersect(&ray).unwrap();
+/// assert!((hit.lambda - 4.8).abs() < 1e-5);
+/// assert_eq!(hit.primitive, 430);
+///
+/// // Between the rows.
+/// let ray = Ray::new(Point::new(-5.0, 3.5, 4.5), Vector::new(1.0, 0.0, 0.0));
+/// assert!(cloud.intersect(&ray).is_none());
+/// assert!(!cloud.occlude(&ray));
+/// ```
+#[derive(Clone, Debug)]
+pub struct Grid<G> {
+    primitives: Vec<G>,
+    /// The bounds of the bounded primitives, divided into the cells.
+    bounds: Bounds,
+    resolution: [usize; 3],
+    cell_size: Vector,
+    /// The position in `items` of the first primitive of every cell,
+    /// and the end of the last cell.
+    offsets: Vec<usize>,
+    items: Vec<usize>,
+    unbounded: Vec<usize>,
+    /// The running sum of the areas of the primitives.
+    areas: Vec<Float>,
+}
+
+impl<G: Geometry> Grid<G> {
+    /// Builds a `Grid` over `primitives`, with about three cells per
+    /// primitive along every axis of a cube.
+    pub fn new(primitives: Vec<G>) -> Grid<G> {
+        let _span = Span::enter("build grid");
+        let bounds: Vec<Bounds> = primitives.iter().map(Geometry::bounds).collect();
+
+        let mut total = Bounds::empty();
+        let mut bounded = 0;
+        let mut unbounded = Vec::new();
+        for (i, b) in bounds.iter().enumerate() {
+            if b.is_empty() {
+                continue;
+            } else if b.is_finite() {
+                total = total.union(b);
+                bounded += 1;
+            } else {
+                unbounded.push(i);
+            }
+        }
+
+        let mut resolution = [1; 3];
+        let mut cell_size = Vector::new(0.0, 0.0, 0.0);
+        if bounded > 0 {
+            let extent = total.diagonal();
+            let widest = extent[0].max(extent[1]).max(extent[2]);
+            if widest > 0.0 {
+                let per_unit = 3.0 * (bounded as Float).cbrt() / widest;
+                for axis in 0..3 {
+                    let cells = (extent[axis] * per_unit).round() as usize;
+                    resolution[axis] = cells.clamp(1, MAX_RESOLUTION);
+                }
+            }
+            for axis in 0..3 {
+                cell_size[axis] = extent[axis] / resolution[axis] as Float;
+            }
+        }
+
+        let mut grid = Grid {
+            primitives,
+            bounds: total,
+            resolution,
+            cell_size,
+            offsets: Vec::new(),
+            items: Vec::new(),
+            unbounded,
+            areas: Vec::new(),
+        };
+
+        // Count the primitives of every cell, then place them.
+        let cells = resolution[0] * resolution[1] * resolution[2];
+        let mut counts = vec![0; cells];
+        let ranges: Vec<Option<([usize; 3], [usize; 3])>> = bounds
+            .iter()
+            .map(|b| {
+                if bounded == 0 || b.is_empty() || !b.is_finite() {
+                    return None;
+                }
+                let lo = grid.cell_of(b.min);
+                let hi = grid.cell_of(b.max);
+                for z in lo[2]..=hi[2] {
+                    for y in lo[1]..=hi[1] {
+                        for x in lo[0]..=hi[0] {
+                            counts[grid.index([x, y, z])] += 1;
+                        }
+                    }
+                }
+                Some((lo, hi))
+            })
+            .collect();
+
+        grid.offsets.reserve_exact(cells + 1);
+        grid.offsets.push(0);
+        for count in &counts {
+            let end = grid.offsets.last().cloned().unwrap_or(0) + count;
+            grid.offsets.push(end);
+        }
+
+        let mut filled = grid.offsets[..cells].to_vec();
+        grid.items = vec![0; grid.offsets[cells]];
+        for (i, range) in ranges.into_iter().enumerate() {
+            if let Some((lo, hi)) = range {
+                for z in lo[2]..=hi[2] {
+                    for y in lo[1]..=hi[1] {
+                        for x in lo[0]..=hi[0] {
+                            let cell = grid.index([x, y, z]);
+                            grid.items[filled[cell]] = i;
+                            filled[cell] += 1;
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut sum = 0.0;
+        grid.areas = grid
+            .primitives
+            .iter()
+            .map(|p| {
+                sum += p.area();
+                sum
+            })
+            .collect();
+
+        debug!(
+            "grid of {} primitives has {}x{}x{} cells with {} references",
+            grid.primitives.len(),
+            resolution[0],
+            resolution[1],
+            resolution[2],
+            grid.items.len()
+        );
+        grid
+    }
+
+    /// The primitives, in the order they were given.
+    pub fn primitives(&self) -> &[G] {
+        &self.primitives
+    }
+
+    /// The cell containing `point`, clamped to the grid.
+    fn cell_of(&self, point: Point) -> [usize; 3] {
+        let mut cell = [0; 3];
+        for (axis, c) in cell.iter_mut().enumerate() {
+            if self.resolution[axis] > 1 {
+                let x = (point[axis] - self.bounds.min[axis]) / self.cell_size[axis];
+                *c = (x.max(0.0) as usize).min(self.resolution[axis] - 1);
+            }
+        }
+        cell
+    }
+
+    /// The position of `cell` in `offsets`.
+    fn index(&self, cell: [usize; 3]) -> usize {
+        (cell[2] * self.resolution[1] + cell[1]) * self.resolution[0] + cell[0]
+    }
+
+    /// The primitives overlapping `cell`.
+    fn cell(&self, cell: [usize; 3]) -> &[usize] {
+        let index = self.index(cell);
+        &self.items[self.offsets[index]..self.offsets[index + 1]]
+    }
+
+    /// Steps through the cells hit by `ray`, front to back, calling
+    /// `visit` with the primitives of each and the distance at which
+    /// the ray leaves it, until `visit` returns `true`.
+    fn walk<F>(&self, ray: &Ray, mut visit: F) -> bool
+    where
+        F: FnMut(&[usize], Float) -> bool,
+    {
+        if self.offsets.len() < 2 {
+            return false;
+        }
+
+        // Clip the ray to the grid (the slab test).
+        let mut near: Float = 0.0;
+        let mut far = ray.length;
+        for axis in 0..3 {
+            let inv = 1.0 / ray.direction[axis];
+            let t0 = (self.bounds.min[axis] - ray.origin[axis]) * inv;
+            let t1 = (self.bounds.max[axis] - ray.origin[axis]) * inv;
+            near = near.max(t0.min(t1));
+            far = far.min(t0.max(t1));
+        }
+        if near > far {
+            return false;
+        }
+
+        let mut cell = self.cell_of(ray.origin + ray.direction * near);
+        let mut next = [Float::INFINITY; 3];
+        let mut delta = [Float::INFINITY; 3];
+        for axis in 0..3 {
+            let d = ray.direction[axis];
+            if self.resolution[axis] == 1 || d == 0.0 {
+                continue;
+            }
+            let size = self.cell_size[axis];
+            let step = if d > 0.0 { cell[axis] + 1 } else { cell[axis] };
+            let plane = self.bounds.min[axis] + step as Float * size;
+            next[axis] = (plane - ray.origin[axis]) / d;
+            delta[axis] = size / d.abs();
+        }
+
+        loop {
+            let axis = if next[0] < next[1] && next[0] < next[2] {
+                0
+            } else if next[1] < next[2] {
+                1
+            } else {
+                2
+            };
+            let exit = next[axis].min(far);
+            if visit(self.cell(cell), exit) {
+                return true;
+            }
+            if next[axis] > far {
+                return false;
+            }
+
+            if ray.direction[axis] > 0.0 {
+                cell[axis] += 1;
+                if cell[axis] == self.resolution[axis] {
+                    return false;
+                }
+            } else {
+                if cell[axis] == 0 {
+                    return false;
+                }
+                cell[axis] -= 1;
+            }
+            next[axis] += delta[axis];
+        }
+    }
+}
+
+impl<G: Geometry> Geometry for Grid<G> {
+    fn intersect(&self, ray: &Ray) -> Option<Intersection> {
+        let test = |i: usize, nearest: &mut Option<Intersection>| {
+            if let Some(mut hit) = self.primitives[i].intersect(ray) {
+                if nearest.as_ref().is_none_or(|n| hit.lambda < n.lambda) {
+                    hit.primitive = i;
+                    *nearest = Some(hit);
+                }
+            }
+        };
+
+        let mut nearest = None;
+        for &i in &self.unbounded {
+            test(i, &mut nearest);
+        }
+        // A hit beyond the cell may still be preceded by one in a
+        // later cell, so only stop once the nearest lies in this one.
+        self.walk(ray, |items, exit| {
+            for &i in items {
+                test(i, &mut nearest);
+            }
+            nearest.as_ref().is_some_and(|n| n.lambda <= exit)
+        });
+
+        nearest
+    }
+
+    fn occlude(&self, ray: &Ray) -> bool {
+        self.unbounded
+            .iter()
+            .any(|&i| self.primitives[i].occlude(ray))
+            || self.walk(ray, |items, _| {
+                items.iter().any(|&i| self.primitives[i].occlude(ray))
+            })
+    }
+
+    fn bounds(&self) -> Bounds {
+        if !self.unbounded.is_empty() {
+            Bounds::infinite()
+        } else {
+            self.bounds
+        }
+    }
+
+    fn triangle_count(&self) -> usize {
+        self.primitives.iter().map(Geometry::triangle_count).sum()
+    }
+
+    fn memory(&self) -> usize {
+        let primitives: usize = self.primitives.iter().map(Geometry::memory).sum();
+        mem::size_of::<Grid<G>>()
+            + primitives
+            + (self.offsets.capacity() + self.items.capacity() + self.unbounded.capacity())
+                * mem::size_of::<usize>()
+            + self.areas.capacity() * mem::size_of::<Float>()
+    }
+
+    fn area(&self) -> Float {
+        self.areas.last().cloned().unwrap_or(0.0)
+    }
+
+    /// Picks a primitive by its area with `u`, and samples it with
+    /// what is left of `u` and `v`.
+    fn sample_surface(&self, u: Float, v: Float) -> Option<(Point, Vector)> {
+        let total = self.area();
+        if total <= 0.0 {
+            return None;
+        }
+
+        let target = u * total;
+        let primitive = self
+            .areas
+            .partition_point(|&sum| sum <= target)
+            .min(self.areas.len() - 1);
+        let start = if primitive > 0 {
+            self.areas[primitive - 1]
+        } else {
+            0.0
+        };
+        let area = self.areas[primitive] - start;
+        let u = ((target - start) / area).clamp(0.0, 1.0);
+        self.primitives[primitive].sample_surface(u, v)
+    }
+}
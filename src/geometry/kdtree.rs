@@ -0,0 +1,515 @@
+use std::mem;
+
+use smallvec::SmallVec;
+
+use geometry::{Bounds, Geometry, Intersection, Point, Ray, Vector};
+use trace::Span;
+use Float;
+
+/// The number of items below which a node is never split.
+const LEAF_SIZE: usize = 2;
+
+/// The deepest a tree may grow, which also bounds the number of nodes
+/// traversal keeps on the call stack.
+const MAX_DEPTH: usize = 64;
+
+/// The cost of testing an item, relative to descending one node.
+const ITEM_COST: Float = 80.0;
+
+/// The reduction of the cost of splits that leave one side empty,
+/// which cut away empty space rays can skip.
+const EMPTY_BONUS: Float = 0.5;
+
+#[derive(Clone, Debug)]
+struct Node {
+    /// The splitting plane's position along `axis`, for inner nodes.
+    split: Float,
+    /// For leaves, the position of the first item; for inner nodes,
+    /// the index of the second child (the first child, below the
+    /// plane, directly follows its parent).
+    offset: usize,
+    /// The number of items of a leaf.
+    count: usize,
+    /// The axis the plane of an inner node is perpendicular to, `3`
+    /// for leaves.
+    axis: usize,
+}
+
+/// A kd-tree over a set of items, given by their bounding boxes.
+///
+/// Like a `Bvh`, which it can replace, the tree only stores the
+/// bounds, and the items are tested by the caller during traversal;
+/// see `KdTreeGroup` for a `Geometry` over a list of primitives.
+/// Instead of grouping the items, the tree splits space by planes
+/// chosen with the surface area heuristic, so an item crossing a plane
+/// is referenced from both sides.  Building takes longer and uses more
+/// memory, but the tree cuts away empty space, and rays step through
+/// the cells strictly front to back, which often pays off for large
+/// static scenes of axis-aligned architecture.
+///
+/// Items with infinite bounds (e.g. a `Plane`) are kept aside and
+/// always tested, items with empty bounds are never tested.
+///
+/// # Examples
+/// ```
+/// use lucifer::geometry::{Bounds, KdTree, Point, Ray, Vector};
+/// use lucifer::Float;
+///
+/// let boxes: Vec<Bounds> = (0..100)
+///     .map(|i| {
+///         let x = i as Float * 2.0;
+///         Bounds::new(Point::new(x, -1.0, -1.0), Point::new(x + 1.0, 1.0, 1.0))
+///     })
+///     .collect();
+/// let tree = KdTree::new(&boxes);
+///
+/// // Only the items of the cell the ray passes through are tested.
+/// let ray = Ray::new(Point::new(21.5, 0.0, 5.0), Vector::new(0.0, 0.0, -1.0));
+/// let mut tested = Vec::new();
+/// tree.traverse(&ray, |i| {
+///     tested.push(i);
+///     None
+/// });
+///
+/// assert!(tested.contains(&10));
+/// assert!(tested.len() <= 2);
+/// assert_eq!(tree.bounds().max, Point::new(199.0, 1.0, 1.0));
+///
+/// // Along the row, the nearest box stops the traversal.
+/// let ray = Ray::new(Point::new(-5.0, 0.0, 0.0), Vector::new(1.0, 0.0, 0.0));
+/// let mut tested = Vec::new();
+/// tree.traverse(&ray, |i| {
+///     tested.push(i);
+///     Some(5.0 + 2.0 * i as Float)
+/// });
+/// assert!(tested.contains(&0));
+/// assert!(tested.len() <= 2);
+/// ```
+#[derive(Clone, Debug)]
+pub struct KdTree {
+    nodes: Vec<Node>,
+    items: Vec<usize>,
+    unbounded: Vec<usize>,
+    bounds: Bounds,
+}
+
+impl KdTree {
+    /// Builds a `KdTree` over items with the given `bounds`.
+    pub fn new(bounds: &[Bounds]) -> KdTree {
+        let _span = Span::enter("build kd-tree");
+        let mut tree = KdTree {
+            nodes: Vec::new(),
+            items: Vec::new(),
+            unbounded: Vec::new(),
+            bounds: Bounds::empty(),
+        };
+        let mut items = Vec::new();
+
+        for (i, b) in bounds.iter().enumerate() {
+            if b.is_empty() {
+                continue;
+            } else if b.is_finite() {
+                items.push(i);
+                tree.bounds = tree.bounds.union(b);
+            } else {
+                tree.unbounded.push(i);
+            }
+        }
+
+        if !items.is_empty() {
+            let depth = (8.0 + 1.3 * (items.len() as Float).log2()) as usize;
+            let root = tree.bounds;
+            tree.build(bounds, items, root, depth.min(MAX_DEPTH));
+            tree.nodes.shrink_to_fit();
+            tree.items.shrink_to_fit();
+        }
+
+        debug!(
+            "kd-tree has {} nodes with {} item references, {} items are unbounded",
+            tree.nodes.len(),
+            tree.items.len(),
+            tree.unbounded.len()
+        );
+        tree
+    }
+
+    fn build(&mut self, bounds: &[Bounds], items: Vec<usize>, cell: Bounds, depth: usize) {
+        let index = self.nodes.len();
+        let split = if items.len() > LEAF_SIZE && depth > 0 {
+            best_split(bounds, &items, &cell)
+        } else {
+            None
+        };
+
+        let (axis, split) = match split {
+            Some(split) => split,
+            None => {
+                self.nodes.push(Node {
+                    split: 0.0,
+                    offset: self.items.len(),
+                    count: items.len(),
+                    axis: 3,
+                });
+                self.items.extend(items);
+                return;
+            }
+        };
+
+        self.nodes.push(Node {
+            split,
+            offset: 0,
+            count: 0,
+            axis,
+        });
+
+        let (below, above) = sides(axis, split);
+        let mut lower = cell;
+        lower.max[axis] = split;
+        let mut upper = cell;
+        upper.min[axis] = split;
+
+        let left = items.iter().cloned().filter(|&i| below(&bounds[i]));
+        self.build(bounds, left.collect(), lower, depth - 1);
+        self.nodes[index].offset = self.nodes.len();
+        let right = items.into_iter().filter(|&i| above(&bounds[i]));
+        self.build(bounds, right.collect(), upper, depth - 1);
+    }
+
+    /// The memory used by the tree, in bytes.
+    pub fn memory(&self) -> usize {
+        mem::size_of::<KdTree>()
+            + self.nodes.capacity() * mem::size_of::<Node>()
+            + (self.items.capacity() + self.unbounded.capacity()) * mem::size_of::<usize>()
+    }
+
+    /// The bounds of all items.
+    pub fn bounds(&self) -> Bounds {
+        if !self.unbounded.is_empty() {
+            Bounds::infinite()
+        } else {
+            self.bounds
+        }
+    }
+
+    /// Calls `visit` for every item in the cells `ray` passes through,
+    /// front to back.  `visit` returns the distance to the item's
+    /// intersection, if any, and cells beyond the nearest intersection
+    /// found so far are skipped.  Items in several cells may be
+    /// visited more than once.
+    pub fn traverse<F>(&self, ray: &Ray, mut visit: F)
+    where
+        F: FnMut(usize) -> Option<Float>,
+    {
+        let mut max = ray.length;
+        for &item in &self.unbounded {
+            if let Some(lambda) = visit(item) {
+                max = max.min(lambda);
+            }
+        }
+
+        self.walk(ray, max, |item, max| {
+            if let Some(lambda) = visit(item) {
+                *max = max.min(lambda);
+            }
+            false
+        });
+    }
+
+    /// Whether `test` returns `true` for any item in the cells `ray`
+    /// passes through.
+    pub fn any<F>(&self, ray: &Ray, mut test: F) -> bool
+    where
+        F: FnMut(usize) -> bool,
+    {
+        self.unbounded.iter().any(|&item| test(item))
+            || self.walk(ray, ray.length, |item, _| test(item))
+    }
+
+    /// Walks the cells hit by `ray` within `max`, front to back, until
+    /// `visit` returns `true`.  `visit` may shorten `max`.
+    fn walk<F>(&self, ray: &Ray, max: Float, mut visit: F) -> bool
+    where
+        F: FnMut(usize, &mut Float) -> bool,
+    {
+        if self.nodes.is_empty() {
+            return false;
+        }
+
+        let inv_direction = Vector::new(
+            1.0 / ray.direction[0],
+            1.0 / ray.direction[1],
+            1.0 / ray.direction[2],
+        );
+        let (near, far) = match clip(&self.bounds, ray.origin, inv_direction, max) {
+            Some(range) => range,
+            None => return false,
+        };
+
+        let mut max = max;
+        let mut stack: SmallVec<[(usize, Float, Float); MAX_DEPTH]> = SmallVec::new();
+        stack.push((0, near, far));
+        while let Some((mut index, near, mut far)) = stack.pop() {
+            if near > max {
+                continue;
+            }
+
+            loop {
+                let node = &self.nodes[index];
+                if node.axis == 3 {
+                    for &item in &self.items[node.offset..node.offset + node.count] {
+                        if visit(item, &mut max) {
+                            return true;
+                        }
+                    }
+                    break;
+                }
+
+                // The child containing the origin comes first; rays
+                // starting on the plane go the way they point.
+                let axis = node.axis;
+                let t = (node.split - ray.origin[axis]) * inv_direction[axis];
+                let below_first = ray.origin[axis] < node.split
+                    || (ray.origin[axis] == node.split && ray.direction[axis] <= 0.0);
+                let (first, second) = if below_first {
+                    (index + 1, node.offset)
+                } else {
+                    (node.offset, index + 1)
+                };
+
+                if t > far || t <= 0.0 {
+                    index = first;
+                } else if t < near {
+                    index = second;
+                } else {
+                    stack.push((second, t, far));
+                    index = first;
+                    far = t;
+                }
+            }
+        }
+
+        false
+    }
+}
+
+/// A list of primitives in a `KdTree`, itself a `Geometry`, like a
+/// `Grid`, or the `Bvh` of a `Mesh`.
+///
+/// Hits report the index of the primitive in the list as their
+/// `primitive`.  Unbounded primitives (e.g. a `Plane`) are kept aside
+/// and tested by every ray.
+///
+/// # Examples
+/// ```
+/// use lucifer::geometry::{Cube, Geometry, KdTreeGroup, Point, Ray, Vector};
+/// use lucifer::Float;
+///
+/// // A hall of pillars along the x axis.
+/// let pillars: Vec<Cube> = (0..50)
+///     .map(|i| {
+///         let x = i as Float * 4.0;
+///         Cube::new(Point::new(x + 0.5, 5.0, 0.5), Vector::new(1.0, 10.0, 1.0))
+///     })
+///     .collect();
+/// let hall = KdTreeGroup::new(pillars);
+/// assert_eq!(hall.bounds().max, Point::new(197.0, 10.0, 1.0));
+///
+/// let ray = Ray::new(Point::new(-5.0, 5.0, 0.5), Vector::new(1.0, 0.0, 0.0));
+/// let hit = hall.intersect(&ray).unwrap();
+/// assert_eq!(hit.lambda, 5.0);
+/// assert_eq!(hit.primitive, 0);
+///
+/// let ray = Ray::new(Point::new(80.5, 5.0, 5.0), Vector::new(0.0, 0.0, -1.0));
+/// assert_eq!(hall.intersect(&ray).unwrap().primitive, 20);
+///
+/// // Between the pillars.
+/// let ray = Ray::new(Point::new(2.5, 5.0, 5.0), Vector::new(0.0, 0.0, -1.0));
+/// assert!(hall.intersect(&ray).is_none());
+/// assert!(!hall.occlude(&ray));
+/// ```
+#[derive(Clone, Debug)]
+pub struct KdTreeGroup<G> {
+    primitives: Vec<G>,
+    tree: KdTree,
+    /// The running sum of the areas of the primitives.
+    areas: Vec<Float>,
+}
+
+impl<G: Geometry> KdTreeGroup<G> {
+    /// Builds a `KdTree` over `primitives`.
+    pub fn new(primitives: Vec<G>) -> KdTreeGroup<G> {
+        let bounds: Vec<Bounds> = primitives.iter().map(Geometry::bounds).collect();
+        let tree = KdTree::new(&bounds);
+
+        let mut sum = 0.0;
+        let areas = primitives
+            .iter()
+            .map(|p| {
+                sum += p.area();
+                sum
+            })
+            .collect();
+
+        KdTreeGroup {
+            primitives,
+            tree,
+            areas,
+        }
+    }
+
+    /// The primitives, in the order they were given.
+    pub fn primitives(&self) -> &[G] {
+        &self.primitives
+    }
+}
+
+impl<G: Geometry> Geometry for KdTreeGroup<G> {
+    fn intersect(&self, ray: &Ray) -> Option<Intersection> {
+        let mut nearest: Option<Intersection> = None;
+        self.tree.traverse(ray, |i| {
+            let mut hit = self.primitives[i].intersect(ray)?;
+            let lambda = hit.lambda;
+            if nearest.as_ref().is_none_or(|n| lambda < n.lambda) {
+                hit.primitive = i;
+                nearest = Some(hit);
+            }
+            Some(lambda)
+        });
+        nearest
+    }
+
+    fn occlude(&self, ray: &Ray) -> bool {
+        self.tree.any(ray, |i| self.primitives[i].occlude(ray))
+    }
+
+    fn bounds(&self) -> Bounds {
+        self.tree.bounds()
+    }
+
+    fn triangle_count(&self) -> usize {
+        self.primitives.iter().map(Geometry::triangle_count).sum()
+    }
+
+    fn memory(&self) -> usize {
+        let primitives: usize = self.primitives.iter().map(Geometry::memory).sum();
+        mem::size_of::<KdTreeGroup<G>>() - mem::size_of::<KdTree>()
+            + self.tree.memory()
+            + primitives
+            + self.areas.capacity() * mem::size_of::<Float>()
+    }
+
+    fn area(&self) -> Float {
+        self.areas.last().cloned().unwrap_or(0.0)
+    }
+
+    /// Picks a primitive by its area with `u`, and samples it with
+    /// what is left of `u` and `v`.
+    fn sample_surface(&self, u: Float, v: Float) -> Option<(Point, Vector)> {
+        let total = self.area();
+        if total <= 0.0 {
+            return None;
+        }
+
+        let target = u * total;
+        let primitive = self
+            .areas
+            .partition_point(|&sum| sum <= target)
+            .min(self.areas.len() - 1);
+        let start = if primitive > 0 {
+            self.areas[primitive - 1]
+        } else {
+            0.0
+        };
+        let area = self.areas[primitive] - start;
+        let u = ((target - start) / area).clamp(0.0, 1.0);
+        self.primitives[primitive].sample_surface(u, v)
+    }
+}
+
+/// Tests telling whether an item's bounds belong below and above the
+/// plane at `split` along `axis`.  Items touching the plane from one
+/// side only belong to that side, flat items on the plane belong
+/// below.
+fn sides(axis: usize, split: Float) -> (impl Fn(&Bounds) -> bool, impl Fn(&Bounds) -> bool) {
+    let below = move |b: &Bounds| b.min[axis] < split || b.max[axis] <= split;
+    let above = move |b: &Bounds| b.max[axis] > split;
+    (below, above)
+}
+
+/// The plane splitting `cell` with the lowest cost by the surface area
+/// heuristic, if any is cheaper than a leaf.  Candidates are the faces
+/// of the items' bounds within the cell.
+fn best_split(bounds: &[Bounds], items: &[usize], cell: &Bounds) -> Option<(usize, Float)> {
+    let area = |d: Vector| 2.0 * (d[0] * d[1] + d[1] * d[2] + d[2] * d[0]);
+    let diagonal = cell.diagonal();
+    let total = area(diagonal);
+    if total <= 0.0 {
+        return None;
+    }
+
+    let leaf_cost = ITEM_COST * items.len() as Float;
+    let mut best: Option<(Float, usize, Float)> = None;
+    for axis in 0..3 {
+        let mut mins: Vec<Float> = items.iter().map(|&i| bounds[i].min[axis]).collect();
+        let mut maxs: Vec<Float> = items.iter().map(|&i| bounds[i].max[axis]).collect();
+        mins.sort_by(Float::total_cmp);
+        maxs.sort_by(Float::total_cmp);
+
+        for &split in mins.iter().chain(&maxs) {
+            if split <= cell.min[axis] || split >= cell.max[axis] {
+                continue;
+            }
+
+            let below = mins.partition_point(|&m| m < split);
+            let above = items.len() - maxs.partition_point(|&m| m <= split);
+
+            let mut lower = diagonal;
+            lower[axis] = split - cell.min[axis];
+            let mut upper = diagonal;
+            upper[axis] = cell.max[axis] - split;
+
+            let bonus = if below == 0 || above == 0 {
+                EMPTY_BONUS
+            } else {
+                0.0
+            };
+            let cost = 1.0
+                + ITEM_COST
+                    * (1.0 - bonus)
+                    * (area(lower) * below as Float + area(upper) * above as Float)
+                    / total;
+            if cost < leaf_cost && best.is_none_or(|b| cost < b.0) {
+                best = Some((cost, axis, split));
+            }
+        }
+    }
+
+    best.map(|(_, axis, split)| (axis, split))
+}
+
+/// The range of distances along a ray within `bounds` and `max`, if
+/// it enters them (the slab test).
+fn clip(
+    bounds: &Bounds,
+    origin: Point,
+    inv_direction: Vector,
+    max: Float,
+) -> Option<(Float, Float)> {
+    let mut near: Float = 0.0;
+    let mut far = max;
+
+    for i in 0..3 {
+        let t0 = (bounds.min[i] - origin[i]) * inv_direction[i];
+        let t1 = (bounds.max[i] - origin[i]) * inv_direction[i];
+        // `min` and `max` ignore the NaN of rays parallel to a slab
+        // and starting on its boundary.
+        near = near.max(t0.min(t1));
+        far = far.min(t0.max(t1));
+    }
+
+    if near <= far {
+        Some((near, far))
+    } else {
+        None
+    }
+}
@@ -0,0 +1,610 @@
+use std::mem;
+
+use cgmath::prelude::*;
+use cgmath::Matrix4;
+
+use geometry::bvh::Split;
+use geometry::triangle::intersect_triangle;
+use geometry::{Bounds, Bvh, Geometry, Intersection, Point, Ray, Vector};
+use montecarlo::warp::uniform_triangle;
+use scene::invert;
+use Error;
+use Float;
+
+/// A mesh of triangles sharing their vertices.
+///
+/// The triangles are given by the indices of their vertices,
+/// counter-clockwise around the front, which is outside for closed
+/// meshes.  With per-vertex normals, the normal is interpolated across
+/// each triangle for smooth shading; otherwise, every triangle is
+/// flat.  The surface coordinates are interpolated across each
+/// triangle from per-vertex coordinates given with `with_uvs`, or are
+/// otherwise the barycentric coordinates of the second and third
+/// corner of the hit triangle.
+///
+/// To place one mesh many times, share it between objects with
+/// `Object::shared`; only its transformation is stored per object.
+///
+/// # Examples
+/// ```
+/// use lucifer::geometry::mesh::Mesh;
+/// use lucifer::geometry::{Geometry, Point, Ray, Vector};
+///
+/// // A unit square in the `xy` plane, facing `+z`.
+/// let square = Mesh::new(
+///     vec![
+///         Point::new(0.0, 0.0, 0.0),
+///         Point::new(1.0, 0.0, 0.0),
+///         Point::new(1.0, 1.0, 0.0),
+///         Point::new(0.0, 1.0, 0.0),
+///     ],
+///     None,
+///     vec![[0, 1, 2], [0, 2, 3]],
+/// );
+/// assert_eq!(square.triangle_count(), 2);
+/// assert_eq!(square.area(), 1.0);
+///
+/// let ray = Ray::new(Point::new(0.25, 0.75, 1.0), Vector::new(0.0, 0.0, -1.0));
+/// let hit = square.intersect(&ray).unwrap();
+/// assert_eq!(hit.lambda, 1.0);
+/// assert_eq!(hit.normal, Vector::new(0.0, 0.0, 1.0));
+/// assert_eq!(hit.primitive, 1);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Mesh {
+    positions: Vec<Point>,
+    normals: Option<Vec<Vector>>,
+    uvs: Option<Vec<(Float, Float)>>,
+    indices: Vec<[u32; 3]>,
+    /// The sums of the areas of all triangles up to each one.
+    areas: Vec<Float>,
+    bvh: Bvh,
+    /// The hierarchy of the triangles laid out in their surface
+    /// coordinates, at `z = 0`, if the vertices have any.
+    uv_bvh: Option<Bvh>,
+    /// How the hierarchies divide the triangles.
+    split: Split,
+}
+
+impl Mesh {
+    /// Creates a new `Mesh` of triangles with the vertices at
+    /// `indices` of `positions` and, if given, `normals`.
+    ///
+    /// # Panics
+    /// Panics if an index is out of range, or the number of normals
+    /// differs from the number of positions; see `try_new`.
+    pub fn new(
+        positions: Vec<Point>,
+        normals: Option<Vec<Vector>>,
+        indices: Vec<[u32; 3]>,
+    ) -> Mesh {
+        Mesh::try_new(positions, normals, indices).unwrap_or_else(|e| panic!("invalid mesh: {}", e))
+    }
+
+    /// Creates a new `Mesh`, failing if an index is out of range, or
+    /// the number of normals differs from the number of positions.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::geometry::mesh::Mesh;
+    /// use lucifer::geometry::{Point, Vector};
+    ///
+    /// let positions = vec![
+    ///     Point::new(0.0, 0.0, 0.0),
+    ///     Point::new(1.0, 0.0, 0.0),
+    ///     Point::new(0.0, 1.0, 0.0),
+    /// ];
+    /// let normals = vec![Vector::new(0.0, 0.0, 1.0); 2];
+    ///
+    /// assert!(Mesh::try_new(positions.clone(), None, vec![[0, 1, 2]]).is_ok());
+    /// assert!(Mesh::try_new(positions.clone(), None, vec![[0, 1, 3]]).is_err());
+    /// assert!(Mesh::try_new(positions, Some(normals), vec![[0, 1, 2]]).is_err());
+    /// ```
+    pub fn try_new(
+        positions: Vec<Point>,
+        normals: Option<Vec<Vector>>,
+        indices: Vec<[u32; 3]>,
+    ) -> Result<Mesh, Error> {
+        Mesh::try_build(positions, normals, indices, Split::default())
+    }
+
+    /// Like `try_new`, dividing the triangles by `split`.
+    fn try_build(
+        positions: Vec<Point>,
+        normals: Option<Vec<Vector>>,
+        indices: Vec<[u32; 3]>,
+        split: Split,
+    ) -> Result<Mesh, Error> {
+        if let Some(ref normals) = normals {
+            if normals.len() != positions.len() {
+                return Err(Error::Parse(format!(
+                    "mesh has {} normals for {} vertices",
+                    normals.len(),
+                    positions.len()
+                )));
+            }
+        }
+        if let Some((t, &i)) = indices
+            .iter()
+            .enumerate()
+            .flat_map(|(t, triangle)| triangle.iter().map(move |i| (t, i)))
+            .find(|&(_, &i)| i as usize >= positions.len())
+        {
+            return Err(Error::Parse(format!(
+                "triangle {} refers to vertex {} of {}",
+                t,
+                i,
+                positions.len()
+            )));
+        }
+
+        let corners = |triangle: &[u32; 3]| {
+            let [a, b, c] = *triangle;
+            (
+                positions[a as usize],
+                positions[b as usize],
+                positions[c as usize],
+            )
+        };
+        let mut total = 0.0;
+        let areas = indices
+            .iter()
+            .map(|triangle| {
+                let (a, b, c) = corners(triangle);
+                total += 0.5 * (b - a).cross(c - a).magnitude();
+                total
+            })
+            .collect();
+
+        Ok(Mesh {
+            bvh: Bvh::with_split(&triangle_bounds(&positions, &indices), split),
+            positions,
+            normals,
+            uvs: None,
+            indices,
+            areas,
+            uv_bvh: None,
+            split,
+        })
+    }
+
+    /// Rebuilds the hierarchies of the triangles dividing them by
+    /// `split`, builder style, e.g. with `Split::Median` to build a
+    /// mesh that is traced only briefly sooner.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::cgmath::{vec3, Matrix4};
+    /// use lucifer::geometry::bvh::Split;
+    /// use lucifer::geometry::mesh::Mesh;
+    /// use lucifer::geometry::{Geometry, Point, Ray, Vector};
+    ///
+    /// let positions = vec![
+    ///     Point::new(0.0, 0.0, 0.0),
+    ///     Point::new(1.0, 0.0, 0.0),
+    ///     Point::new(1.0, 1.0, 0.0),
+    ///     Point::new(0.0, 1.0, 0.0),
+    /// ];
+    /// let square = Mesh::new(positions, None, vec![[0, 1, 2], [0, 2, 3]]);
+    /// let ray = Ray::new(Point::new(0.25, 0.75, 1.0), Vector::new(0.0, 0.0, -1.0));
+    ///
+    /// let hit = |mesh: &Mesh| mesh.intersect(&ray).map(|i| (i.lambda, i.primitive));
+    /// let median = square.clone().with_split(Split::Median);
+    /// assert_eq!(hit(&median), hit(&square));
+    /// assert_eq!(median.split(), Split::Median);
+    ///
+    /// // Baking a transformation keeps the split.
+    /// let moved = median.transform(&Matrix4::from_translation(vec3(0.0, 0.0, -1.0)));
+    /// assert_eq!(moved.split(), Split::Median);
+    /// ```
+    pub fn with_split(self, split: Split) -> Mesh {
+        let mut mesh = self;
+        mesh.split = split;
+        mesh.bvh = Bvh::with_split(&triangle_bounds(&mesh.positions, &mesh.indices), split);
+        if let Some(ref uvs) = mesh.uvs {
+            mesh.uv_bvh = Some(Bvh::with_split(&uv_bounds(uvs, &mesh.indices), split));
+        }
+        mesh
+    }
+
+    /// Gives the vertices surface coordinates, e.g. to map a texture
+    /// onto the mesh, builder style.
+    ///
+    /// # Panics
+    /// Panics if the number of coordinates differs from the number of
+    /// vertices; see `try_with_uvs`.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::geometry::mesh::Mesh;
+    /// use lucifer::geometry::{Geometry, Point, Ray, Vector};
+    ///
+    /// let positions = vec![
+    ///     Point::new(0.0, 0.0, 0.0),
+    ///     Point::new(2.0, 0.0, 0.0),
+    ///     Point::new(0.0, 2.0, 0.0),
+    /// ];
+    /// let triangle = Mesh::new(positions, None, vec![[0, 1, 2]]);
+    /// let ray = Ray::new(Point::new(0.5, 1.0, 1.0), Vector::new(0.0, 0.0, -1.0));
+    /// assert_eq!(triangle.intersect(&ray).unwrap().uv, (0.25, 0.5));
+    ///
+    /// // Mapping the corners to the edges of a texture, upside down.
+    /// let mapped = triangle.with_uvs(vec![(0.0, 1.0), (1.0, 1.0), (0.0, 0.0)]);
+    /// assert_eq!(mapped.intersect(&ray).unwrap().uv, (0.25, 0.5));
+    /// let ray = Ray::new(Point::new(0.5, 0.5, 1.0), Vector::new(0.0, 0.0, -1.0));
+    /// assert_eq!(mapped.intersect(&ray).unwrap().uv, (0.25, 0.75));
+    /// ```
+    pub fn with_uvs(self, uvs: Vec<(Float, Float)>) -> Mesh {
+        self.try_with_uvs(uvs)
+            .unwrap_or_else(|e| panic!("invalid mesh: {}", e))
+    }
+
+    /// Gives the vertices surface coordinates, failing if the number
+    /// of coordinates differs from the number of vertices.
+    pub fn try_with_uvs(self, uvs: Vec<(Float, Float)>) -> Result<Mesh, Error> {
+        if uvs.len() != self.positions.len() {
+            return Err(Error::Parse(format!(
+                "mesh has {} surface coordinates for {} vertices",
+                uvs.len(),
+                self.positions.len()
+            )));
+        }
+
+        let mut mesh = self;
+        mesh.uv_bvh = Some(Bvh::with_split(&uv_bounds(&uvs, &mesh.indices), mesh.split));
+        mesh.uvs = Some(uvs);
+        Ok(mesh)
+    }
+
+    /// How the hierarchies divide the triangles; see `with_split`.
+    pub fn split(&self) -> Split {
+        self.split
+    }
+
+    /// The positions of the vertices.
+    pub fn positions(&self) -> &[Point] {
+        &self.positions
+    }
+
+    /// The normals of the vertices, if any.
+    pub fn normals(&self) -> Option<&[Vector]> {
+        self.normals.as_ref().map(|n| &n[..])
+    }
+
+    /// The surface coordinates of the vertices, if any.
+    pub fn uvs(&self) -> Option<&[(Float, Float)]> {
+        self.uvs.as_ref().map(|uvs| &uvs[..])
+    }
+
+    /// The indices of the vertices of every triangle.
+    pub fn indices(&self) -> &[[u32; 3]] {
+        &self.indices
+    }
+
+    /// A copy of the mesh with `transform` baked into its vertices,
+    /// e.g. to place a static mesh in a scene with an identity
+    /// transformation, which spares every ray the transformation into
+    /// the space of the object and of the hit back.
+    ///
+    /// Mirroring transformations swap the second and third corner of
+    /// every triangle, so their fronts stay outside.
+    ///
+    /// # Panics
+    /// Panics if `transform` is not invertible; see `try_transform`.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::cgmath::{vec3, InnerSpace, Matrix4, SquareMatrix};
+    /// use lucifer::geometry::mesh::Mesh;
+    /// use lucifer::geometry::{Geometry, Point, Ray, Vector};
+    /// use lucifer::lighting::{Albedo, Lambert, Radiance};
+    /// use lucifer::scene::{Object, Scene};
+    ///
+    /// // A unit square in the `xy` plane, facing `+z`.
+    /// let square = Mesh::new(
+    ///     vec![
+    ///         Point::new(0.0, 0.0, 0.0),
+    ///         Point::new(1.0, 0.0, 0.0),
+    ///         Point::new(1.0, 1.0, 0.0),
+    ///         Point::new(0.0, 1.0, 0.0),
+    ///     ],
+    ///     None,
+    ///     vec![[0, 1, 2], [0, 2, 3]],
+    /// );
+    /// let place = Matrix4::from_translation(vec3(0.0, 0.0, -5.0)) * Matrix4::from_scale(2.0);
+    /// let white = Lambert::new(Albedo::gray(0.8));
+    ///
+    /// let mut placed = Scene::new(Radiance::none());
+    /// placed.add(Object::new(square.clone(), white.clone(), place));
+    /// let mut baked = Scene::new(Radiance::none());
+    /// baked.add(Object::new(square.transform(&place), white, Matrix4::identity()));
+    ///
+    /// let ray = Ray::new(Point::new(1.5, 0.5, 0.0), Vector::new(0.0, 0.0, -1.0));
+    /// let a = placed.intersect(&ray).unwrap().intersection;
+    /// let b = baked.intersect(&ray).unwrap().intersection;
+    /// assert!((a.position - b.position).magnitude() < 1e-5);
+    /// assert!((a.normal - b.normal).magnitude() < 1e-5);
+    /// assert!((a.lambda - b.lambda).abs() < 1e-5);
+    ///
+    /// // Mirrored, the square still faces `+z`.
+    /// let mirrored = square.transform(&Matrix4::from_nonuniform_scale(-1.0, 1.0, 1.0));
+    /// let ray = Ray::new(Point::new(-0.5, 0.5, 1.0), Vector::new(0.0, 0.0, -1.0));
+    /// assert!(!mirrored.intersect(&ray).unwrap().inside);
+    /// ```
+    pub fn transform(&self, transform: &Matrix4<Float>) -> Mesh {
+        self.try_transform(transform)
+            .unwrap_or_else(|e| panic!("invalid transformation: {}", e))
+    }
+
+    /// A copy of the mesh with `transform` baked into its vertices,
+    /// failing if `transform` is not invertible.
+    pub fn try_transform(&self, transform: &Matrix4<Float>) -> Result<Mesh, Error> {
+        let inv_trans = invert(*transform)?.transpose();
+        let positions = self
+            .positions
+            .iter()
+            .map(|&p| transform.transform_point(p))
+            .collect();
+        let normals = self.normals.as_ref().map(|normals| {
+            normals
+                .iter()
+                .map(|&n| Transform::<Point>::transform_vector(&inv_trans, n).normalize())
+                .collect()
+        });
+        let indices = if transform.determinant() < 0.0 {
+            self.indices.iter().map(|&[a, b, c]| [a, c, b]).collect()
+        } else {
+            self.indices.clone()
+        };
+
+        let mesh = Mesh::try_build(positions, normals, indices, self.split)?;
+        Ok(Mesh {
+            uvs: self.uvs.clone(),
+            uv_bvh: self.uv_bvh.clone(),
+            ..mesh
+        })
+    }
+
+    /// The corners of the triangle with index `triangle`.
+    fn corners(&self, triangle: usize) -> [Point; 3] {
+        let [a, b, c] = self.indices[triangle];
+        [
+            self.positions[a as usize],
+            self.positions[b as usize],
+            self.positions[c as usize],
+        ]
+    }
+
+    /// The normal of the front of `triangle` at the barycentric
+    /// coordinates `u` and `v` of its second and third corner.
+    fn normal(&self, triangle: usize, u: Float, v: Float) -> Vector {
+        match self.normals {
+            Some(ref normals) => {
+                let [a, b, c] = self.indices[triangle];
+                let n = normals[a as usize] * (1.0 - u - v)
+                    + normals[b as usize] * u
+                    + normals[c as usize] * v;
+                n.normalize()
+            }
+            None => self.normal_of_face(triangle, false),
+        }
+    }
+
+    /// Intersects the mesh with `ray`, missing the backs of the
+    /// triangles if `cull`.
+    fn hit(&self, ray: &Ray, cull: bool) -> Option<Intersection> {
+        let mut nearest: Option<(usize, Float, Float, Float)> = None;
+
+        self.bvh.traverse(ray, |i| {
+            let [a, b, c] = self.corners(i);
+            let (lambda, u, v) = intersect_triangle(a, b, c, ray, cull)?;
+            if nearest.is_none_or(|n| lambda < n.1) {
+                nearest = Some((i, lambda, u, v));
+            }
+            Some(lambda)
+        });
+
+        let (triangle, lambda, u, v) = nearest?;
+        let [a, b, c] = self.corners(triangle);
+        let inside = (b - a).cross(c - a).dot(ray.direction) > 0.0;
+
+        let mut normal = self.normal(triangle, u, v);
+        if inside {
+            normal = -normal;
+        }
+        // Interpolated normals may still face away from grazing rays.
+        if normal.dot(ray.direction) > 0.0 {
+            normal = self.normal_of_face(triangle, inside);
+        }
+
+        Some(Intersection {
+            position: ray.origin + ray.direction * lambda,
+            normal,
+            lambda,
+            inside,
+            primitive: triangle,
+            uv: self.uv(triangle, u, v),
+        })
+    }
+
+    /// Whether `ray` hits any triangle, missing their backs if `cull`.
+    fn any(&self, ray: &Ray, cull: bool) -> bool {
+        self.bvh.any(ray, |i| {
+            let [a, b, c] = self.corners(i);
+            intersect_triangle(a, b, c, ray, cull).is_some()
+        })
+    }
+
+    /// The surface coordinates of `triangle` at the barycentric
+    /// coordinates `u` and `v` of its second and third corner.
+    fn uv(&self, triangle: usize, u: Float, v: Float) -> (Float, Float) {
+        match self.uvs {
+            Some(ref uvs) => {
+                let [a, b, c] = self.indices[triangle];
+                let (a, b, c) = (uvs[a as usize], uvs[b as usize], uvs[c as usize]);
+                let w = 1.0 - u - v;
+                (a.0 * w + b.0 * u + c.0 * v, a.1 * w + b.1 * u + c.1 * v)
+            }
+            None => (u, v),
+        }
+    }
+
+    /// The geometric normal of `triangle`, of its back if `inside`.
+    fn normal_of_face(&self, triangle: usize, inside: bool) -> Vector {
+        let [a, b, c] = self.corners(triangle);
+        let normal = (b - a).cross(c - a).normalize();
+        if inside {
+            -normal
+        } else {
+            normal
+        }
+    }
+}
+
+/// The bounds of the triangles at `indices` of `positions`.
+fn triangle_bounds(positions: &[Point], indices: &[[u32; 3]]) -> Vec<Bounds> {
+    indices
+        .iter()
+        .map(|triangle| {
+            let [a, b, c] = triangle.map(|i| positions[i as usize]);
+            Bounds::new(a, a).include(b).include(c)
+        })
+        .collect()
+}
+
+/// The bounds of the triangles at `indices` laid out in their surface
+/// coordinates `uvs`, at `z = 0`.
+fn uv_bounds(uvs: &[(Float, Float)], indices: &[[u32; 3]]) -> Vec<Bounds> {
+    indices
+        .iter()
+        .map(|triangle| {
+            let [a, b, c] = triangle.map(|i| {
+                let (u, v) = uvs[i as usize];
+                Point::new(u, v, 0.0)
+            });
+            // Padded, as points on the edges of the bounds are
+            // ambiguous for rays along them.
+            let bounds = Bounds::new(a, a).include(b).include(c);
+            let pad = Vector::new(1e-6, 1e-6, 1e-6);
+            Bounds::new(bounds.min - pad, bounds.max + pad)
+        })
+        .collect()
+}
+
+impl Geometry for Mesh {
+    fn intersect(&self, ray: &Ray) -> Option<Intersection> {
+        self.hit(ray, false)
+    }
+
+    fn occlude(&self, ray: &Ray) -> bool {
+        self.any(ray, false)
+    }
+
+    fn intersect_front(&self, ray: &Ray) -> Option<Intersection> {
+        self.hit(ray, true)
+    }
+
+    fn occlude_front(&self, ray: &Ray) -> bool {
+        self.any(ray, true)
+    }
+
+    fn bounds(&self) -> Bounds {
+        self.bvh.bounds()
+    }
+
+    fn triangle_count(&self) -> usize {
+        self.indices.len()
+    }
+
+    fn memory(&self) -> usize {
+        mem::size_of::<Mesh>()
+            + self.positions.len() * mem::size_of::<Point>()
+            + self
+                .normals
+                .as_ref()
+                .map_or(0, |n| n.len() * mem::size_of::<Vector>())
+            + self
+                .uvs
+                .as_ref()
+                .map_or(0, |uvs| uvs.len() * mem::size_of::<(Float, Float)>())
+            + self.uv_bvh.as_ref().map_or(0, Bvh::memory)
+            + self.indices.len() * (mem::size_of::<[u32; 3]>() + mem::size_of::<Float>())
+            + self.bvh.memory()
+    }
+
+    fn area(&self) -> Float {
+        self.areas.last().cloned().unwrap_or(0.0)
+    }
+
+    /// Picks a triangle by its area with `u`, and spreads what is left
+    /// of `u` and `v` over it.
+    fn sample_surface(&self, u: Float, v: Float) -> Option<(Point, Vector)> {
+        let total = self.area();
+        if total <= 0.0 {
+            return None;
+        }
+
+        let target = u * total;
+        let triangle = self
+            .areas
+            .partition_point(|&sum| sum <= target)
+            .min(self.areas.len() - 1);
+        let start = if triangle > 0 {
+            self.areas[triangle - 1]
+        } else {
+            0.0
+        };
+        let area = self.areas[triangle] - start;
+        let u = ((target - start) / area).clamp(0.0, 1.0);
+
+        let [a, b, c] = self.corners(triangle);
+        let (position, _) = uniform_triangle(a, b, c, u, v);
+        Some((position, self.normal_of_face(triangle, false)))
+    }
+
+    /// Finds the triangle covering `u` and `v` in the layout of the
+    /// per-vertex surface coordinates; fails without them.
+    fn surface_at(&self, u: Float, v: Float) -> Result<Option<(Point, Vector)>, Error> {
+        let (uvs, uv_bvh) = match (&self.uvs, &self.uv_bvh) {
+            (Some(uvs), Some(uv_bvh)) => (uvs, uv_bvh),
+            _ => {
+                return Err(Error::Unsupported(
+                    "mapping surface coordinates onto a mesh without them".to_string(),
+                ))
+            }
+        };
+
+        let ray = Ray {
+            origin: Point::new(u, v, -1.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+            length: 2.0,
+            time: 0.0,
+        };
+        let mut found = None;
+        uv_bvh.any(&ray, |i| {
+            let [a, b, c] = self.indices[i].map(|i| uvs[i as usize]);
+            let cross = |p: (Float, Float), q: (Float, Float)| p.0 * q.1 - p.1 * q.0;
+            let (ab, ac, ap) = (
+                (b.0 - a.0, b.1 - a.1),
+                (c.0 - a.0, c.1 - a.1),
+                (u - a.0, v - a.1),
+            );
+
+            let area = cross(ab, ac);
+            let scale = ab.0.hypot(ab.1) * ac.0.hypot(ac.1);
+            if !area.is_finite() || area.abs() <= Float::EPSILON * scale {
+                return false;
+            }
+            let (s, t) = (cross(ap, ac) / area, cross(ab, ap) / area);
+            if s < 0.0 || t < 0.0 || s + t > 1.0 {
+                return false;
+            }
+
+            let [a, b, c] = self.corners(i);
+            found = Some((a + (b - a) * s + (c - a) * t, self.normal(i, s, t)));
+            true
+        });
+
+        Ok(found)
+    }
+}
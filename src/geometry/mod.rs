@@ -1,35 +1,75 @@
 //! Spatial geometry and ray-intersection math
 
-use std::f32::INFINITY;
+use std::{array, mem};
 
 use cgmath;
 use cgmath::{InnerSpace, Transform};
 
+pub mod bounds;
+pub mod bvh;
+pub mod cone;
 pub mod cube;
+pub mod cylinder;
 pub mod disc;
+pub mod displacement;
+pub mod grid;
+pub mod kdtree;
+pub mod mesh;
 pub mod plane;
+pub mod quad;
 pub mod sphere;
+pub mod subdivision;
+pub mod torus;
+pub mod triangle;
+pub mod validated;
+pub mod volume;
 
+pub use self::bounds::Bounds;
+pub use self::bvh::Bvh;
+pub use self::cone::Cone;
 pub use self::cube::Cube;
+pub use self::cylinder::{Cylinder, InfiniteCylinder};
 pub use self::disc::Disc;
+pub use self::grid::Grid;
+pub use self::kdtree::{KdTree, KdTreeGroup};
+pub use self::mesh::Mesh;
 pub use self::plane::Plane;
+pub use self::quad::Quad;
 pub use self::sphere::Sphere;
+pub use self::torus::Torus;
+pub use self::triangle::Triangle;
+pub use self::validated::{ValidatedGeometry, Violation};
+pub use self::volume::{Interval, VolumeBounds};
+use montecarlo::pdf::area_to_solid_angle;
+use Error;
+use Float;
+
+/// The bound on the rounding error of intersection positions,
+/// relative to the magnitude of the values involved.
+const POSITION_ERROR: Float = 128.0 * Float::EPSILON;
 
 /// A direction or distance in space.
-pub type Vector = cgmath::Vector3<f32>;
+pub type Vector = cgmath::Vector3<Float>;
 
 /// A position in space.
-pub type Point = cgmath::Point3<f32>;
+pub type Point = cgmath::Point3<Float>;
 
 /// A photon's (potential) path.
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub struct Ray {
     /// The photon's origin.
     pub origin: Point,
     /// The photon's direction (normalized).
     pub direction: Vector,
     /// The maximum length of the path (often INFINITY).
-    pub length: f32,
+    pub length: Float,
+    /// The moment the photon travels, relative to the time of the
+    /// scene (see `Scene::set_time`), e.g. while the camera's shutter
+    /// is open.  Animated objects are hit where they are at that
+    /// moment, which blurs their motion.
+    #[cfg_attr(feature = "serialize", serde(default))]
+    pub time: Float,
 }
 
 impl Ray {
@@ -39,6 +79,7 @@ impl Ray {
     /// # Examples
     /// ```
     /// use lucifer::geometry::{Point, Ray, Vector};
+    /// use lucifer::Float;
     ///
     /// let origin = Point::new(0.0, 0.0, 0.0);
     /// let direction = Vector::new(1.0, 0.0, 0.0);
@@ -46,16 +87,25 @@ impl Ray {
     ///
     /// assert_eq!(ray.origin, origin);
     /// assert_eq!(ray.direction, direction);
-    /// assert_eq!(ray.length, std::f32::INFINITY);
+    /// assert_eq!(ray.length, Float::INFINITY);
+    /// assert_eq!(ray.time, 0.0);
     /// ```
     pub fn new(origin: Point, direction: Vector) -> Ray {
         Ray {
             origin,
             direction: direction.normalize(),
-            length: INFINITY,
+            length: Float::INFINITY,
+            time: 0.0,
         }
     }
 
+    /// Moves the ray to `time`, builder style; see `Ray::time`.
+    pub fn at_time(self, time: Float) -> Ray {
+        let mut ray = self;
+        ray.time = time;
+        ray
+    }
+
     /// Creates a new `Ray` of finite length from an `origin` and an `target`.
     ///
     /// # Examples
@@ -79,6 +129,7 @@ impl Ray {
             origin,
             direction,
             length,
+            time: 0.0,
         }
     }
 
@@ -86,17 +137,18 @@ impl Ray {
     ///
     /// # Examples
     /// ```
-    /// use lucifer::cgmath::{Matrix3, Transform};
+    /// use lucifer::cgmath::{Matrix4, Transform};
     /// use lucifer::geometry::{Point, Ray, Vector};
+    /// use lucifer::Float;
     ///
     /// let origin = Point::new(0.0, 0.0, 0.0);
     /// let direction = Vector::new(1.0, 0.0, 0.0);
     /// let ray = Ray::new(origin, direction);
-    /// let ray = ray.transform(&<Matrix as Transform<Point>>::one());
+    /// let ray = ray.transform(&<Matrix4<Float> as Transform<Point>>::one());
     ///
     /// assert_eq!(ray.origin, origin);
     /// assert_eq!(ray.direction, direction);
-    /// assert_eq!(ray.length, std::f32::INFINITY);
+    /// assert_eq!(ray.length, Float::INFINITY);
     /// ```
     pub fn transform<T>(self, transform: &T) -> Ray
     where
@@ -110,6 +162,7 @@ impl Ray {
             origin,
             direction: direction / scale,
             length: self.length * scale,
+            time: self.time,
         }
     }
 }
@@ -124,18 +177,166 @@ pub struct Intersection {
     pub normal: Vector,
     /// The distance from the ray's origin (always positive); that is
     /// `position = ray.origin + ray.direction * lambda`.
-    pub lambda: f32,
+    pub lambda: Float,
     /// A boolean indicating whether the ray hit the inside of the
     /// object.
     pub inside: bool,
+    /// The index of the intersected primitive within the geometry,
+    /// e.g. the triangle of a mesh.  Always `0` for analytic shapes.
+    pub primitive: usize,
+    /// The surface coordinates of the position, for texturing.  Each
+    /// shape documents its parameterization; most cover `[0, 1]`.
+    pub uv: (Float, Float),
+}
+
+impl Intersection {
+    /// The bound on the rounding error of `position`, which grows
+    /// with the magnitude of its coordinates and the distance the ray
+    /// travelled to it.
+    pub fn error(&self) -> Float {
+        let p = self.position;
+        let magnitude = p[0].abs().max(p[1].abs()).max(p[2].abs());
+        POSITION_ERROR * (magnitude + self.lambda)
+    }
+
+    /// The origin of a ray leaving the surface in `direction`, pushed
+    /// off the surface along the normal by the `error` of the
+    /// position, to the side `direction` points to.  Rays from there
+    /// do not hit the surface they leave again, whatever the scale of
+    /// the scene.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::geometry::{Geometry, Point, Ray, Sphere, Vector};
+    ///
+    /// let sphere = Sphere::new(Point::new(0.0, 0.0, 2000.0), 1000.0);
+    /// let ray = Ray::new(Point::new(0.0, 0.0, 0.0), Vector::new(0.0, 0.0, 1.0));
+    /// let hit = sphere.intersect(&ray).unwrap();
+    ///
+    /// // Bounced back, the ray escapes; passed on, it reaches the far side.
+    /// let back = hit.spawn(Vector::new(0.0, 0.0, -1.0));
+    /// assert!(back.origin.z < hit.position.z);
+    /// assert!(sphere.intersect(&back).is_none());
+    ///
+    /// let through = hit.spawn(Vector::new(0.0, 0.0, 1.0));
+    /// let exit = sphere.intersect(&through).unwrap();
+    /// assert!(exit.inside);
+    /// assert!((exit.lambda - 2000.0).abs() < 0.1);
+    /// ```
+    pub fn offset_origin(&self, direction: Vector) -> Point {
+        let offset = self.normal * self.error();
+        if direction.dot(self.normal) < 0.0 {
+            self.position - offset
+        } else {
+            self.position + offset
+        }
+    }
+
+    /// A ray leaving the surface in `direction`; see `offset_origin`.
+    pub fn spawn(&self, direction: Vector) -> Ray {
+        Ray::new(self.offset_origin(direction), direction)
+    }
+
+    /// The distance to travel on from the position in the unit
+    /// `direction` to be clear of the surface by the `error` of the
+    /// position, e.g. to continue a ray past the surface it hit
+    /// without hitting it again.  The distance grows as `direction`
+    /// grazes the surface, up to a hundredfold of the `error`.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::geometry::{Geometry, Plane, Point, Ray, Vector};
+    ///
+    /// let plane = Plane::new(Vector::new(0.0, 1.0, 0.0), 0.0);
+    /// let ray = Ray::new(Point::new(-1000.0, 1.0, 0.0), Vector::new(1.0, -1.0, 0.0));
+    /// let hit = plane.intersect(&ray).unwrap();
+    ///
+    /// let step = hit.step(ray.direction);
+    /// assert!(step > hit.error());
+    /// let beyond = hit.position + ray.direction * step;
+    /// assert!(plane.intersect(&Ray::new(beyond, ray.direction)).is_none());
+    /// ```
+    pub fn step(&self, direction: Vector) -> Float {
+        let cos = direction.dot(self.normal).abs().max(0.01);
+        self.error() / cos
+    }
+}
+
+/// Two unit vectors perpendicular to the unit vector `normal` and to
+/// each other, such that `(tangent, bitangent, normal)` is
+/// right-handed.
+pub(crate) fn tangents(normal: Vector) -> (Vector, Vector) {
+    let n = normal;
+    let tangent = if n[0].abs() > n[1].abs() {
+        Vector::new(n[2], 0.0, -n[0]).normalize()
+    } else {
+        Vector::new(0.0, n[2], -n[1]).normalize()
+    };
+    (tangent, n.cross(tangent))
 }
 
 /// Trait describing shapes.
 ///
 /// # Laws
 ///
-/// `g.occlude(r) <-> g.intersect(r).is_some()`
-pub trait Geometry {
+/// * `g.occlude(r) <-> g.intersect(r).is_some()`
+/// * Intersections lie on the ray, at a positive `lambda` of at most
+///   `r.length`.
+/// * Normals are of unit length and face the ray's origin; `inside`
+///   tells whether they were flipped to do so.
+/// * `g.intersect_all(r)` lists the intersections front to back,
+///   starting with `g.intersect(r)`.
+/// * `g.intersect_front(r)` is the first of those not `inside`, and
+///   `g.occlude_front(r) <-> g.intersect_front(r).is_some()`.
+///
+/// `ValidatedGeometry` checks these laws for every ray.
+///
+/// # Examples
+/// ```
+/// use lucifer::geometry::{Cone, Cube, Cylinder, Disc, Geometry, Grid, InfiniteCylinder};
+/// use lucifer::geometry::{KdTreeGroup, Plane, Point, Quad, Ray, Sphere, Torus, Triangle};
+/// use lucifer::geometry::Vector;
+///
+/// let o = Point::new(0.0, 0.0, 0.0);
+/// let (x, y) = (Vector::new(1.0, 0.0, 0.0), Vector::new(0.0, 1.0, 0.0));
+/// let shapes: Vec<Box<dyn Geometry>> = vec![
+///     Box::new(Sphere::new(o, 1.0)),
+///     Box::new(Cube::new(o, Vector::new(2.0, 2.0, 2.0))),
+///     Box::new(Cone::new(Point::new(0.0, 1.0, 0.0), -y, 0.5, 2.0)),
+///     Box::new(Cylinder::new(o, y, 1.0, 2.0)),
+///     Box::new(InfiniteCylinder::new(o, x, 1.0)),
+///     Box::new(Disc::new(o, y, 1.0)),
+///     Box::new(Plane::new(y, 0.0)),
+///     Box::new(Quad::new(o, x * 2.0, Vector::new(0.0, 0.0, 2.0))),
+///     Box::new(Torus::new(o, y, 1.0, 0.3)),
+///     Box::new(Triangle::new(
+///         Point::new(-2.0, 0.0, -1.0),
+///         Point::new(2.0, 0.0, -1.0),
+///         Point::new(0.0, 0.0, 2.0),
+///     )),
+///     Box::new(Grid::new(vec![Sphere::new(o, 1.0), Sphere::new(o + x, 1.0)])),
+///     Box::new(KdTreeGroup::new(vec![Sphere::new(o, 1.0), Sphere::new(o + x, 1.0)])),
+/// ];
+///
+/// // Rays ending just before a surface miss it, rays ending just
+/// // beyond hit it, and shadow rays agree.
+/// let origin = Point::new(0.9, -5.0, 0.0);
+/// for shape in &shapes {
+///     let lambda = shape.intersect(&Ray::new(origin, y)).unwrap().lambda;
+///
+///     let short = Ray::from_endpoints(origin, origin + y * (lambda * 0.99));
+///     assert!(shape.intersect(&short).is_none());
+///     assert!(!shape.occlude(&short));
+///
+///     let long = Ray::from_endpoints(origin, origin + y * (lambda * 1.01));
+///     assert_eq!(shape.intersect(&long).unwrap().lambda, lambda);
+///     assert!(shape.occlude(&long));
+/// }
+/// ```
+///
+/// Shapes are shared between rendering threads, so they must be
+/// `Send` and `Sync`.
+pub trait Geometry: Send + Sync {
     /// Compute the first (nearest to `ray`'s origin) intersection
     /// between `self` and `ray`.
     fn intersect(&self, ray: &Ray) -> Option<Intersection>;
@@ -149,4 +350,218 @@ pub trait Geometry {
     fn occlude(&self, ray: &Ray) -> bool {
         self.intersect(ray).is_some()
     }
+
+    /// Computes all intersections between `self` and `ray`, front to
+    /// back, e.g. to combine shapes in constructive solid geometry, to
+    /// find where a ray passes through a volume, or to let shadow rays
+    /// pass transparent surfaces.
+    ///
+    /// The default implementation calls `self.intersect` repeatedly,
+    /// continuing the ray just past every intersection, by its
+    /// `Intersection::step`.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::geometry::{Cube, Geometry, Point, Ray, Vector};
+    ///
+    /// let cube = Cube::new(Point::new(0.0, 0.0, 0.0), Vector::new(2.0, 2.0, 2.0));
+    /// let ray = Ray::new(Point::new(0.0, 0.0, 5.0), Vector::new(0.0, 0.0, -1.0));
+    ///
+    /// let hits = cube.intersect_all(&ray);
+    /// let lambdas: Vec<_> = hits.iter().map(|i| i.lambda).collect();
+    /// assert_eq!(lambdas, vec![4.0, 6.0]);
+    /// assert!(!hits[0].inside && hits[1].inside);
+    /// assert_eq!(hits[1].position, Point::new(0.0, 0.0, -1.0));
+    ///
+    /// // Rays ending within see only the way in.
+    /// let short = Ray::from_endpoints(Point::new(0.0, 0.0, 5.0), Point::new(0.0, 0.0, 0.0));
+    /// assert_eq!(cube.intersect_all(&short).len(), 1);
+    /// ```
+    fn intersect_all(&self, ray: &Ray) -> Vec<Intersection> {
+        let mut intersections = Vec::new();
+        let mut rest = ray.clone();
+        let mut distance = 0.0;
+
+        while let Some(mut intersection) = self.intersect(&rest) {
+            let step = intersection.lambda + intersection.step(rest.direction);
+            intersection.lambda += distance;
+            intersections.push(intersection);
+
+            distance += step;
+            rest = Ray {
+                origin: rest.origin + rest.direction * step,
+                direction: rest.direction,
+                length: rest.length - step,
+                time: rest.time,
+            };
+        }
+
+        intersections
+    }
+
+    /// Like `intersect`, but culling back faces: only surfaces facing
+    /// the ray's origin from outside, which are not `inside`, are hit,
+    /// e.g. for one-sided objects.
+    ///
+    /// The default implementation calls `self.intersect` repeatedly,
+    /// continuing the ray past back faces by their
+    /// `Intersection::step`.  Shapes may instead skip back faces
+    /// within their intersection test.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::geometry::{Cube, Geometry, Point, Ray, Vector};
+    ///
+    /// let cube = Cube::new(Point::new(0.0, 0.0, 0.0), Vector::new(2.0, 2.0, 2.0));
+    ///
+    /// let outside = Ray::new(Point::new(0.0, 0.0, 5.0), Vector::new(0.0, 0.0, -1.0));
+    /// assert_eq!(cube.intersect_front(&outside).unwrap().lambda, 4.0);
+    ///
+    /// let within = Ray::new(Point::new(0.0, 0.0, 0.0), Vector::new(0.0, 0.0, -1.0));
+    /// assert!(cube.intersect(&within).is_some());
+    /// assert!(cube.intersect_front(&within).is_none());
+    /// assert!(!cube.occlude_front(&within));
+    /// ```
+    fn intersect_front(&self, ray: &Ray) -> Option<Intersection> {
+        let mut rest = ray.clone();
+        let mut distance = 0.0;
+
+        loop {
+            let mut intersection = self.intersect(&rest)?;
+            if !intersection.inside {
+                intersection.lambda += distance;
+                return Some(intersection);
+            }
+
+            let step = intersection.lambda + intersection.step(rest.direction);
+            distance += step;
+            rest = Ray {
+                origin: rest.origin + rest.direction * step,
+                direction: rest.direction,
+                length: rest.length - step,
+                time: rest.time,
+            };
+        }
+    }
+
+    /// Like `occlude`, but culling back faces; see `intersect_front`.
+    ///
+    /// The default implementation calls
+    /// `self.intersect_front(ray).is_some()`.
+    fn occlude_front(&self, ray: &Ray) -> bool {
+        self.intersect_front(ray).is_some()
+    }
+
+    /// Intersects a packet of four rays, e.g. the primary rays of
+    /// neighbouring pixels, returning the same as `intersect` for each.
+    /// Shapes may test the rays side by side with SIMD instructions.
+    ///
+    /// The default implementation calls `self.intersect` for each ray.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::geometry::{Geometry, Point, Ray, Sphere, Vector};
+    ///
+    /// let sphere = Sphere::new(Point::new(0.0, 0.0, -5.0), 1.0);
+    /// let origin = Point::new(0.0, 0.0, 0.0);
+    /// let rays = [
+    ///     Ray::new(origin, Vector::new(0.0, 0.0, -1.0)),
+    ///     Ray::new(origin, Vector::new(0.1, 0.0, -1.0)),
+    ///     Ray::new(origin, Vector::new(0.0, 0.5, -1.0)),
+    ///     Ray::new(origin, Vector::new(0.0, 0.0, 1.0)),
+    /// ];
+    ///
+    /// let hits = sphere.intersect4(&rays);
+    /// for (hit, ray) in hits.iter().zip(&rays) {
+    ///     let single = sphere.intersect(ray);
+    ///     assert_eq!(hit.as_ref().map(|h| h.lambda), single.map(|h| h.lambda));
+    /// }
+    /// assert!(hits[1].is_some());
+    /// assert!(hits[2].is_none());
+    /// ```
+    fn intersect4(&self, rays: &[Ray; 4]) -> [Option<Intersection>; 4] {
+        array::from_fn(|i| self.intersect(&rays[i]))
+    }
+
+    /// Intersects a packet of eight rays, like `intersect4`.
+    fn intersect8(&self, rays: &[Ray; 8]) -> [Option<Intersection>; 8] {
+        array::from_fn(|i| self.intersect(&rays[i]))
+    }
+
+    /// The axis-aligned bounding box of the geometry.  Unbounded
+    /// geometry returns `Bounds::infinite()`.
+    fn bounds(&self) -> Bounds;
+
+    /// The number of triangles making up the geometry, `0` for
+    /// analytic shapes.
+    fn triangle_count(&self) -> usize {
+        0
+    }
+
+    /// The approximate memory used by the geometry, in bytes,
+    /// including any acceleration structures it owns.
+    fn memory(&self) -> usize {
+        mem::size_of_val(self)
+    }
+
+    /// The surface area of the geometry, or `0` if the surface
+    /// cannot be sampled, e.g. because it is infinite.
+    fn area(&self) -> Float {
+        0.0
+    }
+
+    /// Maps `u` and `v` in `[0, 1)` uniformly onto the surface,
+    /// returning the position and the outward surface normal.
+    fn sample_surface(&self, _u: Float, _v: Float) -> Option<(Point, Vector)> {
+        None
+    }
+
+    /// The position and front normal of the surface with the surface
+    /// coordinates `u` and `v`, as reported by `Intersection::uv`,
+    /// e.g. to lay the surface out in a texture.  `None` if no surface
+    /// has them, and an error if the geometry cannot map surface
+    /// coordinates back onto its surface.
+    ///
+    /// The default implementation fails.
+    fn surface_at(&self, _u: Float, _v: Float) -> Result<Option<(Point, Vector)>, Error> {
+        Err(Error::Unsupported(
+            "mapping surface coordinates onto the geometry".to_string(),
+        ))
+    }
+
+    /// The density, with respect to solid angle at `point`, of
+    /// `sample_surface` choosing the surface visible in `direction`,
+    /// e.g. to weight light sampling against BSDF sampling with
+    /// multiple importance sampling.  `0` if the ray misses, or the
+    /// surface cannot be sampled.
+    ///
+    /// Only the nearest surface in `direction` counts, since samples
+    /// behind it are occluded.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::consts::PI;
+    /// use lucifer::geometry::{Disc, Geometry, Point, Vector};
+    ///
+    /// let disc = Disc::new(Point::new(0.0, 0.0, 0.0), Vector::new(0.0, 0.0, 1.0), 1.0);
+    /// let point = Point::new(0.0, 0.0, 2.0);
+    ///
+    /// // A density of `1 / PI` per area, seen head-on from a distance of 2.
+    /// let pdf = disc.pdf_from(point, Vector::new(0.0, 0.0, -1.0));
+    /// assert!((pdf - 4.0 / PI).abs() < 1e-5);
+    ///
+    /// assert_eq!(disc.pdf_from(point, Vector::new(0.0, 0.0, 1.0)), 0.0);
+    /// ```
+    fn pdf_from(&self, point: Point, direction: Vector) -> Float {
+        let area = self.area();
+        if area <= 0.0 {
+            return 0.0;
+        }
+
+        let ray = Ray::new(point, direction);
+        match self.intersect(&ray) {
+            Some(hit) => area_to_solid_angle(1.0 / area, hit.lambda, hit.normal.dot(ray.direction)),
+            None => 0.0,
+        }
+    }
 }
@@ -1,13 +1,18 @@
 use cgmath::prelude::*;
 use cgmath::Vector4;
 
-use geometry::{Geometry, Intersection, Ray, Vector};
+use geometry::{tangents, Bounds, Geometry, Intersection, Ray, Vector};
+use Float;
 
 /// An infinite, two-dimensional plane.
+///
+/// The surface coordinates are the distances along two perpendicular
+/// directions within the plane, from its point closest to the origin.
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub struct Plane {
     /// The plane equation.
-    pub equation: Vector4<f32>,
+    pub equation: Vector4<Float>,
 }
 
 impl Plane {
@@ -26,13 +31,17 @@ impl Plane {
     ///
     /// assert_eq!(plane.equation, normal.extend(-distance));
     /// ```
-    pub fn new(normal: Vector, distance: f32) -> Plane {
+    pub fn new(normal: Vector, distance: Float) -> Plane {
         let equation = normal.normalize().extend(-distance);
         Plane { equation }
     }
 }
 
 impl Geometry for Plane {
+    fn bounds(&self) -> Bounds {
+        Bounds::infinite()
+    }
+
     fn intersect(&self, ray: &Ray) -> Option<Intersection> {
         let lo = self.equation.dot(ray.origin.to_homogeneous());
         let ld = self.equation.dot(ray.direction.extend(0.0));
@@ -40,12 +49,15 @@ impl Geometry for Plane {
         let lambda = -lo / ld;
         let inside = ld > 0.0;
 
-        if lambda <= 0.0 {
+        if !(lambda > 0.0 && lambda <= ray.length) {
             return None;
         }
 
         let position = ray.origin + lambda * ray.direction;
         let mut normal = self.equation.truncate();
+        let (tangent, bitangent) = tangents(normal);
+        let offset = position.to_vec();
+        let uv = (offset.dot(tangent), offset.dot(bitangent));
 
         if inside {
             normal = -normal
@@ -56,6 +68,8 @@ impl Geometry for Plane {
             normal,
             lambda,
             inside,
+            primitive: 0,
+            uv,
         })
     }
 }
@@ -0,0 +1,127 @@
+use cgmath::prelude::*;
+
+use geometry::{Bounds, Geometry, Intersection, Point, Ray, Vector};
+use Float;
+
+/// A flat parallelogram, usually a rectangle.
+///
+/// Like a `Disc`, quads have no volume; rays hitting the back side,
+/// opposite the normal `edges[0] x edges[1]`, are `inside`.  The
+/// surface coordinates run from `0` to `1` along the edges.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct Quad {
+    /// The quad's center point.
+    pub center: Point,
+    /// The quad's two edges, from one corner to the neighbouring ones.
+    pub edges: [Vector; 2],
+}
+
+impl Quad {
+    /// Creates a `Quad` centered on `center`, spanned by the edges
+    /// `u` and `v`.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::geometry::quad::Quad;
+    /// use lucifer::geometry::{Geometry, Point, Ray, Vector};
+    ///
+    /// // A 2 by 1 light panel facing down.
+    /// let center = Point::new(0.0, 2.0, 0.0);
+    /// let panel = Quad::new(center, Vector::new(2.0, 0.0, 0.0), Vector::new(0.0, 0.0, 1.0));
+    /// assert_eq!(panel.normal(), Vector::new(0.0, -1.0, 0.0));
+    /// assert_eq!(panel.area(), 2.0);
+    ///
+    /// let ray = Ray::new(Point::new(0.9, 0.0, 0.4), Vector::new(0.0, 1.0, 0.0));
+    /// let hit = panel.intersect(&ray).unwrap();
+    /// assert_eq!(hit.lambda, 2.0);
+    /// assert_eq!(hit.normal, Vector::new(0.0, -1.0, 0.0));
+    /// assert!(!hit.inside);
+    ///
+    /// let beside = Ray::new(Point::new(0.9, 0.0, 0.6), Vector::new(0.0, 1.0, 0.0));
+    /// assert!(panel.intersect(&beside).is_none());
+    /// ```
+    pub fn new(center: Point, u: Vector, v: Vector) -> Quad {
+        Quad {
+            center,
+            edges: [u, v],
+        }
+    }
+
+    /// Creates a `Quad` with one corner at `origin`, spanned by the
+    /// edges `u` and `v` leaving it.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::geometry::quad::Quad;
+    /// use lucifer::geometry::{Geometry, Point, Vector};
+    ///
+    /// let origin = Point::new(-1.0, 2.0, -0.5);
+    /// let panel = Quad::from_corner(origin, Vector::new(2.0, 0.0, 0.0), Vector::new(0.0, 0.0, 1.0));
+    /// assert_eq!(panel.center, Point::new(0.0, 2.0, 0.0));
+    /// assert_eq!(panel.bounds().min, origin);
+    /// ```
+    pub fn from_corner(origin: Point, u: Vector, v: Vector) -> Quad {
+        Quad::new(origin + (u + v) * 0.5, u, v)
+    }
+
+    /// The unit normal of the front side.
+    pub fn normal(&self) -> Vector {
+        self.edges[0].cross(self.edges[1]).normalize()
+    }
+}
+
+impl Geometry for Quad {
+    fn bounds(&self) -> Bounds {
+        let [u, v] = self.edges;
+        let extent = |i: usize| 0.5 * (u[i].abs() + v[i].abs());
+        let r = Vector::new(extent(0), extent(1), extent(2));
+        Bounds::new(self.center - r, self.center + r)
+    }
+
+    fn intersect(&self, ray: &Ray) -> Option<Intersection> {
+        let [u, v] = self.edges;
+        let n = u.cross(v);
+
+        let ld = n.dot(ray.direction);
+        let lambda = n.dot(self.center - ray.origin) / ld;
+        if !(lambda > 0.0 && lambda <= ray.length) {
+            return None;
+        }
+
+        // The coordinates of the position along the edges, from -0.5
+        // to 0.5 on the quad.
+        let position = ray.origin + lambda * ray.direction;
+        let p = position - self.center;
+        let area2 = n.magnitude2();
+        let (a, b) = (p.cross(v).dot(n) / area2, u.cross(p).dot(n) / area2);
+        if a.abs() > 0.5 || b.abs() > 0.5 {
+            return None;
+        }
+
+        let mut normal = n.normalize();
+        let inside = ld > 0.0;
+        if inside {
+            normal = -normal
+        }
+
+        Some(Intersection {
+            position,
+            normal,
+            lambda,
+            inside,
+            primitive: 0,
+            uv: (a + 0.5, b + 0.5),
+        })
+    }
+
+    fn area(&self) -> Float {
+        self.edges[0].cross(self.edges[1]).magnitude()
+    }
+
+    fn sample_surface(&self, u: Float, v: Float) -> Option<(Point, Vector)> {
+        let [a, b] = self.edges;
+        let position = self.center + a * (u - 0.5) + b * (v - 0.5);
+        Some((position, self.normal()))
+    }
+}
@@ -1,7 +1,13 @@
+use std::array;
+
+use consts::PI;
+
 use cgmath::prelude::*;
 use cgmath::BaseFloat;
 
-use geometry::{Geometry, Intersection, Point, Ray};
+use geometry::{Bounds, Geometry, Intersection, Point, Ray, Vector};
+use montecarlo::warp::uniform_sphere;
+use Float;
 
 #[inline]
 fn project<V: InnerSpace>(x: V, y: V) -> V::Scalar
@@ -12,12 +18,17 @@ where
 }
 
 /// A sphere.
+///
+/// The surface coordinates are the longitude around the `y` axis,
+/// starting at `-z`, and the latitude from the bottom to the top, both
+/// scaled to `[0, 1]`.
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub struct Sphere {
     /// The sphere's center point.
     pub center: Point,
     /// The sphere's radius.
-    pub radius: f32,
+    pub radius: Float,
 }
 
 impl Sphere {
@@ -35,12 +46,84 @@ impl Sphere {
     /// assert_eq!(sphere.center, center);
     /// assert_eq!(sphere.radius, radius);
     /// ```
-    pub fn new(center: Point, radius: f32) -> Sphere {
+    pub fn new(center: Point, radius: Float) -> Sphere {
         Sphere { center, radius }
     }
+
+    /// Intersects `N` rays at once.  The rays are split into arrays of
+    /// their components, so the compiler can test them side by side
+    /// with SIMD instructions, and only hits leave the arrays.  The
+    /// arithmetic matches `intersect` step by step, so both give the
+    /// same results.
+    fn intersect_packet<const N: usize>(&self, rays: &[Ray; N]) -> [Option<Intersection>; N] {
+        let (c, r2) = (self.center, self.radius.powi(2));
+        let ox: [Float; N] = array::from_fn(|i| rays[i].origin.x);
+        let oy: [Float; N] = array::from_fn(|i| rays[i].origin.y);
+        let oz: [Float; N] = array::from_fn(|i| rays[i].origin.z);
+        let dx: [Float; N] = array::from_fn(|i| rays[i].direction.x);
+        let dy: [Float; N] = array::from_fn(|i| rays[i].direction.y);
+        let dz: [Float; N] = array::from_fn(|i| rays[i].direction.z);
+
+        let mut lambda = [0.0; N];
+        let mut inside = [false; N];
+        for i in 0..N {
+            let d2 = dx[i] * dx[i] + dy[i] * dy[i] + dz[i] * dz[i];
+            let (cx, cy, cz) = (c.x - ox[i], c.y - oy[i], c.z - oz[i]);
+            let alpha = (cx * dx[i] + cy * dy[i] + cz * dz[i]) / d2;
+            let rx = ox[i] + dx[i] * alpha - c.x;
+            let ry = oy[i] + dy[i] * alpha - c.y;
+            let rz = oz[i] + dz[i] * alpha - c.z;
+            let beta = r2 - (rx * rx + ry * ry + rz * rz);
+            // Misses leave `NaN`, failing the test of `lambda` below.
+            let gamma = (beta / d2).sqrt();
+            inside[i] = gamma >= alpha;
+            lambda[i] = if inside[i] {
+                alpha + gamma
+            } else {
+                alpha - gamma
+            };
+        }
+
+        array::from_fn(|i| {
+            let ray = &rays[i];
+            if lambda[i] > 0.0 && lambda[i] <= ray.length {
+                Some(self.hit(ray, lambda[i], inside[i]))
+            } else {
+                None
+            }
+        })
+    }
+
+    /// The intersection with `ray` at `lambda`.
+    fn hit(&self, ray: &Ray, lambda: Float, inside: bool) -> Intersection {
+        let position = ray.origin + lambda * ray.direction;
+        let mut normal = (position - self.center) / self.radius;
+        let uv = (
+            0.5 + normal.x.atan2(normal.z) / (2.0 * PI),
+            (-normal.y).clamp(-1.0, 1.0).acos() / PI,
+        );
+
+        if inside {
+            normal = -normal
+        }
+
+        Intersection {
+            position,
+            normal,
+            lambda,
+            inside,
+            primitive: 0,
+            uv,
+        }
+    }
 }
 
 impl Geometry for Sphere {
+    fn bounds(&self) -> Bounds {
+        let r = Vector::new(self.radius, self.radius, self.radius);
+        Bounds::new(self.center - r, self.center + r)
+    }
+
     fn intersect(&self, ray: &Ray) -> Option<Intersection> {
         let alpha = project(self.center - ray.origin, ray.direction);
         let r = ray.origin + ray.direction * alpha - self.center;
@@ -54,22 +137,62 @@ impl Geometry for Sphere {
         let inside = gamma >= alpha;
         let lambda = if inside { alpha + gamma } else { alpha - gamma };
 
-        if lambda <= 0.0 {
+        if !(lambda > 0.0 && lambda <= ray.length) {
             return None;
         }
 
-        let position = ray.origin + lambda * ray.direction;
-        let mut normal = (position - self.center) / self.radius;
+        Some(self.hit(ray, lambda, inside))
+    }
 
-        if inside {
-            normal = -normal
+    /// Both crossings of the sphere, found at once.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::geometry::{Geometry, Point, Ray, Sphere, Vector};
+    ///
+    /// let sphere = Sphere::new(Point::new(0.0, 0.0, 0.0), 1.0);
+    ///
+    /// let ray = Ray::new(Point::new(0.0, 0.0, 5.0), Vector::new(0.0, 0.0, -1.0));
+    /// let lambdas: Vec<_> = sphere.intersect_all(&ray).iter().map(|i| i.lambda).collect();
+    /// assert_eq!(lambdas, vec![4.0, 6.0]);
+    ///
+    /// let within = Ray::new(Point::new(0.0, 0.0, 0.0), Vector::new(0.0, 0.0, -1.0));
+    /// let hits = sphere.intersect_all(&within);
+    /// assert_eq!(hits.len(), 1);
+    /// assert!(hits[0].inside);
+    /// ```
+    fn intersect_all(&self, ray: &Ray) -> Vec<Intersection> {
+        let alpha = project(self.center - ray.origin, ray.direction);
+        let r = ray.origin + ray.direction * alpha - self.center;
+        let beta = self.radius.powi(2) - r.magnitude2();
+
+        if beta < 0.0 {
+            return Vec::new();
         }
 
-        Some(Intersection {
-            position,
-            normal,
-            lambda,
-            inside,
-        })
+        let gamma = (beta / ray.direction.magnitude2()).sqrt();
+        [(alpha - gamma, false), (alpha + gamma, true)]
+            .iter()
+            .filter(|&&(lambda, _)| lambda > 0.0 && lambda <= ray.length)
+            .map(|&(lambda, inside)| self.hit(ray, lambda, inside))
+            .collect()
+    }
+
+    fn intersect4(&self, rays: &[Ray; 4]) -> [Option<Intersection>; 4] {
+        self.intersect_packet(rays)
+    }
+
+    fn intersect8(&self, rays: &[Ray; 8]) -> [Option<Intersection>; 8] {
+        self.intersect_packet(rays)
+    }
+
+    fn area(&self) -> Float {
+        4.0 * PI * self.radius.powi(2)
+    }
+
+    fn sample_surface(&self, u: Float, v: Float) -> Option<(Point, Vector)> {
+        let (normal, _) = uniform_sphere(u, v);
+
+        Some((self.center + normal * self.radius, normal))
     }
 }
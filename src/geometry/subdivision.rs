@@ -0,0 +1,199 @@
+//! Subdivision surfaces, refining a coarse control mesh towards a
+//! smooth limit surface.
+//!
+//! Meshes are refined with Loop's scheme ("Smooth Subdivision Surfaces
+//! Based on Triangles", 1987): every step splits each triangle into
+//! four and moves the vertices towards a weighted average of their
+//! neighbours.  The edges of open meshes are kept as creases, refined
+//! as cubic B-spline curves, so that an open patch stays attached to
+//! its boundary.
+//!
+//! Subdivision happens once, when the scene is built; every level
+//! quadruples the number of triangles.
+
+use std::collections::HashMap;
+
+use cgmath::prelude::*;
+
+use geometry::{Mesh, Point, Vector};
+use Float;
+
+impl Mesh {
+    /// Refines the mesh `levels` times with Loop subdivision, and
+    /// shades the result smoothly with normals averaged from the
+    /// refined triangles.  Vertex normals of `self` are ignored.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::cgmath::{EuclideanSpace, InnerSpace};
+    /// use lucifer::geometry::{Geometry, Mesh, Point};
+    ///
+    /// // An octahedron, rounded towards a sphere.
+    /// let positions = vec![
+    ///     Point::new(1.0, 0.0, 0.0),
+    ///     Point::new(-1.0, 0.0, 0.0),
+    ///     Point::new(0.0, 1.0, 0.0),
+    ///     Point::new(0.0, -1.0, 0.0),
+    ///     Point::new(0.0, 0.0, 1.0),
+    ///     Point::new(0.0, 0.0, -1.0),
+    /// ];
+    /// let indices = vec![
+    ///     [0, 2, 4], [2, 1, 4], [1, 3, 4], [3, 0, 4],
+    ///     [2, 0, 5], [1, 2, 5], [3, 1, 5], [0, 3, 5],
+    /// ];
+    /// let octahedron = Mesh::new(positions, None, indices);
+    ///
+    /// let smooth = octahedron.subdivide(3);
+    /// assert_eq!(smooth.triangle_count(), 8 * 4 * 4 * 4);
+    /// assert!(smooth.normals().is_some());
+    ///
+    /// // The vertices end up at similar distances from the center.
+    /// let distances: Vec<_> = smooth
+    ///     .positions()
+    ///     .iter()
+    ///     .map(|p| p.to_vec().magnitude())
+    ///     .collect();
+    /// let nearest = distances.iter().cloned().fold(1.0, lucifer::Float::min);
+    /// let farthest = distances.iter().cloned().fold(0.0, lucifer::Float::max);
+    /// assert!(nearest > 0.4 && farthest < 0.55);
+    /// ```
+    pub fn subdivide(&self, levels: usize) -> Mesh {
+        let mut positions = self.positions().to_vec();
+        let mut indices = self.indices().to_vec();
+        for _ in 0..levels {
+            let (p, i) = refine(&positions, &indices, true);
+            positions = p;
+            indices = i;
+        }
+
+        let normals = smooth_normals(&positions, &indices);
+        Mesh::new(positions, Some(normals), indices)
+    }
+}
+
+/// An edge of the mesh, with the vertices opposite it in the
+/// triangles sharing it: one on the boundary, usually two elsewhere.
+struct Edge {
+    ends: (u32, u32),
+    opposite: Vec<u32>,
+}
+
+/// One step of Loop subdivision, or, unless `smooth`, of splitting
+/// every triangle into four at the middle of its edges.
+///
+/// The vertices splitting the edges follow the original vertices, in
+/// the order the edges are first met, so the result does not depend on
+/// hashing.
+pub(crate) fn refine(
+    positions: &[Point],
+    indices: &[[u32; 3]],
+    smooth: bool,
+) -> (Vec<Point>, Vec<[u32; 3]>) {
+    let key = |a: u32, b: u32| if a < b { (a, b) } else { (b, a) };
+
+    let mut edges: Vec<Edge> = Vec::new();
+    let mut lookup: HashMap<(u32, u32), usize> = HashMap::new();
+    for &[a, b, c] in indices {
+        for &(from, to, opposite) in &[(a, b, c), (b, c, a), (c, a, b)] {
+            let ends = key(from, to);
+            let index = *lookup.entry(ends).or_insert_with(|| {
+                edges.push(Edge {
+                    ends,
+                    opposite: Vec::new(),
+                });
+                edges.len() - 1
+            });
+            edges[index].opposite.push(opposite);
+        }
+    }
+
+    // The neighbours of every vertex, and those along the boundary.
+    let mut neighbours = vec![Vec::new(); positions.len()];
+    let mut boundary = vec![Vec::new(); positions.len()];
+    for edge in &edges {
+        let (a, b) = edge.ends;
+        neighbours[a as usize].push(b);
+        neighbours[b as usize].push(a);
+        if edge.opposite.len() == 1 {
+            boundary[a as usize].push(b);
+            boundary[b as usize].push(a);
+        }
+    }
+
+    let point = |i: u32| positions[i as usize].to_vec();
+    let mut refined = Vec::with_capacity(positions.len() + edges.len());
+    for (i, &position) in positions.iter().enumerate() {
+        let position = position.to_vec();
+        let moved = match (boundary[i].len(), neighbours[i].len()) {
+            _ if !smooth => position,
+            (2, _) => position * 0.75 + (point(boundary[i][0]) + point(boundary[i][1])) * 0.125,
+            (0, k) if k > 0 => {
+                let beta = if k == 3 {
+                    3.0 / 16.0
+                } else {
+                    3.0 / (8.0 * k as Float)
+                };
+                let sum = neighbours[i]
+                    .iter()
+                    .fold(Vector::zero(), |sum, &n| sum + point(n));
+                position * (1.0 - k as Float * beta) + sum * beta
+            }
+            // Corners of non-manifold boundaries, and unused vertices,
+            // stay in place.
+            _ => position,
+        };
+        refined.push(Point::from_vec(moved));
+    }
+
+    for edge in &edges {
+        let (a, b) = edge.ends;
+        let ends = point(a) + point(b);
+        let split = match edge.opposite[..] {
+            [c, d] if smooth => ends * 0.375 + (point(c) + point(d)) * 0.125,
+            // Boundary edges, and edges shared by more than two
+            // triangles, are always split in the middle.
+            _ => ends * 0.5,
+        };
+        refined.push(Point::from_vec(split));
+    }
+
+    let split = |a: u32, b: u32| (positions.len() + lookup[&key(a, b)]) as u32;
+    let mut triangles = Vec::with_capacity(4 * indices.len());
+    for &[a, b, c] in indices {
+        let (ab, bc, ca) = (split(a, b), split(b, c), split(c, a));
+        triangles.push([a, ab, ca]);
+        triangles.push([b, bc, ab]);
+        triangles.push([c, ca, bc]);
+        triangles.push([ab, bc, ca]);
+    }
+
+    (refined, triangles)
+}
+
+/// The normals of the vertices, averaged over the adjacent triangles
+/// weighted by their area.
+pub(crate) fn smooth_normals(positions: &[Point], indices: &[[u32; 3]]) -> Vec<Vector> {
+    let mut normals = vec![Vector::zero(); positions.len()];
+    for &[a, b, c] in indices {
+        let (pa, pb, pc) = (
+            positions[a as usize],
+            positions[b as usize],
+            positions[c as usize],
+        );
+        let normal = (pb - pa).cross(pc - pa);
+        for &i in &[a, b, c] {
+            normals[i as usize] += normal;
+        }
+    }
+
+    normals
+        .into_iter()
+        .map(|n| {
+            if n.magnitude2() > 0.0 {
+                n.normalize()
+            } else {
+                Vector::unit_z()
+            }
+        })
+        .collect()
+}
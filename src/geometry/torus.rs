@@ -0,0 +1,259 @@
+use consts::PI;
+
+use cgmath::prelude::*;
+use smallvec::SmallVec;
+
+use geometry::{tangents, Bounds, Geometry, Intersection, Point, Ray, Vector};
+use Float;
+
+/// A ring torus, the surface swept by a circle of radius `minor`
+/// around a circle of radius `major`.
+///
+/// The torus encloses a volume, so rays leaving it hit the `inside` of
+/// its surface.  Rays cross the surface up to four times, found as the
+/// roots of a quartic polynomial, which are isolated and refined in
+/// double precision, so grazing rays and self-shadowing in the hole
+/// come out right even with `f32` geometry.
+///
+/// The surface coordinates are the angle around the axis and the angle
+/// around the tube, starting on its outside, both scaled to `[0, 1]`.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct Torus {
+    /// The center of the hole.
+    pub center: Point,
+    /// The axis through the hole (normalized).
+    pub axis: Vector,
+    /// The distance from the center to the middle of the tube.
+    pub major: Float,
+    /// The radius of the tube, less than `major`.
+    pub minor: Float,
+}
+
+impl Torus {
+    /// Creates a `Torus` around `axis` through `center`, whose tube
+    /// of radius `minor` runs at distance `major` from the center.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::geometry::torus::Torus;
+    /// use lucifer::geometry::{Geometry, Point, Ray, Vector};
+    ///
+    /// let center = Point::new(0.0, 0.0, 0.0);
+    /// let torus = Torus::new(center, Vector::new(0.0, 1.0, 0.0), 2.0, 0.5);
+    ///
+    /// // Along the x axis, through both sides of the tube.
+    /// let ray = Ray::new(Point::new(5.0, 0.0, 0.0), Vector::new(-1.0, 0.0, 0.0));
+    /// let hit = torus.intersect(&ray).unwrap();
+    /// assert!((hit.lambda - 2.5).abs() < 1e-5);
+    /// assert_eq!(hit.normal, Vector::new(1.0, 0.0, 0.0));
+    /// assert!(!hit.inside);
+    ///
+    /// // From within the tube.
+    /// let ray = Ray::new(Point::new(2.0, 0.0, 0.0), Vector::new(0.0, 1.0, 0.0));
+    /// let hit = torus.intersect(&ray).unwrap();
+    /// assert!((hit.lambda - 0.5).abs() < 1e-5);
+    /// assert!(hit.inside);
+    ///
+    /// // Down through the hole.
+    /// let ray = Ray::new(Point::new(0.0, 5.0, 0.0), Vector::new(0.0, -1.0, 0.0));
+    /// assert!(torus.intersect(&ray).is_none());
+    /// ```
+    pub fn new(center: Point, axis: Vector, major: Float, minor: Float) -> Torus {
+        Torus {
+            center,
+            axis: axis.normalize(),
+            major,
+            minor,
+        }
+    }
+
+    /// The outward normal at `position`, on the surface.
+    fn normal(&self, position: Point) -> Vector {
+        // Away from the nearest point on the middle of the tube.
+        let p = position - self.center;
+        let ring = p - self.axis * p.dot(self.axis);
+        let middle = if ring.magnitude2() > 0.0 {
+            ring.normalize() * self.major
+        } else {
+            Vector::zero()
+        };
+        (p - middle).normalize()
+    }
+}
+
+impl Geometry for Torus {
+    fn bounds(&self) -> Bounds {
+        // The middle of the tube extends `major * sin(angle to axis)`
+        // along an axis.
+        let a = self.axis;
+        let extent = |a: Float| self.major * (1.0 - a * a).max(0.0).sqrt() + self.minor;
+        let r = Vector::new(extent(a[0]), extent(a[1]), extent(a[2]));
+        Bounds::new(self.center - r, self.center + r)
+    }
+
+    fn intersect(&self, ray: &Ray) -> Option<Intersection> {
+        let (big, small) = (widen(self.major), widen(self.minor));
+        let direction = ray.direction.normalize();
+        let scale = widen(ray.direction.magnitude());
+
+        let wide = |v: Vector| [widen(v.x), widen(v.y), widen(v.z)];
+        let dot = |x: [f64; 3], y: [f64; 3]| x[0] * y[0] + x[1] * y[1] + x[2] * y[2];
+        let (o, d, a) = (
+            wide(ray.origin - self.center),
+            wide(direction),
+            wide(self.axis),
+        );
+
+        // Only the part of the ray within the (slightly enlarged)
+        // bounding sphere can hit, and measuring from its middle keeps
+        // the coefficients small.
+        let closest = -dot(o, d);
+        let miss = dot(o, o) - closest * closest;
+        let reach = ((big + small) * 1.001).powi(2) - miss;
+        if reach < 0.0 {
+            return None;
+        }
+        let reach = reach.sqrt();
+        let start = (closest - reach).max(0.0);
+        let end = (closest + reach).min(widen(ray.length) * scale);
+        if start > end {
+            return None;
+        }
+        let o = [
+            o[0] + d[0] * closest,
+            o[1] + d[1] * closest,
+            o[2] + d[2] * closest,
+        ];
+
+        // `(|p|^2 + R^2 - r^2)^2 = 4 R^2 (|p|^2 - (p . a)^2)` along the
+        // ray `p = o + t d`, with `|d| = 1`.
+        let (od, oo, oa, da) = (dot(o, d), dot(o, o), dot(o, a), dot(d, a));
+        let k = oo + big * big - small * small;
+        let r2 = 4.0 * big * big;
+        let quartic = [
+            k * k - r2 * (oo - oa * oa),
+            4.0 * od * k - 2.0 * r2 * (od - oa * da),
+            4.0 * od * od + 2.0 * k - r2 * (1.0 - da * da),
+            4.0 * od,
+            1.0,
+        ];
+
+        let t = roots(&quartic, start - closest, end - closest)
+            .into_iter()
+            .map(|t| t + closest)
+            .find(|&t| t > 0.0)?;
+
+        let lambda = narrow(t / scale);
+        let position = ray.origin + ray.direction * lambda;
+        let mut normal = self.normal(position);
+
+        let (tangent, bitangent) = tangents(self.axis);
+        let local = position - self.center;
+        let turn = |y: Float, x: Float| {
+            let angle = y.atan2(x) / (2.0 * PI);
+            angle - angle.floor()
+        };
+        let radial = (local - self.axis * local.dot(self.axis)).magnitude();
+        let uv = (
+            turn(local.dot(bitangent), local.dot(tangent)),
+            turn(normal.dot(self.axis), radial - self.major),
+        );
+        let inside = normal.dot(ray.direction) > 0.0;
+        if inside {
+            normal = -normal
+        }
+
+        Some(Intersection {
+            position,
+            normal,
+            lambda,
+            inside,
+            primitive: 0,
+            uv,
+        })
+    }
+
+    fn area(&self) -> Float {
+        4.0 * PI * PI * self.major * self.minor
+    }
+
+    fn sample_surface(&self, u: Float, v: Float) -> Option<(Point, Vector)> {
+        let (tangent, bitangent) = tangents(self.axis);
+        let (big, small) = (self.major, self.minor);
+
+        // The outside of the tube is larger than the inside, so the
+        // angle around the tube is distributed by `R + r cos(phi)`;
+        // invert `R phi + r sin(phi) = 2 pi R v` with Newton's method,
+        // which converges as the function is increasing.
+        let target = 2.0 * PI * big * v;
+        let mut phi = 2.0 * PI * v;
+        for _ in 0..8 {
+            let f = big * phi + small * phi.sin() - target;
+            phi = (phi - f / (big + small * phi.cos())).clamp(0.0, 2.0 * PI);
+        }
+
+        let theta = 2.0 * PI * u;
+        let radial = tangent * theta.cos() + bitangent * theta.sin();
+        let normal = radial * phi.cos() + self.axis * phi.sin();
+        Some((self.center + radial * big + normal * small, normal))
+    }
+}
+
+/// Widens `value` to `f64`, whatever the precision of `Float`.
+#[allow(clippy::unnecessary_cast)]
+fn widen(value: Float) -> f64 {
+    value as f64
+}
+
+/// Narrows `value` back to `Float`.
+#[allow(clippy::unnecessary_cast)]
+fn narrow(value: f64) -> Float {
+    value as Float
+}
+
+/// The roots of the polynomial with `coefficients`, lowest order
+/// first, between `start` and `end`, in increasing order.
+///
+/// The roots of the derivative split the range into pieces on which
+/// the polynomial is monotonic, so each holds at most one root, found
+/// by bisection.
+fn roots(coefficients: &[f64], start: f64, end: f64) -> SmallVec<[f64; 4]> {
+    let value = |x: f64| coefficients.iter().rev().fold(0.0, |acc, &c| acc * x + c);
+
+    let mut bounds: SmallVec<[f64; 6]> = SmallVec::new();
+    bounds.push(start);
+    if coefficients.len() > 2 {
+        let derivative: SmallVec<[f64; 4]> = coefficients
+            .iter()
+            .enumerate()
+            .skip(1)
+            .map(|(i, &c)| c * i as f64)
+            .collect();
+        bounds.extend(roots(&derivative, start, end));
+    }
+    bounds.push(end);
+
+    let mut found = SmallVec::new();
+    for piece in bounds.windows(2) {
+        let (mut lo, mut hi) = (piece[0], piece[1]);
+        let (f_lo, f_hi) = (value(lo), value(hi));
+        if f_lo == 0.0 || f_lo.signum() == f_hi.signum() {
+            // Roots on the bounds are found by the neighbouring piece.
+            if f_hi == 0.0 {
+                found.push(hi);
+            }
+            continue;
+        }
+        for _ in 0..64 {
+            let mid = 0.5 * (lo + hi);
+            if value(mid).signum() == f_lo.signum() {
+                lo = mid;
+            } else {
+                hi = mid;
+            }
+        }
+        found.push(0.5 * (lo + hi));
+    }
+    found
+}
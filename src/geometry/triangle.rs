@@ -0,0 +1,170 @@
+use cgmath::prelude::*;
+
+use geometry::{Bounds, Geometry, Intersection, Point, Ray, Vector};
+use montecarlo::warp::uniform_triangle;
+use Float;
+
+/// A triangle.
+///
+/// Triangles are flat and have no volume; like a `Disc`, rays hitting
+/// the back side, which the vertices wind clockwise around, are
+/// `inside`.  The surface coordinates are the barycentric coordinates
+/// of the second and third corner.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct Triangle {
+    /// The triangle's corners, counter-clockwise around the front.
+    pub vertices: [Point; 3],
+}
+
+impl Triangle {
+    /// Creates a `Triangle` with the corners `a`, `b`, and `c`.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::geometry::triangle::Triangle;
+    /// use lucifer::geometry::{Geometry, Point, Ray, Vector};
+    ///
+    /// let triangle = Triangle::new(
+    ///     Point::new(0.0, 0.0, 0.0),
+    ///     Point::new(1.0, 0.0, 0.0),
+    ///     Point::new(0.0, 1.0, 0.0),
+    /// );
+    ///
+    /// let front = Ray::new(Point::new(0.25, 0.25, 1.0), Vector::new(0.0, 0.0, -1.0));
+    /// let hit = triangle.intersect(&front).unwrap();
+    /// assert_eq!(hit.lambda, 1.0);
+    /// assert_eq!(hit.normal, Vector::new(0.0, 0.0, 1.0));
+    /// assert_eq!(hit.uv, (0.25, 0.25));
+    /// assert!(!hit.inside);
+    ///
+    /// let back = Ray::new(Point::new(0.25, 0.25, -1.0), Vector::new(0.0, 0.0, 1.0));
+    /// let hit = triangle.intersect(&back).unwrap();
+    /// assert_eq!(hit.normal, Vector::new(0.0, 0.0, -1.0));
+    /// assert!(hit.inside);
+    ///
+    /// assert!(triangle.intersect_front(&back).is_none());
+    ///
+    /// let beside = Ray::new(Point::new(0.75, 0.75, 1.0), Vector::new(0.0, 0.0, -1.0));
+    /// assert!(triangle.intersect(&beside).is_none());
+    ///
+    /// // Tiny triangles are hit, too.
+    /// let tiny = Triangle::new(
+    ///     Point::new(0.0, 0.0, 0.0),
+    ///     Point::new(1e-5, 0.0, 0.0),
+    ///     Point::new(0.0, 1e-5, 0.0),
+    /// );
+    /// let ray = Ray::new(Point::new(2.5e-6, 2.5e-6, 1.0), Vector::new(0.0, 0.0, -1.0));
+    /// assert!(tiny.intersect(&ray).is_some());
+    /// ```
+    pub fn new(a: Point, b: Point, c: Point) -> Triangle {
+        Triangle {
+            vertices: [a, b, c],
+        }
+    }
+
+    /// The unit normal of the front side.
+    pub fn normal(&self) -> Vector {
+        let [a, b, c] = self.vertices;
+        (b - a).cross(c - a).normalize()
+    }
+
+    /// Intersects the triangle with `ray`, missing its back if `cull`.
+    fn hit(&self, ray: &Ray, cull: bool) -> Option<Intersection> {
+        let [a, b, c] = self.vertices;
+        let (lambda, u, v) = intersect_triangle(a, b, c, ray, cull)?;
+
+        let mut normal = self.normal();
+        let inside = normal.dot(ray.direction) > 0.0;
+        if inside {
+            normal = -normal;
+        }
+
+        Some(Intersection {
+            position: ray.origin + ray.direction * lambda,
+            normal,
+            lambda,
+            inside,
+            primitive: 0,
+            uv: (u, v),
+        })
+    }
+}
+
+/// Intersects `ray` with the triangle `a`, `b`, `c` (Möller and
+/// Trumbore, "Fast, Minimum Storage Ray/Triangle Intersection"),
+/// returning the distance along the ray and the barycentric
+/// coordinates of `b` and `c` at the intersection.  With `cull`, the
+/// back of the triangle is missed.
+pub(crate) fn intersect_triangle(
+    a: Point,
+    b: Point,
+    c: Point,
+    ray: &Ray,
+    cull: bool,
+) -> Option<(Float, Float, Float)> {
+    let (ab, ac) = (b - a, c - a);
+    let p = ray.direction.cross(ac);
+    let determinant = ab.dot(p);
+
+    // The ray is parallel to the plane of the triangle, or, if the
+    // determinant is negative, hits its back.  The determinant scales
+    // with the edges and the direction, so tiny triangles still count.
+    let scale = ab.magnitude() * ac.magnitude() * ray.direction.magnitude();
+    if !determinant.is_finite()
+        || determinant.abs() <= Float::EPSILON * scale
+        || (cull && determinant < 0.0)
+    {
+        return None;
+    }
+    let inverse = 1.0 / determinant;
+
+    let t = ray.origin - a;
+    let u = t.dot(p) * inverse;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+
+    let q = t.cross(ab);
+    let v = ray.direction.dot(q) * inverse;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+
+    let lambda = ac.dot(q) * inverse;
+    if !(lambda > 0.0 && lambda <= ray.length) {
+        return None;
+    }
+
+    Some((lambda, u, v))
+}
+
+impl Geometry for Triangle {
+    fn bounds(&self) -> Bounds {
+        let [a, b, c] = self.vertices;
+        Bounds::new(a, a).include(b).include(c)
+    }
+
+    fn intersect(&self, ray: &Ray) -> Option<Intersection> {
+        self.hit(ray, false)
+    }
+
+    fn intersect_front(&self, ray: &Ray) -> Option<Intersection> {
+        self.hit(ray, true)
+    }
+
+    fn triangle_count(&self) -> usize {
+        1
+    }
+
+    fn area(&self) -> Float {
+        let [a, b, c] = self.vertices;
+        0.5 * (b - a).cross(c - a).magnitude()
+    }
+
+    fn sample_surface(&self, u: Float, v: Float) -> Option<(Point, Vector)> {
+        let [a, b, c] = self.vertices;
+        let (position, _) = uniform_triangle(a, b, c, u, v);
+        Some((position, self.normal()))
+    }
+}
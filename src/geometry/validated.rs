@@ -0,0 +1,258 @@
+use std::error;
+use std::fmt;
+
+use cgmath::{EuclideanSpace, InnerSpace};
+
+use geometry::{Bounds, Geometry, Intersection, Point, Ray, Vector};
+use Float;
+
+/// The tolerance of the checks, relative to the size of the compared
+/// values.
+const EPSILON: Float = 1e-3;
+
+/// A law of `Geometry` broken by an implementation.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Violation {
+    /// `occlude` returned `occluded`, but `intersect` disagreed.
+    Occlusion { occluded: bool },
+    /// The normal is not of unit length.
+    NormalLength(Float),
+    /// The distance is not positive, or beyond the length of the ray.
+    Lambda(Float),
+    /// The position is not at the distance along the ray.
+    Position(Point),
+    /// The normal does not face the ray, so `inside` does not tell
+    /// which side was hit.
+    Inside { inside: bool },
+    /// `intersect_all` listed an intersection at this distance out of
+    /// order, or did not start with the intersection of `intersect`.
+    Order(Float),
+    /// `intersect_front` hit a back face, at this distance.
+    BackFace(Float),
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Violation::Occlusion { occluded: true } => {
+                write!(f, "occluded, but without intersection")
+            }
+            Violation::Occlusion { occluded: false } => {
+                write!(f, "intersected, but not occluded")
+            }
+            Violation::NormalLength(length) => write!(f, "normal of length {}", length),
+            Violation::Lambda(lambda) => write!(f, "intersection at distance {}", lambda),
+            Violation::Position(p) => write!(f, "intersection at {:?}, not on the ray", p),
+            Violation::Inside { inside } => {
+                write!(f, "normal faces away from the ray, inside is {}", inside)
+            }
+            Violation::Order(lambda) => {
+                write!(f, "intersection at distance {} out of order", lambda)
+            }
+            Violation::BackFace(lambda) => write!(f, "back face hit at distance {}", lambda),
+        }
+    }
+}
+
+impl error::Error for Violation {}
+
+/// Wraps a `Geometry`, checking the laws of `Geometry` for every
+/// intersection test, and panicking with the offending ray when one
+/// is broken.
+///
+/// Useful to debug new shapes: the checks trace every ray twice, so
+/// they are too slow to keep in a regular render.
+///
+/// # Examples
+/// ```
+/// use lucifer::geometry::{Bounds, Geometry, Intersection, Plane, Point, Ray, Sphere, Vector};
+/// use lucifer::geometry::{ValidatedGeometry, Violation};
+///
+/// // A sphere that forgets to normalize its normals.
+/// struct Sloppy(Sphere);
+///
+/// impl Geometry for Sloppy {
+///     fn intersect(&self, ray: &Ray) -> Option<Intersection> {
+///         self.0.intersect(ray).map(|i| Intersection {
+///             normal: i.normal * 2.0,
+///             ..i
+///         })
+///     }
+///
+///     fn bounds(&self) -> Bounds {
+///         self.0.bounds()
+///     }
+/// }
+///
+/// let sphere = Sphere::new(Point::new(0.0, 0.0, 0.0), 1.0);
+/// let ray = Ray::new(Point::new(0.0, 0.0, 5.0), Vector::new(0.0, 0.0, -1.0));
+///
+/// assert!(ValidatedGeometry::new(sphere.clone()).check(&ray).is_ok());
+/// assert_eq!(ValidatedGeometry::new(sphere.clone()).check_all(&ray).unwrap().len(), 2);
+/// assert_eq!(
+///     ValidatedGeometry::new(Sloppy(sphere)).check(&ray).unwrap_err(),
+///     Violation::NormalLength(2.0)
+/// );
+///
+/// // Rays ending in front of a surface do not hit it.
+/// let floor = ValidatedGeometry::new(Plane::new(Vector::new(0.0, 0.0, 1.0), 0.0));
+/// let short = Ray::from_endpoints(Point::new(0.0, 0.0, 5.0), Point::new(0.0, 0.0, 1.0));
+/// assert!(floor.intersect(&short).is_none());
+/// assert!(!floor.occlude(&short));
+/// ```
+#[derive(Clone, Debug)]
+pub struct ValidatedGeometry<G> {
+    geometry: G,
+}
+
+impl<G: Geometry> ValidatedGeometry<G> {
+    /// Creates a new `ValidatedGeometry`, checking `geometry`.
+    pub fn new(geometry: G) -> ValidatedGeometry<G> {
+        ValidatedGeometry { geometry }
+    }
+
+    /// The checked geometry.
+    pub fn inner(&self) -> &G {
+        &self.geometry
+    }
+
+    /// Intersects the geometry with `ray`, failing if any law of
+    /// `Geometry` is broken.
+    pub fn check(&self, ray: &Ray) -> Result<Option<Intersection>, Violation> {
+        let intersection = self.geometry.intersect(ray);
+        let occluded = self.geometry.occlude(ray);
+        if occluded != intersection.is_some() {
+            return Err(Violation::Occlusion { occluded });
+        }
+
+        if let Some(ref i) = intersection {
+            check_intersection(ray, i)?;
+        }
+
+        Ok(intersection)
+    }
+
+    /// Intersects the geometry with `ray` with `intersect_all`, failing
+    /// if any law of `Geometry` is broken by one of the intersections.
+    pub fn check_all(&self, ray: &Ray) -> Result<Vec<Intersection>, Violation> {
+        let intersections = self.geometry.intersect_all(ray);
+        for i in &intersections {
+            check_intersection(ray, i)?;
+        }
+
+        for pair in intersections.windows(2) {
+            if pair[1].lambda < pair[0].lambda {
+                return Err(Violation::Order(pair[1].lambda));
+            }
+        }
+
+        let nearest = self.check(ray)?.map(|i| i.lambda);
+        let first = intersections.first().map(|i| i.lambda);
+        let same = match (nearest, first) {
+            (Some(a), Some(b)) => (a - b).abs() <= EPSILON * (1.0 + a),
+            (a, b) => a == b,
+        };
+        if !same {
+            return Err(Violation::Order(first.or(nearest).unwrap_or(0.0)));
+        }
+
+        Ok(intersections)
+    }
+
+    /// Intersects the geometry with `ray` with `intersect_front`,
+    /// failing if any law of `Geometry` is broken, or a back face is
+    /// hit.
+    pub fn check_front(&self, ray: &Ray) -> Result<Option<Intersection>, Violation> {
+        let intersection = self.geometry.intersect_front(ray);
+        let occluded = self.geometry.occlude_front(ray);
+        if occluded != intersection.is_some() {
+            return Err(Violation::Occlusion { occluded });
+        }
+
+        if let Some(ref i) = intersection {
+            check_intersection(ray, i)?;
+            if i.inside {
+                return Err(Violation::BackFace(i.lambda));
+            }
+        }
+
+        Ok(intersection)
+    }
+
+    fn checked(&self, ray: &Ray) -> Option<Intersection> {
+        self.check(ray)
+            .unwrap_or_else(|v| panic!("geometry law violated by {:?}: {}", ray, v))
+    }
+}
+
+/// Checks the laws of `Geometry` for an intersection `i` with `ray`.
+fn check_intersection(ray: &Ray, i: &Intersection) -> Result<(), Violation> {
+    let length = i.normal.magnitude();
+    if (length - 1.0).abs() > EPSILON {
+        return Err(Violation::NormalLength(length));
+    }
+
+    if !(i.lambda > 0.0 && i.lambda <= ray.length) {
+        return Err(Violation::Lambda(i.lambda));
+    }
+
+    let expected = ray.origin + ray.direction * i.lambda;
+    let scale = 1.0 + i.lambda + expected.to_vec().magnitude();
+    if (i.position - expected).magnitude() > EPSILON * scale {
+        return Err(Violation::Position(i.position));
+    }
+
+    if i.normal.dot(ray.direction) > EPSILON {
+        return Err(Violation::Inside { inside: i.inside });
+    }
+
+    Ok(())
+}
+
+impl<G: Geometry> Geometry for ValidatedGeometry<G> {
+    fn intersect(&self, ray: &Ray) -> Option<Intersection> {
+        self.checked(ray)
+    }
+
+    fn occlude(&self, ray: &Ray) -> bool {
+        self.checked(ray).is_some()
+    }
+
+    fn intersect_all(&self, ray: &Ray) -> Vec<Intersection> {
+        self.check_all(ray)
+            .unwrap_or_else(|v| panic!("geometry law violated by {:?}: {}", ray, v))
+    }
+
+    fn intersect_front(&self, ray: &Ray) -> Option<Intersection> {
+        self.check_front(ray)
+            .unwrap_or_else(|v| panic!("geometry law violated by {:?}: {}", ray, v))
+    }
+
+    fn occlude_front(&self, ray: &Ray) -> bool {
+        self.intersect_front(ray).is_some()
+    }
+
+    fn bounds(&self) -> Bounds {
+        self.geometry.bounds()
+    }
+
+    fn triangle_count(&self) -> usize {
+        self.geometry.triangle_count()
+    }
+
+    fn memory(&self) -> usize {
+        self.geometry.memory()
+    }
+
+    fn area(&self) -> Float {
+        self.geometry.area()
+    }
+
+    fn sample_surface(&self, u: Float, v: Float) -> Option<(Point, Vector)> {
+        self.geometry.sample_surface(u, v)
+    }
+
+    fn pdf_from(&self, point: Point, direction: Vector) -> Float {
+        self.geometry.pdf_from(point, direction)
+    }
+}
@@ -0,0 +1,154 @@
+use geometry::{Bounds, Geometry, Intersection, Point, Ray, Vector};
+use Float;
+
+/// The part of a ray within a volume, from where it enters to where
+/// it leaves.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct Interval {
+    /// The distance along the ray at which it enters the volume, `0`
+    /// for rays starting within.
+    pub t_near: Float,
+    /// The distance along the ray at which it leaves the volume, at
+    /// most the length of the ray.
+    pub t_far: Float,
+}
+
+impl Interval {
+    /// The length of the ray within the volume.
+    pub fn length(&self) -> Float {
+        self.t_far - self.t_near
+    }
+}
+
+/// Wraps a closed `Geometry` as the boundary of a region of space,
+/// e.g. a bank of fog or a plume of smoke, reporting where rays pass
+/// through the region instead of where they hit its surface.
+///
+/// A volumetric integrator marches through the `Interval` of a ray
+/// within the region.  Later intervals of non-convex regions are found
+/// by continuing the ray past the end of the first one.  As a
+/// `Geometry`, the volume is just its boundary, so it can also be
+/// placed in a scene, e.g. as an `Object::region`.
+///
+/// # Examples
+/// ```
+/// use lucifer::geometry::{Interval, Point, Ray, Sphere, Vector, VolumeBounds};
+///
+/// let fog = VolumeBounds::new(Sphere::new(Point::new(0.0, 0.0, 0.0), 1.0));
+///
+/// // Through the volume, from outside and from within.
+/// let ray = Ray::new(Point::new(-5.0, 0.0, 0.0), Vector::new(1.0, 0.0, 0.0));
+/// let interval = fog.interval(&ray).unwrap();
+/// assert_eq!(interval, Interval { t_near: 4.0, t_far: 6.0 });
+/// assert_eq!(interval.length(), 2.0);
+///
+/// let ray = Ray::new(Point::new(0.0, 0.0, 0.0), Vector::new(0.0, 1.0, 0.0));
+/// assert_eq!(fog.interval(&ray), Some(Interval { t_near: 0.0, t_far: 1.0 }));
+///
+/// // Rays ending within the volume leave it at their end.
+/// let ray = Ray::from_endpoints(Point::new(-5.0, 0.0, 0.0), Point::new(0.5, 0.0, 0.0));
+/// assert_eq!(fog.interval(&ray), Some(Interval { t_near: 4.0, t_far: 5.5 }));
+///
+/// // Past the volume.
+/// let ray = Ray::new(Point::new(-5.0, 2.0, 0.0), Vector::new(1.0, 0.0, 0.0));
+/// assert_eq!(fog.interval(&ray), None);
+///
+/// // However small the volume.
+/// let droplet = VolumeBounds::new(Sphere::new(Point::new(0.0, 0.0, 0.0), 1e-5));
+/// let ray = Ray::new(Point::new(-5e-5, 0.0, 0.0), Vector::new(1.0, 0.0, 0.0));
+/// let interval = droplet.interval(&ray).unwrap();
+/// assert!((interval.length() - 2e-5).abs() < 1e-7);
+/// ```
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct VolumeBounds<G> {
+    geometry: G,
+}
+
+impl<G: Geometry> VolumeBounds<G> {
+    /// Creates a new `VolumeBounds` within the surface of `geometry`,
+    /// which must be closed.
+    pub fn new(geometry: G) -> VolumeBounds<G> {
+        VolumeBounds { geometry }
+    }
+
+    /// The boundary of the volume.
+    pub fn inner(&self) -> &G {
+        &self.geometry
+    }
+
+    /// The first part of `ray` within the volume, if any.
+    pub fn interval(&self, ray: &Ray) -> Option<Interval> {
+        let first = self.geometry.intersect(ray)?;
+        if first.inside {
+            return Some(Interval {
+                t_near: 0.0,
+                t_far: first.lambda,
+            });
+        }
+
+        // Entering, so look for the way out beyond the entry, stepping
+        // past it by the error bound of the entry point.
+        let t_near = first.lambda;
+        let step = t_near + first.step(ray.direction);
+        let rest = Ray {
+            origin: ray.origin + ray.direction * step,
+            direction: ray.direction,
+            length: ray.length - step,
+            time: ray.time,
+        };
+        let t_far = match self.geometry.intersect(&rest) {
+            Some(exit) => step + exit.lambda,
+            None => ray.length,
+        };
+
+        Some(Interval { t_near, t_far })
+    }
+}
+
+impl<G: Geometry> Geometry for VolumeBounds<G> {
+    fn intersect(&self, ray: &Ray) -> Option<Intersection> {
+        self.geometry.intersect(ray)
+    }
+
+    fn occlude(&self, ray: &Ray) -> bool {
+        self.geometry.occlude(ray)
+    }
+
+    fn intersect_all(&self, ray: &Ray) -> Vec<Intersection> {
+        self.geometry.intersect_all(ray)
+    }
+
+    fn intersect_front(&self, ray: &Ray) -> Option<Intersection> {
+        self.geometry.intersect_front(ray)
+    }
+
+    fn occlude_front(&self, ray: &Ray) -> bool {
+        self.geometry.occlude_front(ray)
+    }
+
+    fn bounds(&self) -> Bounds {
+        self.geometry.bounds()
+    }
+
+    fn triangle_count(&self) -> usize {
+        self.geometry.triangle_count()
+    }
+
+    fn memory(&self) -> usize {
+        self.geometry.memory()
+    }
+
+    fn area(&self) -> Float {
+        self.geometry.area()
+    }
+
+    fn sample_surface(&self, u: Float, v: Float) -> Option<(Point, Vector)> {
+        self.geometry.sample_surface(u, v)
+    }
+
+    fn pdf_from(&self, point: Point, direction: Vector) -> Float {
+        self.geometry.pdf_from(point, direction)
+    }
+}
@@ -3,12 +3,52 @@ pub extern crate cgmath;
 #[macro_use]
 extern crate derive_more;
 
-extern crate rand;
+#[cfg(feature = "image")]
+extern crate image;
+pub extern crate rand;
 extern crate smallvec;
 
+#[cfg(feature = "log")]
+extern crate log;
+
+#[cfg(feature = "scene-file")]
+extern crate ron;
+#[cfg(feature = "serialize")]
+#[macro_use]
+extern crate serde_derive;
+#[cfg(feature = "scene-file")]
+extern crate serde_json;
+
+#[macro_use]
+mod trace;
+
 pub mod camera;
+mod error;
 pub mod geometry;
 pub mod lighting;
 pub mod montecarlo;
 pub mod render;
 pub mod scene;
+pub mod verification;
+#[cfg(target_arch = "wasm32")]
+pub mod wasm;
+
+pub use error::{Error, Result};
+
+/// The floating point type of all geometry, transformations, and
+/// shading: `f32`, or `f64` with the `f64` feature, e.g. for scenes
+/// far from the origin, where `f32` positions are too coarse.
+#[cfg(not(feature = "f64"))]
+pub type Float = f32;
+/// The floating point type of all geometry, transformations, and
+/// shading: `f32`, or `f64` with the `f64` feature, e.g. for scenes
+/// far from the origin, where `f32` positions are too coarse.
+#[cfg(feature = "f64")]
+pub type Float = f64;
+
+/// Mathematical constants of type `Float`.
+#[cfg(not(feature = "f64"))]
+pub use std::f32::consts;
+/// Mathematical constants of type `Float`.
+#[cfg(feature = "f64")]
+pub use std::f64::consts;
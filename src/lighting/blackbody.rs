@@ -3,6 +3,7 @@ use lighting::{Bsdf, Distribution, Effect, Material, Radiance};
 
 /// A pure emitter of light.
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub struct Blackbody {
     pub radiance: Radiance,
 }
@@ -23,4 +24,8 @@ impl Material for Blackbody {
 
         bsdf
     }
+
+    fn emission(&self) -> Radiance {
+        self.radiance
+    }
 }
@@ -0,0 +1,487 @@
+//! CIE colorimetry for `Radiance`.
+//!
+//! The channels of `Radiance` are taken as the linear primaries of
+//! sRGB (ITU-R BT.709), with the white point of illuminant D65.
+//! Spectral power distributions are converted through the CIE 1931
+//! standard observer, interpolated linearly between wavelengths
+//! 10nm apart.
+
+use std::sync::OnceLock;
+
+use cgmath::Vector3;
+
+use lighting::{Albedo, Radiance};
+use Float;
+
+/// The range of wavelengths, in nanometers, integrated over.
+const VISIBLE: (Float, Float) = (380.0, 780.0);
+
+/// The spacing of the wavelengths integrated over, in nanometers.
+const STEP: Float = 1.0;
+
+/// A color in the CIE 1931 XYZ space.  `y` is the luminance.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct Xyz {
+    pub x: Float,
+    pub y: Float,
+    pub z: Float,
+}
+
+/// A color given by its CIE chromaticity `x`, `y`, and its
+/// luminance.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct Xyy {
+    pub x: Float,
+    pub y: Float,
+    pub luminance: Float,
+}
+
+impl Xyz {
+    /// Creates a new `Xyz` color.
+    pub fn new(x: Float, y: Float, z: Float) -> Self {
+        Xyz { x, y, z }
+    }
+
+    /// The color of a spectral power distribution, scaled so that a
+    /// constant spectrum of `1` has a luminance of `1`.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::lighting::color::Xyz;
+    /// use lucifer::Float;
+    ///
+    /// let white = Xyz::from_spectrum(&|_| 1.0);
+    /// assert!((white.y - 1.0).abs() < 1e-4);
+    ///
+    /// // Monochromatic light of 700nm is deep red.
+    /// let red = Xyz::from_spectrum(&|l: Float| if (l - 700.0).abs() < 5.0 { 1.0 } else { 0.0 });
+    /// assert!(red.to_xyy().x > 0.73);
+    /// ```
+    pub fn from_spectrum<S: Spectrum + ?Sized>(spectrum: &S) -> Self {
+        let mut xyz = Xyz::new(0.0, 0.0, 0.0);
+        let mut norm = 0.0;
+
+        let mut wavelength = VISIBLE.0;
+        while wavelength <= VISIBLE.1 {
+            let power = spectrum.power(wavelength);
+            let (x, y, z) = observer(wavelength);
+            xyz.x += power * x;
+            xyz.y += power * y;
+            xyz.z += power * z;
+            norm += y;
+            wavelength += STEP;
+        }
+
+        Xyz::new(xyz.x / norm, xyz.y / norm, xyz.z / norm)
+    }
+
+    /// An estimate of the color of a spectral power distribution from
+    /// its `power` at a single `wavelength`, drawn uniformly from the
+    /// visible range, e.g. by `sample_wavelength`.  The mean over the
+    /// wavelengths is the color computed by `from_spectrum`.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::lighting::color::{sample_wavelength, Xyz};
+    /// use lucifer::Float;
+    ///
+    /// let n = 1000;
+    /// let white = (0..n)
+    ///     .map(|i| Xyz::from_wavelength(sample_wavelength((i as Float + 0.5) / n as Float), 1.0))
+    ///     .fold(0.0, |sum, xyz| sum + xyz.y / n as Float);
+    /// assert!((white - 1.0).abs() < 1e-2);
+    /// ```
+    pub fn from_wavelength(wavelength: Float, power: Float) -> Self {
+        static NORM: OnceLock<Float> = OnceLock::new();
+        let norm = NORM.get_or_init(|| {
+            let steps = ((VISIBLE.1 - VISIBLE.0) / STEP) as usize;
+            (0..=steps)
+                .map(|i| observer(VISIBLE.0 + i as Float * STEP).1)
+                .sum::<Float>()
+        });
+
+        let (x, y, z) = observer(wavelength);
+        let scale = power * (VISIBLE.1 - VISIBLE.0) / (STEP * norm);
+        Xyz::new(x * scale, y * scale, z * scale)
+    }
+
+    /// The chromaticity and luminance of the color.  Black has the
+    /// chromaticity of D65.
+    pub fn to_xyy(self) -> Xyy {
+        let sum = self.x + self.y + self.z;
+        if sum == 0.0 {
+            let white = Illuminant::D65.chromaticity();
+            return Xyy::new(white.0, white.1, 0.0);
+        }
+
+        Xyy::new(self.x / sum, self.y / sum, self.y)
+    }
+}
+
+impl Xyy {
+    /// Creates a new `Xyy` color.
+    pub fn new(x: Float, y: Float, luminance: Float) -> Self {
+        Xyy { x, y, luminance }
+    }
+
+    /// The color in XYZ.  Chromaticities with a `y` of zero are
+    /// black.
+    pub fn to_xyz(self) -> Xyz {
+        if self.y == 0.0 {
+            return Xyz::new(0.0, 0.0, 0.0);
+        }
+
+        let scale = self.luminance / self.y;
+        Xyz::new(
+            self.x * scale,
+            self.luminance,
+            (1.0 - self.x - self.y) * scale,
+        )
+    }
+}
+
+impl From<Radiance> for Xyz {
+    fn from(radiance: Radiance) -> Xyz {
+        let (r, g, b) = (radiance[0], radiance[1], radiance[2]);
+        Xyz::new(
+            0.412_456_4 * r + 0.357_576_1 * g + 0.180_437_5 * b,
+            0.212_672_9 * r + 0.715_152_2 * g + 0.072_175 * b,
+            0.019_333_9 * r + 0.119_192 * g + 0.950_304_1 * b,
+        )
+    }
+}
+
+/// Colors outside the gamut of sRGB get negative channels.
+impl From<Xyz> for Radiance {
+    fn from(xyz: Xyz) -> Radiance {
+        let Xyz { x, y, z } = xyz;
+        Radiance::new(
+            3.240_454_2 * x - 1.537_138_5 * y - 0.498_531_4 * z,
+            -0.969_266 * x + 1.876_010_8 * y + 0.041_556 * z,
+            0.055_643_4 * x - 0.204_025_9 * y + 1.057_225_2 * z,
+        )
+    }
+}
+
+impl From<Xyy> for Radiance {
+    fn from(xyy: Xyy) -> Radiance {
+        Radiance::from(xyy.to_xyz())
+    }
+}
+
+impl From<Radiance> for Xyy {
+    fn from(radiance: Radiance) -> Xyy {
+        Xyz::from(radiance).to_xyy()
+    }
+}
+
+/// A spectral power distribution over wavelengths in nanometers.
+pub trait Spectrum {
+    /// The power at `wavelength`.
+    fn power(&self, wavelength: Float) -> Float;
+}
+
+impl<F> Spectrum for F
+where
+    F: Fn(Float) -> Float,
+{
+    fn power(&self, wavelength: Float) -> Float {
+        self(wavelength)
+    }
+}
+
+/// The CIE standard illuminants, and other light of known spectrum.
+///
+/// # Examples
+/// ```
+/// use lucifer::lighting::color::{Illuminant, Xyz};
+/// use lucifer::lighting::Radiance;
+///
+/// // D65 is the white of sRGB.
+/// let white = Illuminant::D65.color();
+/// for c in 0..3 {
+///     assert!((white[c] - 1.0).abs() < 0.01);
+/// }
+///
+/// // Incandescent light is orange, and hotter light bluer.
+/// let a = Illuminant::A.color();
+/// assert!(a[0] > a[1] && a[1] > a[2]);
+/// let (x, y) = Illuminant::A.chromaticity();
+/// assert!((x - 0.4476).abs() < 0.001 && (y - 0.4074).abs() < 0.001);
+/// assert!(Illuminant::Planck(10000.0).color()[2] > 1.0);
+///
+/// assert!((Xyz::from(white).y - 1.0).abs() < 1e-3);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum Illuminant {
+    /// Average daylight, the white point of sRGB.
+    D65,
+    /// Incandescent light, a black body at 2856 K.
+    A,
+    /// A black body at the given temperature in kelvin.
+    Planck(Float),
+}
+
+impl Illuminant {
+    /// The color of the illuminant, with a luminance of `1`.
+    pub fn color(self) -> Radiance {
+        Radiance::from(Xyy::new(self.chromaticity().0, self.chromaticity().1, 1.0))
+    }
+
+    /// The chromaticity `x`, `y` of the illuminant.
+    pub fn chromaticity(self) -> (Float, Float) {
+        let xyy = Xyz::from_spectrum(&self).to_xyy();
+        (xyy.x, xyy.y)
+    }
+}
+
+impl Spectrum for Illuminant {
+    /// The relative power, `100` at 560nm.
+    fn power(&self, wavelength: Float) -> Float {
+        match *self {
+            Illuminant::D65 => d65(wavelength),
+            Illuminant::A => planck(wavelength, 2856.0),
+            Illuminant::Planck(temperature) => planck(wavelength, temperature),
+        }
+    }
+}
+
+/// The number of bins of spectra upsampled by Smits' method.
+const BINS: usize = 10;
+
+/// The smooth spectra of Smits' method, with ten bins of equal width
+/// from 380nm to 720nm.
+const SMITS_WHITE: [Float; BINS] = [1.0, 1.0, 0.9999, 0.9993, 0.9992, 0.9998, 1.0, 1.0, 1.0, 1.0];
+const SMITS_CYAN: [Float; BINS] = [
+    0.971, 0.9426, 1.0007, 1.0007, 1.0007, 1.0007, 0.1564, 0.0, 0.0, 0.0,
+];
+const SMITS_MAGENTA: [Float; BINS] = [
+    1.0, 1.0, 0.9685, 0.2229, 0.0, 0.0458, 0.8369, 1.0, 1.0, 0.9959,
+];
+const SMITS_YELLOW: [Float; BINS] = [
+    0.0001, 0.0, 0.1088, 0.6651, 1.0, 1.0, 0.9996, 0.9586, 0.9685, 0.984,
+];
+const SMITS_RED: [Float; BINS] = [
+    0.1012, 0.0515, 0.0, 0.0, 0.0, 0.0, 0.8325, 1.0149, 1.0149, 1.0149,
+];
+const SMITS_GREEN: [Float; BINS] = [
+    0.0, 0.0, 0.0273, 0.7937, 1.0, 0.9418, 0.1719, 0.0, 0.0, 0.0025,
+];
+const SMITS_BLUE: [Float; BINS] = [
+    1.0, 1.0, 0.8916, 0.3323, 0.0, 0.0, 0.0003, 0.0369, 0.0483, 0.0496,
+];
+
+/// A spectrum upsampled from an RGB color by Smits' method ("An
+/// RGB-to-Spectrum Conversion for Reflectances"), which composes it
+/// of smooth white, cyan, magenta, yellow, red, green, and blue
+/// spectra.  Spectra of `Radiance` are lit by D65, the white of sRGB,
+/// so that white light keeps its color.  Converted back, colors are
+/// within a few percent of the original, and saturated primaries
+/// lose a little saturation.
+///
+/// # Examples
+/// ```
+/// use lucifer::lighting::color::{Spectrum, Upsampled, Xyz};
+/// use lucifer::lighting::{Albedo, Radiance};
+///
+/// let orange = Radiance::new(0.8, 0.4, 0.1);
+/// let back = Radiance::from(Xyz::from_spectrum(&Upsampled::from(orange)));
+/// for c in 0..3 {
+///     assert!((back[c] - orange[c]).abs() < 0.05);
+/// }
+///
+/// // Reflectances stay between zero and one.
+/// let red = Upsampled::from(Albedo::new(1.0, 0.0, 0.0));
+/// assert!((380..780).all(|l| (0.0..=1.02).contains(&red.power(l as lucifer::Float))));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Upsampled {
+    bins: [Float; BINS],
+    /// The factor of the power of D65, for emission.
+    illuminant: Option<Float>,
+}
+
+impl Upsampled {
+    fn new(r: Float, g: Float, b: Float) -> Self {
+        let mut bins = [0.0; BINS];
+        let mut add = |weight: Float, spectrum: &[Float; BINS]| {
+            for (bin, value) in bins.iter_mut().zip(spectrum.iter()) {
+                *bin += weight * value;
+            }
+        };
+
+        // Take as much white as possible, then as much of the
+        // secondary color, and the rest of the primary.
+        if r <= g && r <= b {
+            add(r, &SMITS_WHITE);
+            if g <= b {
+                add(g - r, &SMITS_CYAN);
+                add(b - g, &SMITS_BLUE);
+            } else {
+                add(b - r, &SMITS_CYAN);
+                add(g - b, &SMITS_GREEN);
+            }
+        } else if g <= r && g <= b {
+            add(g, &SMITS_WHITE);
+            if r <= b {
+                add(r - g, &SMITS_MAGENTA);
+                add(b - r, &SMITS_BLUE);
+            } else {
+                add(b - g, &SMITS_MAGENTA);
+                add(r - b, &SMITS_RED);
+            }
+        } else {
+            add(b, &SMITS_WHITE);
+            if r <= g {
+                add(r - b, &SMITS_YELLOW);
+                add(g - r, &SMITS_GREEN);
+            } else {
+                add(g - b, &SMITS_YELLOW);
+                add(r - g, &SMITS_RED);
+            }
+        }
+
+        Upsampled {
+            bins,
+            illuminant: None,
+        }
+    }
+}
+
+impl From<Albedo> for Upsampled {
+    fn from(albedo: Albedo) -> Upsampled {
+        let v = Vector3::from(albedo);
+        Upsampled::new(v.x, v.y, v.z)
+    }
+}
+
+impl From<Radiance> for Upsampled {
+    fn from(radiance: Radiance) -> Upsampled {
+        static SCALE: OnceLock<Float> = OnceLock::new();
+        let scale = SCALE.get_or_init(|| 1.0 / Xyz::from_spectrum(&Illuminant::D65).y);
+
+        let mut spectrum = Upsampled::new(radiance[0], radiance[1], radiance[2]);
+        spectrum.illuminant = Some(*scale);
+        spectrum
+    }
+}
+
+impl Spectrum for Upsampled {
+    fn power(&self, wavelength: Float) -> Float {
+        let f = (wavelength - 380.0) / (720.0 - 380.0) * BINS as Float;
+        let bin = (f.max(0.0) as usize).min(BINS - 1);
+
+        match self.illuminant {
+            Some(scale) => self.bins[bin] * d65(wavelength) * scale,
+            None => self.bins[bin],
+        }
+    }
+}
+
+/// The wavelength, in nanometers, at `u` in the unit interval of the
+/// visible range, for estimating colors with `Xyz::from_wavelength`.
+pub fn sample_wavelength(u: Float) -> Float {
+    VISIBLE.0 + u * (VISIBLE.1 - VISIBLE.0)
+}
+
+/// The relative spectral power of a black body at `temperature`,
+/// by Planck's law, `100` at 560nm.
+fn planck(wavelength: Float, temperature: Float) -> Float {
+    const C2: Float = 1.4388e7; // nm K
+    let radiance = |l: Float| 1.0 / (l.powi(5) * ((C2 / (l * temperature)).exp() - 1.0));
+
+    100.0 * radiance(wavelength) / radiance(560.0)
+}
+
+/// The relative spectral power of D65 from 380nm to 780nm, every
+/// 10nm.
+const D65: [Float; 41] = [
+    49.9755, 54.6482, 82.7549, 91.486, 93.4318, 86.6823, 104.865, 117.008, 117.812, 114.861,
+    115.923, 108.811, 109.354, 107.802, 104.79, 107.689, 104.405, 104.046, 100.0, 96.3342, 95.788,
+    88.6856, 90.0062, 89.5991, 87.6987, 83.2886, 83.6992, 80.0268, 80.2146, 82.2778, 78.2842,
+    69.7213, 71.6091, 74.349, 61.604, 69.8856, 75.087, 63.5927, 46.4182, 66.8054, 63.3828,
+];
+
+fn d65(wavelength: Float) -> Float {
+    match interpolate(wavelength, D65.len()) {
+        Some((i, t)) => D65[i] * (1.0 - t) + D65[i + 1] * t,
+        None => 0.0,
+    }
+}
+
+/// The CIE 1931 color matching functions from 380nm to 780nm, every
+/// 10nm.
+const OBSERVER: [(Float, Float, Float); 41] = [
+    (0.001368, 0.000039, 0.00645),
+    (0.004243, 0.00012, 0.02005),
+    (0.01431, 0.000396, 0.06785),
+    (0.04351, 0.00121, 0.2074),
+    (0.13438, 0.004, 0.6456),
+    (0.2839, 0.0116, 1.3856),
+    (0.34828, 0.023, 1.74706),
+    (0.3362, 0.038, 1.77211),
+    (0.2908, 0.06, 1.6692),
+    (0.19536, 0.09098, 1.28764),
+    (0.09564, 0.13902, 0.81295),
+    (0.03201, 0.20802, 0.46518),
+    (0.0049, 0.323, 0.272),
+    (0.0093, 0.503, 0.1582),
+    (0.06327, 0.71, 0.07825),
+    (0.1655, 0.862, 0.04216),
+    (0.2904, 0.954, 0.0203),
+    (0.43345, 0.99495, 0.00875),
+    (0.5945, 0.995, 0.0039),
+    (0.7621, 0.952, 0.0021),
+    (0.9163, 0.87, 0.00165),
+    (1.0263, 0.757, 0.0011),
+    (1.0622, 0.631, 0.0008),
+    (1.0026, 0.503, 0.00034),
+    (0.85445, 0.381, 0.00019),
+    (0.6424, 0.265, 0.00005),
+    (0.4479, 0.175, 0.00002),
+    (0.2835, 0.107, 0.0),
+    (0.1649, 0.061, 0.0),
+    (0.0874, 0.032, 0.0),
+    (0.04677, 0.017, 0.0),
+    (0.0227, 0.00821, 0.0),
+    (0.011359, 0.004102, 0.0),
+    (0.00579, 0.002091, 0.0),
+    (0.002899, 0.001047, 0.0),
+    (0.00144, 0.00052, 0.0),
+    (0.00069, 0.000249, 0.0),
+    (0.000332, 0.00012, 0.0),
+    (0.000166, 0.00006, 0.0),
+    (0.000083, 0.00003, 0.0),
+    (0.000042, 0.000015, 0.0),
+];
+
+/// Interpolates linearly in a table of values from 380nm to 780nm,
+/// every 10nm, returning the two neighbouring indices and the weight
+/// of the second.
+fn interpolate(wavelength: Float, len: usize) -> Option<(usize, Float)> {
+    let f = (wavelength - 380.0) / 10.0;
+    if !(0.0..=(len - 1) as Float).contains(&f) {
+        return None;
+    }
+
+    let i = (f as usize).min(len - 2);
+    Some((i, f - i as Float))
+}
+
+/// The CIE 1931 color matching functions at `wavelength`.
+fn observer(wavelength: Float) -> (Float, Float, Float) {
+    match interpolate(wavelength, OBSERVER.len()) {
+        Some((i, t)) => {
+            let (a, b) = (OBSERVER[i], OBSERVER[i + 1]);
+            (
+                a.0 * (1.0 - t) + b.0 * t,
+                a.1 * (1.0 - t) + b.1 * t,
+                a.2 * (1.0 - t) + b.2 * t,
+            )
+        }
+        None => (0.0, 0.0, 0.0),
+    }
+}
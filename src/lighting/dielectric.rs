@@ -0,0 +1,34 @@
+use geometry::Intersection;
+use lighting::{Albedo, Bsdf, Distribution, Effect, Ior, Material};
+
+/// A smooth, transparent material such as glass or water.
+///
+/// With an `Ior` of `1.0`, a `Dielectric` is an invisible boundary,
+/// which is useful to delimit a participating `Medium`.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct Dielectric {
+    pub albedo: Albedo,
+    pub ior: Ior,
+}
+
+impl Dielectric {
+    /// Creates a new `Dielectric` material.
+    pub fn new(albedo: Albedo, ior: Ior) -> Self {
+        Dielectric { albedo, ior }
+    }
+}
+
+impl Material for Dielectric {
+    fn shade(&self, _: &Intersection) -> Bsdf {
+        let mut bsdf = Bsdf::new();
+
+        bsdf.effects.push(Effect::SpecularRefraction(
+            self.albedo,
+            self.ior,
+            Distribution::Dirac,
+        ));
+
+        bsdf
+    }
+}
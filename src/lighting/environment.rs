@@ -0,0 +1,265 @@
+use consts::PI;
+#[cfg(feature = "image")]
+use std::fs::File;
+#[cfg(feature = "image")]
+use std::io::BufReader;
+#[cfg(feature = "image")]
+use std::path::Path;
+
+use cgmath::num_traits::clamp;
+use cgmath::{vec3, InnerSpace};
+#[cfg(feature = "image")]
+use image::hdr::HDRDecoder;
+#[cfg(feature = "image")]
+use image::ImageResult;
+
+use geometry::Vector;
+use lighting::Radiance;
+use montecarlo::pdf::spherical_to_solid_angle;
+use montecarlo::Distribution2D;
+use Float;
+
+/// The light arriving from infinitely far away, i.e. from directions
+/// in which a ray leaves the scene.
+pub trait Environment: Send + Sync {
+    /// The radiance arriving from `direction` (pointing away from
+    /// the scene).
+    fn radiance(&self, direction: Vector) -> Radiance;
+
+    /// Maps `u` and `v` in `[0, 1)` to a direction, returning the
+    /// direction and its probability density with respect to solid
+    /// angle.  Samples the sphere uniformly by default.
+    fn sample(&self, u: Float, v: Float) -> (Vector, Float) {
+        let cos_theta = 1.0 - 2.0 * u;
+        let r = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = v * 2.0 * PI;
+
+        (vec3(r * phi.cos(), cos_theta, r * phi.sin()), 0.25 / PI)
+    }
+
+    /// The probability density of `sample` returning `direction`.
+    fn pdf(&self, _direction: Vector) -> Float {
+        0.25 / PI
+    }
+}
+
+/// A constant environment.
+impl Environment for Radiance {
+    fn radiance(&self, _: Vector) -> Radiance {
+        *self
+    }
+}
+
+fn lerp(a: Radiance, b: Radiance, t: Float) -> Radiance {
+    (1.0 - t) * a + t * b
+}
+
+/// A vertical gradient, blending from `ground` (straight down) over
+/// `horizon` to `zenith` (straight up, along `+y`).
+///
+/// # Examples
+/// ```
+/// use lucifer::geometry::Vector;
+/// use lucifer::lighting::{Environment, Gradient, Radiance};
+///
+/// let sky = Gradient::new(Radiance::gray(0.0), Radiance::gray(1.0), Radiance::gray(0.5));
+///
+/// assert_eq!(sky.radiance(Vector::new(0.0, 1.0, 0.0)), Radiance::gray(0.5));
+/// assert_eq!(sky.radiance(Vector::new(1.0, 0.0, 0.0)), Radiance::gray(1.0));
+/// assert_eq!(sky.radiance(Vector::new(0.0, -1.0, 0.0)), Radiance::gray(0.0));
+/// ```
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct Gradient {
+    pub ground: Radiance,
+    pub horizon: Radiance,
+    pub zenith: Radiance,
+}
+
+impl Gradient {
+    pub fn new(ground: Radiance, horizon: Radiance, zenith: Radiance) -> Gradient {
+        Gradient {
+            ground,
+            horizon,
+            zenith,
+        }
+    }
+}
+
+impl Environment for Gradient {
+    fn radiance(&self, direction: Vector) -> Radiance {
+        let y = direction.normalize()[1];
+        if y >= 0.0 {
+            lerp(self.horizon, self.zenith, y)
+        } else {
+            lerp(self.horizon, self.ground, -y)
+        }
+    }
+}
+
+/// A simple procedural daylight sky: a gradient that brightens
+/// towards the horizon, and a sun disc.
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct Sky {
+    /// The direction towards the sun.
+    pub sun: Vector,
+    /// The radiance of the sun disc.
+    pub sun_radiance: Radiance,
+    /// The angular radius of the sun disc, in radians.
+    pub sun_radius: Float,
+    pub horizon: Radiance,
+    pub zenith: Radiance,
+    pub ground: Radiance,
+}
+
+impl Sky {
+    /// Creates a new `Sky` with a sun in direction `sun` and default
+    /// colors.
+    pub fn new(sun: Vector) -> Sky {
+        Sky {
+            sun: sun.normalize(),
+            sun_radiance: Radiance::new(20000.0, 18000.0, 15000.0),
+            sun_radius: 0.0047,
+            horizon: Radiance::new(0.9, 0.95, 1.0),
+            zenith: Radiance::new(0.25, 0.45, 0.9),
+            ground: Radiance::gray(0.2),
+        }
+    }
+}
+
+impl Environment for Sky {
+    fn radiance(&self, direction: Vector) -> Radiance {
+        let direction = direction.normalize();
+
+        if direction.dot(self.sun) >= self.sun_radius.cos() {
+            return self.sun_radiance;
+        }
+
+        let y = direction[1];
+        if y < 0.0 {
+            return self.ground;
+        }
+
+        // Falls off quickly above the horizon, like scattered light
+        // travelling through more atmosphere at grazing angles.
+        lerp(self.horizon, self.zenith, 1.0 - (1.0 - y).powi(4))
+    }
+}
+
+/// An image-based environment in latitude-longitude layout, with
+/// `+y` pointing up.
+///
+/// `sample` picks directions proportional to their brightness, which
+/// greatly reduces noise for maps with small, bright features such
+/// as the sun.
+#[derive(Clone, Debug)]
+pub struct EnvironmentMap {
+    width: u32,
+    height: u32,
+    pixels: Vec<Radiance>,
+    distribution: Distribution2D,
+}
+
+impl EnvironmentMap {
+    /// Creates a new `EnvironmentMap` from pixels in row-major order,
+    /// starting at the top.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::geometry::Vector;
+    /// use lucifer::lighting::{Environment, EnvironmentMap, Radiance};
+    ///
+    /// let map = EnvironmentMap::new(1, 2, vec![Radiance::gray(1.0), Radiance::gray(0.0)]);
+    ///
+    /// assert_eq!(map.radiance(Vector::new(0.0, 1.0, 0.0)), Radiance::gray(1.0));
+    /// assert_eq!(map.radiance(Vector::new(0.0, -1.0, 0.0)), Radiance::gray(0.0));
+    ///
+    /// let (direction, pdf) = map.sample(0.3, 0.7);
+    /// assert!(direction[1] > 0.0);
+    /// assert!((pdf - map.pdf(direction)).abs() < 1e-3);
+    /// ```
+    pub fn new(width: u32, height: u32, pixels: Vec<Radiance>) -> EnvironmentMap {
+        assert_eq!(pixels.len(), (width * height) as usize);
+
+        let weights: Vec<Float> = pixels
+            .iter()
+            .enumerate()
+            .map(|(i, p)| {
+                let theta = ((i as u32 / width) as Float + 0.5) / height as Float * PI;
+                p.luma() * theta.sin()
+            })
+            .collect();
+
+        EnvironmentMap {
+            width,
+            height,
+            pixels,
+            distribution: Distribution2D::new(width as usize, height as usize, &weights),
+        }
+    }
+
+    /// Loads an `EnvironmentMap` from a Radiance HDR (`.hdr`) file.
+    #[cfg(feature = "image")]
+    pub fn load<P: AsRef<Path>>(path: P) -> ImageResult<EnvironmentMap> {
+        let decoder = HDRDecoder::new(BufReader::new(File::open(path)?))?;
+        let metadata = decoder.metadata();
+        let pixels = decoder
+            .read_image_hdr()?
+            .into_iter()
+            .map(|p| Radiance::new(p.data[0] as Float, p.data[1] as Float, p.data[2] as Float))
+            .collect();
+
+        Ok(EnvironmentMap::new(metadata.width, metadata.height, pixels))
+    }
+
+    /// The position of `direction` in the unit square covered by the
+    /// map, and the sine of its polar angle.
+    fn coordinates(&self, direction: Vector) -> ((Float, Float), Float) {
+        let direction = direction.normalize();
+        let theta = clamp(direction[1], -1.0, 1.0).acos();
+        let phi = direction[2].atan2(direction[0]) + PI;
+
+        ((phi / (2.0 * PI), theta / PI), theta.sin())
+    }
+
+    fn pixel(&self, direction: Vector) -> usize {
+        let ((s, t), _) = self.coordinates(direction);
+
+        let x = ((s * self.width as Float) as u32).min(self.width - 1);
+        let y = ((t * self.height as Float) as u32).min(self.height - 1);
+
+        (y * self.width + x) as usize
+    }
+}
+
+impl Environment for EnvironmentMap {
+    fn radiance(&self, direction: Vector) -> Radiance {
+        self.pixels[self.pixel(direction)]
+    }
+
+    fn sample(&self, u: Float, v: Float) -> (Vector, Float) {
+        let ((s, t), pdf) = match self.distribution.sample(u, v) {
+            Some(sample) => sample,
+            None => return (vec3(0.0, 1.0, 0.0), 0.0),
+        };
+
+        let phi = s * 2.0 * PI - PI;
+        let theta = t * PI;
+
+        let direction = vec3(
+            theta.sin() * phi.cos(),
+            theta.cos(),
+            theta.sin() * phi.sin(),
+        );
+
+        // The unit square covers `2 pi * pi` in spherical coordinates.
+        let pdf = spherical_to_solid_angle(pdf / (2.0 * PI * PI), theta.sin());
+        (direction, pdf)
+    }
+
+    fn pdf(&self, direction: Vector) -> Float {
+        let ((s, t), sin_theta) = self.coordinates(direction);
+        spherical_to_solid_angle(self.distribution.pdf(s, t) / (2.0 * PI * PI), sin_theta)
+    }
+}
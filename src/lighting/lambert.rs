@@ -3,6 +3,7 @@ use lighting::{Albedo, Bsdf, Distribution, Effect, Material};
 
 /// An ideal diffusely reflective material.
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub struct Lambert {
     pub albedo: Albedo,
 }
@@ -0,0 +1,122 @@
+use consts::PI;
+
+use cgmath::{vec3, Vector3};
+use rand::Rng;
+
+use lighting::{Albedo, Radiance};
+use Float;
+
+/// A homogeneous participating medium, such as fog, smoke, or murky
+/// water.
+///
+/// Light travelling through the medium is absorbed and scattered
+/// with the given coefficients (per unit distance).  Absorbing
+/// particles may additionally emit light, e.g. for fire or glowing
+/// gas.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct Medium {
+    /// The absorption coefficient.
+    pub absorption: Float,
+    /// The scattering coefficient.
+    pub scattering: Float,
+    /// The color of scattered light.
+    pub albedo: Albedo,
+    /// The radiance emitted by absorbing particles.
+    pub emission: Radiance,
+    /// The Henyey-Greenstein asymmetry parameter, between `-1`
+    /// (backward scattering) and `1` (forward scattering).  `0`
+    /// scatters isotropically.
+    pub anisotropy: Float,
+}
+
+impl Medium {
+    /// Creates a new, white, non-emissive, isotropically scattering
+    /// `Medium`.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::lighting::Medium;
+    /// use lucifer::Float;
+    ///
+    /// let fog = Medium::new(0.1, 0.3);
+    ///
+    /// assert_eq!(fog.extinction(), 0.4);
+    /// assert_eq!(fog.transmittance(0.0), 1.0);
+    /// assert_eq!(fog.transmittance(1.0), (-0.4 as Float).exp());
+    /// ```
+    pub fn new(absorption: Float, scattering: Float) -> Self {
+        Medium {
+            absorption,
+            scattering,
+            albedo: Albedo::white(),
+            emission: Radiance::none(),
+            anisotropy: 0.0,
+        }
+    }
+
+    /// Sets the color of scattered light.
+    pub fn color(self, albedo: Albedo) -> Self {
+        let mut medium = self;
+        medium.albedo = albedo;
+        medium
+    }
+
+    /// Sets the emission of absorbing particles.
+    pub fn glow(self, emission: Radiance) -> Self {
+        let mut medium = self;
+        medium.emission = emission;
+        medium
+    }
+
+    /// Sets the Henyey-Greenstein asymmetry parameter.
+    pub fn anisotropy(self, g: Float) -> Self {
+        let mut medium = self;
+        medium.anisotropy = g;
+        medium
+    }
+
+    /// The extinction coefficient, i.e. the sum of absorption and
+    /// scattering.
+    pub fn extinction(&self) -> Float {
+        self.absorption + self.scattering
+    }
+
+    /// The fraction of light transmitted unhindered over `distance`.
+    pub fn transmittance(&self, distance: Float) -> Float {
+        (-self.extinction() * distance).exp()
+    }
+
+    /// Randomly samples the distance to the next interaction with
+    /// the medium, proportional to the transmittance.  Returns
+    /// infinity for a vacuum.
+    pub fn sample_distance<R: Rng>(&self, rng: &mut R) -> Float {
+        let extinction = self.extinction();
+        if extinction <= 0.0 {
+            return Float::INFINITY;
+        }
+
+        let u: Float = rng.gen();
+        -(1.0 - u).ln() / extinction
+    }
+
+    /// Randomly samples a scattering direction around the direction
+    /// of propagation `+z`, proportional to the phase function.
+    pub fn sample_phase<R: Rng>(&self, rng: &mut R) -> Vector3<Float> {
+        let x: Float = rng.gen();
+        let y: Float = rng.gen();
+        let g = self.anisotropy;
+
+        let cos_theta = if g.abs() < 1e-3 {
+            1.0 - 2.0 * y
+        } else {
+            let s = (1.0 - g * g) / (1.0 - g + 2.0 * g * y);
+            (1.0 + g * g - s * s) / (2.0 * g)
+        };
+
+        let phi = x * 2.0 * PI;
+        let r = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+
+        vec3(r * phi.cos(), r * phi.sin(), cos_theta)
+    }
+}
@@ -1,6 +1,6 @@
 //! Materials and quantities of light and color
 
-use std::f32::consts::PI;
+use consts::PI;
 use std::ops::{Mul, MulAssign};
 
 use cgmath::{dot, vec3, ElementWise, One, Vector3, Zero};
@@ -10,12 +10,23 @@ use smallvec::SmallVec;
 use geometry::Intersection;
 
 pub mod blackbody;
+pub mod color;
+pub mod dielectric;
+pub mod environment;
 pub mod lambert;
+pub mod medium;
 pub mod phong;
+pub mod polarization;
 
 pub use self::blackbody::Blackbody;
+pub use self::color::{Illuminant, Spectrum, Upsampled, Xyy, Xyz};
+pub use self::dielectric::Dielectric;
+pub use self::environment::{Environment, EnvironmentMap, Gradient, Sky};
 pub use self::lambert::Lambert;
+pub use self::medium::Medium;
 pub use self::phong::Phong;
+pub use self::polarization::{Mueller, Stokes};
+use Float;
 
 /// The radiant intensity of a ray of light.
 #[derive(
@@ -34,12 +45,13 @@ pub use self::phong::Phong;
     Div,
     DivAssign,
 )]
-pub struct Radiance(Vector3<f32>);
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct Radiance(Vector3<Float>);
 
 impl Radiance {
     /// Creates a new `Radiance` with the given intensities for red,
     /// blue, and green.
-    pub fn new(r: f32, g: f32, b: f32) -> Self {
+    pub fn new(r: Float, g: Float, b: Float) -> Self {
         Radiance(vec3(r, g, b))
     }
 
@@ -51,32 +63,32 @@ impl Radiance {
 
     /// Creates a new `Radiance` with the given red intensity.
     #[inline]
-    pub fn red(f: f32) -> Self {
+    pub fn red(f: Float) -> Self {
         Self::new(f, 0.0, 0.0)
     }
 
     /// Creates a new `Radiance` with the given green intensity.
     #[inline]
-    pub fn green(f: f32) -> Self {
+    pub fn green(f: Float) -> Self {
         Self::new(0.0, f, 0.0)
     }
 
     /// Creates a new `Radiance` with the given blue intensity.
     #[inline]
-    pub fn blue(f: f32) -> Self {
+    pub fn blue(f: Float) -> Self {
         Self::new(0.0, 0.0, f)
     }
 
     /// Creates a new `Radiance` with equal red, green, and blue
     /// intensity.
     #[inline]
-    pub fn gray(f: f32) -> Self {
+    pub fn gray(f: Float) -> Self {
         Self::new(f, f, f)
     }
 
     /// Computes the lightness according to NTSC.
     #[inline]
-    pub fn luma(self) -> f32 {
+    pub fn luma(self) -> Float {
         dot(self.0, vec3(0.21, 0.72, 0.07))
     }
 }
@@ -97,7 +109,7 @@ impl Zero for Radiance {
     }
 }
 
-impl Mul<Radiance> for f32 {
+impl Mul<Radiance> for Float {
     type Output = Radiance;
     fn mul(self, rhs: Radiance) -> Radiance {
         Radiance(rhs.0 * self)
@@ -108,12 +120,13 @@ impl Mul<Radiance> for f32 {
 #[derive(
     Clone, Copy, Debug, PartialEq, From, Into, Index, IndexMut, Mul, MulAssign, Div, DivAssign,
 )]
-pub struct Albedo(Vector3<f32>);
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct Albedo(Vector3<Float>);
 
 impl Albedo {
     /// Creates a new `Albedo` with the given absorption factors for
     /// red, green, and blue.
-    pub fn new(r: f32, g: f32, b: f32) -> Self {
+    pub fn new(r: Float, g: Float, b: Float) -> Self {
         Albedo(vec3(r, g, b))
     }
 
@@ -132,34 +145,34 @@ impl Albedo {
     /// Creates a new `Albedo` for a surface that absorbs all non-red
     /// light.
     #[inline]
-    pub fn red(f: f32) -> Self {
+    pub fn red(f: Float) -> Self {
         Self::new(f, 0.0, 0.0)
     }
 
     /// Creates a new `Albedo` for a surface that absorbs all
     /// non-green light.
     #[inline]
-    pub fn green(f: f32) -> Self {
+    pub fn green(f: Float) -> Self {
         Self::new(0.0, f, 0.0)
     }
 
     /// Creates a new `Albedo` for a surface that absorbs all non-blue
     /// light.
     #[inline]
-    pub fn blue(f: f32) -> Self {
+    pub fn blue(f: Float) -> Self {
         Self::new(0.0, 0.0, f)
     }
 
     /// Creates a new `Albedo` for a surface that absorbs all colors
     /// of light equally.
     #[inline]
-    pub fn gray(f: f32) -> Self {
+    pub fn gray(f: Float) -> Self {
         Self::new(f, f, f)
     }
 
     /// Computes the influence on lightness according to NTSC.
     #[inline]
-    pub fn luma_factor(self) -> f32 {
+    pub fn luma_factor(self) -> Float {
         dot(self.0, vec3(0.21, 0.72, 0.07))
     }
 }
@@ -189,7 +202,7 @@ impl MulAssign<Albedo> for Albedo {
     }
 }
 
-impl Mul<Albedo> for f32 {
+impl Mul<Albedo> for Float {
     type Output = Albedo;
     fn mul(self, rhs: Albedo) -> Albedo {
         Albedo(rhs.0 * self)
@@ -218,7 +231,8 @@ impl MulAssign<Albedo> for Radiance {
 
 /// The refractive index.
 #[derive(Clone, Copy, Debug)]
-pub struct Ior(f32);
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct Ior(pub Float);
 
 /// The distribution for light emitted, reflected, or refracted by a
 /// surface.
@@ -228,6 +242,7 @@ pub struct Ior(f32);
 /// It is computed from the cosine of the angle the hemisphere's
 /// center axis, usually called `theta`.
 #[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub enum Distribution {
     /// All light is emitted, reflected, or refacted exactly along the
     /// axis, without any scattering.  This is the `Distribution` for a perfect
@@ -247,42 +262,92 @@ pub enum Distribution {
     /// resulting in less scattering.  An exponent of `0` is equal to
     /// `Uniform`, an exponent of `1` is equal to `Cosine`, and an
     /// exponent of `infinity` is equal to `Dirac`.
-    CosineExp(f32),
+    CosineExp(Float),
 }
 
 impl Distribution {
-    pub fn eval(self, cos_t: f32) -> f32 {
-        assert!(cos_t >= -1.0 && cos_t <= 1.0);
+    pub fn eval(self, cos_t: Float) -> Float {
+        assert!((-1.0..=1.0).contains(&cos_t));
 
         if cos_t < 0.0 {
             return 0.0;
         }
 
         match self {
-            Distribution::Dirac => if cos_t >= 1.0 {
-                1.0
-            } else {
-                0.0
-            },
+            Distribution::Dirac => {
+                if cos_t >= 1.0 {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
             Distribution::Uniform => 1.0 / cos_t,
             Distribution::Cosine => 1.0,
             Distribution::CosineExp(e) => cos_t.powf(e - 1.0),
         }
     }
 
+    /// The probability density, with respect to solid angle, of
+    /// `sample` returning a vector at an angle with cosine `cos_t` to
+    /// the axis.  `0` for `Dirac`, which no other sampling strategy
+    /// can hit.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::consts::PI;
+    /// use lucifer::lighting::Distribution;
+    ///
+    /// assert_eq!(Distribution::Cosine.pdf(1.0), 1.0 / PI);
+    /// assert_eq!(Distribution::Uniform.pdf(-0.5), 0.0);
+    /// assert_eq!(Distribution::Dirac.pdf(1.0), 0.0);
+    /// ```
+    pub fn pdf(self, cos_t: Float) -> Float {
+        if cos_t <= 0.0 {
+            return 0.0;
+        }
+
+        match self {
+            Distribution::Dirac => 0.0,
+            Distribution::Uniform => 0.5 / PI,
+            Distribution::Cosine => cos_t / PI,
+            Distribution::CosineExp(e) => (e + 1.0) * cos_t.powf(e) * 0.5 / PI,
+        }
+    }
+
     /// Randomly sample a vector in the hemisphere around `+z`.
     ///
     /// Returns a unit-vector, randomly sampled from the hemisphere
     /// around `+z` according to the given `Distribution` and the
     /// value of the probability density function (pdf) for the returned
     /// sample.
-    pub fn sample<R: Rng>(self, rng: &mut R) -> (Vector3<f32>, f32) {
+    pub fn sample<R: Rng>(self, rng: &mut R) -> (Vector3<Float>, Float) {
+        match self {
+            Distribution::Dirac => self.warp(0.0, 0.0),
+            _ => {
+                let x: Float = rng.gen();
+                let y: Float = rng.gen();
+                self.warp(x, y)
+            }
+        }
+    }
+
+    /// Maps `x` and `y` in `[0, 1)` to a vector in the hemisphere
+    /// around `+z` like `sample`, e.g. to draw them from a
+    /// low-discrepancy sequence instead.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::lighting::Distribution;
+    ///
+    /// let (v, pdf) = Distribution::Cosine.warp(0.25, 0.0);
+    ///
+    /// assert!((v.z - 1.0).abs() < 1e-6);
+    /// assert_eq!(pdf, Distribution::Cosine.pdf(v.z));
+    /// ```
+    pub fn warp(self, x: Float, y: Float) -> (Vector3<Float>, Float) {
         match self {
             Distribution::Dirac => (vec3(0.0, 0.0, 1.0), 0.5 / PI),
             Distribution::Uniform => {
-                let x: f32 = rng.gen();
-                let y: f32 = rng.gen();
-
                 let phi = x * 2.0 * PI;
                 let cos_theta = 1.0 - y;
                 let r = (1.0 - cos_theta * cos_theta).sqrt();
@@ -290,9 +355,6 @@ impl Distribution {
                 (vec3(r * phi.cos(), r * phi.sin(), cos_theta), 0.5 / PI)
             }
             Distribution::Cosine => {
-                let x: f32 = rng.gen();
-                let y: f32 = rng.gen();
-
                 let phi = x * 2.0 * PI;
                 let cos_theta = (1.0 - y).sqrt();
                 let r = (1.0 - cos_theta * cos_theta).sqrt();
@@ -303,16 +365,13 @@ impl Distribution {
                 )
             }
             Distribution::CosineExp(e) => {
-                let x: f32 = rng.gen();
-                let y: f32 = rng.gen();
-
                 let phi = x * 2.0 * PI;
                 let cos_theta = (1.0 - y).powf(1.0 / (e + 1.0));
                 let r = (1.0 - cos_theta * cos_theta).sqrt();
 
                 (
-                    vec3(r * phi.cos(), r * phi.cos(), cos_theta),
-                    (e + 1.0) * cos_theta.powf(e) / PI,
+                    vec3(r * phi.cos(), r * phi.sin(), cos_theta),
+                    (e + 1.0) * cos_theta.powf(e) * 0.5 / PI,
                 )
             }
         }
@@ -321,6 +380,7 @@ impl Distribution {
 
 /// A component effect of the appearance of a surface.
 #[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub enum Effect {
     /// Light emission independent of incoming light.
     Emission(Radiance, Distribution),
@@ -348,6 +408,33 @@ impl Bsdf {
             effects: SmallVec::new(),
         }
     }
+
+    /// The combined albedo of all reflection and refraction effects,
+    /// ignoring emission.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::lighting::{Albedo, Bsdf, Distribution, Effect, Radiance};
+    ///
+    /// let mut bsdf = Bsdf::new();
+    /// bsdf.effects.push(Effect::Emission(Radiance::gray(1.0), Distribution::Cosine));
+    /// bsdf.effects.push(Effect::DiffuseReflection(Albedo::gray(0.25), Distribution::Cosine));
+    /// bsdf.effects.push(Effect::SpecularReflection(Albedo::red(0.5), Distribution::Cosine));
+    ///
+    /// assert_eq!(bsdf.albedo(), Albedo::new(0.75, 0.25, 0.25));
+    /// ```
+    pub fn albedo(&self) -> Albedo {
+        let albedo = self.effects.iter().fold(Vector3::zero(), |sum, effect| {
+            sum + match *effect {
+                Effect::Emission(..) => Vector3::zero(),
+                Effect::DiffuseReflection(a, _)
+                | Effect::SpecularReflection(a, _)
+                | Effect::DiffuseRefraction(a, _, _)
+                | Effect::SpecularRefraction(a, _, _) => a.0,
+            }
+        });
+        Albedo(albedo)
+    }
 }
 
 impl Default for Bsdf {
@@ -357,6 +444,52 @@ impl Default for Bsdf {
 }
 
 /// Trait describing materials.
-pub trait Material {
+///
+/// Materials are shared between rendering threads, so they must be
+/// `Send` and `Sync`.
+pub trait Material: Send + Sync {
+    /// The scattering at `intersection`, in the space of the object's
+    /// geometry.  Textured materials look up their parameters by the
+    /// surface coordinates `intersection.uv`.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::geometry::quad::Quad;
+    /// use lucifer::geometry::{Geometry, Intersection, Point, Ray, Vector};
+    /// use lucifer::lighting::{Albedo, Bsdf, Distribution, Effect, Material};
+    ///
+    /// // Squares of dark and light gray, eight across the surface.
+    /// struct Checkers;
+    ///
+    /// impl Material for Checkers {
+    ///     fn shade(&self, intersection: &Intersection) -> Bsdf {
+    ///         let (u, v) = intersection.uv;
+    ///         let dark = ((u * 8.0).floor() + (v * 8.0).floor()) % 2.0 == 0.0;
+    ///         let albedo = Albedo::gray(if dark { 0.2 } else { 0.8 });
+    ///
+    ///         let mut bsdf = Bsdf::new();
+    ///         bsdf.effects.push(Effect::DiffuseReflection(albedo, Distribution::Cosine));
+    ///         bsdf
+    ///     }
+    /// }
+    ///
+    /// let x = Vector::new(1.0, 0.0, 0.0);
+    /// let floor = Quad::new(Point::new(0.0, 0.0, 0.0), x, Vector::new(0.0, 0.0, -1.0));
+    /// let down = Vector::new(0.0, -1.0, 0.0);
+    ///
+    /// let hit = floor.intersect(&Ray::new(Point::new(-0.45, 1.0, 0.45), down)).unwrap();
+    /// assert!((hit.uv.0 - 0.05).abs() < 1e-6 && (hit.uv.1 - 0.05).abs() < 1e-6);
+    /// assert_eq!(Checkers.shade(&hit).albedo(), Albedo::gray(0.2));
+    ///
+    /// let hit = floor.intersect(&Ray::new(Point::new(-0.3, 1.0, 0.45), down)).unwrap();
+    /// assert_eq!(Checkers.shade(&hit).albedo(), Albedo::gray(0.8));
+    /// ```
     fn shade(&self, intersection: &Intersection) -> Bsdf;
+
+    /// The radiance emitted by the material, used to find the light
+    /// sources of a scene.  Non-emissive materials return
+    /// `Radiance::none()`.
+    fn emission(&self) -> Radiance {
+        Radiance::none()
+    }
 }
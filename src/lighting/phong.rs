@@ -1,13 +1,15 @@
 use geometry::Intersection;
 use lighting::{Albedo, Bsdf, Distribution, Effect, Material, Radiance};
+use Float;
 
 /// A combination of emission, diffuse, and specular reflection.
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub struct Phong {
     pub emission: Radiance,
     pub diffuse: Albedo,
     pub specular: Albedo,
-    pub shininess: f32,
+    pub shininess: Float,
 }
 
 impl Phong {
@@ -37,7 +39,7 @@ impl Phong {
     }
 
     /// Sets the specular reflection color and exponent.
-    pub fn highlight(self, color: Albedo, shininess: f32) -> Self {
+    pub fn highlight(self, color: Albedo, shininess: Float) -> Self {
         let mut mat = self;
         mat.specular = color;
         mat.shininess = shininess;
@@ -76,4 +78,8 @@ impl Material for Phong {
 
         bsdf
     }
+
+    fn emission(&self) -> Radiance {
+        self.emission
+    }
 }
@@ -0,0 +1,371 @@
+//! Polarized light, as Stokes vectors transformed by Mueller matrices.
+//!
+//! A `Stokes` vector describes the intensity of a beam of light and
+//! how it is polarized, relative to a reference axis perpendicular to
+//! its direction: `q` is the excess of light polarized along the axis
+//! over light polarized across it, `u` the same for the diagonals, and
+//! `v` the excess of right- over left-handed circular polarization.
+//! A `Mueller` matrix describes how an interaction, such as a Fresnel
+//! reflection or a polarizing filter, transforms a Stokes vector.
+//!
+//! # Examples
+//! Light reflected off water at Brewster's angle is polarized along
+//! the surface, so a polarizing filter at right angles removes the
+//! glare completely.
+//!
+//! ```
+//! use lucifer::lighting::polarization::{Mueller, Stokes};
+//! use lucifer::lighting::Radiance;
+//!
+//! let eta: lucifer::Float = 1.0 / 1.333;
+//! let brewster = (1.0 / eta).atan();
+//!
+//! let sun = Stokes::unpolarized(Radiance::gray(1.0));
+//! let glare = Mueller::fresnel_reflection(brewster.cos(), eta) * sun;
+//! assert!((glare.degree_of_polarization() - 1.0).abs() < 1e-4);
+//!
+//! let filtered = Mueller::linear_polarizer(lucifer::consts::FRAC_PI_2) * glare;
+//! assert!(filtered.radiance().luma() < 1e-6);
+//! ```
+
+use std::ops::{Add, AddAssign, Mul};
+
+use cgmath::prelude::*;
+
+use geometry::Vector;
+use lighting::Radiance;
+use Float;
+
+/// The intensity and polarization of a beam of light, relative to a
+/// reference axis perpendicular to its direction.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct Stokes {
+    /// The total intensity.
+    pub i: Radiance,
+    /// The excess of horizontal over vertical linear polarization.
+    pub q: Radiance,
+    /// The excess of diagonal over anti-diagonal linear polarization.
+    pub u: Radiance,
+    /// The excess of right- over left-handed circular polarization.
+    pub v: Radiance,
+}
+
+impl Stokes {
+    /// Creates a new `Stokes` vector of unpolarized light.
+    pub fn unpolarized(radiance: Radiance) -> Self {
+        Stokes {
+            i: radiance,
+            q: Radiance::none(),
+            u: Radiance::none(),
+            v: Radiance::none(),
+        }
+    }
+
+    /// Creates a new `Stokes` vector of light polarized linearly at
+    /// `angle` radians to the reference axis.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::lighting::polarization::Stokes;
+    /// use lucifer::lighting::Radiance;
+    ///
+    /// let diagonal = Stokes::linear(Radiance::gray(1.0), lucifer::consts::FRAC_PI_4);
+    /// assert!((diagonal.angle_of_polarization() - lucifer::consts::FRAC_PI_4).abs() < 1e-6);
+    /// assert!((diagonal.degree_of_polarization() - 1.0).abs() < 1e-6);
+    /// ```
+    pub fn linear(radiance: Radiance, angle: Float) -> Self {
+        Stokes {
+            i: radiance,
+            q: radiance * (2.0 * angle).cos(),
+            u: radiance * (2.0 * angle).sin(),
+            v: Radiance::none(),
+        }
+    }
+
+    /// Creates a new `Stokes` vector of right-handed circularly
+    /// polarized light.
+    pub fn circular(radiance: Radiance) -> Self {
+        Stokes {
+            i: radiance,
+            q: Radiance::none(),
+            u: Radiance::none(),
+            v: radiance,
+        }
+    }
+
+    /// The total intensity, as seen by a sensor insensitive to
+    /// polarization.
+    pub fn radiance(&self) -> Radiance {
+        self.i
+    }
+
+    /// The fraction of the luma of the light that is polarized, from
+    /// zero for unpolarized to one for fully polarized light.
+    pub fn degree_of_polarization(&self) -> Float {
+        let i = self.i.luma();
+        if i <= 0.0 {
+            return 0.0;
+        }
+        let (q, u, v) = (self.q.luma(), self.u.luma(), self.v.luma());
+        ((q * q + u * u + v * v).sqrt() / i).min(1.0)
+    }
+
+    /// The angle of the plane of linear polarization of the luma to
+    /// the reference axis, between `-π/2` and `π/2` radians.
+    pub fn angle_of_polarization(&self) -> Float {
+        0.5 * self.u.luma().atan2(self.q.luma())
+    }
+}
+
+impl Add for Stokes {
+    type Output = Stokes;
+
+    fn add(self, other: Stokes) -> Stokes {
+        Stokes {
+            i: self.i + other.i,
+            q: self.q + other.q,
+            u: self.u + other.u,
+            v: self.v + other.v,
+        }
+    }
+}
+
+impl AddAssign for Stokes {
+    fn add_assign(&mut self, other: Stokes) {
+        *self = *self + other;
+    }
+}
+
+impl Mul<Float> for Stokes {
+    type Output = Stokes;
+
+    fn mul(self, factor: Float) -> Stokes {
+        Stokes {
+            i: self.i * factor,
+            q: self.q * factor,
+            u: self.u * factor,
+            v: self.v * factor,
+        }
+    }
+}
+
+/// A linear transformation of `Stokes` vectors, alike for all
+/// channels.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct Mueller(pub [[Float; 4]; 4]);
+
+impl Mueller {
+    /// The `Mueller` matrix leaving light unchanged.
+    pub fn identity() -> Self {
+        Mueller([
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    /// The `Mueller` matrix expressing a `Stokes` vector relative to
+    /// a reference axis rotated by `angle` radians.
+    pub fn rotation(angle: Float) -> Self {
+        let (s, c) = (2.0 * angle).sin_cos();
+        Mueller([
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, c, s, 0.0],
+            [0.0, -s, c, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    /// The `Mueller` matrix expressing a `Stokes` vector of light
+    /// travelling along `direction` relative to the reference axis
+    /// `to` instead of `from`, both perpendicular to `direction`.
+    ///
+    /// This aligns Stokes vectors with the plane of incidence before
+    /// an interaction, and back with the camera afterwards.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::geometry::Vector;
+    /// use lucifer::lighting::polarization::{Mueller, Stokes};
+    /// use lucifer::lighting::Radiance;
+    ///
+    /// let direction = Vector::new(0.0, 0.0, 1.0);
+    /// let (from, to) = (Vector::new(1.0, 0.0, 0.0), Vector::new(0.0, 1.0, 0.0));
+    /// let reframed = Mueller::reframe(direction, from, to) * Stokes::linear(Radiance::gray(1.0), 0.0);
+    ///
+    /// // Horizontal to the old axis is vertical to the new one.
+    /// assert!((reframed.q.luma() + 1.0).abs() < 1e-6);
+    /// ```
+    pub fn reframe(direction: Vector, from: Vector, to: Vector) -> Self {
+        Mueller::rotation(from.cross(to).dot(direction).atan2(from.dot(to)))
+    }
+
+    /// The `Mueller` matrix of an ideal linear polarizer, passing
+    /// light polarized at `angle` radians to the reference axis.
+    ///
+    /// # Examples
+    /// Unpolarized light loses half its intensity through a polarizer,
+    /// and the rest follows Malus's law through a second one.
+    ///
+    /// ```
+    /// use lucifer::lighting::polarization::{Mueller, Stokes};
+    /// use lucifer::lighting::Radiance;
+    ///
+    /// let light = Stokes::unpolarized(Radiance::gray(1.0));
+    /// let first = Mueller::linear_polarizer(0.0) * light;
+    /// assert!((first.radiance().luma() - 0.5).abs() < 1e-6);
+    ///
+    /// let angle: lucifer::Float = 0.6;
+    /// let second = Mueller::linear_polarizer(angle) * first;
+    /// assert!((second.radiance().luma() - 0.5 * angle.cos().powi(2)).abs() < 1e-6);
+    /// ```
+    pub fn linear_polarizer(angle: Float) -> Self {
+        let (s, c) = (2.0 * angle).sin_cos();
+        Mueller([
+            [0.5, 0.5 * c, 0.5 * s, 0.0],
+            [0.5 * c, 0.5 * c * c, 0.5 * c * s, 0.0],
+            [0.5 * s, 0.5 * c * s, 0.5 * s * s, 0.0],
+            [0.0, 0.0, 0.0, 0.0],
+        ])
+    }
+
+    /// The `Mueller` matrix of the specular reflection off a smooth
+    /// dielectric with relative index of refraction `eta`, for light
+    /// arriving at an angle with the cosine `cos_i` to the normal.
+    ///
+    /// Stokes vectors are relative to the normal of the plane of
+    /// incidence, so `q` is positive for `s`-polarized light.  Beyond
+    /// the critical angle, all light is reflected, and the phase
+    /// shift between `s` and `p` turns linear polarization elliptic.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::lighting::polarization::Mueller;
+    ///
+    /// // Glass reflects 4% of the light at normal incidence.
+    /// let glass = Mueller::fresnel_reflection(1.0, 1.0 / 1.5);
+    /// assert!((glass.0[0][0] - 0.04).abs() < 1e-6);
+    ///
+    /// // Total internal reflection.
+    /// let inside = Mueller::fresnel_reflection(0.5, 1.5);
+    /// assert!((inside.0[0][0] - 1.0).abs() < 1e-6);
+    /// ```
+    pub fn fresnel_reflection(cos_i: Float, eta: Float) -> Self {
+        let (rs, rp) = fresnel_amplitudes(cos_i, eta);
+        let (s2, p2) = (rs.norm(), rp.norm());
+        // The product `rs * conj(rp)`.
+        let re = rs.0 * rp.0 + rs.1 * rp.1;
+        let im = rs.1 * rp.0 - rs.0 * rp.1;
+        Mueller([
+            [0.5 * (s2 + p2), 0.5 * (s2 - p2), 0.0, 0.0],
+            [0.5 * (s2 - p2), 0.5 * (s2 + p2), 0.0, 0.0],
+            [0.0, 0.0, re, im],
+            [0.0, 0.0, -im, re],
+        ])
+    }
+
+    /// The `Mueller` matrix of the transmission through the surface of
+    /// a smooth dielectric, the counterpart of `fresnel_reflection`,
+    /// such that the reflected and transmitted intensities add up to
+    /// the incident one.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::lighting::polarization::Mueller;
+    ///
+    /// let (cos_i, eta) = (0.3, 1.0 / 1.5);
+    /// let r = Mueller::fresnel_reflection(cos_i, eta);
+    /// let t = Mueller::fresnel_transmission(cos_i, eta);
+    /// assert!((r.0[0][0] + t.0[0][0] - 1.0).abs() < 1e-5);
+    /// assert!((r.0[0][1] + t.0[0][1]).abs() < 1e-5);
+    /// ```
+    pub fn fresnel_transmission(cos_i: Float, eta: Float) -> Self {
+        let sin2_t = eta * eta * (1.0 - cos_i * cos_i);
+        if sin2_t >= 1.0 {
+            return Mueller([[0.0; 4]; 4]);
+        }
+        let cos_t = (1.0 - sin2_t).sqrt();
+
+        let ts = 2.0 * eta * cos_i / (eta * cos_i + cos_t);
+        let tp = 2.0 * eta * cos_i / (cos_i + eta * cos_t);
+        // The ratio of the power per area on either side.
+        let k = cos_t / (eta * cos_i);
+        let (s2, p2) = (k * ts * ts, k * tp * tp);
+        let sp = k * ts * tp;
+        Mueller([
+            [0.5 * (s2 + p2), 0.5 * (s2 - p2), 0.0, 0.0],
+            [0.5 * (s2 - p2), 0.5 * (s2 + p2), 0.0, 0.0],
+            [0.0, 0.0, sp, 0.0],
+            [0.0, 0.0, 0.0, sp],
+        ])
+    }
+}
+
+impl Mul for Mueller {
+    type Output = Mueller;
+
+    fn mul(self, other: Mueller) -> Mueller {
+        let mut m = [[0.0; 4]; 4];
+        for (r, row) in m.iter_mut().enumerate() {
+            for (c, value) in row.iter_mut().enumerate() {
+                *value = (0..4).map(|k| self.0[r][k] * other.0[k][c]).sum();
+            }
+        }
+        Mueller(m)
+    }
+}
+
+impl Mul<Stokes> for Mueller {
+    type Output = Stokes;
+
+    fn mul(self, s: Stokes) -> Stokes {
+        let m = &self.0;
+        let row = |r: usize| s.i * m[r][0] + s.q * m[r][1] + s.u * m[r][2] + s.v * m[r][3];
+        Stokes {
+            i: row(0),
+            q: row(1),
+            u: row(2),
+            v: row(3),
+        }
+    }
+}
+
+/// A complex number, as real and imaginary part.
+#[derive(Clone, Copy)]
+struct Complex(Float, Float);
+
+impl Complex {
+    fn div(self, other: Complex) -> Complex {
+        let d = other.norm();
+        Complex(
+            (self.0 * other.0 + self.1 * other.1) / d,
+            (self.1 * other.0 - self.0 * other.1) / d,
+        )
+    }
+
+    /// The squared magnitude.
+    fn norm(self) -> Float {
+        self.0 * self.0 + self.1 * self.1
+    }
+}
+
+/// The Fresnel amplitude reflection coefficients for `s`- and
+/// `p`-polarized light, which are complex beyond the critical angle.
+fn fresnel_amplitudes(cos_i: Float, eta: Float) -> (Complex, Complex) {
+    let sin2_t = eta * eta * (1.0 - cos_i * cos_i);
+    // The cosine of the refracted angle, imaginary beyond the
+    // critical angle.
+    let cos_t = if sin2_t <= 1.0 {
+        Complex((1.0 - sin2_t).sqrt(), 0.0)
+    } else {
+        Complex(0.0, (sin2_t - 1.0).sqrt())
+    };
+
+    let rs = Complex(eta * cos_i - cos_t.0, -cos_t.1).div(Complex(eta * cos_i + cos_t.0, cos_t.1));
+    let rp = Complex(cos_i - eta * cos_t.0, -eta * cos_t.1)
+        .div(Complex(cos_i + eta * cos_t.0, eta * cos_t.1));
+    (rs, rp)
+}
@@ -0,0 +1,132 @@
+use std::mem;
+
+use Float;
+
+/// A discrete probability distribution over indices, proportional to
+/// a list of non-negative weights, sampled in constant time with
+/// Walker's alias method.
+///
+/// Unlike `Discrete`, which searches its cumulative distribution,
+/// sampling does not depend on the number of weights, which pays off
+/// for scenes with many lights.
+///
+/// # Examples
+/// ```
+/// use lucifer::montecarlo::Discrete1D;
+/// use lucifer::Float;
+///
+/// let distribution = Discrete1D::new(&[1.0, 0.0, 3.0, 4.0]);
+///
+/// assert_eq!(distribution.len(), 4);
+/// assert_eq!(distribution.probability(2), 0.375);
+/// assert_eq!(distribution.probability(1), 0.0);
+///
+/// let mut counts = [0; 4];
+/// for i in 0..1000 {
+///     let (index, probability) = distribution.sample(i as Float / 1000.0).unwrap();
+///     assert_eq!(probability, distribution.probability(index));
+///     counts[index] += 1;
+/// }
+/// assert_eq!(counts, [125, 0, 375, 500]);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Discrete1D {
+    /// The probability of keeping each bucket's own index.
+    threshold: Vec<Float>,
+    /// The index chosen when not keeping a bucket's own index.
+    alias: Vec<usize>,
+    /// The normalized probability of each index.
+    probability: Vec<Float>,
+    total: Float,
+}
+
+impl Discrete1D {
+    /// Creates a new `Discrete1D` distribution from `weights`.
+    pub fn new(weights: &[Float]) -> Discrete1D {
+        let n = weights.len();
+        let total: Float = weights.iter().map(|w| w.max(0.0)).sum();
+        if total <= 0.0 {
+            return Discrete1D {
+                total,
+                ..Discrete1D::default()
+            };
+        }
+
+        let probability: Vec<Float> = weights.iter().map(|w| w.max(0.0) / total).collect();
+        let mut threshold: Vec<Float> = probability.iter().map(|p| p * n as Float).collect();
+        let mut alias: Vec<usize> = (0..n).collect();
+
+        // Vose's algorithm: fill each underfull bucket with the rest
+        // of an overfull one.
+        let (mut small, mut large): (Vec<usize>, Vec<usize>) =
+            (0..n).partition(|&i| threshold[i] < 1.0);
+
+        while let (Some(&s), Some(&l)) = (small.last(), large.last()) {
+            small.pop();
+            alias[s] = l;
+            threshold[l] -= 1.0 - threshold[s];
+            if threshold[l] < 1.0 {
+                large.pop();
+                small.push(l);
+            }
+        }
+
+        // Leftovers are full up to rounding errors.
+        for i in small.into_iter().chain(large) {
+            threshold[i] = 1.0;
+        }
+
+        Discrete1D {
+            threshold,
+            alias,
+            probability,
+            total,
+        }
+    }
+
+    /// The number of indices.
+    pub fn len(&self) -> usize {
+        self.probability.len()
+    }
+
+    /// Whether there are no indices with non-zero weight.
+    pub fn is_empty(&self) -> bool {
+        self.probability.is_empty()
+    }
+
+    /// The sum of all weights.
+    pub fn total(&self) -> Float {
+        self.total
+    }
+
+    /// The probability of sampling index `i`.
+    pub fn probability(&self, i: usize) -> Float {
+        self.probability.get(i).cloned().unwrap_or(0.0)
+    }
+
+    /// Maps `u` in `[0, 1)` to an index, returning the index and its
+    /// probability.  Returns `None` if all weights are zero.
+    pub fn sample(&self, u: Float) -> Option<(usize, Float)> {
+        let n = self.len();
+        if n == 0 {
+            return None;
+        }
+
+        let x = u * n as Float;
+        let bucket = (x as usize).min(n - 1);
+        let i = if x - (bucket as Float) < self.threshold[bucket] {
+            bucket
+        } else {
+            self.alias[bucket]
+        };
+
+        Some((i, self.probability[i]))
+    }
+
+    /// The memory used by the distribution, in bytes.
+    pub fn memory(&self) -> usize {
+        mem::size_of::<Discrete1D>()
+            + (self.threshold.capacity() + self.probability.capacity()) * mem::size_of::<Float>()
+            + self.alias.capacity() * mem::size_of::<usize>()
+    }
+}
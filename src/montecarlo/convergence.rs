@@ -0,0 +1,163 @@
+use std::ops::AddAssign;
+use Float;
+
+/// Running mean and variance of a stream of scalar estimates, e.g.
+/// the luminance of a pixel's samples (Welford's algorithm).
+///
+/// # Examples
+/// ```
+/// use lucifer::montecarlo::Moments;
+///
+/// let mut moments = Moments::new();
+/// for &x in &[2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0] {
+///     moments += x;
+/// }
+///
+/// assert_eq!(moments.count(), 8);
+/// assert_eq!(moments.mean(), 5.0);
+/// assert_eq!(moments.variance(), 32.0 / 7.0);
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Moments {
+    pub(crate) n: u32,
+    pub(crate) mean: Float,
+    /// The sum of squared differences from the mean.
+    pub(crate) m2: Float,
+}
+
+impl Moments {
+    /// Creates new, empty `Moments`.
+    pub fn new() -> Moments {
+        Moments::default()
+    }
+
+    /// The number of estimates.
+    pub fn count(&self) -> u32 {
+        self.n
+    }
+
+    /// The mean of all estimates, or `0` if there are none.
+    pub fn mean(&self) -> Float {
+        self.mean
+    }
+
+    /// The (unbiased) sample variance of the estimates, or `0` for
+    /// fewer than two estimates.
+    pub fn variance(&self) -> Float {
+        if self.n < 2 {
+            0.0
+        } else {
+            self.m2 / (self.n - 1) as Float
+        }
+    }
+
+    /// The half-width of the confidence interval of the mean at
+    /// `confidence` (e.g. `0.95`), relative to the mean.
+    ///
+    /// Returns `0` if all estimates were zero and infinity if there
+    /// are fewer than two estimates, or the mean is zero but not all
+    /// estimates were.
+    pub fn relative_error(&self, confidence: Float) -> Float {
+        if self.n < 2 {
+            return Float::INFINITY;
+        }
+
+        let half_width =
+            normal_quantile(0.5 + 0.5 * confidence) * (self.variance() / self.n as Float).sqrt();
+        if half_width == 0.0 {
+            0.0
+        } else {
+            half_width / self.mean.abs()
+        }
+    }
+}
+
+impl AddAssign<Float> for Moments {
+    fn add_assign(&mut self, x: Float) {
+        self.n += 1;
+        let delta = x - self.mean;
+        self.mean += delta / self.n as Float;
+        self.m2 += delta * (x - self.mean);
+    }
+}
+
+/// A statistical stopping criterion: an estimate has converged once
+/// the confidence interval of its mean, at the chosen `confidence`
+/// level, is narrower than `tolerance` times the mean.
+///
+/// # Examples
+/// ```
+/// use lucifer::montecarlo::{Convergence, Moments};
+///
+/// let criterion = Convergence::new(0.95, 0.05);
+///
+/// let mut noisy = Moments::new();
+/// let mut smooth = Moments::new();
+/// for i in 0..64 {
+///     noisy += if i % 2 == 0 { 0.0 } else { 2.0 };
+///     smooth += if i % 2 == 0 { 0.99 } else { 1.01 };
+/// }
+///
+/// assert!(!criterion.is_converged(&noisy));
+/// assert!(criterion.is_converged(&smooth));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct Convergence {
+    /// The confidence level, e.g. `0.95`.
+    pub confidence: Float,
+    /// The largest acceptable half-width of the confidence interval,
+    /// relative to the mean.
+    pub tolerance: Float,
+    /// The number of estimates required before testing, to guard
+    /// against a few equal estimates that merely miss rare, bright
+    /// paths.
+    pub min_samples: u32,
+}
+
+impl Convergence {
+    /// Creates a new `Convergence` criterion, requiring at least 16
+    /// estimates.
+    pub fn new(confidence: Float, tolerance: Float) -> Convergence {
+        Convergence {
+            confidence,
+            tolerance,
+            min_samples: 16,
+        }
+    }
+
+    /// Sets the number of estimates required before testing.
+    pub fn min_samples(self, min_samples: u32) -> Self {
+        let mut criterion = self;
+        criterion.min_samples = min_samples.max(2);
+        criterion
+    }
+
+    /// Whether the estimates summarized by `moments` have converged.
+    pub fn is_converged(&self, moments: &Moments) -> bool {
+        moments.count() >= self.min_samples
+            && moments.relative_error(self.confidence) < self.tolerance
+    }
+}
+
+/// The quantile function of the standard normal distribution, with an
+/// absolute error below `4.5e-4` (Abramowitz and Stegun, 26.2.23).
+fn normal_quantile(p: Float) -> Float {
+    if p <= 0.0 {
+        return Float::NEG_INFINITY;
+    } else if p >= 1.0 {
+        return Float::INFINITY;
+    }
+
+    let q = if p < 0.5 { p } else { 1.0 - p };
+    let t = (-2.0 * q.ln()).sqrt();
+    let x = t
+        - (2.515_517 + 0.802_853 * t + 0.010_328 * t * t)
+            / (1.0 + 1.432_788 * t + 0.189_269 * t * t + 0.001_308 * t * t * t);
+
+    if p < 0.5 {
+        -x
+    } else {
+        x
+    }
+}
@@ -0,0 +1,91 @@
+use montecarlo::Discrete;
+use Float;
+
+/// A piecewise-constant probability distribution over the unit
+/// square, given by a grid of non-negative weights, e.g. the
+/// brightness of the pixels of an image.
+///
+/// Points are sampled by first picking a row from the marginal
+/// distribution of the rows, then a column from the conditional
+/// distribution within that row.
+///
+/// # Examples
+/// ```
+/// use lucifer::montecarlo::Distribution2D;
+///
+/// // A 2x2 grid whose bottom-right cell holds half the weight.
+/// let distribution = Distribution2D::new(2, 2, &[1.0, 1.0, 0.0, 2.0]);
+///
+/// let ((x, y), pdf) = distribution.sample(0.5, 0.9).unwrap();
+/// assert!(x >= 0.5 && y >= 0.5);
+/// assert_eq!(pdf, 2.0);
+/// assert_eq!(distribution.pdf(x, y), pdf);
+///
+/// assert_eq!(distribution.pdf(0.1, 0.9), 0.0);
+/// assert_eq!(distribution.pdf(0.1, 0.1), 1.0);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Distribution2D {
+    width: usize,
+    height: usize,
+    /// The distribution of the rows.
+    marginal: Discrete,
+    /// The distribution of the columns within each row.
+    conditional: Vec<Discrete>,
+}
+
+impl Distribution2D {
+    /// Creates a new `Distribution2D` from `width * height` weights
+    /// in row-major order.
+    pub fn new(width: usize, height: usize, weights: &[Float]) -> Distribution2D {
+        assert_eq!(weights.len(), width * height);
+
+        let conditional: Vec<Discrete> = weights
+            .chunks(width.max(1))
+            .take(height)
+            .map(Discrete::new)
+            .collect();
+        let rows: Vec<Float> = conditional.iter().map(|row| row.total()).collect();
+
+        Distribution2D {
+            width,
+            height,
+            marginal: Discrete::new(&rows),
+            conditional,
+        }
+    }
+
+    /// The sum of all weights.
+    pub fn total(&self) -> Float {
+        self.marginal.total()
+    }
+
+    /// Maps `u` and `v` in `[0, 1)` to a point in the unit square,
+    /// returning the point and its density.  `v` picks the row, `u`
+    /// the column.  Returns `None` if all weights are zero.
+    pub fn sample(&self, u: Float, v: Float) -> Option<((Float, Float), Float)> {
+        let (y, row_probability, dy) = self.marginal.sample_remapped(v)?;
+        let (x, column_probability, dx) = self.conditional[y].sample_remapped(u)?;
+
+        let point = (
+            (x as Float + dx) / self.width as Float,
+            (y as Float + dy) / self.height as Float,
+        );
+        let cells = (self.width * self.height) as Float;
+
+        Some((point, row_probability * column_probability * cells))
+    }
+
+    /// The density of sampling the point `(x, y)` of the unit square.
+    pub fn pdf(&self, x: Float, y: Float) -> Float {
+        if self.width == 0 || self.height == 0 {
+            return 0.0;
+        }
+
+        let column = ((x * self.width as Float) as usize).min(self.width - 1);
+        let row = ((y * self.height as Float) as usize).min(self.height - 1);
+        let cells = (self.width * self.height) as Float;
+
+        self.marginal.probability(row) * self.conditional[row].probability(column) * cells
+    }
+}
@@ -0,0 +1,124 @@
+//! Multiple importance sampling (MIS)
+//!
+//! When a sample could have been drawn by several strategies (e.g. a
+//! direction towards a light, drawn either by sampling the BSDF or by
+//! sampling the light), weighting each strategy's samples with a
+//! heuristic combines their estimates without bias while keeping the
+//! low variance of whichever strategy suits a given sample best.
+
+use Float;
+
+/// The weight of a sample drawn from `nf` samples of a strategy with
+/// density `pdf_f`, combined with `ng` samples of another strategy
+/// with density `pdf_g`, proportional to the densities.
+///
+/// # Examples
+/// ```
+/// use lucifer::montecarlo::balance_heuristic;
+///
+/// assert_eq!(balance_heuristic(1, 3.0, 1, 1.0), 0.75);
+/// assert_eq!(balance_heuristic(1, 1.0, 3, 1.0), 0.25);
+/// ```
+pub fn balance_heuristic(nf: u32, pdf_f: Float, ng: u32, pdf_g: Float) -> Float {
+    MisWeight::new(Heuristic::Balance, nf, pdf_f)
+        .other(ng, pdf_g)
+        .weight()
+}
+
+/// Like `balance_heuristic`, but proportional to the squared
+/// densities, which reduces variance further when one strategy is
+/// much better than the other.
+///
+/// # Examples
+/// ```
+/// use lucifer::montecarlo::power_heuristic;
+///
+/// assert_eq!(power_heuristic(1, 3.0, 1, 1.0), 0.9);
+/// ```
+pub fn power_heuristic(nf: u32, pdf_f: Float, ng: u32, pdf_g: Float) -> Float {
+    MisWeight::new(Heuristic::Power, nf, pdf_f)
+        .other(ng, pdf_g)
+        .weight()
+}
+
+/// How `MisWeight` weights the strategies.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Heuristic {
+    /// Proportional to the densities.
+    Balance,
+    /// Proportional to the squared densities.
+    #[default]
+    Power,
+}
+
+impl Heuristic {
+    fn term(self, n: u32, pdf: Float) -> Float {
+        let x = n as Float * pdf;
+        match self {
+            Heuristic::Balance => x,
+            Heuristic::Power => x * x,
+        }
+    }
+}
+
+/// The MIS weight of a sample, for any number of strategies.
+///
+/// Starts from the strategy that drew the sample, and collects the
+/// densities with which all other strategies would have drawn the
+/// same sample.
+///
+/// # Examples
+/// ```
+/// use lucifer::montecarlo::{Heuristic, MisWeight};
+/// use lucifer::Float;
+///
+/// // A direction drawn by sampling a light, which BSDF sampling and
+/// // environment sampling could have found as well.
+/// let weight = MisWeight::new(Heuristic::Balance, 1, 2.0)
+///     .other(1, 1.0)
+///     .other(1, 1.0)
+///     .weight();
+///
+/// assert_eq!(weight, 0.5);
+///
+/// // A strategy with a Dirac density takes all the weight.
+/// assert_eq!(MisWeight::new(Heuristic::Power, 1, Float::INFINITY).other(1, 1.0).weight(), 1.0);
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct MisWeight {
+    heuristic: Heuristic,
+    own: Float,
+    sum: Float,
+}
+
+impl MisWeight {
+    /// Starts the weight of a sample drawn with density `pdf` by a
+    /// strategy taking `n` samples.
+    pub fn new(heuristic: Heuristic, n: u32, pdf: Float) -> MisWeight {
+        let own = heuristic.term(n, pdf);
+        MisWeight {
+            heuristic,
+            own,
+            sum: own,
+        }
+    }
+
+    /// Adds another strategy, taking `n` samples, which would have
+    /// drawn the sample with density `pdf`.
+    pub fn other(self, n: u32, pdf: Float) -> MisWeight {
+        let mut weight = self;
+        weight.sum += self.heuristic.term(n, pdf);
+        weight
+    }
+
+    /// The weight of the sample, in `[0, 1]`.
+    pub fn weight(&self) -> Float {
+        if self.own.is_infinite() {
+            1.0
+        } else if self.sum <= 0.0 || !self.sum.is_finite() {
+            0.0
+        } else {
+            self.own / self.sum
+        }
+    }
+}
@@ -0,0 +1,419 @@
+//! Monte Carlo integration and sampling
+
+use std::ops::{Add, AddAssign, Div, Mul, MulAssign};
+
+use cgmath::num_traits::{clamp, Zero};
+use rand::Rng;
+
+mod alias;
+mod convergence;
+mod distribution2d;
+pub mod mis;
+pub mod pdf;
+pub mod sobol;
+pub mod stratified;
+pub mod warp;
+
+pub use self::alias::Discrete1D;
+pub use self::convergence::{Convergence, Moments};
+pub use self::distribution2d::Distribution2D;
+pub use self::mis::{balance_heuristic, power_heuristic, Heuristic, MisWeight};
+use Float;
+
+/// A Monte Carlo sample: a `value` drawn with probability density
+/// `probability`, representing the estimate `value / probability`.
+///
+/// Multiplying samples combines independent random decisions, e.g. a
+/// sampled direction and the radiance arriving from it, so their
+/// values and densities multiply.  Adding samples combines separate
+/// contributions to the same integral, e.g. the lobes of a BSDF, each
+/// sampled with its own density; the sum is the sum of their
+/// estimates, which is certain (has probability `1`) once the
+/// individual samples are taken.
+///
+/// # Examples
+/// ```
+/// use lucifer::montecarlo::{Estimator, Sample};
+/// use lucifer::Float;
+///
+/// // Estimates the integral of `x + x²` over `[0, 1]`, which is 5/6,
+/// // sampling `x` uniformly for the first term and with density `3x²`
+/// // for the second one.
+/// let n = 1000;
+/// let mut estimator = Estimator::new();
+/// for i in 0..n {
+///     let u = (i as Float + 0.5) / n as Float;
+///     let linear = Sample::new(u, 1.0);
+///     let x = u.cbrt();
+///     let square = Sample::new(x * x, 3.0 * x * x);
+///
+///     estimator.add(linear + square);
+/// }
+///
+/// assert!((estimator.value() - 5.0 / 6.0).abs() < 1e-4);
+/// ```
+///
+/// Products keep the joint density of independent decisions:
+/// ```
+/// use lucifer::montecarlo::Sample;
+/// use lucifer::Float;
+///
+/// let product = Sample::new(2.0 as Float, 0.5) * Sample::new(3.0 as Float, 0.25);
+///
+/// assert_eq!(product.value(), 6.0);
+/// assert_eq!(product.probability(), 0.125);
+/// assert_eq!(product.estimate(), 48.0);
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct Sample<T> {
+    value: T,
+    probability: Float,
+}
+
+impl<T> Sample<T> {
+    /// Creates a new `Sample` of `value`, drawn with density
+    /// `probability`.
+    pub fn new(value: T, probability: Float) -> Sample<T> {
+        Sample { value, probability }
+    }
+
+    /// The sampled value.
+    pub fn value(self) -> T {
+        self.value
+    }
+
+    /// The probability density with which the value was drawn.
+    pub fn probability(&self) -> Float {
+        self.probability
+    }
+
+    /// The estimate `value / probability`, or zero for samples that
+    /// could not have been drawn.
+    pub fn estimate(self) -> T
+    where
+        T: Div<Float, Output = T> + Zero,
+    {
+        if self.probability > 0.0 {
+            self.value / self.probability
+        } else {
+            T::zero()
+        }
+    }
+}
+
+impl<T> Default for Sample<T>
+where
+    T: Default,
+{
+    fn default() -> Sample<T> {
+        T::default().into()
+    }
+}
+
+impl<T> From<T> for Sample<T> {
+    fn from(value: T) -> Sample<T> {
+        Sample::new(value, 1.0)
+    }
+}
+
+impl<T, U, V> Add<Sample<U>> for Sample<T>
+where
+    T: Add<U, Output = V> + Div<Float, Output = T> + Zero,
+    U: Div<Float, Output = U> + Zero,
+{
+    type Output = Sample<V>;
+    fn add(self, rhs: Sample<U>) -> Sample<V> {
+        Sample::from(self.estimate() + rhs.estimate())
+    }
+}
+
+impl<T> AddAssign<Sample<T>> for Sample<T>
+where
+    T: Add<T, Output = T> + Div<Float, Output = T> + Zero + Copy,
+{
+    fn add_assign(&mut self, rhs: Sample<T>) {
+        *self = *self + rhs;
+    }
+}
+
+impl<T, U, V> Mul<Sample<U>> for Sample<T>
+where
+    T: Mul<U, Output = V>,
+{
+    type Output = Sample<V>;
+    fn mul(self, rhs: Sample<U>) -> Sample<V> {
+        Sample {
+            value: self.value * rhs.value,
+            probability: self.probability * rhs.probability,
+        }
+    }
+}
+
+impl<T, U> MulAssign<Sample<U>> for Sample<T>
+where
+    T: MulAssign<U>,
+{
+    fn mul_assign(&mut self, rhs: Sample<U>) {
+        self.value *= rhs.value;
+        self.probability *= rhs.probability;
+    }
+}
+
+/// The mean of the estimates of a number of `Sample`s.
+#[derive(Clone, Copy, Debug)]
+pub struct Estimator<T> {
+    value: T,
+    n: u32,
+}
+
+#[allow(clippy::new_without_default)]
+impl<T> Estimator<T> {
+    pub fn new() -> Estimator<T>
+    where
+        T: Zero,
+    {
+        Estimator {
+            value: T::zero(),
+            n: 0,
+        }
+    }
+
+    pub fn add(&mut self, sample: Sample<T>)
+    where
+        T: AddAssign<T> + Div<Float, Output = T> + Zero,
+    {
+        self.value += sample.estimate();
+        self.n += 1;
+    }
+
+    pub fn value(self) -> T
+    where
+        T: Div<Float, Output = T>,
+    {
+        assert!(self.n != 0);
+        self.value / (self.n as Float)
+    }
+}
+
+impl<T> Default for Estimator<T>
+where
+    T: Zero,
+{
+    fn default() -> Estimator<T> {
+        Estimator::new()
+    }
+}
+
+/// A weighted reservoir for resampled importance sampling (RIS).
+///
+/// A `Reservoir` sees a stream of candidate samples, each with a
+/// resampling weight, and keeps exactly one of them with probability
+/// proportional to its weight.  Reservoirs can be merged, which is
+/// the basis for reusing samples between neighboring pixels and
+/// successive frames (ReSTIR).
+///
+/// # Examples
+/// ```
+/// use lucifer::montecarlo::Reservoir;
+/// use lucifer::rand::{SeedableRng, XorShiftRng};
+///
+/// let mut rng = XorShiftRng::from_seed([1; 16]);
+/// let mut reservoir = Reservoir::new();
+///
+/// reservoir.update("a", 0.0, 1.0, &mut rng);
+/// reservoir.update("b", 2.0, 4.0, &mut rng);
+///
+/// assert_eq!(reservoir.sample(), Some(&"b"));
+/// assert_eq!(reservoir.count(), 2);
+/// assert_eq!(reservoir.contribution_weight(), 0.25);
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct Reservoir<T> {
+    sample: Option<T>,
+    target: Float,
+    weight_sum: Float,
+    count: u32,
+}
+
+impl<T> Reservoir<T> {
+    /// Creates a new, empty `Reservoir`.
+    pub fn new() -> Reservoir<T> {
+        Reservoir {
+            sample: None,
+            target: 0.0,
+            weight_sum: 0.0,
+            count: 0,
+        }
+    }
+
+    /// Streams a `candidate` with resampling weight `weight` into the
+    /// reservoir.  `target` is the value of the (unnormalized) target
+    /// function for the candidate.  Returns whether the candidate was
+    /// selected.
+    pub fn update<R: Rng>(
+        &mut self,
+        candidate: T,
+        weight: Float,
+        target: Float,
+        rng: &mut R,
+    ) -> bool {
+        self.count += 1;
+        self.add_weight(candidate, weight, target, rng)
+    }
+
+    /// Merges `other` into `self`.  `target` is the value of `self`'s
+    /// target function for the sample held by `other`.
+    pub fn merge<R: Rng>(&mut self, other: Reservoir<T>, target: Float, rng: &mut R) -> bool {
+        let count = other.count;
+        let weight = target * other.contribution_weight() * count as Float;
+
+        self.count += count;
+        match other.sample {
+            Some(sample) => self.add_weight(sample, weight, target, rng),
+            None => false,
+        }
+    }
+
+    fn add_weight<R: Rng>(
+        &mut self,
+        candidate: T,
+        weight: Float,
+        target: Float,
+        rng: &mut R,
+    ) -> bool {
+        if weight <= 0.0 || !weight.is_finite() {
+            return false;
+        }
+
+        self.weight_sum += weight;
+        if rng.gen::<Float>() * self.weight_sum < weight {
+            self.sample = Some(candidate);
+            self.target = target;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// The currently selected sample.
+    pub fn sample(&self) -> Option<&T> {
+        self.sample.as_ref()
+    }
+
+    /// The number of candidates seen by the reservoir.
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    /// Limits the number of candidates the reservoir claims to have
+    /// seen, preserving the selected sample's contribution weight.
+    /// Used to bound the influence of stale temporal history.
+    pub fn clamp(&mut self, max_count: u32) {
+        if self.count > max_count {
+            self.weight_sum *= max_count as Float / self.count as Float;
+            self.count = max_count;
+        }
+    }
+
+    /// Discards the selected sample, e.g. after it was found to be
+    /// occluded.
+    pub fn discard(&mut self) {
+        self.sample = None;
+        self.target = 0.0;
+        self.weight_sum = 0.0;
+    }
+
+    /// The unbiased contribution weight `W` of the selected sample,
+    /// i.e. the reciprocal of its effective probability density.
+    pub fn contribution_weight(&self) -> Float {
+        if self.sample.is_none() || self.target <= 0.0 || self.count == 0 {
+            0.0
+        } else {
+            self.weight_sum / (self.count as Float * self.target)
+        }
+    }
+}
+
+impl<T> Default for Reservoir<T> {
+    fn default() -> Reservoir<T> {
+        Reservoir::new()
+    }
+}
+
+/// A discrete probability distribution over indices, proportional to
+/// a list of non-negative weights.
+///
+/// # Examples
+/// ```
+/// use lucifer::montecarlo::Discrete;
+///
+/// let distribution = Discrete::new(&[1.0, 0.0, 3.0]);
+///
+/// assert_eq!(distribution.sample(0.1), Some((0, 0.25)));
+/// assert_eq!(distribution.sample(0.5), Some((2, 0.75)));
+/// assert_eq!(distribution.probability(1), 0.0);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Discrete {
+    cdf: Vec<Float>,
+}
+
+impl Discrete {
+    /// Creates a new `Discrete` distribution from `weights`.
+    pub fn new(weights: &[Float]) -> Discrete {
+        let mut sum = 0.0;
+        let cdf = weights
+            .iter()
+            .map(|w| {
+                sum += w.max(0.0);
+                sum
+            })
+            .collect();
+
+        Discrete { cdf }
+    }
+
+    /// The sum of all weights.
+    pub fn total(&self) -> Float {
+        self.cdf.last().cloned().unwrap_or(0.0)
+    }
+
+    /// The probability of sampling index `i`.
+    pub fn probability(&self, i: usize) -> Float {
+        let total = self.total();
+        if total <= 0.0 {
+            return 0.0;
+        }
+
+        let lower = if i == 0 { 0.0 } else { self.cdf[i - 1] };
+        (self.cdf[i] - lower) / total
+    }
+
+    /// Maps `u` in `[0, 1)` to an index, returning the index and its
+    /// probability.  Returns `None` if all weights are zero.
+    pub fn sample(&self, u: Float) -> Option<(usize, Float)> {
+        self.sample_remapped(u)
+            .map(|(i, probability, _)| (i, probability))
+    }
+
+    /// Like `sample`, but additionally returns `u` remapped to
+    /// `[0, 1)` within the chosen index, so it can be reused for
+    /// another decision.
+    pub fn sample_remapped(&self, u: Float) -> Option<(usize, Float, Float)> {
+        let total = self.total();
+        if total <= 0.0 {
+            return None;
+        }
+
+        let x = u * total;
+        let i = self
+            .cdf
+            .partition_point(|&c| c <= x)
+            .min(self.cdf.len() - 1);
+
+        let lower = if i == 0 { 0.0 } else { self.cdf[i - 1] };
+        let remapped = clamp((x - lower) / (self.cdf[i] - lower), 0.0, 1.0);
+
+        Some((i, self.probability(i), remapped))
+    }
+}
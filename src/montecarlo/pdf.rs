@@ -0,0 +1,96 @@
+//! Conversions of probability densities between measures
+//!
+//! A density only means something together with the measure it
+//! refers to: a light sampled by area has a density per unit area, a
+//! BSDF sampled by direction has a density per steradian.  Before
+//! combining or dividing by such densities, they have to be converted
+//! to the same measure.
+
+use consts::PI;
+use Float;
+
+/// Converts a density with respect to surface area into one with
+/// respect to solid angle, as seen from a point at `distance`.  `cos`
+/// is the cosine between the surface normal and the direction
+/// towards the point.
+///
+/// Returns `0` for surfaces seen exactly edge-on.
+///
+/// # Examples
+/// ```
+/// use lucifer::montecarlo::pdf::{area_to_solid_angle, solid_angle_to_area};
+///
+/// let pdf = area_to_solid_angle(0.5, 2.0, 0.5);
+///
+/// assert_eq!(pdf, 4.0);
+/// assert_eq!(solid_angle_to_area(pdf, 2.0, 0.5), 0.5);
+/// ```
+pub fn area_to_solid_angle(pdf: Float, distance: Float, cos: Float) -> Float {
+    if cos == 0.0 {
+        0.0
+    } else {
+        pdf * distance * distance / cos.abs()
+    }
+}
+
+/// Converts a density with respect to solid angle, as seen from a
+/// point at `distance`, into one with respect to surface area.  `cos`
+/// is the cosine between the surface normal and the direction
+/// towards the point.
+pub fn solid_angle_to_area(pdf: Float, distance: Float, cos: Float) -> Float {
+    pdf * cos.abs() / (distance * distance)
+}
+
+/// Converts a density with respect to solid angle into one relative
+/// to uniform sampling of the hemisphere, i.e. with respect to the
+/// hemisphere's solid angle normalized to `1`.
+///
+/// # Examples
+/// ```
+/// use lucifer::consts::PI;
+///
+/// use lucifer::montecarlo::pdf::{hemisphere_to_solid_angle, solid_angle_to_hemisphere};
+///
+/// assert_eq!(solid_angle_to_hemisphere(0.5 / PI), 1.0);
+/// assert_eq!(hemisphere_to_solid_angle(1.0), 0.5 / PI);
+/// ```
+pub fn solid_angle_to_hemisphere(pdf: Float) -> Float {
+    pdf * 2.0 * PI
+}
+
+/// Converts a density relative to uniform sampling of the hemisphere
+/// into one with respect to solid angle.
+pub fn hemisphere_to_solid_angle(pdf: Float) -> Float {
+    pdf / (2.0 * PI)
+}
+
+/// Converts a density with respect to solid angle into one with
+/// respect to the spherical coordinates `(theta, phi)`, given
+/// `sin(theta)`.
+pub fn solid_angle_to_spherical(pdf: Float, sin_theta: Float) -> Float {
+    pdf * sin_theta
+}
+
+/// Converts a density with respect to the spherical coordinates
+/// `(theta, phi)` into one with respect to solid angle, given
+/// `sin(theta)`.  Returns `0` at the poles.
+///
+/// # Examples
+/// ```
+/// use lucifer::consts::PI;
+///
+/// use lucifer::montecarlo::pdf::spherical_to_solid_angle;
+///
+/// // Uniform over theta in [0, pi] and phi in [0, 2 pi).
+/// let pdf = 1.0 / (2.0 * PI * PI);
+///
+/// assert_eq!(spherical_to_solid_angle(pdf, 1.0), pdf);
+/// assert_eq!(spherical_to_solid_angle(pdf, 0.0), 0.0);
+/// ```
+pub fn spherical_to_solid_angle(pdf: Float, sin_theta: Float) -> Float {
+    if sin_theta == 0.0 {
+        0.0
+    } else {
+        pdf / sin_theta
+    }
+}
@@ -0,0 +1,125 @@
+//! Owen-scrambled Sobol sequences
+//!
+//! Points are made of padded two-dimensional Sobol points: every pair
+//! of dimensions uses the first two Sobol dimensions, which are well
+//! distributed for any prefix of the sequence, with the sample order
+//! shuffled independently per pair.  Nested uniform (Owen) scrambling
+//! with a per-pixel seed decorrelates neighbouring pixels while
+//! keeping the stratification of the unscrambled points, so the
+//! sample count can be increased progressively.
+//!
+//! The scrambling follows Burley, "Practical Hash-based Owen
+//! Scrambling", JCGT 2020.
+
+use Float;
+
+/// A scrambled Sobol sequence, e.g. one per pixel.
+///
+/// # Examples
+/// ```
+/// use lucifer::montecarlo::sobol::Sobol;
+///
+/// let sobol = Sobol::new(42);
+///
+/// // Every power-of-two prefix of a pair of dimensions puts exactly
+/// // one point into every cell of a 4x4 grid...
+/// let mut cells = [0; 16];
+/// for i in 0..16 {
+///     let (u, v) = sobol.sample_2d(i, 0);
+///     cells[(u * 4.0) as usize * 4 + (v * 4.0) as usize] += 1;
+/// }
+/// assert_eq!(cells, [1; 16]);
+///
+/// // ...and into every one of 16 intervals in each dimension.
+/// let mut intervals = [0; 16];
+/// for i in 0..16 {
+///     intervals[(sobol.sample(i, 5) * 16.0) as usize] += 1;
+/// }
+/// assert_eq!(intervals, [1; 16]);
+///
+/// // Different seeds give differently scrambled points.
+/// assert_ne!(sobol.sample(3, 0), Sobol::new(43).sample(3, 0));
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct Sobol {
+    seed: u32,
+}
+
+impl Sobol {
+    /// Creates a new `Sobol` sequence, scrambled by `seed`.
+    pub fn new(seed: u32) -> Sobol {
+        Sobol { seed }
+    }
+
+    /// Coordinate `dimension` of the `index`th point, in `[0, 1)`.
+    pub fn sample(&self, index: u32, dimension: u32) -> Float {
+        let (u, v) = self.sample_2d(index, dimension & !1);
+        if dimension & 1 == 0 {
+            u
+        } else {
+            v
+        }
+    }
+
+    /// Coordinates `dimension` and `dimension + 1` of the `index`th
+    /// point, in `[0, 1)`.  `dimension` should be even, so that pairs
+    /// don't overlap.
+    pub fn sample_2d(&self, index: u32, dimension: u32) -> (Float, Float) {
+        let seed = hash(self.seed, dimension / 2);
+        let index = nested_uniform_scramble(index, seed);
+        let (x, y) = sobol_2d(index);
+
+        (
+            to_unit(nested_uniform_scramble(x, hash(seed, 0))),
+            to_unit(nested_uniform_scramble(y, hash(seed, 1))),
+        )
+    }
+}
+
+/// The first two dimensions of the unscrambled Sobol sequence, as
+/// 32-bit fractions.
+fn sobol_2d(index: u32) -> (u32, u32) {
+    let mut y = 0;
+    let mut direction = 1 << 31;
+    let mut bits = index;
+    while bits != 0 {
+        if bits & 1 != 0 {
+            y ^= direction;
+        }
+        bits >>= 1;
+        direction ^= direction >> 1;
+    }
+
+    (index.reverse_bits(), y)
+}
+
+/// Owen scrambling of a 32-bit fraction, i.e. randomly flipping every
+/// bit depending on all higher bits.
+fn nested_uniform_scramble(x: u32, seed: u32) -> u32 {
+    laine_karras_permutation(x.reverse_bits(), seed).reverse_bits()
+}
+
+/// A hash in which every bit only depends on the lower bits, for
+/// scrambling bit-reversed numbers.
+fn laine_karras_permutation(x: u32, seed: u32) -> u32 {
+    let mut x = x.wrapping_add(seed);
+    x ^= x.wrapping_mul(0x6c50_b47c);
+    x ^= x.wrapping_mul(0xb82f_1e52);
+    x ^= x.wrapping_mul(0xc7af_e638);
+    x ^= x.wrapping_mul(0x8d22_f6e6);
+    x
+}
+
+fn hash(a: u32, b: u32) -> u32 {
+    let mut x = a ^ b.wrapping_mul(0x9e37_79b9);
+    x ^= x >> 16;
+    x = x.wrapping_mul(0x21f0_aaad);
+    x ^= x >> 15;
+    x = x.wrapping_mul(0x735a_2d97);
+    x ^ (x >> 15)
+}
+
+/// Converts a 32-bit fraction into an `f32` strictly below `1`.
+fn to_unit(x: u32) -> Float {
+    (x >> 8) as Float / (1 << 24) as Float
+}
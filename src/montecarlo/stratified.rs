@@ -0,0 +1,106 @@
+//! Stratified sample sets
+//!
+//! Splitting the domain into equally sized strata and placing one
+//! jittered sample in each avoids the clumps and holes of independent
+//! random samples.  The sets are shuffled, so any prefix is still
+//! spread over the whole domain, and so that sets drawn for different
+//! dimensions can be paired without correlating them.
+
+use rand::Rng;
+use Float;
+
+/// Shuffles `samples` in place (Fisher-Yates).
+pub fn shuffle<T, R: Rng>(samples: &mut [T], rng: &mut R) {
+    for i in (1..samples.len()).rev() {
+        let j = rng.gen_range(0, i + 1);
+        samples.swap(i, j);
+    }
+}
+
+/// `n` samples in `[0, 1)`, one in each of `n` equal intervals, in
+/// random order.
+///
+/// # Examples
+/// ```
+/// use lucifer::montecarlo::stratified::stratified_1d;
+/// use lucifer::rand::{SeedableRng, XorShiftRng};
+///
+/// let mut rng = XorShiftRng::from_seed([1; 16]);
+/// let mut samples = stratified_1d(8, &mut rng);
+/// samples.sort_by(|a, b| a.partial_cmp(b).unwrap());
+///
+/// for (i, x) in samples.into_iter().enumerate() {
+///     assert_eq!((x * 8.0) as usize, i);
+/// }
+/// ```
+pub fn stratified_1d<R: Rng>(n: usize, rng: &mut R) -> Vec<Float> {
+    let mut samples: Vec<Float> = (0..n).map(|i| jitter(i, n, rng)).collect();
+    shuffle(&mut samples, rng);
+    samples
+}
+
+/// `nx * ny` samples in `[0, 1)²`, one in each cell of an `nx` by
+/// `ny` grid, in random order.
+///
+/// # Examples
+/// ```
+/// use lucifer::montecarlo::stratified::stratified_2d;
+/// use lucifer::rand::{SeedableRng, XorShiftRng};
+///
+/// let mut rng = XorShiftRng::from_seed([1; 16]);
+/// let samples = stratified_2d(4, 2, &mut rng);
+///
+/// let mut cells = [0; 8];
+/// for (x, y) in samples {
+///     cells[(y * 2.0) as usize * 4 + (x * 4.0) as usize] += 1;
+/// }
+/// assert_eq!(cells, [1; 8]);
+/// ```
+pub fn stratified_2d<R: Rng>(nx: usize, ny: usize, rng: &mut R) -> Vec<(Float, Float)> {
+    let mut samples = Vec::with_capacity(nx * ny);
+    for y in 0..ny {
+        for x in 0..nx {
+            samples.push((jitter(x, nx, rng), jitter(y, ny, rng)));
+        }
+    }
+    shuffle(&mut samples, rng);
+    samples
+}
+
+/// `n` samples in `[0, 1)^dimensions` forming a Latin hypercube: in
+/// every dimension, each of `n` equal intervals contains exactly one
+/// sample.  Unlike a grid, this works for any `n` and any number of
+/// dimensions.
+///
+/// # Examples
+/// ```
+/// use lucifer::montecarlo::stratified::latin_hypercube;
+/// use lucifer::rand::{SeedableRng, XorShiftRng};
+///
+/// let mut rng = XorShiftRng::from_seed([1; 16]);
+/// let samples = latin_hypercube(5, 3, &mut rng);
+///
+/// assert_eq!(samples.len(), 5);
+/// for d in 0..3 {
+///     let mut intervals = [0; 5];
+///     for sample in &samples {
+///         intervals[(sample[d] * 5.0) as usize] += 1;
+///     }
+///     assert_eq!(intervals, [1; 5]);
+/// }
+/// ```
+pub fn latin_hypercube<R: Rng>(n: usize, dimensions: usize, rng: &mut R) -> Vec<Vec<Float>> {
+    let mut samples = vec![Vec::with_capacity(dimensions); n];
+    for _ in 0..dimensions {
+        for (sample, x) in samples.iter_mut().zip(stratified_1d(n, rng)) {
+            sample.push(x);
+        }
+    }
+    samples
+}
+
+/// A random position within interval `i` of `n`.
+fn jitter<R: Rng>(i: usize, n: usize, rng: &mut R) -> Float {
+    // Rounding may reach the upper bound for large `i`.
+    ((i as Float + rng.gen::<Float>()) / n as Float).min(1.0 - Float::EPSILON / 2.0)
+}
@@ -0,0 +1,108 @@
+//! Mappings of uniform samples in the unit square onto other domains
+//!
+//! Each mapping takes two numbers `u` and `v` in `[0, 1)` and returns
+//! the sample together with its density with respect to the target
+//! domain's measure: area for disks and triangles, solid angle for
+//! directions.
+
+use consts::PI;
+
+use cgmath::{vec2, vec3, EuclideanSpace, InnerSpace, Point3, Vector2, Vector3};
+use Float;
+
+/// Maps `u` and `v` onto the unit disk, keeping neighbouring samples
+/// close together (Shirley and Chiu's concentric mapping).
+///
+/// # Examples
+/// ```
+/// use lucifer::montecarlo::warp::concentric_disk;
+///
+/// let (center, pdf) = concentric_disk(0.5, 0.5);
+/// assert_eq!((center.x, center.y), (0.0, 0.0));
+/// assert_eq!(pdf, 1.0 / lucifer::consts::PI);
+///
+/// let (edge, _) = concentric_disk(1.0, 0.5);
+/// assert_eq!((edge.x, edge.y), (1.0, 0.0));
+/// ```
+pub fn concentric_disk(u: Float, v: Float) -> (Vector2<Float>, Float) {
+    let (x, y) = (2.0 * u - 1.0, 2.0 * v - 1.0);
+    let point = if x == 0.0 && y == 0.0 {
+        vec2(0.0, 0.0)
+    } else if x.abs() > y.abs() {
+        let phi = PI / 4.0 * (y / x);
+        vec2(x * phi.cos(), x * phi.sin())
+    } else {
+        let phi = PI / 2.0 - PI / 4.0 * (x / y);
+        vec2(y * phi.cos(), y * phi.sin())
+    };
+
+    (point, 1.0 / PI)
+}
+
+/// Maps `u` and `v` uniformly onto the triangle `a`, `b`, `c`.
+///
+/// # Examples
+/// ```
+/// use lucifer::cgmath::Point3;
+/// use lucifer::montecarlo::warp::uniform_triangle;
+///
+/// let a = Point3::new(0.0, 0.0, 0.0);
+/// let b = Point3::new(2.0, 0.0, 0.0);
+/// let c = Point3::new(0.0, 2.0, 0.0);
+///
+/// let (point, pdf) = uniform_triangle(a, b, c, 0.25, 0.5);
+/// assert!(point.x >= 0.0 && point.y >= 0.0 && point.x + point.y <= 2.0);
+/// assert_eq!(pdf, 0.5);
+/// ```
+pub fn uniform_triangle(
+    a: Point3<Float>,
+    b: Point3<Float>,
+    c: Point3<Float>,
+    u: Float,
+    v: Float,
+) -> (Point3<Float>, Float) {
+    let s = u.sqrt();
+    let (b0, b1) = (1.0 - s, v * s);
+    let point = Point3::from_vec(a.to_vec() * b0 + b.to_vec() * b1 + c.to_vec() * (1.0 - b0 - b1));
+    let area = 0.5 * (b - a).cross(c - a).magnitude();
+
+    (point, if area > 0.0 { 1.0 / area } else { 0.0 })
+}
+
+/// Maps `u` and `v` uniformly onto the directions within the cone
+/// around `+z` whose opening half-angle has the cosine `cos_max`.
+///
+/// # Examples
+/// ```
+/// use lucifer::montecarlo::warp::uniform_cone;
+///
+/// let (direction, pdf) = uniform_cone(0.5, 0.3, 0.7);
+/// assert!(direction.z >= 0.5);
+/// assert_eq!(pdf, 1.0 / lucifer::consts::PI);
+/// ```
+pub fn uniform_cone(cos_max: Float, u: Float, v: Float) -> (Vector3<Float>, Float) {
+    let cos_theta = 1.0 - u * (1.0 - cos_max);
+    let r = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+    let phi = v * 2.0 * PI;
+
+    (
+        vec3(r * phi.cos(), r * phi.sin(), cos_theta),
+        1.0 / (2.0 * PI * (1.0 - cos_max)),
+    )
+}
+
+/// Maps `u` and `v` uniformly onto the directions of the unit
+/// sphere.
+///
+/// # Examples
+/// ```
+/// use lucifer::cgmath::InnerSpace;
+/// use lucifer::montecarlo::warp::uniform_sphere;
+///
+/// let (direction, pdf) = uniform_sphere(0.3, 0.8);
+/// assert!((direction.magnitude() - 1.0).abs() < 1e-6);
+/// assert_eq!(pdf, 0.25 / lucifer::consts::PI);
+/// ```
+pub fn uniform_sphere(u: Float, v: Float) -> (Vector3<Float>, Float) {
+    uniform_cone(-1.0, u, v)
+}
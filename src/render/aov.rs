@@ -0,0 +1,103 @@
+use camera::{Camera, Resolution, Target};
+use geometry::Vector;
+use lighting::Radiance;
+use render::debug::id_color;
+use render::{Context, Renderer};
+use scene::Scene;
+
+/// An arbitrary output variable, an auxiliary image rendered
+/// alongside the final image, e.g. as input to a denoiser.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum Aov {
+    /// The surface normals, mapped from `[-1, 1]` to `[0, 1]`.
+    Normal,
+    /// The distance to the first hit, `0` where nothing is hit.
+    Depth,
+    /// The albedo of the first hit.
+    Albedo,
+    /// The variance of every pixel's mean luma, see `Film::variance`.
+    Variance,
+    /// A distinct color per object.
+    Id,
+}
+
+impl Aov {
+    /// All outputs.
+    pub const ALL: [Aov; 5] = [Aov::Normal, Aov::Depth, Aov::Albedo, Aov::Variance, Aov::Id];
+
+    /// The name of the output, e.g. for file names.
+    pub fn name(self) -> &'static str {
+        match self {
+            Aov::Normal => "normal",
+            Aov::Depth => "depth",
+            Aov::Albedo => "albedo",
+            Aov::Variance => "variance",
+            Aov::Id => "id",
+        }
+    }
+
+    /// The output called `name`, if any.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::render::Aov;
+    ///
+    /// assert_eq!(Aov::from_name("depth"), Some(Aov::Depth));
+    /// assert_eq!(Aov::from_name("beauty"), None);
+    /// ```
+    pub fn from_name(name: &str) -> Option<Aov> {
+        Aov::ALL.iter().cloned().find(|aov| aov.name() == name)
+    }
+}
+
+/// A `Renderer` producing an `Aov` from the first hit of every
+/// primary ray.
+///
+/// `Aov::Variance` is not a property of the scene, but of the
+/// estimates of a render, and left black.
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct AovRenderer {
+    pub aov: Aov,
+}
+
+impl AovRenderer {
+    pub fn new(aov: Aov) -> AovRenderer {
+        AovRenderer { aov }
+    }
+}
+
+impl Renderer for AovRenderer {
+    fn render(
+        &self,
+        context: &mut Context,
+        scene: &Scene,
+        camera: &dyn Camera,
+        resolution: Resolution,
+        target: Target,
+    ) -> Radiance {
+        if self.aov == Aov::Variance {
+            return Radiance::none();
+        }
+
+        let ray = camera.primary(resolution, target);
+        context.statistics.paths += 1;
+        context.statistics.rays += 1;
+        let hit = match scene.intersect(&ray) {
+            None => return Radiance::none(),
+            Some(hit) => hit,
+        };
+        context.statistics.path_vertices += 1;
+
+        match self.aov {
+            Aov::Normal => {
+                Radiance::from(0.5 * hit.intersection.normal + Vector::new(0.5, 0.5, 0.5))
+            }
+            Aov::Depth => Radiance::gray(hit.intersection.lambda),
+            Aov::Albedo => hit.bsdf.albedo() * Radiance::gray(1.0),
+            Aov::Id => id_color(hit.object),
+            Aov::Variance => Radiance::none(),
+        }
+    }
+}
@@ -0,0 +1,276 @@
+//! Comparisons of rendered images, e.g. with a reference rendered
+//! with many more samples, to validate changes to renderers.
+//!
+//! # Examples
+//! ```
+//! use lucifer::camera::{Resolution, Target};
+//! use lucifer::lighting::Radiance;
+//! use lucifer::render::compare::compare;
+//! use lucifer::render::Film;
+//!
+//! let mut reference = Film::new(Resolution::new(8, 8));
+//! let mut image = Film::new(Resolution::new(8, 8));
+//! for y in 0..8 {
+//!     for x in 0..8 {
+//!         let target = Target::new(x, y);
+//!         reference.add(target, Radiance::gray(0.5));
+//!         image.add(target, Radiance::gray(if x == 0 { 0.9 } else { 0.5 }));
+//!     }
+//! }
+//!
+//! let same = compare(&reference, &reference);
+//! assert_eq!((same.rmse, same.ssim), (0.0, 1.0));
+//!
+//! let different = compare(&image, &reference);
+//! assert!((different.rmse.powi(2) - 0.02).abs() < 1e-6);
+//! assert!(different.ssim < 1.0);
+//! ```
+
+use std::fmt;
+#[cfg(feature = "image")]
+use std::fs::File;
+#[cfg(feature = "image")]
+use std::io::BufReader;
+#[cfg(feature = "image")]
+use std::path::Path;
+
+#[cfg(feature = "image")]
+use image::hdr::HDRDecoder;
+#[cfg(feature = "image")]
+use image::{self, ImageResult};
+
+use camera::{Resolution, Target};
+use lighting::Radiance;
+use render::Film;
+use Float;
+
+/// The size of the square windows compared by `ssim`.
+const WINDOW: u32 = 8;
+
+/// The differences between an image and a reference.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Comparison {
+    /// The root mean square error of all channels.
+    pub rmse: Float,
+    /// The mean squared error of all channels, relative to the
+    /// squared reference; suits high dynamic range images.
+    pub relative_mse: Float,
+    /// The structural similarity of the luma, one for identical
+    /// images.
+    pub ssim: Float,
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "rmse:              {:.6}", self.rmse)?;
+        writeln!(f, "relative mse:      {:.6}", self.relative_mse)?;
+        write!(f, "ssim:              {:.6}", self.ssim)
+    }
+}
+
+/// Compares `image` with `reference`.
+///
+/// # Panics
+/// Panics if the resolutions of the images differ.
+pub fn compare(image: &Film, reference: &Film) -> Comparison {
+    Comparison {
+        rmse: rmse(image, reference),
+        relative_mse: relative_mse(image, reference),
+        ssim: ssim(image, reference),
+    }
+}
+
+fn pixels(image: &Film, reference: &Film) -> (Vec<Radiance>, Vec<Radiance>) {
+    assert!(
+        image.resolution() == reference.resolution(),
+        "cannot compare images of different resolutions"
+    );
+    (image.pixels(), reference.pixels())
+}
+
+/// The root mean square error of all channels of `image`.
+///
+/// # Panics
+/// Panics if the resolutions of the images differ.
+pub fn rmse(image: &Film, reference: &Film) -> Float {
+    let (a, b) = pixels(image, reference);
+    let sum: Float = a
+        .iter()
+        .zip(&b)
+        .map(|(a, b)| (0..3).map(|c| (a[c] - b[c]).powi(2)).sum::<Float>())
+        .sum();
+
+    match a.len() {
+        0 => 0.0,
+        n => (sum / (3 * n) as Float).sqrt(),
+    }
+}
+
+/// The mean squared error of all channels of `image`, each divided
+/// by the square of the reference.  A small constant keeps black
+/// reference pixels from dominating.
+///
+/// # Panics
+/// Panics if the resolutions of the images differ.
+pub fn relative_mse(image: &Film, reference: &Film) -> Float {
+    let (a, b) = pixels(image, reference);
+    let sum: Float = a
+        .iter()
+        .zip(&b)
+        .map(|(a, b)| {
+            (0..3)
+                .map(|c| (a[c] - b[c]).powi(2) / (b[c] * b[c] + 0.01))
+                .sum::<Float>()
+        })
+        .sum();
+
+    match a.len() {
+        0 => 0.0,
+        n => sum / (3 * n) as Float,
+    }
+}
+
+/// The mean structural similarity of the luma of `image` over
+/// overlapping windows of 8×8 pixels, assuming a dynamic range of
+/// one (Wang et al., "Image Quality Assessment: From Error
+/// Visibility to Structural Similarity").
+///
+/// # Panics
+/// Panics if the resolutions of the images differ.
+pub fn ssim(image: &Film, reference: &Film) -> Float {
+    const C1: Float = 0.01 * 0.01;
+    const C2: Float = 0.03 * 0.03;
+
+    let (a, b) = pixels(image, reference);
+    let Resolution { width, height } = image.resolution();
+    if a.is_empty() {
+        return 1.0;
+    }
+    let a: Vec<Float> = a.iter().map(|p| p.luma()).collect();
+    let b: Vec<Float> = b.iter().map(|p| p.luma()).collect();
+
+    let (w, h) = (WINDOW.min(width), WINDOW.min(height));
+    let step = (WINDOW / 2) as usize;
+    let n = (w * h) as Float;
+
+    let mut sum = 0.0;
+    let mut windows = 0;
+    for y0 in (0..=height - h).step_by(step) {
+        for x0 in (0..=width - w).step_by(step) {
+            let window = || {
+                (y0..y0 + h).flat_map(move |y| (x0..x0 + w).map(move |x| (y * width + x) as usize))
+            };
+            let mean_a = window().map(|i| a[i]).sum::<Float>() / n;
+            let mean_b = window().map(|i| b[i]).sum::<Float>() / n;
+            let (mut var_a, mut var_b, mut cov) = (0.0, 0.0, 0.0);
+            for i in window() {
+                let (da, db) = (a[i] - mean_a, b[i] - mean_b);
+                var_a += da * da;
+                var_b += db * db;
+                cov += da * db;
+            }
+            let (var_a, var_b, cov) = (var_a / n, var_b / n, cov / n);
+
+            sum += (2.0 * mean_a * mean_b + C1) * (2.0 * cov + C2)
+                / ((mean_a * mean_a + mean_b * mean_b + C1) * (var_a + var_b + C2));
+            windows += 1;
+        }
+    }
+
+    sum / windows as Float
+}
+
+/// A heatmap of the difference of the luma of `image` and
+/// `reference`, scaled by `scale`, going from black over red and
+/// yellow to white.
+///
+/// # Panics
+/// Panics if the resolutions of the images differ.
+///
+/// # Examples
+/// ```
+/// use lucifer::camera::{Resolution, Target};
+/// use lucifer::lighting::Radiance;
+/// use lucifer::render::compare::heatmap;
+/// use lucifer::render::Film;
+///
+/// let mut image = Film::new(Resolution::new(2, 1));
+/// image.add(Target::new(0, 0), Radiance::gray(0.5));
+/// image.add(Target::new(1, 0), Radiance::gray(1.0));
+/// let reference = Film::new(Resolution::new(2, 1));
+///
+/// let map = heatmap(&image, &reference, 1.0);
+/// assert_eq!(map.get(Target::new(0, 0)), Radiance::new(1.0, 0.5, 0.0));
+/// assert_eq!(map.get(Target::new(1, 0)), Radiance::gray(1.0));
+/// ```
+pub fn heatmap(image: &Film, reference: &Film, scale: Float) -> Film {
+    let (a, b) = pixels(image, reference);
+    let resolution = image.resolution();
+    let mut map = Film::new(resolution);
+
+    let ramp = |t: Float, offset: Float| (3.0 * t - offset).clamp(0.0, 1.0);
+    for (i, (a, b)) in a.iter().zip(&b).enumerate() {
+        let t = ((a.luma() - b.luma()).abs() * scale).min(1.0);
+        let target = Target::new(i as u32 % resolution.width, i as u32 / resolution.width);
+        map.add(
+            target,
+            Radiance::new(ramp(t, 0.0), ramp(t, 1.0), ramp(t, 2.0)),
+        );
+    }
+
+    map
+}
+
+/// Loads an image for comparison from a Radiance HDR (`.hdr`),
+/// Portable FloatMap (`.pfm`), or any low dynamic range file.  The
+/// pixels of low dynamic range files are taken as they are, between
+/// zero and one, without undoing any tonemapping.
+#[cfg(feature = "image")]
+pub fn load<P: AsRef<Path>>(path: P) -> ImageResult<Film> {
+    let path = path.as_ref();
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("pfm") => Ok(Film::read_pfm(&mut BufReader::new(File::open(path)?))?),
+        Some("hdr") => {
+            let decoder = HDRDecoder::new(BufReader::new(File::open(path)?))?;
+            let metadata = decoder.metadata();
+            let pixels = decoder.read_image_hdr()?;
+            let to_radiance = |p: &image::Rgb<f32>| {
+                Radiance::new(
+                    Float::from(p.data[0]),
+                    Float::from(p.data[1]),
+                    Float::from(p.data[2]),
+                )
+            };
+            Ok(film_of(
+                Resolution::new(metadata.width, metadata.height),
+                pixels.iter().map(to_radiance),
+            ))
+        }
+        _ => {
+            let rgb = image::open(path)?.to_rgb();
+            let level = |v: u8| Float::from(v) / 255.0;
+            Ok(film_of(
+                Resolution::new(rgb.width(), rgb.height()),
+                rgb.pixels()
+                    .map(|p| Radiance::new(level(p.data[0]), level(p.data[1]), level(p.data[2]))),
+            ))
+        }
+    }
+}
+
+/// A film of `pixels` in row-major order from the top.
+#[cfg(feature = "image")]
+fn film_of<I>(resolution: Resolution, pixels: I) -> Film
+where
+    I: Iterator<Item = Radiance>,
+{
+    let mut film = Film::new(resolution);
+    for (i, pixel) in pixels.enumerate() {
+        let i = i as u32;
+        film.add(
+            Target::new(i % resolution.width, i / resolution.width),
+            pixel,
+        );
+    }
+    film
+}
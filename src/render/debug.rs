@@ -1,38 +1,79 @@
 use cgmath::num_traits::clamp;
 
 use camera::{Camera, Resolution, Target};
-use geometry::{Intersection, Vector};
+use geometry::Vector;
 use lighting::Radiance;
-use render::Renderer;
-use scene::Scene;
+use render::{Context, Renderer};
+use scene::{Scene, ShadedIntersection};
+use Float;
+
+/// What the `DebugRenderer` shows.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum DebugMode {
+    /// Surface normals, darkening with distance.
+    #[default]
+    Normals,
+    /// A distinct color per object, e.g. to find the object producing
+    /// an artifact.
+    ObjectIds,
+}
+
+/// A pseudo-random but stable color for `id`.
+pub(crate) fn id_color(id: usize) -> Radiance {
+    // Spread consecutive ids over the color space (Knuth's
+    // multiplicative hash).
+    let hash = (id as u32).wrapping_add(1).wrapping_mul(2_654_435_761);
+    let channel = |shift: u32| ((hash >> shift) & 0xff) as Float / 255.0;
+    Radiance::new(channel(0), channel(8), channel(16))
+}
 
 #[derive(Clone, Debug, Default)]
-pub struct DebugRenderer {}
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct DebugRenderer {
+    pub mode: DebugMode,
+}
 
 impl DebugRenderer {
     pub fn new() -> DebugRenderer {
-        DebugRenderer {}
+        DebugRenderer::with_mode(DebugMode::Normals)
+    }
+
+    pub fn with_mode(mode: DebugMode) -> DebugRenderer {
+        DebugRenderer { mode }
     }
 
-    fn visualize(&mut self, intersection: &Intersection) -> Radiance {
-        let brightness = clamp(1.0 - intersection.lambda / 9.0, 0.0, 1.0);
-        let color = 0.5 * intersection.normal + Vector::new(0.5, 0.5, 0.5);
-        Radiance::from(color * brightness)
+    fn visualize(&self, hit: &ShadedIntersection) -> Radiance {
+        let intersection = &hit.intersection;
+        match self.mode {
+            DebugMode::Normals => {
+                let brightness = clamp(1.0 - intersection.lambda / 9.0, 0.0, 1.0);
+                let color = 0.5 * intersection.normal + Vector::new(0.5, 0.5, 0.5);
+                Radiance::from(color * brightness)
+            }
+            DebugMode::ObjectIds => id_color(hit.object),
+        }
     }
 }
 
 impl Renderer for DebugRenderer {
     fn render(
-        &mut self,
+        &self,
+        context: &mut Context,
         scene: &Scene,
-        camera: &Camera,
+        camera: &dyn Camera,
         resolution: Resolution,
         target: Target,
     ) -> Radiance {
         let ray = camera.primary(resolution, target);
+        context.statistics.paths += 1;
+        context.statistics.rays += 1;
         match scene.intersect(&ray) {
-            None => scene.background(),
-            Some(i) => self.visualize(&i.intersection),
+            None => scene.background(ray.direction),
+            Some(i) => {
+                context.statistics.path_vertices += 1;
+                self.visualize(&i)
+            }
         }
     }
 }
@@ -0,0 +1,275 @@
+use std::io::{self, Write};
+
+use camera::{Camera, Resolution, Target};
+use geometry::Ray;
+use lighting::{Effect, Radiance};
+use render::{single, Context, Film, Renderer};
+use scene::Scene;
+use Float;
+
+/// A single surface contributing to a deep pixel.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DeepSample {
+    /// The distance of the surface from the camera.
+    pub depth: Float,
+    /// The radiance leaving the surface towards the camera,
+    /// premultiplied by `alpha`.
+    pub radiance: Radiance,
+    /// The opacity of the surface.
+    pub alpha: Float,
+}
+
+/// An image storing, for every pixel, all surfaces along the camera
+/// ray, sorted by depth.
+#[derive(Clone, Debug)]
+pub struct DeepImage {
+    resolution: Resolution,
+    pixels: Vec<Vec<DeepSample>>,
+}
+
+impl DeepImage {
+    /// Creates a new `DeepImage` without any samples.
+    pub fn new(resolution: Resolution) -> DeepImage {
+        DeepImage {
+            resolution,
+            pixels: vec![Vec::new(); (resolution.width * resolution.height) as usize],
+        }
+    }
+
+    /// The `Resolution` of the image.
+    pub fn resolution(&self) -> Resolution {
+        self.resolution
+    }
+
+    /// The samples of pixel `target`, sorted front to back.
+    pub fn get(&self, target: Target) -> &[DeepSample] {
+        &self.pixels[(target.y * self.resolution.width + target.x) as usize]
+    }
+
+    /// Replaces the samples of pixel `target`.  The samples are
+    /// sorted by depth.
+    pub fn set(&mut self, target: Target, mut samples: Vec<DeepSample>) {
+        samples.sort_by(|a, b| a.depth.total_cmp(&b.depth));
+        self.pixels[(target.y * self.resolution.width + target.x) as usize] = samples;
+    }
+
+    /// Composites the samples of every pixel front to back into a
+    /// flat image.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::camera::{Resolution, Target};
+    /// use lucifer::lighting::Radiance;
+    /// use lucifer::render::deep::{DeepImage, DeepSample};
+    ///
+    /// let mut image = DeepImage::new(Resolution::new(1, 1));
+    /// image.set(Target::new(0, 0), vec![
+    ///     DeepSample { depth: 2.0, radiance: Radiance::gray(1.0), alpha: 1.0 },
+    ///     DeepSample { depth: 1.0, radiance: Radiance::gray(0.25), alpha: 0.5 },
+    /// ]);
+    ///
+    /// let film = image.flatten();
+    /// assert_eq!(film.get(Target::new(0, 0)), Radiance::gray(0.75));
+    /// ```
+    pub fn flatten(&self) -> Film {
+        let mut film = Film::new(self.resolution);
+
+        for y in 0..self.resolution.height {
+            for x in 0..self.resolution.width {
+                let target = Target::new(x, y);
+                let mut radiance = Radiance::none();
+                let mut transmittance = 1.0;
+
+                for sample in self.get(target) {
+                    radiance += transmittance * sample.radiance;
+                    transmittance *= 1.0 - sample.alpha;
+                }
+
+                film.add(target, radiance);
+            }
+        }
+
+        film
+    }
+
+    /// Writes the image in a simple binary deep format.
+    ///
+    /// The format starts with the magic bytes `LDEEP\0`, followed by
+    /// the width and height as `u32`.  For every pixel, in row-major
+    /// order, follows the number of samples as `u32` and, for every
+    /// sample, its depth, red, green, blue, and alpha as `f32`.  All
+    /// numbers are little-endian.
+    pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(b"LDEEP\0")?;
+        writer.write_all(&self.resolution.width.to_le_bytes())?;
+        writer.write_all(&self.resolution.height.to_le_bytes())?;
+
+        for pixel in &self.pixels {
+            writer.write_all(&(pixel.len() as u32).to_le_bytes())?;
+            for sample in pixel {
+                for v in &[
+                    sample.depth,
+                    sample.radiance[0],
+                    sample.radiance[1],
+                    sample.radiance[2],
+                    sample.alpha,
+                ] {
+                    writer.write_all(&single(*v).to_le_bytes())?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A `Camera` that always produces the same ray.
+struct RayCamera(Ray);
+
+impl Camera for RayCamera {
+    fn primary(&self, _: Resolution, _: Target) -> Ray {
+        self.0.clone()
+    }
+}
+
+/// Renders `DeepImage`s by finding all surfaces along each camera ray,
+/// with `Scene::intersect_each`, and shading each of them with a
+/// regular `Renderer`.
+///
+/// The opacity of a surface is derived from its refractive effects;
+/// surfaces without refraction are fully opaque.
+#[derive(Clone, Copy, Debug)]
+pub struct DeepRenderer {
+    /// The maximum number of samples per pixel.
+    pub max_samples: usize,
+}
+
+impl DeepRenderer {
+    pub fn new(max_samples: usize) -> DeepRenderer {
+        DeepRenderer { max_samples }
+    }
+
+    /// Computes the depth-sorted samples for a single pixel.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::camera::{AffineTransformCamera, Camera, Resolution, Target};
+    /// use lucifer::cgmath::{Matrix4, SquareMatrix};
+    /// use lucifer::geometry::Sphere;
+    /// use lucifer::lighting::{Albedo, Dielectric, Ior, Lambert, Radiance};
+    /// use lucifer::render::{Context, DeepRenderer, PathTracer};
+    /// use lucifer::scene::{Object, Scene};
+    ///
+    /// let camera = AffineTransformCamera::new(Matrix4::identity());
+    /// let res = Resolution::new(1, 1);
+    /// let ray = camera.primary(res, Target::new(0, 0));
+    ///
+    /// // A tiny glass bead in front of a wall.
+    /// let mut scene = Scene::new(Radiance::gray(1.0));
+    /// scene.add(Object::new(
+    ///     Sphere::new(ray.origin + ray.direction, 1e-5),
+    ///     Dielectric::new(Albedo::gray(0.5), Ior(1.5)),
+    ///     Matrix4::identity(),
+    /// ));
+    /// scene.add(Object::new(
+    ///     Sphere::new(ray.origin + ray.direction * 3.0, 0.5),
+    ///     Lambert::new(Albedo::gray(0.5)),
+    ///     Matrix4::identity(),
+    /// ));
+    ///
+    /// let renderer = PathTracer::new(0.01, 4, 4);
+    /// let deep = DeepRenderer::new(8);
+    /// let mut context = Context::seeded(7, 0);
+    /// let samples =
+    ///     deep.render_pixel(&renderer, &mut context, &scene, &camera, res, Target::new(0, 0));
+    ///
+    /// // Both sides of the bead, and the wall behind it.
+    /// let depths: Vec<_> = samples.iter().map(|sample| sample.depth).collect();
+    /// assert_eq!(depths.len(), 3);
+    /// assert!(depths[0] < 1.0 && 1.0 < depths[1] && depths[1] < 1.0001);
+    /// assert!((depths[2] - 2.5).abs() < 1e-4);
+    /// assert_eq!(samples[2].alpha, 1.0);
+    /// ```
+    pub fn render_pixel<R>(
+        &self,
+        renderer: &R,
+        context: &mut Context,
+        scene: &Scene,
+        camera: &dyn Camera,
+        resolution: Resolution,
+        target: Target,
+    ) -> Vec<DeepSample>
+    where
+        R: Renderer + ?Sized,
+    {
+        let mut samples = Vec::new();
+        let ray = camera.primary(resolution, target);
+        // Where the ray continues past the previous surface.
+        let mut start = 0.0;
+
+        scene.intersect_each(&ray, |hit| {
+            if samples.len() >= self.max_samples {
+                return false;
+            }
+
+            let transmission: Float = hit
+                .bsdf
+                .effects
+                .iter()
+                .map(|effect| match *effect {
+                    Effect::DiffuseRefraction(albedo, _, _)
+                    | Effect::SpecularRefraction(albedo, _, _) => albedo.luma_factor(),
+                    _ => 0.0,
+                })
+                .sum();
+            let alpha = 1.0 - transmission.min(1.0);
+
+            // Shade the surface as seen along the rest of the ray.
+            let layer = RayCamera(Ray {
+                origin: ray.origin + ray.direction * start,
+                direction: ray.direction,
+                length: ray.length - start,
+                time: ray.time,
+            });
+            let radiance = renderer.render(context, scene, &layer, resolution, target);
+
+            let depth = hit.intersection.lambda;
+            samples.push(DeepSample {
+                depth,
+                radiance: alpha * radiance,
+                alpha,
+            });
+
+            start = depth + hit.intersection.step(ray.direction);
+            alpha < 1.0
+        });
+
+        samples
+    }
+
+    /// Renders a full `DeepImage`.
+    pub fn render<R>(
+        &self,
+        renderer: &R,
+        context: &mut Context,
+        scene: &Scene,
+        camera: &dyn Camera,
+        resolution: Resolution,
+    ) -> DeepImage
+    where
+        R: Renderer + ?Sized,
+    {
+        let mut image = DeepImage::new(resolution);
+
+        for y in 0..resolution.height {
+            for x in 0..resolution.width {
+                let target = Target::new(x, y);
+                let samples =
+                    self.render_pixel(renderer, context, scene, camera, resolution, target);
+                image.set(target, samples);
+            }
+        }
+
+        image
+    }
+}
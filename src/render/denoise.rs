@@ -0,0 +1,134 @@
+use cgmath::{InnerSpace, Vector3, Zero};
+
+use camera::Target;
+use lighting::Radiance;
+use render::Film;
+use trace::Span;
+use Float;
+
+/// An edge-preserving filter removing noise from rendered images.
+///
+/// The `Denoiser` is a cross-bilateral filter: every pixel becomes a
+/// weighted average of its neighbourhood, where neighbours count less
+/// the farther away they are, and the more their color differs.
+/// Optional normal and albedo `Aov`s keep geometric and texture edges
+/// sharp, even where noise hides them in the image itself.
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct Denoiser {
+    /// The radius of the neighbourhood, in pixels.
+    pub radius: u32,
+    /// The falloff with distance, in pixels.
+    pub sigma_spatial: Float,
+    /// The falloff with the difference of the compressed colors.
+    pub sigma_color: Float,
+    /// The falloff with the difference of the normal `Aov`.
+    pub sigma_normal: Float,
+    /// The falloff with the difference of the albedo `Aov`.
+    pub sigma_albedo: Float,
+}
+
+impl Default for Denoiser {
+    fn default() -> Denoiser {
+        Denoiser {
+            radius: 5,
+            sigma_spatial: 3.0,
+            sigma_color: 1.0,
+            sigma_normal: 0.2,
+            sigma_albedo: 0.1,
+        }
+    }
+}
+
+/// Maps each channel into `[0, 1)`, so bright outliers don't
+/// dominate the color differences.
+fn compress(radiance: Radiance) -> Vector3<Float> {
+    let v = Vector3::from(radiance);
+    v.map(|c| c.max(0.0) / (1.0 + c.max(0.0)))
+}
+
+impl Denoiser {
+    /// Creates a new `Denoiser` with default parameters.
+    pub fn new() -> Denoiser {
+        Denoiser::default()
+    }
+
+    /// Sets the radius of the neighbourhood, in pixels.
+    pub fn radius(self, radius: u32) -> Self {
+        let mut denoiser = self;
+        denoiser.radius = radius;
+        denoiser
+    }
+
+    /// Filters `image`, guided by the `normal` and `albedo` `Aov`s
+    /// of the same resolution, if given.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::camera::{Resolution, Target};
+    /// use lucifer::lighting::Radiance;
+    /// use lucifer::render::{Denoiser, Film};
+    ///
+    /// let res = Resolution::new(8, 1);
+    /// let mut image = Film::new(res);
+    /// let mut normal = Film::new(res);
+    /// for x in 0..8 {
+    ///     let noise = if x % 2 == 0 { 0.1 } else { -0.1 };
+    ///     let value = if x < 4 { 0.5 + noise } else { 2.0 };
+    ///     image.add(Target::new(x, 0), Radiance::gray(value));
+    ///     normal.add(Target::new(x, 0), Radiance::gray(if x < 4 { 0.0 } else { 1.0 }));
+    /// }
+    ///
+    /// let denoised = Denoiser::new().denoise(&image, Some(&normal), None);
+    ///
+    /// // The noise is reduced, but the edge stays sharp.
+    /// let error = |film: &Film| (film.get(Target::new(2, 0)).luma() - 0.5).abs();
+    /// assert!(error(&denoised) < error(&image) / 2.0);
+    /// assert!(denoised.get(Target::new(3, 0)).luma() < 0.6);
+    /// ```
+    pub fn denoise(&self, image: &Film, normal: Option<&Film>, albedo: Option<&Film>) -> Film {
+        let _span = Span::enter("denoise");
+        let res = image.resolution();
+        let radius = self.radius as i64;
+        let falloff = |sigma: Float| 1.0 / (2.0 * sigma * sigma);
+        let guide = |film: Option<&Film>, sigma: Float, a: Target, b: Target| {
+            film.map_or(0.0, |film| {
+                let d = Vector3::from(film.get(a)) - Vector3::from(film.get(b));
+                d.magnitude2() * falloff(sigma)
+            })
+        };
+
+        let mut output = Film::new(res);
+        for y in 0..res.height {
+            for x in 0..res.width {
+                let center = Target::new(x, y);
+                let color = compress(image.get(center));
+
+                let mut sum = Vector3::zero();
+                let mut total = 0.0;
+                for dy in -radius..=radius {
+                    for dx in -radius..=radius {
+                        let (nx, ny) = (x as i64 + dx, y as i64 + dy);
+                        if nx < 0 || ny < 0 || nx >= res.width as i64 || ny >= res.height as i64 {
+                            continue;
+                        }
+                        let neighbour = Target::new(nx as u32, ny as u32);
+                        let value = image.get(neighbour);
+
+                        let exponent = (dx * dx + dy * dy) as Float * falloff(self.sigma_spatial)
+                            + (compress(value) - color).magnitude2() * falloff(self.sigma_color)
+                            + guide(normal, self.sigma_normal, center, neighbour)
+                            + guide(albedo, self.sigma_albedo, center, neighbour);
+                        let weight = (-exponent).exp();
+
+                        sum += Vector3::from(value) * weight;
+                        total += weight;
+                    }
+                }
+
+                output.add(center, Radiance::from(sum / total));
+            }
+        }
+        output
+    }
+}
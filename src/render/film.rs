@@ -0,0 +1,521 @@
+use std::io::{self, Read, Write};
+
+use camera::{Resolution, Target, Tonemap};
+use lighting::Radiance;
+use montecarlo::{Convergence, Moments};
+use render::{single, Histogram, ImageStatistics};
+use Float;
+
+/// An accumulation buffer for radiance estimates.
+///
+/// Each pixel stores the sum of all estimates added to it and their
+/// number, so that progressive renderers can keep refining an image
+/// over multiple passes.  It also tracks the `Moments` of every
+/// pixel's luma, to tell when a pixel has converged.
+#[derive(Clone, Debug)]
+pub struct Film {
+    resolution: Resolution,
+    sums: Vec<Radiance>,
+    counts: Vec<u32>,
+    moments: Vec<Moments>,
+}
+
+impl Film {
+    /// Creates a new, empty `Film` for the given `Resolution`.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::camera::{Resolution, Target};
+    /// use lucifer::lighting::Radiance;
+    /// use lucifer::render::Film;
+    ///
+    /// let mut film = Film::new(Resolution::new(2, 2));
+    /// film.add(Target::new(1, 0), Radiance::gray(1.0));
+    /// film.add(Target::new(1, 0), Radiance::gray(0.0));
+    ///
+    /// assert_eq!(film.get(Target::new(1, 0)), Radiance::gray(0.5));
+    /// assert_eq!(film.samples(Target::new(1, 0)), 2);
+    /// assert_eq!(film.get(Target::new(0, 0)), Radiance::none());
+    /// ```
+    pub fn new(resolution: Resolution) -> Film {
+        let size = (resolution.width * resolution.height) as usize;
+        Film {
+            resolution,
+            sums: vec![Radiance::none(); size],
+            counts: vec![0; size],
+            moments: vec![Moments::new(); size],
+        }
+    }
+
+    /// The `Resolution` of the film.
+    pub fn resolution(&self) -> Resolution {
+        self.resolution
+    }
+
+    fn index(&self, target: Target) -> usize {
+        (target.y * self.resolution.width + target.x) as usize
+    }
+
+    /// Adds a radiance estimate for pixel `target`.
+    pub fn add(&mut self, target: Target, radiance: Radiance) {
+        let i = self.index(target);
+        self.sums[i] += radiance;
+        self.counts[i] += 1;
+        self.moments[i] += radiance.luma();
+    }
+
+    /// The mean of all estimates for pixel `target`, or no radiance
+    /// if there are none.
+    pub fn get(&self, target: Target) -> Radiance {
+        let i = self.index(target);
+        match self.counts[i] {
+            0 => Radiance::none(),
+            n => self.sums[i] / n as Float,
+        }
+    }
+
+    /// The number of estimates added for pixel `target`.
+    pub fn samples(&self, target: Target) -> u32 {
+        self.counts[self.index(target)]
+    }
+
+    /// The `Moments` of the luma of all estimates for pixel `target`.
+    pub fn moments(&self, target: Target) -> Moments {
+        self.moments[self.index(target)]
+    }
+
+    /// Whether the estimates for pixel `target` satisfy `criterion`.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::camera::{Resolution, Target};
+    /// use lucifer::lighting::Radiance;
+    /// use lucifer::montecarlo::Convergence;
+    /// use lucifer::render::Film;
+    ///
+    /// let criterion = Convergence::new(0.95, 0.01);
+    /// let mut film = Film::new(Resolution::new(1, 1));
+    /// let target = Target::new(0, 0);
+    ///
+    /// for _ in 0..15 {
+    ///     film.add(target, Radiance::gray(0.5));
+    /// }
+    /// assert!(!film.converged(target, &criterion));
+    ///
+    /// film.add(target, Radiance::gray(0.5));
+    /// assert!(film.converged(target, &criterion));
+    /// ```
+    pub fn converged(&self, target: Target, criterion: &Convergence) -> bool {
+        criterion.is_converged(&self.moments[self.index(target)])
+    }
+
+    /// A film holding the variance of every pixel's mean luma, the
+    /// sample variance of its estimates divided by their number, as a
+    /// gray level.  Pixels with fewer than two estimates are black.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::camera::{Resolution, Target};
+    /// use lucifer::lighting::Radiance;
+    /// use lucifer::render::Film;
+    ///
+    /// let mut film = Film::new(Resolution::new(1, 1));
+    /// let target = Target::new(0, 0);
+    /// film.add(target, Radiance::gray(1.0));
+    /// film.add(target, Radiance::gray(0.0));
+    ///
+    /// // A sample variance of 0.5, over two estimates.
+    /// assert_eq!(film.variance().get(target), Radiance::gray(0.25));
+    /// ```
+    pub fn variance(&self) -> Film {
+        let mut variance = Film::new(self.resolution);
+        for (i, moments) in self.moments.iter().enumerate() {
+            let x = i as u32 % self.resolution.width;
+            let y = i as u32 / self.resolution.width;
+            let value = match moments.count() {
+                0 => 0.0,
+                n => moments.variance() / n as Float,
+            };
+            variance.add(Target::new(x, y), Radiance::gray(value));
+        }
+        variance
+    }
+
+    /// The number of estimates of all pixels, in row-major order.
+    pub fn sample_counts(&self) -> &[u32] {
+        &self.counts
+    }
+
+    /// The mean estimates of all pixels, in row-major order.
+    pub fn pixels(&self) -> Vec<Radiance> {
+        self.sums
+            .iter()
+            .zip(&self.counts)
+            .map(|(&sum, &n)| match n {
+                0 => Radiance::none(),
+                n => sum / n as Float,
+            })
+            .collect()
+    }
+
+    /// The mean, maximum, and number of black and invalid pixels.
+    pub fn statistics(&self) -> ImageStatistics {
+        ImageStatistics::from_pixels(&self.pixels())
+    }
+
+    /// A histogram of the luma of the pixels in `bins` bins, spanning
+    /// the stops `min` to `max`.
+    pub fn histogram(&self, bins: usize, min: Float, max: Float) -> Histogram {
+        let lumas: Vec<Float> = self.pixels().iter().map(|p| p.luma()).collect();
+        Histogram::from_lumas(&lumas, bins, min, max)
+    }
+
+    /// The luma below which the fraction `p` of the pixels lie.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::camera::{Resolution, Target};
+    /// use lucifer::lighting::Radiance;
+    /// use lucifer::render::Film;
+    ///
+    /// let mut film = Film::new(Resolution::new(4, 1));
+    /// for (x, &level) in [0.0, 0.25, 1.0, 4.0].iter().enumerate() {
+    ///     film.add(Target::new(x as u32, 0), Radiance::gray(level));
+    /// }
+    ///
+    /// assert!((film.percentile(0.5) - 0.25).abs() < 1e-6);
+    /// assert!((film.percentile(1.0) - 4.0).abs() < 1e-6);
+    ///
+    /// // Mapping the median to one clips the two brightest pixels.
+    /// let exposure = film.auto_exposure(0.5);
+    /// assert!((exposure - 4.0).abs() < 1e-4);
+    /// assert_eq!(film.clipped(exposure), 2);
+    /// assert_eq!(film.clipped(1.0), 1);
+    /// ```
+    pub fn percentile(&self, p: Float) -> Float {
+        let mut lumas: Vec<Float> = self
+            .pixels()
+            .iter()
+            .map(|p| p.luma())
+            .filter(|l| l.is_finite())
+            .collect();
+        if lumas.is_empty() {
+            return 0.0;
+        }
+        lumas.sort_by(Float::total_cmp);
+
+        let rank = (p.clamp(0.0, 1.0) * lumas.len() as Float).ceil() as usize;
+        lumas[rank.clamp(1, lumas.len()) - 1]
+    }
+
+    /// The exposure that maps the luma at percentile `p` to one,
+    /// e.g. `0.99` to clip about one percent of the pixels.  Black
+    /// images keep an exposure of one.
+    pub fn auto_exposure(&self, p: Float) -> Float {
+        match self.percentile(p) {
+            luma if luma > 0.0 => 1.0 / luma,
+            _ => 1.0,
+        }
+    }
+
+    /// The number of pixels with a channel beyond one when scaled by
+    /// `exposure`, which any tonemapping short of a curve clips.
+    pub fn clipped(&self, exposure: Float) -> usize {
+        self.pixels()
+            .iter()
+            .filter(|p| (0..3).any(|c| p[c] * exposure > 1.0))
+            .count()
+    }
+
+    /// The mean estimates scaled by `exposure` and mapped by
+    /// `tonemap`, as 8-bit RGBA pixels in row-major order from the
+    /// top, e.g. for an HTML canvas.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::camera::{Resolution, Target, Tonemap};
+    /// use lucifer::lighting::Radiance;
+    /// use lucifer::render::Film;
+    ///
+    /// let mut film = Film::new(Resolution::new(2, 1));
+    /// film.add(Target::new(1, 0), Radiance::new(0.5, 1.0, 2.0));
+    ///
+    /// let pixels = film.to_rgba8(1.0, &Tonemap::Linear);
+    ///
+    /// assert_eq!(pixels, vec![0, 0, 0, 255, 128, 255, 255, 255]);
+    /// ```
+    pub fn to_rgba8(&self, exposure: Float, tonemap: &Tonemap) -> Vec<u8> {
+        let byte = |c: Float| (tonemap.apply(c * exposure).clamp(0.0, 1.0) * 255.0).round() as u8;
+
+        let mut pixels = Vec::with_capacity(self.sums.len() * 4);
+        for y in 0..self.resolution.height {
+            for x in 0..self.resolution.width {
+                let radiance = self.get(Target::new(x, y));
+                pixels.extend_from_slice(&[
+                    byte(radiance[0]),
+                    byte(radiance[1]),
+                    byte(radiance[2]),
+                    255,
+                ]);
+            }
+        }
+        pixels
+    }
+
+    /// Writes the mean estimates in the Radiance HDR (`.hdr`) format,
+    /// with uncompressed RGBE scanlines from the top.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::camera::{Resolution, Target};
+    /// use lucifer::lighting::Radiance;
+    /// use lucifer::render::Film;
+    ///
+    /// let mut film = Film::new(Resolution::new(2, 1));
+    /// film.add(Target::new(0, 0), Radiance::gray(1.0));
+    ///
+    /// let mut bytes = Vec::new();
+    /// film.write_hdr(&mut bytes).unwrap();
+    ///
+    /// assert!(bytes.starts_with(b"#?RADIANCE\n"));
+    /// assert!(bytes.ends_with(b"-Y 1 +X 2\n\x80\x80\x80\x81\0\0\0\0"));
+    /// ```
+    pub fn write_hdr<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n")?;
+        writeln!(
+            writer,
+            "-Y {} +X {}",
+            self.resolution.height, self.resolution.width
+        )?;
+
+        for y in 0..self.resolution.height {
+            for x in 0..self.resolution.width {
+                writer.write_all(&rgbe(self.get(Target::new(x, y))))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes the mean estimates in the Portable FloatMap (`.pfm`)
+    /// format, as little-endian `f32` triples with the bottom row
+    /// first.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::camera::{Resolution, Target};
+    /// use lucifer::lighting::Radiance;
+    /// use lucifer::render::Film;
+    ///
+    /// let mut film = Film::new(Resolution::new(1, 2));
+    /// film.add(Target::new(0, 1), Radiance::new(0.5, 1.0, 2.0));
+    ///
+    /// let mut bytes = Vec::new();
+    /// film.write_pfm(&mut bytes).unwrap();
+    ///
+    /// assert!(bytes.starts_with(b"PF\n1 2\n-1.0\n"));
+    /// assert_eq!(bytes.len(), 12 + 2 * 12);
+    /// assert_eq!(&bytes[12..16], &0.5f32.to_le_bytes());
+    /// ```
+    pub fn write_pfm<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        write!(
+            writer,
+            "PF\n{} {}\n-1.0\n",
+            self.resolution.width, self.resolution.height
+        )?;
+
+        for y in (0..self.resolution.height).rev() {
+            for x in 0..self.resolution.width {
+                let radiance = self.get(Target::new(x, y));
+                for i in 0..3 {
+                    writer.write_all(&single(radiance[i]).to_le_bytes())?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reads an image in the Portable FloatMap (`.pfm`) format, e.g.
+    /// written by `write_pfm`, as a film with one estimate per pixel.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::camera::{Resolution, Target};
+    /// use lucifer::lighting::Radiance;
+    /// use lucifer::render::Film;
+    ///
+    /// let mut film = Film::new(Resolution::new(2, 3));
+    /// film.add(Target::new(1, 0), Radiance::new(0.5, 1.0, 2.0));
+    ///
+    /// let mut bytes = Vec::new();
+    /// film.write_pfm(&mut bytes).unwrap();
+    /// let read = Film::read_pfm(&mut &bytes[..]).unwrap();
+    ///
+    /// assert_eq!(read.resolution(), Resolution::new(2, 3));
+    /// assert_eq!(read.get(Target::new(1, 0)), Radiance::new(0.5, 1.0, 2.0));
+    /// ```
+    pub fn read_pfm<R: Read>(reader: &mut R) -> io::Result<Film> {
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message);
+
+        // The header is three lines: the kind, the size, and a scale
+        // whose sign gives the byte order.
+        let mut header = Vec::new();
+        let mut byte = [0; 1];
+        while header.iter().filter(|&&b| b == b'\n').count() < 3 {
+            reader.read_exact(&mut byte)?;
+            header.push(byte[0]);
+            if header.len() > 256 {
+                return Err(invalid("not a PFM image"));
+            }
+        }
+        let header = String::from_utf8_lossy(&header);
+        let fields: Vec<&str> = header.split_whitespace().collect();
+        let (width, height, scale) = match fields[..] {
+            ["PF", width, height, scale] => (width.parse(), height.parse(), scale.parse()),
+            _ => return Err(invalid("not a color PFM image")),
+        };
+        let (width, height, scale): (u32, u32, f32) = match (width, height, scale) {
+            (Ok(width), Ok(height), Ok(scale)) => (width, height, scale),
+            _ => return Err(invalid("invalid PFM header")),
+        };
+
+        let mut film = Film::new(Resolution::new(width, height));
+        let mut word = [0; 4];
+        for y in (0..height).rev() {
+            for x in 0..width {
+                let mut rgb = [0.0; 3];
+                for c in &mut rgb {
+                    reader.read_exact(&mut word)?;
+                    let value = if scale < 0.0 {
+                        f32::from_le_bytes(word)
+                    } else {
+                        f32::from_be_bytes(word)
+                    };
+                    *c = Float::from(value);
+                }
+                film.add(Target::new(x, y), Radiance::new(rgb[0], rgb[1], rgb[2]));
+            }
+        }
+
+        Ok(film)
+    }
+
+    /// Writes the complete state of the film, so that rendering can
+    /// later be resumed from it with `read_checkpoint`.
+    ///
+    /// The format starts with the magic bytes `LFILM\0`, followed by
+    /// the width and height as `u32`.  For every pixel, in row-major
+    /// order, follow the red, green, and blue sums as `f32`, the number
+    /// of estimates as `u32`, and the luma moments (count as `u32`,
+    /// mean and sum of squared differences as `f32`).  All numbers are
+    /// little-endian.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::camera::{Resolution, Target};
+    /// use lucifer::lighting::Radiance;
+    /// use lucifer::render::Film;
+    ///
+    /// let mut film = Film::new(Resolution::new(3, 2));
+    /// film.add(Target::new(2, 1), Radiance::gray(1.0));
+    /// film.add(Target::new(2, 1), Radiance::gray(0.5));
+    ///
+    /// let mut bytes = Vec::new();
+    /// film.write_checkpoint(&mut bytes).unwrap();
+    /// let resumed = Film::read_checkpoint(&mut &bytes[..]).unwrap();
+    ///
+    /// assert_eq!(resumed.get(Target::new(2, 1)), Radiance::gray(0.75));
+    /// assert_eq!(resumed.samples(Target::new(2, 1)), 2);
+    /// assert_eq!(resumed.moments(Target::new(2, 1)), film.moments(Target::new(2, 1)));
+    /// ```
+    pub fn write_checkpoint<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(CHECKPOINT_MAGIC)?;
+        writer.write_all(&self.resolution.width.to_le_bytes())?;
+        writer.write_all(&self.resolution.height.to_le_bytes())?;
+
+        for ((sum, count), moments) in self.sums.iter().zip(&self.counts).zip(&self.moments) {
+            for i in 0..3 {
+                writer.write_all(&single(sum[i]).to_le_bytes())?;
+            }
+            writer.write_all(&count.to_le_bytes())?;
+            writer.write_all(&moments.n.to_le_bytes())?;
+            writer.write_all(&single(moments.mean).to_le_bytes())?;
+            writer.write_all(&single(moments.m2).to_le_bytes())?;
+        }
+
+        Ok(())
+    }
+
+    /// Reads a film written by `write_checkpoint`.
+    pub fn read_checkpoint<R: Read>(reader: &mut R) -> io::Result<Film> {
+        let mut magic = [0; 6];
+        reader.read_exact(&mut magic)?;
+        if &magic != CHECKPOINT_MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not a film checkpoint",
+            ));
+        }
+
+        let mut word = [0; 4];
+        let mut read_u32 = |reader: &mut R| -> io::Result<u32> {
+            reader.read_exact(&mut word)?;
+            Ok(u32::from_le_bytes(word))
+        };
+
+        let width = read_u32(reader)?;
+        let height = read_u32(reader)?;
+        let mut film = Film::new(Resolution::new(width, height));
+
+        for i in 0..film.counts.len() {
+            let r = f32::from_bits(read_u32(reader)?) as Float;
+            let g = f32::from_bits(read_u32(reader)?) as Float;
+            let b = f32::from_bits(read_u32(reader)?) as Float;
+            film.sums[i] = Radiance::new(r, g, b);
+            film.counts[i] = read_u32(reader)?;
+            film.moments[i] = Moments {
+                n: read_u32(reader)?,
+                mean: f32::from_bits(read_u32(reader)?) as Float,
+                m2: f32::from_bits(read_u32(reader)?) as Float,
+            };
+        }
+
+        Ok(film)
+    }
+
+    /// Discards all estimates.
+    pub fn clear(&mut self) {
+        for sum in &mut self.sums {
+            *sum = Radiance::none();
+        }
+        for count in &mut self.counts {
+            *count = 0;
+        }
+        for moments in &mut self.moments {
+            *moments = Moments::new();
+        }
+    }
+}
+
+const CHECKPOINT_MAGIC: &[u8; 6] = b"LFILM\0";
+
+/// Encodes `radiance` as shared-exponent RGBE.
+fn rgbe(radiance: Radiance) -> [u8; 4] {
+    let (r, g, b) = (radiance[0], radiance[1], radiance[2]);
+    let v = r.max(g).max(b);
+    if v.is_nan() || v < 1e-32 {
+        return [0; 4];
+    }
+
+    // The exponent `e` with `v < 2^e`, i.e. the one of frexp.
+    let mut e = v.log2().floor() as i32 + 1;
+    if v >= Float::powi(2.0, e) {
+        e += 1;
+    }
+    let e = e.min(127);
+    let scale = 256.0 / Float::powi(2.0, e);
+    let encode = |c: Float| (c.max(0.0) * scale).min(255.0) as u8;
+
+    [encode(r), encode(g), encode(b), (e + 128) as u8]
+}
@@ -0,0 +1,132 @@
+//! The interiors of nested and overlapping objects along a path.
+//!
+//! Paths entering an object push its interior, with the refractive
+//! index of its material and the medium filling it, onto a stack, and
+//! pop it again when leaving.  Refraction then bends paths by the
+//! ratio of the refractive indices on either side of a surface, e.g.
+//! of glass and water for a glass submerged in a pool, instead of
+//! assuming that one side is vacuum.
+//!
+//! Overlapping objects are resolved by their `Object::priority`, after
+//! "Simple Nested Dielectrics in Ray Traced Images" by Schmidt and
+//! Budge: paths are within the interior of highest priority, and the
+//! surfaces of other objects within it are false hits, which the path
+//! passes straight through, only noting that it entered or left them.
+//! The water in a glass can thus fill the glass up to and a little
+//! into its walls, without a gap of air between the two.  Opaque
+//! objects have no interior, and their surfaces are always hit.
+
+use smallvec::SmallVec;
+
+use lighting::{Ior, Medium};
+
+/// The interior of an object a path has entered.
+#[derive(Clone, Copy, Debug)]
+pub struct Interior {
+    /// The index of the object in the scene.
+    pub object: usize,
+    /// The priority of the object; see `Object::priority`.
+    pub priority: u32,
+    /// The refractive index of the object's material.
+    pub ior: Ior,
+    /// The medium filling the object.
+    pub medium: Option<Medium>,
+}
+
+/// The interiors a path is within, in the order it entered them.
+///
+/// # Examples
+/// ```
+/// use lucifer::lighting::{Ior, Medium};
+/// use lucifer::render::interior::{Interior, InteriorStack};
+///
+/// // A glass, with water filling it up to the middle of its walls.
+/// let glass = Interior { object: 0, priority: 1, ior: Ior(1.5), medium: None };
+/// let water = Interior { object: 1, priority: 0, ior: Ior(1.33), medium: Some(Medium::new(0.1, 0.0)) };
+///
+/// let air = InteriorStack::new(None);
+/// assert_eq!(air.ior().0, 1.0);
+///
+/// // Into the wall of the glass, where the surface of the water is
+/// // hidden.
+/// assert!(!air.hides(glass.object, glass.priority));
+/// let wall = air.enter(glass);
+/// assert!(wall.hides(water.object, water.priority));
+/// let wall = wall.enter(water);
+/// assert_eq!(wall.ior().0, 1.5);
+/// assert_eq!(wall.medium(), None);
+///
+/// // Out of the wall into the water.
+/// assert!(!wall.hides(glass.object, glass.priority));
+/// let pool = wall.leave(glass.object).unwrap();
+/// assert_eq!(pool.ior().0, 1.33);
+/// assert_eq!(pool.medium(), water.medium);
+/// assert!(pool.leave(glass.object).is_none());
+/// ```
+#[derive(Clone, Debug)]
+pub struct InteriorStack {
+    interiors: SmallVec<[Interior; 4]>,
+    /// The medium outside of all interiors.
+    outside: Option<Medium>,
+}
+
+impl InteriorStack {
+    /// Creates a new `InteriorStack` for paths starting outside of all
+    /// objects, in the `outside` medium.
+    pub fn new(outside: Option<Medium>) -> Self {
+        InteriorStack {
+            interiors: SmallVec::new(),
+            outside,
+        }
+    }
+
+    /// The interior the path is in, the one of highest priority, or of
+    /// those the last entered.
+    pub fn current(&self) -> Option<&Interior> {
+        // `max_by_key` returns the last of equal elements.
+        self.interiors.iter().max_by_key(|i| i.priority)
+    }
+
+    /// The medium the path is in.
+    pub fn medium(&self) -> Option<Medium> {
+        self.current().map_or(self.outside, |i| i.medium)
+    }
+
+    /// The refractive index of the interior the path is in, `1` outside
+    /// of all objects.
+    pub fn ior(&self) -> Ior {
+        self.current().map_or(Ior(1.0), |i| i.ior)
+    }
+
+    /// Whether a surface of `object`, with `priority`, lies within an
+    /// interior of higher priority, making it a false hit.
+    pub fn hides(&self, object: usize, priority: u32) -> bool {
+        self.interiors
+            .iter()
+            .any(|i| i.object != object && i.priority > priority)
+    }
+
+    /// The interiors after entering `interior`.
+    pub fn enter(&self, interior: Interior) -> InteriorStack {
+        let mut stack = self.clone();
+        stack.interiors.push(interior);
+        stack
+    }
+
+    /// The interiors after leaving `object`, or `None` if the path was
+    /// not known to be within it, e.g. as it started there.
+    pub fn leave(&self, object: usize) -> Option<InteriorStack> {
+        let position = self.interiors.iter().rposition(|i| i.object == object)?;
+        let mut stack = self.clone();
+        stack.interiors.remove(position);
+        Some(stack)
+    }
+
+    /// The interiors with a different medium outside of all of them.
+    pub fn with_outside(&self, outside: Option<Medium>) -> InteriorStack {
+        InteriorStack {
+            interiors: self.interiors.clone(),
+            outside,
+        }
+    }
+}
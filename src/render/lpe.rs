@@ -0,0 +1,411 @@
+//! Light path expressions, selecting the paths of light by the
+//! events along them.
+//!
+//! A path is the sequence of events from the camera to where its light
+//! was emitted, each with a type and, for scattering, how the light
+//! was scattered:
+//!
+//! | Event | Type                             | Scattering                        |
+//! |-------|----------------------------------|-----------------------------------|
+//! | `C`   | camera                           |                                   |
+//! | `R`   | reflection                       | `D`iffuse, `G`lossy, or `S`ingular |
+//! | `T`   | transmission                     | `D`iffuse, `G`lossy, or `S`ingular |
+//! | `V`   | scattering in a volume           |                                   |
+//! | `L`   | emission by a light or a surface |                                   |
+//! | `B`   | the background                   |                                   |
+//!
+//! Expressions follow those of the Open Shading Language.  An event
+//! is matched by `<` its type and scattering `>`, each a letter, `.`
+//! for any, or a set of letters in brackets, optionally negated with
+//! `^`.  Outside of angle brackets, a letter matches events of that
+//! type or scattering, `.` any event, and `[RT]` or `[^S]` a set of
+//! them.  Events are combined with parentheses, `|` for alternatives,
+//! and `*`, `+`, and `?` for repetitions.  Whitespace is ignored.
+//!
+//! # Examples
+//! ```
+//! use lucifer::render::lpe::{Event, LightPathExpression};
+//!
+//! let caustics = LightPathExpression::parse("C <.D> <.S>+ L").unwrap();
+//!
+//! let (diffuse, mirror) = (Event::new('R', 'D'), Event::new('R', 'S'));
+//! let path = [Event::CAMERA, diffuse, mirror, Event::LIGHT];
+//! assert!(caustics.matches(&path));
+//!
+//! let direct = [Event::CAMERA, diffuse, Event::LIGHT];
+//! assert!(!caustics.matches(&direct));
+//! ```
+
+use std::str::FromStr;
+
+use Error;
+
+/// Something happening to light along its path.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Event {
+    /// The type of event, one of `C`, `R`, `T`, `V`, `L`, and `B`.
+    pub kind: char,
+    /// How light is scattered by reflections and transmissions, one
+    /// of `D`, `G`, and `S`.
+    pub scattering: Option<char>,
+}
+
+impl Event {
+    /// The start of every path.
+    pub const CAMERA: Event = Event {
+        kind: 'C',
+        scattering: None,
+    };
+    /// The emission of light by a light source or emissive surface.
+    pub const LIGHT: Event = Event {
+        kind: 'L',
+        scattering: None,
+    };
+    /// The end of paths leaving the scene.
+    pub const BACKGROUND: Event = Event {
+        kind: 'B',
+        scattering: None,
+    };
+    /// The scattering of light within a participating medium.
+    pub const VOLUME: Event = Event {
+        kind: 'V',
+        scattering: None,
+    };
+
+    /// Creates a new scattering `Event` of type `kind`.
+    pub fn new(kind: char, scattering: char) -> Self {
+        Event {
+            kind,
+            scattering: Some(scattering),
+        }
+    }
+}
+
+/// A set of letters, matching any of them, or none if negated.
+#[derive(Clone, Debug, PartialEq)]
+enum Class {
+    Any,
+    Set(Vec<char>, bool),
+}
+
+impl Class {
+    fn contains(&self, letter: Option<char>) -> bool {
+        match *self {
+            Class::Any => true,
+            Class::Set(ref letters, negated) => {
+                letter.is_some_and(|l| letters.contains(&l)) != negated
+            }
+        }
+    }
+}
+
+/// What an event must look like to be matched.
+#[derive(Clone, Debug, PartialEq)]
+enum Symbol {
+    /// Events whose type or scattering is in the class.
+    Either(Class),
+    /// Events whose type and scattering are in the classes.
+    Both(Class, Class),
+}
+
+impl Symbol {
+    fn matches(&self, event: Event) -> bool {
+        match *self {
+            Symbol::Either(Class::Set(ref letters, true)) => {
+                !letters.contains(&event.kind)
+                    && !event.scattering.is_some_and(|s| letters.contains(&s))
+            }
+            Symbol::Either(ref class) => {
+                class.contains(Some(event.kind)) || class.contains(event.scattering)
+            }
+            Symbol::Both(ref kind, ref scattering) => {
+                kind.contains(Some(event.kind)) && scattering.contains(event.scattering)
+            }
+        }
+    }
+}
+
+/// The syntax tree of an expression.
+#[derive(Debug)]
+enum Node {
+    Symbol(Symbol),
+    Sequence(Vec<Node>),
+    Alternatives(Vec<Node>),
+    Star(Box<Node>),
+    Plus(Box<Node>),
+    Optional(Box<Node>),
+}
+
+/// A state of the automaton matching an expression.
+#[derive(Clone, Debug, PartialEq)]
+enum State {
+    Accept,
+    /// Moves on to the state on an event matching the symbol.
+    Match(Symbol, usize),
+    /// Moves on to all of the states without an event.
+    Split(Vec<usize>),
+}
+
+/// The states reached by the events of a path so far.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PathState(u64);
+
+impl PathState {
+    /// Whether no continuation of the path can match.
+    pub fn is_dead(self) -> bool {
+        self.0 == 0
+    }
+}
+
+/// An expression matching paths by their events.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LightPathExpression {
+    /// The automaton, with at most 64 states.
+    states: Vec<State>,
+    start: usize,
+}
+
+impl LightPathExpression {
+    /// Parses the light path expression `expression`, failing if it is
+    /// malformed or too long.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::render::lpe::LightPathExpression;
+    ///
+    /// assert!(LightPathExpression::parse("C (<R.>|<T[DG]>)* [LB]").is_ok());
+    /// assert!(LightPathExpression::parse("C <RD L").is_err());
+    /// assert!(LightPathExpression::parse("C (D L").is_err());
+    /// ```
+    pub fn parse(expression: &str) -> Result<Self, Error> {
+        let letters: Vec<char> = expression.chars().filter(|c| !c.is_whitespace()).collect();
+        let mut parser = Parser {
+            letters: &letters,
+            at: 0,
+        };
+        let node = parser.alternatives()?;
+        if parser.at < letters.len() {
+            return Err(parser.error("unexpected"));
+        }
+
+        let mut states = vec![State::Accept];
+        let start = compile(&node, 0, &mut states);
+        if states.len() > 64 {
+            return Err(Error::Parse(format!(
+                "light path expression {} is too long",
+                expression
+            )));
+        }
+
+        Ok(LightPathExpression { states, start })
+    }
+
+    /// The state of a path without any events.
+    pub fn start(&self) -> PathState {
+        self.closure(1 << self.start)
+    }
+
+    /// The state of a path in `state` followed by `event`.
+    pub fn advance(&self, state: PathState, event: Event) -> PathState {
+        let mut next = 0;
+        for (i, s) in self.states.iter().enumerate() {
+            if let State::Match(ref symbol, to) = *s {
+                if state.0 & (1 << i) != 0 && symbol.matches(event) {
+                    next |= 1 << to;
+                }
+            }
+        }
+        self.closure(next)
+    }
+
+    /// Whether a path in `state` is matched.
+    pub fn accepts(&self, state: PathState) -> bool {
+        state.0 & 1 != 0
+    }
+
+    /// Whether the path made of `events` is matched.
+    pub fn matches(&self, events: &[Event]) -> bool {
+        let state = events
+            .iter()
+            .fold(self.start(), |state, &event| self.advance(state, event));
+        self.accepts(state)
+    }
+
+    /// The states reachable from `states` without events.
+    fn closure(&self, states: u64) -> PathState {
+        let mut reached = states;
+        let mut pending: Vec<usize> = (0..self.states.len())
+            .filter(|i| states & (1 << i) != 0)
+            .collect();
+        while let Some(i) = pending.pop() {
+            if let State::Split(ref targets) = self.states[i] {
+                for &t in targets {
+                    if reached & (1 << t) == 0 {
+                        reached |= 1 << t;
+                        pending.push(t);
+                    }
+                }
+            }
+        }
+        PathState(reached)
+    }
+}
+
+impl FromStr for LightPathExpression {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        LightPathExpression::parse(s)
+    }
+}
+
+/// Adds the states matching `node` followed by the state `next` to
+/// `states`, returning the first of them.
+fn compile(node: &Node, next: usize, states: &mut Vec<State>) -> usize {
+    let push = |states: &mut Vec<State>, state| {
+        states.push(state);
+        states.len() - 1
+    };
+
+    match *node {
+        Node::Symbol(ref symbol) => push(states, State::Match(symbol.clone(), next)),
+        Node::Sequence(ref nodes) => nodes
+            .iter()
+            .rev()
+            .fold(next, |next, node| compile(node, next, states)),
+        Node::Alternatives(ref nodes) => {
+            let starts = nodes.iter().map(|n| compile(n, next, states)).collect();
+            push(states, State::Split(starts))
+        }
+        Node::Star(ref node) => {
+            let split = push(states, State::Split(Vec::new()));
+            let body = compile(node, split, states);
+            states[split] = State::Split(vec![body, next]);
+            split
+        }
+        Node::Plus(ref node) => {
+            let split = push(states, State::Split(Vec::new()));
+            let body = compile(node, split, states);
+            states[split] = State::Split(vec![body, next]);
+            body
+        }
+        Node::Optional(ref node) => {
+            let body = compile(node, next, states);
+            push(states, State::Split(vec![body, next]))
+        }
+    }
+}
+
+/// A recursive descent parser of expressions without whitespace.
+struct Parser<'a> {
+    letters: &'a [char],
+    at: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<char> {
+        self.letters.get(self.at).cloned()
+    }
+
+    fn error(&self, problem: &str) -> Error {
+        match self.peek() {
+            Some(c) => Error::Parse(format!(
+                "{} '{}' in light path expression at {}",
+                problem, c, self.at
+            )),
+            None => Error::Parse("unexpected end of light path expression".to_string()),
+        }
+    }
+
+    fn expect(&mut self, letter: char) -> Result<(), Error> {
+        if self.peek() == Some(letter) {
+            self.at += 1;
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{}' instead of", letter)))
+        }
+    }
+
+    fn alternatives(&mut self) -> Result<Node, Error> {
+        let mut nodes = vec![self.sequence()?];
+        while self.peek() == Some('|') {
+            self.at += 1;
+            nodes.push(self.sequence()?);
+        }
+        Ok(match nodes.len() {
+            1 => nodes.remove(0),
+            _ => Node::Alternatives(nodes),
+        })
+    }
+
+    fn sequence(&mut self) -> Result<Node, Error> {
+        let mut nodes = Vec::new();
+        while let Some(c) = self.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+            nodes.push(self.repetition()?);
+        }
+        Ok(Node::Sequence(nodes))
+    }
+
+    fn repetition(&mut self) -> Result<Node, Error> {
+        let mut node = self.atom()?;
+        loop {
+            node = match self.peek() {
+                Some('*') => Node::Star(Box::new(node)),
+                Some('+') => Node::Plus(Box::new(node)),
+                Some('?') => Node::Optional(Box::new(node)),
+                _ => return Ok(node),
+            };
+            self.at += 1;
+        }
+    }
+
+    fn atom(&mut self) -> Result<Node, Error> {
+        match self.peek() {
+            Some('(') => {
+                self.at += 1;
+                let node = self.alternatives()?;
+                self.expect(')')?;
+                Ok(node)
+            }
+            Some('<') => {
+                self.at += 1;
+                let kind = self.class()?;
+                let scattering = self.class()?;
+                self.expect('>')?;
+                Ok(Node::Symbol(Symbol::Both(kind, scattering)))
+            }
+            _ => Ok(Node::Symbol(Symbol::Either(self.class()?))),
+        }
+    }
+
+    fn class(&mut self) -> Result<Class, Error> {
+        match self.peek() {
+            Some('.') => {
+                self.at += 1;
+                Ok(Class::Any)
+            }
+            Some('[') => {
+                self.at += 1;
+                let negated = self.peek() == Some('^');
+                if negated {
+                    self.at += 1;
+                }
+                let mut letters = Vec::new();
+                while let Some(c) = self.peek().filter(char::is_ascii_alphabetic) {
+                    letters.push(c);
+                    self.at += 1;
+                }
+                self.expect(']')?;
+                Ok(Class::Set(letters, negated))
+            }
+            Some(c) if c.is_ascii_alphabetic() => {
+                self.at += 1;
+                Ok(Class::Set(vec![c], false))
+            }
+            _ => Err(self.error("unexpected")),
+        }
+    }
+}
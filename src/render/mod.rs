@@ -1,21 +1,142 @@
+use rand::{RngCore, SeedableRng, XorShiftRng};
+
 use camera::{Camera, Resolution, Target};
 use lighting::Radiance;
 use scene::Scene;
+use Float;
 
+pub mod aov;
+pub mod compare;
 pub mod debug;
+pub mod deep;
+pub mod denoise;
+pub mod film;
+pub mod interior;
+pub mod lpe;
 pub mod path;
+pub mod preview;
+pub mod probe;
 pub mod ray;
+pub mod remote;
+pub mod restir;
+pub mod stats;
+pub mod tiled;
 
-pub use self::debug::DebugRenderer;
-pub use self::path::PathTracer;
+pub use self::aov::{Aov, AovRenderer};
+pub use self::debug::{DebugMode, DebugRenderer};
+pub use self::deep::{DeepImage, DeepRenderer};
+pub use self::denoise::Denoiser;
+pub use self::film::Film;
+pub use self::path::{FilteredPathTracer, PathTracer};
+pub use self::preview::Preview;
 pub use self::ray::RayTracer;
+pub use self::remote::{Coordinator, Worker};
+pub use self::restir::Restir;
+pub use self::stats::{
+    Histogram, ImageStatistics, Progress, Report, SampleDistribution, Statistics,
+};
+pub use self::tiled::{Tile, TiledRenderer};
+
+/// Narrows `value` to the `f32` stored in files and sent over the
+/// network, whatever the precision of `Float`.
+#[allow(clippy::unnecessary_cast)]
+pub(crate) fn single(value: Float) -> f32 {
+    value as f32
+}
+
+/// Per-thread state used while rendering.
+///
+/// A `Context` owns the random number generator and collects the
+/// `Statistics` of all renders performed with it, allowing
+/// `Renderer`s themselves to be immutable and shared between threads.
+pub struct Context {
+    /// The source of random numbers.
+    pub rng: Box<dyn RngCore + Send>,
+    /// The counters collected so far.
+    pub statistics: Statistics,
+}
+
+impl Context {
+    /// Creates a new `Context` using the given random number
+    /// generator.
+    pub fn new<R>(rng: R) -> Context
+    where
+        R: RngCore + Send + 'static,
+    {
+        Context {
+            rng: Box::new(rng),
+            statistics: Statistics::new(),
+        }
+    }
+
+    /// Creates a new `Context` whose random number stream is fully
+    /// determined by `seed` and `stream`.  Drivers use this to give
+    /// every tile (or pixel) its own reproducible stream, independent
+    /// of the order in which the work is performed.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::rand::RngCore;
+    /// use lucifer::render::Context;
+    ///
+    /// let a = Context::seeded(42, 7).rng.next_u64();
+    /// let b = Context::seeded(42, 7).rng.next_u64();
+    /// let c = Context::seeded(42, 8).rng.next_u64();
+    ///
+    /// assert_eq!(a, b);
+    /// assert_ne!(a, c);
+    /// ```
+    pub fn seeded(seed: u64, stream: u64) -> Context {
+        let mut state = seed;
+        state = splitmix64(&mut state) ^ stream;
+
+        let mut bytes = [0; 16];
+        bytes[..8].copy_from_slice(&splitmix64(&mut state).to_le_bytes());
+        bytes[8..].copy_from_slice(&splitmix64(&mut state).to_le_bytes());
+
+        Context::new(XorShiftRng::from_seed(bytes))
+    }
+
+    /// Returns the `Statistics` collected so far and resets them.
+    pub fn take_statistics(&mut self) -> Statistics {
+        ::std::mem::take(&mut self.statistics)
+    }
+}
+
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
 
 pub trait Renderer {
     fn render(
-        &mut self,
+        &self,
+        context: &mut Context,
         scene: &Scene,
-        camera: &Camera,
+        camera: &dyn Camera,
         resolution: Resolution,
         target: Target,
     ) -> Radiance;
+
+    /// Renders `target` in the views of all `cameras`, which share a
+    /// `resolution`, e.g. the two eyes of a stereo pair.  Renderers
+    /// may share work between the views, such as tracing their
+    /// camera rays through the scene together; by default, every
+    /// view is rendered on its own.
+    fn render_views(
+        &self,
+        context: &mut Context,
+        scene: &Scene,
+        cameras: &[&dyn Camera],
+        resolution: Resolution,
+        target: Target,
+    ) -> Vec<Radiance> {
+        cameras
+            .iter()
+            .map(|&camera| self.render(context, scene, camera, resolution, target))
+            .collect()
+    }
 }
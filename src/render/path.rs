@@ -1,28 +1,491 @@
-use std::f32::consts::PI;
-
-use cgmath::{dot, vec3, InnerSpace, Point3, Vector3};
-use rand::Rng;
-
 use camera::{Camera, Resolution, Target};
-use geometry::Ray;
-use lighting::{Albedo, Effect, Radiance};
-use montecarlo::{Estimator, Sample};
-use render::Renderer;
-use scene::Scene;
+use cgmath::{dot, vec3, InnerSpace, Vector3};
+use consts::PI;
+use geometry::{Intersection, Ray};
+use lighting::color::{sample_wavelength, Spectrum, Upsampled, Xyz};
+use lighting::{Albedo, Bsdf, Distribution, Effect, Ior, Medium, Mueller, Radiance};
+use montecarlo::pdf::solid_angle_to_hemisphere;
+use montecarlo::sobol::Sobol;
+use montecarlo::{power_heuristic, Estimator, Sample};
+use rand::Rng;
+use render::interior::{Interior, InteriorStack};
+use render::lpe::{Event, LightPathExpression, PathState};
+use render::{Context, Renderer};
+use scene::{Scene, ShadedIntersection};
+use Error;
+use Float;
 
+/// A Monte Carlo path tracer.
+///
+/// `PathTracer` only holds immutable settings; all sampling state is
+/// kept in the `Context` passed to `render`, so a single tracer can be
+/// shared by any number of threads.
 #[derive(Clone, Copy, Debug)]
-pub struct PathTracer<R: Rng> {
-    pub rng: R,
-    pub contribution_limit: f32,
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct PathTracer {
+    pub contribution_limit: Float,
     pub depth_limit: u8,
     pub samples: u32,
+    /// Whether to trace the polarization of light; see
+    /// `with_polarization`.
+    #[cfg_attr(feature = "serialize", serde(default))]
+    pub polarized: bool,
+    /// Whether to trace light of single wavelengths; see
+    /// `with_spectral`.
+    #[cfg_attr(feature = "serialize", serde(default))]
+    pub spectral: bool,
+}
+
+/// A `PathTracer` counting only the light of paths matching a
+/// `LightPathExpression`, e.g. to render the caustics of a scene
+/// separately for compositing.
+///
+/// # Examples
+/// ```
+/// use lucifer::camera::{Camera, ProbeCamera, Resolution, Target};
+/// use lucifer::geometry::Point;
+/// use lucifer::lighting::Radiance;
+/// use lucifer::render::lpe::LightPathExpression;
+/// use lucifer::render::{Context, FilteredPathTracer, PathTracer, Renderer};
+/// use lucifer::scene::Scene;
+///
+/// let scene = Scene::new(Radiance::gray(1.0));
+/// let camera = ProbeCamera::new(Point::new(0.0, 0.0, 0.0));
+/// let (res, target) = (Resolution::new(1, 1), Target::new(0, 0));
+/// let tracer = PathTracer::new(0.0, 4, 1);
+///
+/// let sky = LightPathExpression::parse("C B").unwrap();
+/// let lights = LightPathExpression::parse("C .* L").unwrap();
+/// let mut context = Context::seeded(0, 0);
+/// let render = |renderer: &dyn Renderer, context: &mut Context| {
+///     renderer.render(context, &scene, &camera, res, target)
+/// };
+///
+/// let filtered = FilteredPathTracer::new(tracer, sky);
+/// assert_eq!(render(&filtered, &mut context), Radiance::gray(1.0));
+/// let filtered = FilteredPathTracer::new(tracer, lights);
+/// assert_eq!(render(&filtered, &mut context), Radiance::none());
+/// ```
+#[derive(Clone, Debug)]
+pub struct FilteredPathTracer {
+    pub tracer: PathTracer,
+    pub expression: LightPathExpression,
+}
+
+impl FilteredPathTracer {
+    /// Creates a new `FilteredPathTracer`, counting the light of the
+    /// paths of `tracer` that match `expression`.
+    pub fn new(tracer: PathTracer, expression: LightPathExpression) -> Self {
+        FilteredPathTracer { tracer, expression }
+    }
+}
+
+/// The expression paths must match to be counted, and the state of
+/// the path so far; `None` counts all paths.
+type Filter<'a> = Option<(&'a LightPathExpression, PathState)>;
+
+/// The filter of a path continued by `event`, or `None` if no light
+/// along the continued path would be counted.
+fn follow(filter: Filter, event: Event) -> Option<Filter> {
+    match filter {
+        None => Some(None),
+        Some((expression, state)) => {
+            let state = expression.advance(state, event);
+            if state.is_dead() {
+                None
+            } else {
+                Some(Some((expression, state)))
+            }
+        }
+    }
+}
+
+/// Whether the light of a path ending in `event` is counted.
+fn counts(filter: Filter, event: Event) -> bool {
+    filter.is_none_or(|(expression, state)| expression.accepts(expression.advance(state, event)))
+}
+
+/// The point of the pixel's Sobol sequence a path started with, which
+/// hands out its dimensions in pairs to the camera and the decisions
+/// at the first vertex, whose distribution matters the most.  Later
+/// vertices draw from the random number generator of the `Context`.
+#[derive(Clone, Copy, Debug)]
+struct Dimensions {
+    sobol: Sobol,
+    index: u32,
+    next: u32,
+}
+
+/// Two numbers in `[0, 1)`, the next pair of `dimensions`, or random
+/// ones without.
+fn uniform_2d(context: &mut Context, dimensions: &mut Option<Dimensions>) -> (Float, Float) {
+    match *dimensions {
+        Some(ref mut d) => {
+            let point = d.sobol.sample_2d(d.index, d.next);
+            d.next += 2;
+            point
+        }
+        None => (context.rng.gen(), context.rng.gen()),
+    }
+}
+
+/// The polarization a path is sensitive to, as the Stokes vector
+/// `stokes` relative to the reference `axis` perpendicular to the
+/// ray: the camera sees its dot product with the Stokes vector of the
+/// light arriving along the ray.
+///
+/// Paths start with the camera's polarizer, and carry the `Mueller`
+/// matrices of the interactions along them, transposed.  All light
+/// sources are unpolarized, so the first component weighs the light
+/// emitted into the path.
+#[derive(Clone, Copy, Debug)]
+struct Analyzer {
+    axis: Vector3<Float>,
+    stokes: [Float; 4],
+}
+
+impl Analyzer {
+    /// The `Analyzer` of the light the camera sees along `ray`.
+    fn camera(camera: &dyn Camera, ray: &Ray) -> Self {
+        match camera.polarizer(ray) {
+            Some(axis) => Analyzer {
+                axis,
+                stokes: [0.5, 0.5, 0.0, 0.0],
+            },
+            None => Analyzer {
+                axis: make_tangent(ray.direction.normalize()),
+                stokes: [1.0, 0.0, 0.0, 0.0],
+            },
+        }
+    }
+
+    /// The weight of unpolarized light.
+    fn intensity(&self) -> Float {
+        self.stokes[0]
+    }
+
+    /// The `Analyzer` of the light arriving along `direction` at an
+    /// interaction depolarizing it.
+    fn depolarized(&self, direction: Vector3<Float>) -> Self {
+        Analyzer {
+            axis: make_tangent(direction),
+            stokes: [self.stokes[0], 0.0, 0.0, 0.0],
+        }
+    }
+
+    /// The `Analyzer` of the light arriving at an interaction of a
+    /// `ray` along `direction` with a surface with `normal`, which
+    /// transforms light by `mueller` relative to the plane of
+    /// incidence, and was sampled with `probability`.
+    fn interact(
+        &self,
+        direction: Vector3<Float>,
+        normal: Vector3<Float>,
+        mueller: Mueller,
+        probability: Float,
+    ) -> Self {
+        let perpendicular = direction.cross(normal);
+        let axis = if perpendicular.magnitude2() > 1e-12 {
+            perpendicular.normalize()
+        } else {
+            self.axis
+        };
+
+        // Light leaves towards the camera against `direction`.
+        let m = (Mueller::reframe(-direction, axis, self.axis) * mueller).0;
+        let mut stokes = [0.0; 4];
+        for (c, value) in stokes.iter_mut().enumerate() {
+            *value = (0..4).map(|r| m[r][c] * self.stokes[r]).sum::<Float>() / probability;
+        }
+        Analyzer { axis, stokes }
+    }
+}
+
+/// The gray `Radiance` of the upsampled spectrum of `radiance` at
+/// the `wavelength` of a spectral path, or `radiance` itself.
+fn radiance_at(radiance: Radiance, wavelength: Option<Float>) -> Radiance {
+    match wavelength {
+        Some(wavelength) => Radiance::gray(Upsampled::from(radiance).power(wavelength)),
+        None => radiance,
+    }
+}
+
+/// The gray `Albedo` of the upsampled spectrum of `albedo` at
+/// `wavelength`.
+fn albedo_at(albedo: Albedo, wavelength: Float) -> Albedo {
+    Albedo::gray(Upsampled::from(albedo).power(wavelength))
+}
+
+/// The effects of `bsdf` for light of `wavelength`.
+fn bsdf_at(bsdf: &Bsdf, wavelength: Float) -> Bsdf {
+    let mut monochromatic = Bsdf::new();
+    for effect in &bsdf.effects {
+        let radiance = |radiance| radiance_at(radiance, Some(wavelength));
+        let albedo = |albedo| albedo_at(albedo, wavelength);
+        monochromatic.effects.push(match *effect {
+            Effect::Emission(emission, dist) => Effect::Emission(radiance(emission), dist),
+            Effect::DiffuseReflection(a, dist) => Effect::DiffuseReflection(albedo(a), dist),
+            Effect::SpecularReflection(a, dist) => Effect::SpecularReflection(albedo(a), dist),
+            Effect::DiffuseRefraction(a, ior, dist) => {
+                Effect::DiffuseRefraction(albedo(a), ior, dist)
+            }
+            Effect::SpecularRefraction(a, ior, dist) => {
+                Effect::SpecularRefraction(albedo(a), ior, dist)
+            }
+        });
+    }
+    monochromatic
+}
+
+/// The scattering of light by the `Distribution` of a specular effect.
+fn scattering(dist: Distribution) -> char {
+    match dist {
+        Distribution::Dirac => 'S',
+        _ => 'G',
+    }
+}
+
+/// A ray leaving the surface at `intersection`, at the `time` of the
+/// ray that hit it.
+fn secondary(intersection: &Intersection, direction: Vector3<Float>, time: Float) -> Ray {
+    intersection.spawn(direction).at_time(time)
+}
+
+/// The interiors on the other side of the surface at `hit`, of
+/// refractive index `ior`, for paths passing through it from
+/// `interiors`.
+fn transmitted(
+    scene: &Scene,
+    interiors: &InteriorStack,
+    hit: &ShadedIntersection,
+    ior: Ior,
+) -> InteriorStack {
+    let intersection = &hit.intersection;
+    if !intersection.inside {
+        return interiors.enter(Interior {
+            object: hit.object,
+            priority: hit.priority,
+            ior,
+            medium: hit.medium,
+        });
+    }
+
+    // Paths leaving an object they started in, e.g. the water around
+    // an underwater camera, find the enclosing medium in the scene.
+    interiors.leave(hit.object).unwrap_or_else(|| {
+        let beyond = intersection.offset_origin(-intersection.normal);
+        interiors.with_outside(scene.medium_at(beyond))
+    })
+}
+
+/// The refractive index of the interior behind a surface with `bsdf`,
+/// if light passes through it.
+fn ior(bsdf: &Bsdf) -> Option<Ior> {
+    bsdf.effects.iter().find_map(|effect| match *effect {
+        Effect::DiffuseRefraction(_, ior, _) | Effect::SpecularRefraction(_, ior, _) => Some(ior),
+        _ => None,
+    })
+}
+
+/// The light a glossy or diffuse reflection `effect` scatters back
+/// along `direction`, the direction of a ray hitting a surface with
+/// `normal`, per unit of radiance arriving from `incidence` and unit
+/// solid angle, together with the density per solid angle of sampling
+/// `incidence` from the effect.  `None` for the other effects, which
+/// light from a given direction cannot reach this way.
+pub(crate) fn reflection(
+    effect: &Effect,
+    direction: Vector3<Float>,
+    normal: Vector3<Float>,
+    incidence: Vector3<Float>,
+) -> Option<(Albedo, Float)> {
+    let cos_t_view = (-dot(direction, normal)).min(1.0);
+    let (albedo, dist, axis, scale) = match *effect {
+        Effect::DiffuseReflection(albedo, dist) => (albedo, dist, normal, 1.0 / PI),
+        Effect::SpecularReflection(albedo, dist) => {
+            (albedo, dist, reflect(direction, normal), 0.5 / PI)
+        }
+        _ => return None,
+    };
+    if let Distribution::Dirac = dist {
+        return None;
+    }
+
+    let cos_t_in = dot(incidence, axis).min(1.0);
+    let factor = (cos_t_in.max(0.0) * dist.eval(cos_t_view) * scale) * albedo;
+    Some((factor, dist.pdf(cos_t_in)))
+}
+
+/// The light arriving from `incidence` with `radiance`, sampled with
+/// density `pdf` per solid angle, that the reflections at `hit` scatter
+/// back along `ray`, weighted against sampling them instead.  Only the
+/// reflections whose paths count when ending in `event` contribute.
+fn reflected(
+    ray: &Ray,
+    hit: &ShadedIntersection,
+    filter: Filter,
+    event: Event,
+    incidence: Vector3<Float>,
+    radiance: Radiance,
+    pdf: Float,
+) -> Radiance {
+    let normal = hit.intersection.normal;
+    let mut reflected = Radiance::none();
+    for effect in &hit.bsdf.effects {
+        let kind = match *effect {
+            Effect::DiffuseReflection(..) => Event::new('R', 'D'),
+            Effect::SpecularReflection(_, dist) => Event::new('R', scattering(dist)),
+            _ => continue,
+        };
+        if !follow(filter, kind).is_some_and(|next| counts(next, event)) {
+            continue;
+        }
+
+        if let Some((factor, bsdf_pdf)) = reflection(effect, ray.direction, normal, incidence) {
+            let weight = power_heuristic(1, pdf, 1, bsdf_pdf);
+            reflected += factor * radiance * (weight / pdf);
+        }
+    }
+    reflected
+}
+
+/// Samples a point on a light source for the surface at `hit`,
+/// returning the direction towards it, the radiance it emits along
+/// that direction, and the density per solid angle of sampling it.
+/// `None` if the point is hidden.
+fn sample_emitter(
+    context: &mut Context,
+    dimensions: &mut Option<Dimensions>,
+    scene: &Scene,
+    ray: &Ray,
+    hit: &ShadedIntersection,
+) -> Option<(Vector3<Float>, Radiance, Float)> {
+    let (choice, _) = uniform_2d(context, dimensions);
+    let (u, v) = uniform_2d(context, dimensions);
+    let (emitter, _) = scene.sample_emitter(choice)?;
+    let (position, _) = scene.sample_emitter_surface(emitter, u, v)?;
+
+    let intersection = &hit.intersection;
+    let offset = position - intersection.position;
+    let distance = offset.magnitude();
+    let shadow = secondary(intersection, offset / distance, ray.time);
+
+    // Points sampled on the far side of the light are hidden by its
+    // near side, which alone counts towards `emitter_pdf`.
+    context.statistics.rays += 1;
+    let light = scene.intersect(&shadow).filter(|light| {
+        light.object == emitter.object && light.intersection.lambda >= (1.0 - 1e-3) * distance
+    })?;
+    let pdf = scene.emitter_pdf(emitter.object, &shadow);
+    if pdf <= 0.0 {
+        return None;
+    }
+
+    let cos_t_light = (-dot(shadow.direction, light.intersection.normal)).min(1.0);
+    let emission = light
+        .bsdf
+        .effects
+        .iter()
+        .fold(Radiance::none(), |sum, effect| match *effect {
+            Effect::Emission(emission, dist) => sum + emission * dist.eval(cos_t_light),
+            _ => sum,
+        });
+    Some((shadow.direction, emission, pdf))
+}
+
+/// Samples a direction of the environment for the surface at `hit`,
+/// returning the direction, the radiance arriving from it, and its
+/// density per solid angle.  `None` if the environment is hidden.
+fn sample_environment(
+    context: &mut Context,
+    dimensions: &mut Option<Dimensions>,
+    scene: &Scene,
+    ray: &Ray,
+    hit: &ShadedIntersection,
+) -> Option<(Vector3<Float>, Radiance, Float)> {
+    let (u, v) = uniform_2d(context, dimensions);
+    let (direction, pdf) = scene.environment().sample(u, v);
+    let radiance = scene.background(direction);
+    if pdf <= 0.0 || radiance == Radiance::none() {
+        return None;
+    }
+
+    context.statistics.shadow_rays += 1;
+    if scene.occlude(&secondary(&hit.intersection, direction, ray.time)) {
+        return None;
+    }
+    Some((direction, radiance, pdf))
+}
+
+/// Samples the light sources and the environment for the reflections
+/// at `hit`, which a `ray` within no medium arrived at, for light of
+/// `wavelength` on spectral paths.
+fn sample_lights(
+    context: &mut Context,
+    dimensions: &mut Option<Dimensions>,
+    scene: &Scene,
+    ray: &Ray,
+    hit: &ShadedIntersection,
+    filter: Filter,
+    wavelength: Option<Float>,
+) -> Sample<Radiance> {
+    let mut radiance = Radiance::none();
+    if let Some((incidence, emission, pdf)) = sample_emitter(context, dimensions, scene, ray, hit) {
+        let emission = radiance_at(emission, wavelength);
+        radiance += reflected(ray, hit, filter, Event::LIGHT, incidence, emission, pdf);
+    }
+    if let Some((incidence, background, pdf)) =
+        sample_environment(context, dimensions, scene, ray, hit)
+    {
+        radiance += reflected(
+            ray,
+            hit,
+            filter,
+            Event::BACKGROUND,
+            incidence,
+            radiance_at(background, wavelength),
+            pdf,
+        );
+    }
+    Sample::from(radiance)
+}
+
+fn reflect(direction: Vector3<Float>, normal: Vector3<Float>) -> Vector3<Float> {
+    let proj_ray = normal * dot(normal, direction);
+    (direction - proj_ray * 2.0).normalize()
 }
 
-fn secondary(origin: Point3<f32>, direction: Vector3<f32>) -> Ray {
-    Ray::new(origin + direction * 0.0001, direction)
+/// Refracts `direction` at a surface with `normal` (facing against
+/// `direction`) and relative index of refraction `eta`.  Returns
+/// `None` in case of total internal reflection.
+fn refract(
+    direction: Vector3<Float>,
+    normal: Vector3<Float>,
+    eta: Float,
+) -> Option<Vector3<Float>> {
+    let cos_i = -dot(direction, normal);
+    let k = 1.0 - eta * eta * (1.0 - cos_i * cos_i);
+
+    if k < 0.0 {
+        None
+    } else {
+        Some((direction * eta + normal * (eta * cos_i - k.sqrt())).normalize())
+    }
+}
+
+/// Schlick's approximation of the Fresnel reflectance.
+fn fresnel(cos_i: Float, eta: Float) -> Float {
+    let r0 = ((1.0 - eta) / (1.0 + eta)).powi(2);
+    let cos = if eta > 1.0 {
+        (1.0 - eta * eta * (1.0 - cos_i * cos_i)).max(0.0).sqrt()
+    } else {
+        cos_i
+    };
+
+    r0 + (1.0 - r0) * (1.0 - cos).powi(5)
 }
 
-fn make_tangent(normal: Vector3<f32>) -> Vector3<f32> {
+fn make_tangent(normal: Vector3<Float>) -> Vector3<Float> {
     if normal[0].abs() > normal[1].abs() {
         vec3(normal[2], 0.0, -normal[0]).normalize()
     } else {
@@ -30,103 +493,542 @@ fn make_tangent(normal: Vector3<f32>) -> Vector3<f32> {
     }
 }
 
-fn align_with(normal: Vector3<f32>, vector: Vector3<f32>) -> Vector3<f32> {
+fn align_with(normal: Vector3<Float>, vector: Vector3<Float>) -> Vector3<Float> {
     let tangent = make_tangent(normal);
     let bitangent = normal.cross(tangent);
 
     vector[0] * tangent + vector[1] * bitangent + vector[2] * normal
 }
 
-impl<R: Rng> PathTracer<R> {
-    pub fn new(rng: R, contribution_limit: f32, depth_limit: u8, samples: u32) -> PathTracer<R> {
+impl PathTracer {
+    /// Creates a new `PathTracer`, tracing `samples` paths per pixel
+    /// of at most `depth_limit` vertices.  Rendering with zero
+    /// `samples` panics; see `try_new`.
+    pub fn new(contribution_limit: Float, depth_limit: u8, samples: u32) -> PathTracer {
         PathTracer {
-            rng,
             contribution_limit,
             depth_limit,
             samples,
+            polarized: false,
+            spectral: false,
+        }
+    }
+
+    /// Creates a new `PathTracer`, failing if it would trace no
+    /// paths.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::render::PathTracer;
+    ///
+    /// assert!(PathTracer::try_new(0.01, 8, 16).is_ok());
+    /// assert!(PathTracer::try_new(0.01, 8, 0).is_err());
+    /// ```
+    pub fn try_new(
+        contribution_limit: Float,
+        depth_limit: u8,
+        samples: u32,
+    ) -> Result<Self, Error> {
+        if samples == 0 {
+            return Err(Error::InvalidSettings("no samples per pixel".into()));
         }
+        Ok(PathTracer::new(contribution_limit, depth_limit, samples))
+    }
+
+    /// Enables or disables tracing the polarization of light.
+    ///
+    /// Polarized paths see the reflection and refraction of smooth
+    /// dielectrics with the exact Fresnel equations of polarized
+    /// light, and the camera's polarizer, if any; see
+    /// `Camera::polarizer`.  Other interactions depolarize the light.
+    ///
+    /// # Examples
+    /// A polarizing filter removes the reflection of the sky off
+    /// water at Brewster's angle.
+    ///
+    /// ```
+    /// # extern crate cgmath;
+    /// # extern crate lucifer;
+    /// use cgmath::{Matrix4, SquareMatrix};
+    /// use lucifer::camera::{Camera, PolarizerCamera, Resolution, Target};
+    /// use lucifer::geometry::plane::Plane;
+    /// use lucifer::geometry::{Point, Ray, Vector};
+    /// use lucifer::lighting::{Albedo, Dielectric, Ior, Lambert, Radiance};
+    /// use lucifer::render::{Context, PathTracer, Renderer};
+    /// use lucifer::scene::{Object, Scene};
+    /// use lucifer::Float;
+    ///
+    /// struct Glance(Ray);
+    ///
+    /// impl Camera for Glance {
+    ///     fn primary(&self, _: Resolution, _: Target) -> Ray {
+    ///         self.0.clone()
+    ///     }
+    /// }
+    ///
+    /// # fn main() {
+    /// let up = Vector::new(0.0, 0.0, 1.0);
+    /// let mut scene = Scene::new(Radiance::gray(1.0));
+    /// let water = Dielectric::new(Albedo::white(), Ior(1.333));
+    /// scene.add(Object::new(Plane::new(up, 0.0), water, Matrix4::identity()));
+    /// let ground = Lambert::new(Albedo::gray(0.0));
+    /// scene.add(Object::new(Plane::new(up, -1.0), ground, Matrix4::identity()));
+    ///
+    /// let brewster = (1.333 as Float).atan();
+    /// let direction = Vector::new(brewster.sin(), 0.0, -brewster.cos());
+    /// let glance = Glance(Ray::new(Point::new(0.0, 0.0, 1.0), direction));
+    ///
+    /// let tracer = PathTracer::new(0.0, 4, 64).with_polarization(true);
+    /// let (res, target) = (Resolution::new(1, 1), Target::new(0, 0));
+    /// let render = |camera: &dyn Camera| {
+    ///     let mut context = Context::seeded(0, 0);
+    ///     tracer.render(&mut context, &scene, camera, res, target).luma()
+    /// };
+    ///
+    /// // The reflection is polarized along the surface.
+    /// let glare = render(&glance);
+    /// assert!(glare > 0.02);
+    /// let along = PolarizerCamera::new(Glance(glance.0.clone()), Vector::new(0.0, 1.0, 0.0));
+    /// assert!((render(&along) - glare).abs() < 1e-4);
+    /// assert!(render(&PolarizerCamera::new(glance, up)) < 1e-4);
+    /// # }
+    /// ```
+    pub fn with_polarization(self, polarized: bool) -> Self {
+        let mut tracer = self;
+        tracer.polarized = polarized;
+        tracer
     }
 
+    /// Enables or disables tracing light of single wavelengths.
+    ///
+    /// Every path of a spectral `PathTracer` carries light of a
+    /// wavelength drawn from the visible range, and sees the spectra
+    /// of the colors of the scene upsampled from RGB; see `Upsampled`.
+    /// The image converges to nearly the same colors, with the noise
+    /// of the sampled wavelengths.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::camera::{ProbeCamera, Resolution, Target};
+    /// use lucifer::geometry::Point;
+    /// use lucifer::lighting::Radiance;
+    /// use lucifer::render::{Context, PathTracer, Renderer};
+    /// use lucifer::scene::Scene;
+    ///
+    /// let sky = Radiance::new(0.8, 0.4, 0.1);
+    /// let scene = Scene::new(sky);
+    /// let camera = ProbeCamera::new(Point::new(0.0, 0.0, 0.0));
+    ///
+    /// let tracer = PathTracer::new(0.0, 4, 256).with_spectral(true);
+    /// let mut context = Context::seeded(0, 0);
+    /// let (res, target) = (Resolution::new(1, 1), Target::new(0, 0));
+    /// let color = tracer.render(&mut context, &scene, &camera, res, target);
+    /// for c in 0..3 {
+    ///     assert!((color[c] - sky[c]).abs() < 0.05);
+    /// }
+    /// ```
+    pub fn with_spectral(self, spectral: bool) -> Self {
+        let mut tracer = self;
+        tracer.spectral = spectral;
+        tracer
+    }
+
+    /// Computes the light scattered towards `ray`'s origin by the
+    /// `medium` at `distance` along the ray, within `interiors`.
+    #[allow(clippy::too_many_arguments)]
+    fn scatter(
+        &self,
+        context: &mut Context,
+        scene: &Scene,
+        ray: &Ray,
+        interiors: &InteriorStack,
+        medium: Medium,
+        distance: Float,
+        contribution: Albedo,
+        depth: u8,
+        filter: Filter,
+        analyzer: Option<Analyzer>,
+        wavelength: Option<Float>,
+    ) -> Sample<Radiance> {
+        let extinction = medium.extinction();
+        let albedo = (medium.scattering / extinction) * medium.albedo;
+        let intensity = analyzer.map_or(1.0, |a| a.intensity());
+
+        let mut sample = Sample::from(Radiance::none());
+        if counts(filter, Event::LIGHT) {
+            let emitted = medium.absorption / extinction * intensity;
+            sample += Sample::from(medium.emission * emitted);
+        }
+
+        let next = match follow(filter, Event::VOLUME) {
+            Some(next) => next,
+            None => return sample,
+        };
+        let position = ray.origin + ray.direction * distance;
+        let direction = align_with(ray.direction, medium.sample_phase(&mut context.rng));
+        let incoming = self.trace(
+            context,
+            scene,
+            &Ray::new(position, direction).at_time(ray.time),
+            interiors,
+            contribution * albedo,
+            depth + 1,
+            next,
+            None,
+            None,
+            analyzer.map(|a| a.depolarized(direction)),
+            wavelength,
+        );
+
+        sample += incoming * Sample::from(albedo);
+        sample
+    }
+
+    /// Traces `ray`, returning the light arriving along it.  Rays
+    /// sampled from a reflection whose lights were sampled as well
+    /// carry `bsdf_pdf`, their density per solid angle, to weight the
+    /// light sources and environment they hit against the sampled
+    /// ones.  Camera rays carry the `dimensions` of their pixel's
+    /// sequence left for the first vertex.  Polarized paths carry the
+    /// `analyzer` of the light along the ray, and spectral paths the
+    /// `wavelength` of their light.
+    #[allow(clippy::too_many_arguments)]
     fn trace(
-        &mut self,
+        &self,
+        context: &mut Context,
         scene: &Scene,
         ray: &Ray,
+        interiors: &InteriorStack,
         contribution: Albedo,
         depth: u8,
+        filter: Filter,
+        bsdf_pdf: Option<Float>,
+        dimensions: Option<Dimensions>,
+        analyzer: Option<Analyzer>,
+        wavelength: Option<Float>,
     ) -> Sample<Radiance> {
-        if depth >= self.depth_limit || contribution.luma_factor() < self.contribution_limit {
+        if self.ends(contribution, depth) {
             return Sample::from(Radiance::none());
         }
 
-        match scene.intersect(ray) {
-            None => Sample::from(scene.background()),
+        context.statistics.rays += 1;
+        let hit = scene.intersect(ray);
+        self.arrive(
+            context,
+            scene,
+            ray,
+            hit,
+            interiors,
+            contribution,
+            depth,
+            filter,
+            bsdf_pdf,
+            dimensions,
+            analyzer,
+            wavelength,
+        )
+    }
+
+    /// Whether paths end before carrying `contribution` at `depth`.
+    fn ends(&self, contribution: Albedo, depth: u8) -> bool {
+        depth >= self.depth_limit || contribution.luma_factor() < self.contribution_limit
+    }
+
+    /// Continues a path along `ray` to its nearest surface `hit`, or
+    /// into the environment; see `trace`.
+    #[allow(clippy::too_many_arguments)]
+    fn arrive(
+        &self,
+        context: &mut Context,
+        scene: &Scene,
+        ray: &Ray,
+        hit: Option<ShadedIntersection>,
+        interiors: &InteriorStack,
+        contribution: Albedo,
+        depth: u8,
+        filter: Filter,
+        bsdf_pdf: Option<Float>,
+        dimensions: Option<Dimensions>,
+        analyzer: Option<Analyzer>,
+        wavelength: Option<Float>,
+    ) -> Sample<Radiance> {
+        let mut dimensions = dimensions;
+        if let Some(medium) = interiors.medium() {
+            let distance = medium.sample_distance(&mut context.rng);
+            let surface = hit
+                .as_ref()
+                .map_or(Float::INFINITY, |i| i.intersection.lambda);
+
+            if distance < surface {
+                context.statistics.path_vertices += 1;
+                let medium = match wavelength {
+                    Some(wavelength) => medium
+                        .color(albedo_at(medium.albedo, wavelength))
+                        .glow(radiance_at(medium.emission, Some(wavelength))),
+                    None => medium,
+                };
+                return self.scatter(
+                    context,
+                    scene,
+                    ray,
+                    interiors,
+                    medium,
+                    distance,
+                    contribution,
+                    depth,
+                    filter,
+                    analyzer,
+                    wavelength,
+                );
+            }
+        }
+
+        let intensity = analyzer.map_or(1.0, |a| a.intensity());
+
+        match hit {
+            None if counts(filter, Event::BACKGROUND) => {
+                let weight = bsdf_pdf.map_or(1.0, |pdf| {
+                    power_heuristic(1, pdf, 1, scene.environment().pdf(ray.direction))
+                });
+                let background = radiance_at(scene.background(ray.direction), wavelength);
+                Sample::from(background * (weight * intensity))
+            }
+            None => Sample::from(Radiance::none()),
             Some(i) => {
+                let i = match wavelength {
+                    Some(wavelength) => ShadedIntersection {
+                        bsdf: bsdf_at(&i.bsdf, wavelength),
+                        ..i
+                    },
+                    None => i,
+                };
                 let intersection = &i.intersection;
                 let bsdf = &i.bsdf;
 
-                let cos_t_view = -dot(ray.direction, intersection.normal);
+                // Transparent surfaces within an interior of higher
+                // priority only move the path into or out of their
+                // object.
+                if let Some(ior) = ior(bsdf).filter(|_| interiors.hides(i.object, i.priority)) {
+                    return self.trace(
+                        context,
+                        scene,
+                        &secondary(intersection, ray.direction, ray.time),
+                        &transmitted(scene, interiors, &i, ior),
+                        contribution,
+                        depth,
+                        filter,
+                        None,
+                        dimensions,
+                        analyzer,
+                        wavelength,
+                    );
+                }
+                context.statistics.path_vertices += 1;
+
+                // Rounding may push the cosine of nearly parallel unit
+                // vectors just past `1`.
+                let cos_t_view = (-dot(ray.direction, intersection.normal)).min(1.0);
 
                 let mut sample = Sample::from(Radiance::none());
 
+                // Light sources and the environment are sampled for the
+                // reflections as well, unless a medium would attenuate
+                // their light, or it would arrive past the depth limit.
+                let lights = depth + 1 < self.depth_limit && interiors.medium().is_none();
+                if lights {
+                    let lit =
+                        sample_lights(context, &mut dimensions, scene, ray, &i, filter, wavelength);
+                    sample += Sample::from(lit.estimate() * intensity);
+                }
+                let mis = |dist: Distribution, prob: Float| match dist {
+                    Distribution::Dirac => None,
+                    _ if lights => Some(prob),
+                    _ => None,
+                };
+
                 for effect in &bsdf.effects {
                     match *effect {
                         Effect::Emission(emission, dist) => {
-                            sample += Sample::from(emission * dist.eval(cos_t_view));
+                            if counts(filter, Event::LIGHT) {
+                                let weight = bsdf_pdf.map_or(1.0, |pdf| {
+                                    power_heuristic(1, pdf, 1, scene.emitter_pdf(i.object, ray))
+                                });
+                                let emitted = dist.eval(cos_t_view) * weight * intensity;
+                                sample += Sample::from(emission * emitted);
+                            }
                         }
 
                         Effect::DiffuseReflection(albedo, dist) => {
-                            let (v, prob) = dist.sample(&mut self.rng);
+                            let next = match follow(filter, Event::new('R', 'D')) {
+                                Some(next) => next,
+                                None => continue,
+                            };
+                            let (x, y) = uniform_2d(context, &mut dimensions);
+                            let (v, prob) = dist.warp(x, y);
                             let cos_t_in = v[2];
                             let factor = cos_t_in * albedo * dist.eval(cos_t_view);
 
                             let incidence = align_with(intersection.normal, v);
                             let incoming = self.trace(
+                                context,
                                 scene,
-                                &secondary(intersection.position, incidence),
+                                &secondary(intersection, incidence, ray.time),
+                                interiors,
                                 contribution * factor,
                                 depth + 1,
+                                next,
+                                mis(dist, prob),
+                                None,
+                                analyzer.map(|a| a.depolarized(incidence)),
+                                wavelength,
                             );
 
-                            sample += incoming * Sample::new(factor, prob * 2.0 * PI);
+                            // The diffuse BRDF is `albedo / π`, against
+                            // the density per solid angle.
+                            sample += incoming * Sample::new(factor / PI, prob);
                         }
 
                         Effect::SpecularReflection(albedo, dist) => {
-                            let proj_ray =
-                                intersection.normal * dot(intersection.normal, ray.direction);
-                            let reflected_ray = (ray.direction - proj_ray * 2.0).normalize();
+                            let next = match follow(filter, Event::new('R', scattering(dist))) {
+                                Some(next) => next,
+                                None => continue,
+                            };
+                            let reflected_ray = reflect(ray.direction, intersection.normal);
 
-                            let (v, prob) = dist.sample(&mut self.rng);
+                            let (x, y) = uniform_2d(context, &mut dimensions);
+                            let (v, prob) = dist.warp(x, y);
                             let cos_t_in = v[2];
                             let factor = cos_t_in * albedo * dist.eval(cos_t_view);
 
                             let incidence = align_with(reflected_ray, v);
                             let incoming = self.trace(
+                                context,
                                 scene,
-                                &secondary(intersection.position, incidence),
+                                &secondary(intersection, incidence, ray.time),
+                                interiors,
                                 contribution * factor,
                                 depth + 1,
+                                next,
+                                mis(dist, prob),
+                                None,
+                                analyzer.map(|a| a.depolarized(incidence)),
+                                wavelength,
                             );
 
-                            sample += incoming * Sample::new(factor, prob * 2.0 * PI);
+                            sample +=
+                                incoming * Sample::new(factor, solid_angle_to_hemisphere(prob));
                         }
 
-                        Effect::DiffuseRefraction(albedo, _, dist) => {
-                            let (v, prob) = dist.sample(&mut self.rng);
+                        Effect::DiffuseRefraction(albedo, ior, dist) => {
+                            let next = match follow(filter, Event::new('T', 'D')) {
+                                Some(next) => next,
+                                None => continue,
+                            };
+                            let (x, y) = uniform_2d(context, &mut dimensions);
+                            let (v, prob) = dist.warp(x, y);
                             let cos_t_in = v[2];
                             let factor = cos_t_in * albedo * dist.eval(cos_t_view);
 
                             let incidence = align_with(-intersection.normal, v);
                             let incoming = self.trace(
+                                context,
                                 scene,
-                                &secondary(intersection.position, incidence),
+                                &secondary(intersection, incidence, ray.time),
+                                &transmitted(scene, interiors, &i, ior),
                                 contribution * factor,
                                 depth + 1,
+                                next,
+                                None,
+                                None,
+                                analyzer.map(|a| a.depolarized(incidence)),
+                                wavelength,
                             );
 
-                            sample += incoming * Sample::new(factor, prob * 2.0 * PI);
+                            sample += incoming * Sample::new(factor / PI, prob);
                         }
 
-                        Effect::SpecularRefraction(_, _, _) => assert!(false),
+                        Effect::SpecularRefraction(albedo, ior, dist) => {
+                            let beyond = transmitted(scene, interiors, &i, ior);
+                            let eta = if intersection.inside {
+                                ior.0 / beyond.ior().0
+                            } else {
+                                interiors.ior().0 / ior.0
+                            };
+
+                            // Polarized paths through smooth surfaces
+                            // follow the exact Fresnel equations.
+                            let polarizing = match dist {
+                                Distribution::Dirac => analyzer,
+                                _ => None,
+                            };
+                            let reflectance = match polarizing {
+                                Some(_) => Mueller::fresnel_reflection(cos_t_view, eta).0[0][0],
+                                None => fresnel(cos_t_view, eta),
+                            };
+
+                            let (choice, _) = uniform_2d(context, &mut dimensions);
+                            let refracted = refract(ray.direction, intersection.normal, eta)
+                                .filter(|_| choice >= reflectance);
+
+                            let (axis, next_interiors, kind) = match refracted {
+                                Some(direction) => (direction, &beyond, 'T'),
+                                None => {
+                                    (reflect(ray.direction, intersection.normal), interiors, 'R')
+                                }
+                            };
+                            let next = match follow(filter, Event::new(kind, scattering(dist))) {
+                                Some(next) => next,
+                                None => continue,
+                            };
+
+                            let (incidence, weight) = match dist {
+                                Distribution::Dirac => (axis, Sample::from(albedo)),
+                                _ => {
+                                    let (x, y) = uniform_2d(context, &mut dimensions);
+                                    let (v, prob) = dist.warp(x, y);
+                                    let cos_t_in = v[2];
+                                    let factor = cos_t_in * albedo * dist.eval(cos_t_view);
+                                    (
+                                        align_with(axis, v),
+                                        Sample::new(factor, solid_angle_to_hemisphere(prob)),
+                                    )
+                                }
+                            };
+
+                            let analyzer = match polarizing {
+                                Some(a) if kind == 'T' => {
+                                    let mueller = Mueller::fresnel_transmission(cos_t_view, eta);
+                                    let normal = intersection.normal;
+                                    Some(a.interact(
+                                        ray.direction,
+                                        normal,
+                                        mueller,
+                                        1.0 - reflectance,
+                                    ))
+                                }
+                                Some(a) => {
+                                    let mueller = Mueller::fresnel_reflection(cos_t_view, eta);
+                                    let normal = intersection.normal;
+                                    Some(a.interact(ray.direction, normal, mueller, reflectance))
+                                }
+                                None => analyzer.map(|a| a.depolarized(incidence)),
+                            };
+                            let incoming = self.trace(
+                                context,
+                                scene,
+                                &secondary(intersection, incidence, ray.time),
+                                next_interiors,
+                                contribution * albedo,
+                                depth + 1,
+                                next,
+                                None,
+                                None,
+                                analyzer,
+                                wavelength,
+                            );
+
+                            sample += incoming * weight;
+                        }
                     }
                 }
 
@@ -136,25 +1038,160 @@ impl<R: Rng> PathTracer<R> {
     }
 }
 
-impl<R: Rng> Renderer for PathTracer<R> {
+impl PathTracer {
+    /// Estimates the light arriving at `target` in the views of all
+    /// `cameras` along the paths matching `expression`, or all paths.
+    /// The camera rays of a sample in all views are traced through the
+    /// scene together.
+    fn estimate(
+        &self,
+        context: &mut Context,
+        scene: &Scene,
+        cameras: &[&dyn Camera],
+        resolution: Resolution,
+        target: Target,
+        expression: Option<&LightPathExpression>,
+    ) -> Vec<Radiance> {
+        let filter = match expression {
+            Some(expression) => {
+                match follow(Some((expression, expression.start())), Event::CAMERA) {
+                    Some(filter) => filter,
+                    None => return vec![Radiance::none(); cameras.len()],
+                }
+            }
+            None => None,
+        };
+        let mut estimates = vec![Estimator::new(); cameras.len()];
+
+        context.statistics.paths += u64::from(self.samples) * cameras.len() as u64;
+        // Every estimate of a pixel scrambles its own sequence, drawn
+        // like all its random numbers from the context.
+        let sobols: Vec<Sobol> = cameras
+            .iter()
+            .map(|_| Sobol::new(context.rng.gen()))
+            .collect();
+        let interiors = InteriorStack::new(scene.camera_medium());
+        let ends = self.ends(Albedo::white(), 0);
+        for index in 0..self.samples {
+            let mut rays = Vec::with_capacity(cameras.len());
+            let mut paths = Vec::with_capacity(cameras.len());
+            for (&camera, &sobol) in cameras.iter().zip(&sobols) {
+                let mut dimensions = Some(Dimensions {
+                    sobol,
+                    index,
+                    next: 0,
+                });
+                let pixel = uniform_2d(context, &mut dimensions);
+                let lens = uniform_2d(context, &mut dimensions);
+                let (shutter, spectrum) = uniform_2d(context, &mut dimensions);
+                let mut ray = camera.sample_pixel(resolution, target, pixel, lens);
+                let (open, close) = camera.shutter();
+                if close > open {
+                    ray.time = open + (close - open) * shutter;
+                }
+                let analyzer = if self.polarized {
+                    Some(Analyzer::camera(camera, &ray))
+                } else {
+                    None
+                };
+                let wavelength = if self.spectral {
+                    Some(sample_wavelength(spectrum))
+                } else {
+                    None
+                };
+                rays.push(ray);
+                paths.push((dimensions, analyzer, wavelength));
+            }
+
+            let hits = if ends {
+                rays.iter().map(|_| None).collect()
+            } else {
+                context.statistics.rays += rays.len() as u64;
+                scene.intersect_batch(&rays)
+            };
+            for (view, ((ray, hit), (dimensions, analyzer, wavelength))) in
+                rays.iter().zip(hits).zip(paths).enumerate()
+            {
+                let sample = if ends {
+                    Sample::from(Radiance::none())
+                } else {
+                    self.arrive(
+                        context,
+                        scene,
+                        ray,
+                        hit,
+                        &interiors,
+                        Albedo::white(),
+                        0,
+                        filter,
+                        None,
+                        dimensions,
+                        analyzer,
+                        wavelength,
+                    )
+                };
+                estimates[view].add(match wavelength {
+                    Some(wavelength) => {
+                        let power = sample.estimate()[0];
+                        Sample::from(Radiance::from(Xyz::from_wavelength(wavelength, power)))
+                    }
+                    None => sample,
+                })
+            }
+        }
+
+        estimates.into_iter().map(Estimator::value).collect()
+    }
+}
+
+impl Renderer for PathTracer {
     fn render(
-        &mut self,
+        &self,
+        context: &mut Context,
         scene: &Scene,
-        camera: &Camera,
+        camera: &dyn Camera,
         resolution: Resolution,
         target: Target,
     ) -> Radiance {
-        let mut estimate = Estimator::new();
-
-        for _ in 0..self.samples {
-            estimate.add(self.trace(
-                scene,
-                &camera.primary(resolution, target),
-                Albedo::white(),
-                0,
-            ))
-        }
+        self.estimate(context, scene, &[camera], resolution, target, None)[0]
+    }
+
+    fn render_views(
+        &self,
+        context: &mut Context,
+        scene: &Scene,
+        cameras: &[&dyn Camera],
+        resolution: Resolution,
+        target: Target,
+    ) -> Vec<Radiance> {
+        self.estimate(context, scene, cameras, resolution, target, None)
+    }
+}
 
-        estimate.value()
+impl Renderer for FilteredPathTracer {
+    fn render(
+        &self,
+        context: &mut Context,
+        scene: &Scene,
+        camera: &dyn Camera,
+        resolution: Resolution,
+        target: Target,
+    ) -> Radiance {
+        let expression = Some(&self.expression);
+        self.tracer
+            .estimate(context, scene, &[camera], resolution, target, expression)[0]
+    }
+
+    fn render_views(
+        &self,
+        context: &mut Context,
+        scene: &Scene,
+        cameras: &[&dyn Camera],
+        resolution: Resolution,
+        target: Target,
+    ) -> Vec<Radiance> {
+        let expression = Some(&self.expression);
+        self.tracer
+            .estimate(context, scene, cameras, resolution, target, expression)
     }
 }
@@ -0,0 +1,179 @@
+use std::sync::mpsc::Sender;
+
+use rand::RngCore;
+
+use camera::{Camera, Resolution};
+use lighting::Radiance;
+use render::{Context, Film, Renderer, Statistics, Tile, TiledRenderer};
+use scene::Scene;
+use trace::Span;
+
+/// A refined region of the image, emitted while rendering a
+/// `Preview`.
+#[derive(Clone, Debug)]
+pub struct Update {
+    /// The pass that produced the update, starting at `0`.
+    pub pass: u32,
+    /// The refined region.
+    pub tile: Tile,
+    /// The current estimates of the pixels in `tile`, in row-major
+    /// order.
+    pub pixels: Vec<Radiance>,
+}
+
+/// The action requested by an update callback.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Control {
+    /// Continue rendering.
+    Continue,
+    /// Abandon the current pass as soon as possible.
+    Cancel,
+}
+
+/// A progressive render session for interactive viewers.
+///
+/// Each call to `refine` renders one more pass over the image,
+/// reporting every finished tile to a callback.  When the scene or
+/// camera changes, `restart` discards the accumulated image so the
+/// next pass starts from scratch.
+///
+/// # Examples
+/// ```
+/// use std::sync::mpsc::channel;
+///
+/// use lucifer::camera::{AffineTransformCamera, Resolution};
+/// use lucifer::cgmath::{Matrix4, SquareMatrix};
+/// use lucifer::lighting::Radiance;
+/// use lucifer::rand::{SeedableRng, XorShiftRng};
+/// use lucifer::render::preview::{send_to, Preview};
+/// use lucifer::render::{Context, DebugRenderer};
+/// use lucifer::scene::Scene;
+///
+/// let scene = Scene::new(Radiance::gray(1.0));
+/// let camera = AffineTransformCamera::new(Matrix4::identity());
+/// let renderer = DebugRenderer::new();
+/// let mut context = Context::new(XorShiftRng::from_seed([0; 16]));
+/// let mut preview = Preview::new(Resolution::new(32, 16), 16);
+///
+/// let (tx, rx) = channel();
+/// assert!(preview.refine(&renderer, &mut context, &scene, &camera, send_to(tx)));
+///
+/// let updates: Vec<_> = rx.iter().collect();
+/// assert_eq!(updates.len(), 2);
+/// assert_eq!(updates[0].pixels[0], Radiance::gray(1.0));
+/// assert_eq!(preview.passes(), 1);
+///
+/// preview.restart();
+/// assert_eq!(preview.passes(), 0);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Preview {
+    tiler: TiledRenderer,
+    film: Film,
+    passes: u32,
+    statistics: Statistics,
+}
+
+impl Preview {
+    /// Creates a new `Preview` session rendering tiles of size
+    /// `tile_size`.
+    pub fn new(resolution: Resolution, tile_size: u32) -> Preview {
+        Preview {
+            tiler: TiledRenderer::new(tile_size, 1),
+            film: Film::new(resolution),
+            passes: 0,
+            statistics: Statistics::new(),
+        }
+    }
+
+    /// The accumulated image.
+    pub fn film(&self) -> &Film {
+        &self.film
+    }
+
+    /// The number of completed passes since the last restart.
+    pub fn passes(&self) -> u32 {
+        self.passes
+    }
+
+    /// The statistics collected since the last restart.
+    pub fn statistics(&self) -> Statistics {
+        self.statistics
+    }
+
+    /// Discards the accumulated image, e.g. because the scene or
+    /// camera changed.
+    pub fn restart(&mut self) {
+        self.film.clear();
+        self.passes = 0;
+        self.statistics = Statistics::new();
+    }
+
+    /// Changes the resolution of the session, which implies a
+    /// restart.
+    pub fn resize(&mut self, resolution: Resolution) {
+        self.film = Film::new(resolution);
+        self.restart();
+    }
+
+    /// Renders one more pass over the image, invoking `callback`
+    /// after every tile.  Returns `false` if the callback cancelled
+    /// the pass, in which case the tiles rendered so far are kept
+    /// but the pass is not counted as complete.
+    pub fn refine<R, F>(
+        &mut self,
+        renderer: &R,
+        context: &mut Context,
+        scene: &Scene,
+        camera: &dyn Camera,
+        mut callback: F,
+    ) -> bool
+    where
+        R: Renderer + ?Sized,
+        F: FnMut(Update) -> Control,
+    {
+        let _span = Span::enter("preview pass");
+        let resolution = self.film.resolution();
+        let seed = context.rng.next_u64();
+        let mut statistics = Statistics::new();
+        let mut completed = true;
+
+        for (i, tile) in self.tiler.tiles(resolution).into_iter().enumerate() {
+            let mut local = Context::seeded(seed, i as u64);
+
+            for target in tile.targets() {
+                let radiance = renderer.render(&mut local, scene, camera, resolution, target);
+                self.film.add(target, radiance);
+            }
+            statistics += local.statistics;
+
+            let update = Update {
+                pass: self.passes,
+                tile,
+                pixels: tile.targets().map(|t| self.film.get(t)).collect(),
+            };
+
+            if callback(update) == Control::Cancel {
+                completed = false;
+                break;
+            }
+        }
+
+        context.statistics += statistics;
+        self.statistics += statistics;
+
+        if completed {
+            self.passes += 1;
+        }
+        completed
+    }
+}
+
+/// Creates an update callback that forwards all updates to a
+/// channel, cancelling the pass once the receiver is gone.
+pub fn send_to(sender: Sender<Update>) -> impl FnMut(Update) -> Control {
+    move |update| match sender.send(update) {
+        Ok(()) => Control::Continue,
+        Err(_) => Control::Cancel,
+    }
+}
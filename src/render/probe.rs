@@ -0,0 +1,302 @@
+//! Irradiance probes for real-time global illumination.
+//!
+//! A probe projects the radiance arriving at a point onto the nine
+//! spherical harmonics of the first three bands, which is enough to
+//! reconstruct the irradiance for any normal to within a few percent
+//! (Ramamoorthi and Hanrahan, "An Efficient Representation for
+//! Irradiance Environment Maps").  A `ProbeGrid` places probes
+//! regularly within a box, and interpolates between them.
+
+use cgmath::Vector3;
+
+use camera::{ProbeCamera, Resolution, Target};
+use consts::PI;
+use geometry::{Bounds, Point, Vector};
+use lighting::Radiance;
+use render::{Context, Renderer};
+use scene::Scene;
+use trace::Span;
+use Float;
+
+/// The number of coefficients of the first three bands.
+const COEFFICIENTS: usize = 9;
+
+/// The spherical harmonics of the first three bands at `direction`,
+/// in the order `Y00, Y1-1, Y10, Y11, Y2-2, Y2-1, Y20, Y21, Y22`.
+fn basis(direction: Vector) -> [Float; COEFFICIENTS] {
+    let Vector3 { x, y, z } = direction;
+    [
+        0.282_095,
+        0.488_603 * y,
+        0.488_603 * z,
+        0.488_603 * x,
+        1.092_548 * x * y,
+        1.092_548 * y * z,
+        0.315_392 * (3.0 * z * z - 1.0),
+        1.092_548 * x * z,
+        0.546_274 * (x * x - y * y),
+    ]
+}
+
+/// The convolution of each band with the clamped cosine, turning
+/// radiance into irradiance.
+const COSINE: [Float; 3] = [PI, 2.0 * PI / 3.0, PI / 4.0];
+
+/// The radiance arriving at a point from all directions, projected
+/// onto spherical harmonics.
+///
+/// # Examples
+/// ```
+/// # extern crate cgmath;
+/// # extern crate lucifer;
+/// use cgmath::{Matrix4, SquareMatrix, Vector3};
+/// use lucifer::consts::PI;
+/// use lucifer::camera::Resolution;
+/// use lucifer::geometry::{Plane, Point, Vector};
+/// use lucifer::lighting::{Albedo, Lambert, Radiance};
+/// use lucifer::render::probe::SphericalHarmonics;
+/// use lucifer::render::{Context, PathTracer};
+/// use lucifer::scene::{Object, Scene};
+///
+/// # fn main() {
+/// // A white sky above a black floor.
+/// let mut scene = Scene::new(Radiance::gray(1.0));
+/// scene.add(Object::new(
+///     Plane::new(Vector::new(0.0, 1.0, 0.0), -1.0),
+///     Lambert::new(Albedo::black()),
+///     Matrix4::identity(),
+/// ));
+///
+/// let probe = SphericalHarmonics::bake(
+///     &PathTracer::new(0.0, 1, 1),
+///     &mut Context::seeded(0, 0),
+///     &scene,
+///     Point::new(0.0, 0.0, 0.0),
+///     Resolution::new(32, 32),
+/// );
+///
+/// let up = probe.irradiance(Vector::new(0.0, 1.0, 0.0));
+/// let down = probe.irradiance(Vector::new(0.0, -1.0, 0.0));
+/// assert!((Vector3::from(up).x - PI).abs() < 0.05 * PI);
+/// assert!(Vector3::from(down).x.abs() < 0.05 * PI);
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct SphericalHarmonics {
+    /// The coefficients, in the order `L00, L1-1, L10, L11, L2-2,
+    /// L2-1, L20, L21, L22`.
+    pub coefficients: [Radiance; COEFFICIENTS],
+}
+
+impl SphericalHarmonics {
+    /// Creates new `SphericalHarmonics` of no radiance.
+    pub fn none() -> Self {
+        SphericalHarmonics {
+            coefficients: [Radiance::none(); COEFFICIENTS],
+        }
+    }
+
+    /// Renders the radiance arriving at `position` from all
+    /// directions with `renderer`, into an image of `resolution` as
+    /// seen by a `ProbeCamera`, and projects it.
+    pub fn bake<R>(
+        renderer: &R,
+        context: &mut Context,
+        scene: &Scene,
+        position: Point,
+        resolution: Resolution,
+    ) -> Self
+    where
+        R: Renderer + ?Sized,
+    {
+        let camera = ProbeCamera::new(position);
+        let solid_angle = 4.0 * PI / (resolution.width as Float * resolution.height as Float);
+        let mut harmonics = SphericalHarmonics::none();
+
+        for y in 0..resolution.height {
+            for x in 0..resolution.width {
+                let target = Target::new(x, y);
+                let radiance = renderer.render(context, scene, &camera, resolution, target);
+                harmonics.add(camera.direction(resolution, target), radiance * solid_angle);
+            }
+        }
+
+        harmonics
+    }
+
+    /// Adds `radiance` arriving from `direction`, weighted by the
+    /// solid angle it covers.
+    pub fn add(&mut self, direction: Vector, radiance: Radiance) {
+        for (c, y) in self.coefficients.iter_mut().zip(basis(direction).iter()) {
+            *c += radiance * *y;
+        }
+    }
+
+    /// The radiance arriving from `direction`.
+    pub fn radiance(&self, direction: Vector) -> Radiance {
+        self.coefficients
+            .iter()
+            .zip(basis(direction).iter())
+            .fold(Radiance::none(), |sum, (c, y)| sum + *c * *y)
+    }
+
+    /// The irradiance on a surface with the `normal`, i.e. the
+    /// radiance arriving from all directions weighted by their cosine
+    /// to the normal.  The radiance reflected by a diffuse surface of
+    /// albedo `ρ` is `ρ / π` of the irradiance.
+    pub fn irradiance(&self, normal: Vector) -> Radiance {
+        let bands = [0, 1, 1, 1, 2, 2, 2, 2, 2];
+        self.coefficients
+            .iter()
+            .zip(basis(normal).iter())
+            .zip(bands.iter())
+            .fold(Radiance::none(), |sum, ((c, y), &l)| {
+                sum + *c * (*y * COSINE[l])
+            })
+    }
+
+    /// Interpolates linearly between `self` and `other`.
+    pub fn lerp(&self, other: &SphericalHarmonics, t: Float) -> SphericalHarmonics {
+        let mut harmonics = *self;
+        for (c, o) in harmonics
+            .coefficients
+            .iter_mut()
+            .zip(other.coefficients.iter())
+        {
+            *c = *c * (1.0 - t) + *o * t;
+        }
+        harmonics
+    }
+}
+
+/// Probes placed regularly within a box, from corner to corner.
+///
+/// # Examples
+/// ```
+/// use lucifer::camera::Resolution;
+/// use lucifer::cgmath::Vector3;
+/// use lucifer::geometry::{Bounds, Point, Vector};
+/// use lucifer::lighting::Radiance;
+/// use lucifer::render::probe::ProbeGrid;
+/// use lucifer::render::{Context, PathTracer};
+/// use lucifer::scene::Scene;
+///
+/// let scene = Scene::new(Radiance::gray(1.0));
+/// let bounds = Bounds::new(Point::new(-1.0, -1.0, -1.0), Point::new(1.0, 1.0, 1.0));
+/// let grid = ProbeGrid::bake(
+///     &PathTracer::new(0.0, 1, 1),
+///     &mut Context::seeded(0, 0),
+///     &scene,
+///     bounds,
+///     [2, 3, 2],
+///     Resolution::new(8, 8),
+/// );
+///
+/// assert_eq!(grid.probes.len(), 12);
+/// assert_eq!(grid.position(1, 1, 0), Point::new(1.0, 0.0, -1.0));
+///
+/// // A uniform sky lights every normal everywhere alike.
+/// let irradiance = grid.irradiance(Point::new(0.3, 0.2, 0.1), Vector::new(1.0, 0.0, 0.0));
+/// assert!((Vector3::from(irradiance).x - lucifer::consts::PI).abs() < 0.05);
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct ProbeGrid {
+    /// The box spanned by the probes.
+    pub bounds: Bounds,
+    /// The number of probes along each axis.
+    pub counts: [u32; 3],
+    /// The probes, with `x` varying fastest, then `y`, then `z`.
+    pub probes: Vec<SphericalHarmonics>,
+}
+
+impl ProbeGrid {
+    /// Bakes `counts` probes within `bounds`, each from an image of
+    /// `resolution`; see `SphericalHarmonics::bake`.  Counts below
+    /// two place a single probe at the center of the axis.
+    pub fn bake<R>(
+        renderer: &R,
+        context: &mut Context,
+        scene: &Scene,
+        bounds: Bounds,
+        counts: [u32; 3],
+        resolution: Resolution,
+    ) -> Self
+    where
+        R: Renderer + ?Sized,
+    {
+        let counts = [counts[0].max(1), counts[1].max(1), counts[2].max(1)];
+        let mut grid = ProbeGrid {
+            bounds,
+            counts,
+            probes: Vec::with_capacity((counts[0] * counts[1] * counts[2]) as usize),
+        };
+
+        debug!("baking {} probes", grid.probes.capacity());
+        let _span = Span::enter("bake probes");
+        for z in 0..counts[2] {
+            for y in 0..counts[1] {
+                for x in 0..counts[0] {
+                    let position = grid.position(x, y, z);
+                    let probe =
+                        SphericalHarmonics::bake(renderer, context, scene, position, resolution);
+                    grid.probes.push(probe);
+                }
+            }
+        }
+
+        grid
+    }
+
+    /// The position of the probe with the indices `x`, `y`, `z`.
+    pub fn position(&self, x: u32, y: u32, z: u32) -> Point {
+        let mut position = self.bounds.center();
+        for (axis, &i) in [x, y, z].iter().enumerate() {
+            let n = self.counts[axis];
+            if n > 1 {
+                let t = i as Float / (n - 1) as Float;
+                position[axis] = self.bounds.min[axis] * (1.0 - t) + self.bounds.max[axis] * t;
+            }
+        }
+        position
+    }
+
+    /// The probe with the indices `x`, `y`, `z`.
+    pub fn probe(&self, x: u32, y: u32, z: u32) -> &SphericalHarmonics {
+        let [nx, ny, _] = self.counts;
+        &self.probes[(x + nx * (y + ny * z)) as usize]
+    }
+
+    /// The probe at `point`, interpolated trilinearly from the eight
+    /// probes around it, or the nearest ones outside the grid.
+    pub fn sample(&self, point: Point) -> SphericalHarmonics {
+        let mut cell = [0; 3];
+        let mut t = [0.0; 3];
+        for axis in 0..3 {
+            let n = self.counts[axis];
+            if n > 1 {
+                let extent = self.bounds.max[axis] - self.bounds.min[axis];
+                let f = ((point[axis] - self.bounds.min[axis]) / extent * (n - 1) as Float)
+                    .max(0.0)
+                    .min((n - 1) as Float);
+                cell[axis] = (f as u32).min(n - 2);
+                t[axis] = f - cell[axis] as Float;
+            }
+        }
+
+        let corner = |dx: u32, dy: u32, dz: u32| {
+            let at = |axis: usize, d: u32| (cell[axis] + d).min(self.counts[axis] - 1);
+            self.probe(at(0, dx), at(1, dy), at(2, dz))
+        };
+        let edge = |dy, dz| corner(0, dy, dz).lerp(corner(1, dy, dz), t[0]);
+        let face = |dz| edge(0, dz).lerp(&edge(1, dz), t[1]);
+        face(0).lerp(&face(1), t[2])
+    }
+
+    /// The irradiance on a surface at `point` with the `normal`; see
+    /// `SphericalHarmonics::irradiance`.
+    pub fn irradiance(&self, point: Point, normal: Vector) -> Radiance {
+        self.sample(point).irradiance(normal)
+    }
+}
@@ -1,35 +1,30 @@
-use std::f32::consts::FRAC_1_PI;
+use consts::FRAC_1_PI;
 
 use cgmath::{InnerSpace, Point3};
 
 use camera::{Camera, Resolution, Target};
 use geometry::{Intersection, Ray};
 use lighting::{Bsdf, Effect, Radiance};
-use render::Renderer;
+use render::{Context, Renderer};
 use scene::Scene;
+use Float;
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub struct Light {
-    pub position: Point3<f32>,
+    pub position: Point3<Float>,
     pub emission: Radiance,
-    pub radius: f32,
+    pub radius: Float,
 }
 
-#[derive(Clone, Debug)]
-pub struct RayTracer {
-    light: Light,
-}
-
-impl RayTracer {
-    pub fn new(light: Light) -> RayTracer {
-        RayTracer { light }
-    }
-
-    fn phong(&mut self, ray: &Ray, intersection: &Intersection, bsdf: &Bsdf) -> Radiance {
-        let light_to_intersection = self.light.position - intersection.position;
+impl Light {
+    /// Computes the light reflected towards `ray`'s origin at
+    /// `intersection` due to this light, ignoring occlusion and the
+    /// surface's own emission.
+    pub fn illuminate(&self, ray: &Ray, intersection: &Intersection, bsdf: &Bsdf) -> Radiance {
+        let light_to_intersection = self.position - intersection.position;
         let incidence = light_to_intersection.normalize();
-        let coverage =
-            (self.light.radius / light_to_intersection.magnitude()).atan() * 0.5 * FRAC_1_PI;
+        let coverage = (self.radius / light_to_intersection.magnitude()).atan() * 0.5 * FRAC_1_PI;
 
         let proj_ray = intersection.normal * intersection.normal.dot(ray.direction);
         let reflected_ray = (ray.direction - proj_ray * 2.0).normalize();
@@ -41,13 +36,11 @@ impl RayTracer {
 
         for effect in &bsdf.effects {
             match *effect {
-                Effect::Emission(emission, pdf) => radiance += emission * pdf.eval(cos_t_normal),
-
                 Effect::DiffuseReflection(albedo, pdf) => {
                     if cos_t_normal > 0.0 {
                         radiance += cos_t_normal
                             * coverage
-                            * self.light.emission
+                            * self.emission
                             * albedo
                             * pdf.eval(cos_t_normal)
                     }
@@ -55,15 +48,41 @@ impl RayTracer {
 
                 Effect::SpecularReflection(albedo, pdf) => {
                     if cos_t_ray > 0.0 {
-                        radiance += cos_t_normal
-                            * coverage
-                            * self.light.emission
-                            * albedo
-                            * pdf.eval(cos_t_ray)
+                        radiance +=
+                            cos_t_normal * coverage * self.emission * albedo * pdf.eval(cos_t_ray)
                     }
                 }
 
-                Effect::DiffuseRefraction(_, _, _) | Effect::SpecularRefraction(_, _, _) => {}
+                Effect::Emission(_, _)
+                | Effect::DiffuseRefraction(_, _, _)
+                | Effect::SpecularRefraction(_, _, _) => {}
+            }
+        }
+
+        radiance
+    }
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct RayTracer {
+    light: Light,
+}
+
+impl RayTracer {
+    pub fn new(light: Light) -> RayTracer {
+        RayTracer { light }
+    }
+
+    fn phong(&self, ray: &Ray, intersection: &Intersection, bsdf: &Bsdf) -> Radiance {
+        let incidence = (self.light.position - intersection.position).normalize();
+        let cos_t_normal = incidence.dot(intersection.normal);
+
+        let mut radiance = self.light.illuminate(ray, intersection, bsdf);
+
+        for effect in &bsdf.effects {
+            if let Effect::Emission(emission, pdf) = *effect {
+                radiance += emission * pdf.eval(cos_t_normal)
             }
         }
 
@@ -73,16 +92,22 @@ impl RayTracer {
 
 impl Renderer for RayTracer {
     fn render(
-        &mut self,
+        &self,
+        context: &mut Context,
         scene: &Scene,
-        camera: &Camera,
+        camera: &dyn Camera,
         resolution: Resolution,
         target: Target,
     ) -> Radiance {
         let ray = camera.primary(resolution, target);
+        context.statistics.paths += 1;
+        context.statistics.rays += 1;
         match scene.intersect(&ray) {
-            None => scene.background(),
-            Some(i) => self.phong(&ray, &i.intersection, &i.bsdf),
+            None => scene.background(ray.direction),
+            Some(i) => {
+                context.statistics.path_vertices += 1;
+                self.phong(&ray, &i.intersection, &i.bsdf)
+            }
         }
     }
 }
@@ -0,0 +1,539 @@
+use std::collections::VecDeque;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use rand::RngCore;
+
+use camera::{Camera, Resolution};
+use lighting::Radiance;
+use render::stats::Stopwatch;
+use render::{
+    single, Context, Film, Progress, Renderer, Report, SampleDistribution, Statistics, Tile,
+    TiledRenderer,
+};
+use scene::Scene;
+use trace::Span;
+use Float;
+
+// The messages of the protocol, all numbers in little endian:
+//
+// worker -> coordinator, once: threads (u32)
+// coordinator -> worker, once: JOB, seed (u64), length (u32), job
+// coordinator -> worker: TILE, stream (u64), x, y, width, height (u32)
+// worker -> coordinator: stream (u64), pixels (u32), rgb (3 * f32)
+//     per pixel, paths, rays, shadow rays, path vertices (u64)
+// coordinator -> worker, last: FINISH
+const JOB: u8 = 0;
+const TILE: u8 = 1;
+const FINISH: u8 = 2;
+
+/// How often idle loops check for new work.
+const POLL: Duration = Duration::from_millis(50);
+
+/// The most threads a worker is trusted to render with.
+const MAX_THREADS: u32 = 1024;
+
+/// The longest job a worker accepts, and a coordinator hands out.
+const MAX_JOB: usize = 1 << 30;
+
+fn read_u8<R: Read>(reader: &mut R) -> io::Result<u8> {
+    let mut bytes = [0; 1];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes[0])
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> io::Result<u64> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn read_f32<R: Read>(reader: &mut R) -> io::Result<f32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(f32::from_le_bytes(bytes))
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// A tile of one pass, identified by the random number stream it is
+/// rendered with.
+#[derive(Clone, Copy, Debug)]
+struct Task {
+    stream: u64,
+    tile: usize,
+}
+
+/// Distributes the tiles of a render over workers connecting via TCP,
+/// and merges the rendered tiles into a `Film`.
+///
+/// The coordinator does not know the scene, or how to render it: it
+/// hands every worker an opaque job, which tells the worker what to
+/// render.  Tiles are rendered with the same random number streams
+/// as by the `TiledRenderer`, so the image does not depend on the
+/// number of workers, or on which of them renders which tile.
+pub struct Coordinator {
+    listener: TcpListener,
+    tiler: TiledRenderer,
+    timeout: Duration,
+}
+
+impl Coordinator {
+    /// Creates a new `Coordinator`, accepting workers on `listener`,
+    /// and splitting the image into tiles and passes like `tiler`.
+    pub fn new(listener: TcpListener, tiler: TiledRenderer) -> Coordinator {
+        Coordinator {
+            listener,
+            tiler,
+            timeout: Duration::from_secs(600),
+        }
+    }
+
+    /// Sets how long a worker may stall, neither sending a rendered
+    /// tile nor accepting new ones, before it is given up on and its
+    /// tiles are rendered by others.  Defaults to ten minutes, and
+    /// should exceed the time a worker takes to render a tile.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::io::{Read, Write};
+    /// use std::net::{TcpListener, TcpStream};
+    /// use std::sync::mpsc;
+    /// use std::thread;
+    /// use std::time::Duration;
+    ///
+    /// use lucifer::camera::{AffineTransformCamera, Resolution};
+    /// use lucifer::cgmath::{Matrix4, SquareMatrix};
+    /// use lucifer::lighting::Radiance;
+    /// use lucifer::render::{Context, Coordinator, Film, PathTracer, TiledRenderer, Worker};
+    /// use lucifer::scene::Scene;
+    ///
+    /// let scene = Scene::new(Radiance::gray(1.0));
+    /// let camera = AffineTransformCamera::new(Matrix4::identity());
+    /// let renderer = PathTracer::new(0.01, 4, 4);
+    /// let res = Resolution::new(8, 8);
+    ///
+    /// let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    /// let address = listener.local_addr().unwrap();
+    /// let coordinator = Coordinator::new(listener, TiledRenderer::new(4, 1))
+    ///     .timeout(Duration::from_millis(200));
+    ///
+    /// let mut film = Film::new(res);
+    /// thread::scope(|scope| {
+    ///     let (connected, stalled) = mpsc::channel();
+    ///     // A worker that takes tiles, but never renders them.
+    ///     scope.spawn(move || {
+    ///         let mut stream = TcpStream::connect(address).unwrap();
+    ///         stream.write_all(&1u32.to_le_bytes()).unwrap();
+    ///         let mut byte = [0];
+    ///         stream.read_exact(&mut byte).unwrap();
+    ///         connected.send(()).unwrap();
+    ///         while stream.read(&mut byte).map_or(false, |n| n > 0) {}
+    ///     });
+    ///     let (renderer, scene, camera) = (&renderer, &scene, &camera);
+    ///     scope.spawn(move || {
+    ///         stalled.recv().unwrap();
+    ///         let worker = Worker::connect(address, 1).unwrap();
+    ///         worker.work(renderer, scene, camera, res).unwrap();
+    ///     });
+    ///     let mut context = Context::seeded(7, 0);
+    ///     coordinator
+    ///         .render_with_progress(b"job", &mut context, &mut film, |_, _| {})
+    ///         .unwrap();
+    /// });
+    ///
+    /// assert!(film.sample_counts().iter().all(|&count| count == 1));
+    /// ```
+    pub fn timeout(self, timeout: Duration) -> Self {
+        let mut coordinator = self;
+        coordinator.timeout = timeout;
+        coordinator
+    }
+
+    /// Renders all passes over `film` on the workers connecting while
+    /// rendering, calling `progress` after every tile.  Workers that
+    /// fail or stall have their tiles rendered by others.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::net::TcpListener;
+    /// use std::thread;
+    ///
+    /// use lucifer::camera::{AffineTransformCamera, Resolution, Target};
+    /// use lucifer::cgmath::{Matrix4, SquareMatrix};
+    /// use lucifer::geometry::{Point, Sphere};
+    /// use lucifer::lighting::{Albedo, Lambert, Radiance};
+    /// use lucifer::render::{Context, Coordinator, Film, PathTracer, TiledRenderer, Worker};
+    /// use lucifer::scene::{Object, Scene};
+    ///
+    /// let mut scene = Scene::new(Radiance::gray(1.0));
+    /// scene.add(Object::new(
+    ///     Sphere::new(Point::new(0.0, 0.0, 0.5), 0.5),
+    ///     Lambert::new(Albedo::gray(0.5)),
+    ///     Matrix4::identity(),
+    /// ));
+    /// let camera = AffineTransformCamera::new(Matrix4::identity());
+    /// let renderer = PathTracer::new(0.01, 4, 4);
+    /// let res = Resolution::new(8, 8);
+    /// let tiler = TiledRenderer::new(4, 2);
+    ///
+    /// let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    /// let address = listener.local_addr().unwrap();
+    /// let coordinator = Coordinator::new(listener, tiler);
+    ///
+    /// let mut remote = Film::new(res);
+    /// thread::scope(|scope| {
+    ///     scope.spawn(|| {
+    ///         let worker = Worker::connect(address, 2).unwrap();
+    ///         assert_eq!(worker.job, b"job");
+    ///         worker.work(&renderer, &scene, &camera, res).unwrap();
+    ///     });
+    ///     let mut context = Context::seeded(7, 0);
+    ///     coordinator
+    ///         .render_with_progress(b"job", &mut context, &mut remote, |_, _| {})
+    ///         .unwrap();
+    /// });
+    ///
+    /// // The same image as rendered locally.
+    /// let mut local = Film::new(res);
+    /// tiler.render(&renderer, &mut Context::seeded(7, 0), &scene, &camera, &mut local);
+    /// assert_eq!(remote.sample_counts(), local.sample_counts());
+    /// for y in 0..8 {
+    ///     for x in 0..8 {
+    ///         let target = Target::new(x, y);
+    ///         assert_eq!(remote.get(target), local.get(target));
+    ///     }
+    /// }
+    /// ```
+    pub fn render_with_progress<F>(
+        &self,
+        job: &[u8],
+        context: &mut Context,
+        film: &mut Film,
+        mut progress: F,
+    ) -> io::Result<Report>
+    where
+        F: FnMut(&Progress, &Film),
+    {
+        let _span = Span::enter("distributed render");
+        let start = Stopwatch::start();
+        let tiles = self.tiler.tiles(film.resolution());
+        let seed = context.rng.next_u64();
+        let tasks: VecDeque<Task> = (0..u64::from(self.tiler.passes))
+            .flat_map(|pass| {
+                let count = tiles.len();
+                (0..count).map(move |tile| Task {
+                    stream: pass * count as u64 + tile as u64,
+                    tile,
+                })
+            })
+            .collect();
+        let total = tasks.len();
+        if job.len() > MAX_JOB {
+            return Err(invalid("job too long"));
+        }
+
+        let queue = Mutex::new(tasks);
+        let finished = AtomicBool::new(false);
+        let (sender, receiver) = mpsc::channel();
+        let mut statistics = Statistics::new();
+        let mut done = 0;
+
+        self.listener.set_nonblocking(true)?;
+        let result = thread::scope(|scope| {
+            let result = (|| {
+                while done < total {
+                    loop {
+                        match self.listener.accept() {
+                            Ok((stream, address)) => {
+                                debug!("worker {} connected", address);
+                                stream.set_nonblocking(false)?;
+                                stream.set_read_timeout(Some(self.timeout))?;
+                                stream.set_write_timeout(Some(self.timeout))?;
+                                let sender = sender.clone();
+                                let (tiles, queue, finished) = (&tiles, &queue, &finished);
+                                scope.spawn(move || {
+                                    let _ =
+                                        serve(stream, seed, job, tiles, queue, finished, sender);
+                                });
+                            }
+                            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                            Err(e) => return Err(e),
+                        }
+                    }
+
+                    if let Ok((task, pixels, local)) = receiver.recv_timeout(POLL) {
+                        for (target, radiance) in tiles[task.tile].targets().zip(pixels) {
+                            film.add(target, radiance);
+                        }
+                        statistics += local;
+                        done += 1;
+
+                        let report = Progress {
+                            done,
+                            total,
+                            elapsed: start.elapsed(),
+                            statistics,
+                        };
+                        progress(&report, film);
+                    }
+                }
+                Ok(())
+            })();
+
+            finished.store(true, Ordering::Relaxed);
+            result
+        });
+        self.listener.set_nonblocking(false)?;
+        result?;
+
+        context.statistics += statistics;
+
+        Ok(Report {
+            elapsed: start.elapsed(),
+            passes: self.tiler.passes,
+            statistics,
+            samples: SampleDistribution::from_counts(film.sample_counts()),
+        })
+    }
+}
+
+/// Feeds tiles from `queue` to the worker at the other end of
+/// `stream`, until all tiles are `finished`.  The tiles in flight are
+/// put back into `queue` if the worker fails, including when reading
+/// or writing times out on a stalled worker.
+fn serve(
+    stream: TcpStream,
+    seed: u64,
+    job: &[u8],
+    tiles: &[Tile],
+    queue: &Mutex<VecDeque<Task>>,
+    finished: &AtomicBool,
+    results: mpsc::Sender<(Task, Vec<Radiance>, Statistics)>,
+) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = BufWriter::new(stream);
+    let mut pending: VecDeque<Task> = VecDeque::new();
+
+    let result = (|| {
+        // Keep every thread of the worker busy while results travel.
+        let capacity = 2 * read_u32(&mut reader)?.clamp(1, MAX_THREADS) as usize;
+
+        writer.write_all(&[JOB])?;
+        writer.write_all(&seed.to_le_bytes())?;
+        writer.write_all(&(job.len() as u32).to_le_bytes())?;
+        writer.write_all(job)?;
+
+        loop {
+            while pending.len() < capacity {
+                let task = match queue.lock().unwrap().pop_front() {
+                    Some(task) => task,
+                    None => break,
+                };
+                let tile = tiles[task.tile];
+                writer.write_all(&[TILE])?;
+                writer.write_all(&task.stream.to_le_bytes())?;
+                for value in &[tile.x, tile.y, tile.width, tile.height] {
+                    writer.write_all(&value.to_le_bytes())?;
+                }
+                pending.push_back(task);
+            }
+            writer.flush()?;
+
+            if pending.is_empty() {
+                if finished.load(Ordering::Relaxed) {
+                    writer.write_all(&[FINISH])?;
+                    return writer.flush();
+                }
+                // Wait for the tiles of failing workers.
+                thread::sleep(POLL);
+                continue;
+            }
+
+            let stream = read_u64(&mut reader)?;
+            let index = pending
+                .iter()
+                .position(|task| task.stream == stream)
+                .ok_or_else(|| invalid("result for a tile never requested"))?;
+            let task = pending[index];
+
+            let count = read_u32(&mut reader)?;
+            let tile = tiles[task.tile];
+            if count != tile.width * tile.height {
+                return Err(invalid("result of the wrong size"));
+            }
+            let mut pixels = Vec::with_capacity(count as usize);
+            for _ in 0..count {
+                let r = read_f32(&mut reader)?;
+                let g = read_f32(&mut reader)?;
+                let b = read_f32(&mut reader)?;
+                pixels.push(Radiance::new(r as Float, g as Float, b as Float));
+            }
+            let statistics = Statistics {
+                paths: read_u64(&mut reader)?,
+                rays: read_u64(&mut reader)?,
+                shadow_rays: read_u64(&mut reader)?,
+                path_vertices: read_u64(&mut reader)?,
+            };
+
+            pending.remove(index);
+            if results.send((task, pixels, statistics)).is_err() {
+                return Ok(());
+            }
+        }
+    })();
+
+    if let Err(ref e) = result {
+        debug!(
+            "worker failed, {} tiles are rendered again: {}",
+            pending.len(),
+            e
+        );
+        queue.lock().unwrap().extend(pending);
+    }
+    result
+}
+
+/// Renders tiles on behalf of a `Coordinator`.
+pub struct Worker {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+    threads: usize,
+    seed: u64,
+    /// The job handed out by the coordinator, describing what to
+    /// render.
+    pub job: Vec<u8>,
+}
+
+impl Worker {
+    /// Connects to the coordinator at `address`, offering to render
+    /// with `threads` threads, and receives the job.
+    pub fn connect<A: ToSocketAddrs>(address: A, threads: usize) -> io::Result<Worker> {
+        let mut writer = TcpStream::connect(address)?;
+        let mut reader = BufReader::new(writer.try_clone()?);
+
+        let threads = threads.max(1);
+        writer.write_all(&(threads as u32).to_le_bytes())?;
+
+        if read_u8(&mut reader)? != JOB {
+            return Err(invalid("expected a job"));
+        }
+        let seed = read_u64(&mut reader)?;
+        let length = read_u32(&mut reader)? as usize;
+        if length > MAX_JOB {
+            return Err(invalid("job too long"));
+        }
+        let mut job = vec![0; length];
+        reader.read_exact(&mut job)?;
+
+        Ok(Worker {
+            reader,
+            writer,
+            threads,
+            seed,
+            job,
+        })
+    }
+
+    /// Renders the tiles requested by the coordinator, until it
+    /// finishes, and returns the counters of all tiles rendered.
+    pub fn work<R>(
+        self,
+        renderer: &R,
+        scene: &Scene,
+        camera: &dyn Camera,
+        resolution: Resolution,
+    ) -> io::Result<Statistics>
+    where
+        R: Renderer + Sync + ?Sized,
+    {
+        let Worker {
+            mut reader,
+            writer,
+            threads,
+            seed,
+            ..
+        } = self;
+        let writer = Mutex::new(BufWriter::new(writer));
+        let statistics = Mutex::new(Statistics::new());
+        let (sender, receiver) = mpsc::channel::<(u64, Tile)>();
+        let receiver = Mutex::new(receiver);
+
+        let render_tile = |stream: u64, tile: Tile| -> io::Result<()> {
+            let mut local = Context::seeded(seed, stream);
+            let pixels: Vec<Radiance> = tile
+                .targets()
+                .map(|target| renderer.render(&mut local, scene, camera, resolution, target))
+                .collect();
+
+            let mut writer = writer.lock().unwrap();
+            writer.write_all(&stream.to_le_bytes())?;
+            writer.write_all(&(pixels.len() as u32).to_le_bytes())?;
+            for radiance in &pixels {
+                for c in 0..3 {
+                    writer.write_all(&single(radiance[c]).to_le_bytes())?;
+                }
+            }
+            let s = local.statistics;
+            for value in &[s.paths, s.rays, s.shadow_rays, s.path_vertices] {
+                writer.write_all(&value.to_le_bytes())?;
+            }
+            writer.flush()?;
+
+            *statistics.lock().unwrap() += s;
+            Ok(())
+        };
+
+        thread::scope(|scope| {
+            let (receiver, render_tile) = (&receiver, &render_tile);
+            for _ in 0..threads {
+                scope.spawn(move || loop {
+                    let task = receiver.lock().unwrap().recv();
+                    match task {
+                        Ok((stream, tile)) => {
+                            if render_tile(stream, tile).is_err() {
+                                break;
+                            }
+                        }
+                        Err(_) => break,
+                    }
+                });
+            }
+
+            // Dropping `sender` on return stops the rendering threads.
+            let sender = sender;
+            loop {
+                match read_u8(&mut reader)? {
+                    TILE => {
+                        let stream = read_u64(&mut reader)?;
+                        let tile = Tile {
+                            x: read_u32(&mut reader)?,
+                            y: read_u32(&mut reader)?,
+                            width: read_u32(&mut reader)?,
+                            height: read_u32(&mut reader)?,
+                        };
+                        if sender.send((stream, tile)).is_err() {
+                            return Err(invalid("the rendering threads failed"));
+                        }
+                    }
+                    FINISH => return Ok(()),
+                    _ => return Err(invalid("unknown message")),
+                }
+            }
+        })?;
+
+        Ok(statistics.into_inner().unwrap())
+    }
+}
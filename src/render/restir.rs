@@ -0,0 +1,442 @@
+use std::sync::OnceLock;
+
+use cgmath::num_traits::clamp;
+use cgmath::InnerSpace;
+use rand::Rng;
+
+use camera::{Camera, Resolution, Target};
+use geometry::{Point, Ray, Vector};
+use lighting::{Effect, Radiance};
+use montecarlo::{Discrete, Reservoir};
+use render::path::reflection;
+use render::ray::Light;
+use render::stats::Stopwatch;
+use render::{Context, Film, Progress, Renderer, Report, SampleDistribution};
+use scene::{Scene, ShadedIntersection};
+use Float;
+
+/// Direct lighting from many point lights and emissive objects using
+/// reservoir-based spatiotemporal importance resampling (ReSTIR).
+///
+/// Every pixel draws `candidates` lights proportional to their
+/// emitted power, either point lights or points on the surfaces of
+/// the scene's emitters, resamples them according to their unshadowed
+/// contribution, and then shares the surviving sample with
+/// `spatial_samples` neighbors within `spatial_radius` pixels and
+/// with the same pixel in the previous frame.  Only a single shadow
+/// ray per pixel is traced, independent of the number of lights.
+///
+/// Reuse happens in `render_frame`, which renders a whole image at
+/// once.  When used as a per-pixel `Renderer`, only the initial
+/// resampling step is performed.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct Restir {
+    lights: Vec<Light>,
+    pub candidates: u32,
+    pub spatial_samples: u32,
+    pub spatial_radius: u32,
+    pub temporal: bool,
+    /// The distribution of `lights`, built with them, or by the first
+    /// pixel after deserializing.
+    #[cfg_attr(feature = "serialize", serde(skip))]
+    distribution: OnceLock<Discrete>,
+    #[cfg_attr(feature = "serialize", serde(skip))]
+    history: Option<(Resolution, Vec<Reservoir<Candidate>>)>,
+}
+
+/// A light sample held by a reservoir.
+#[derive(Clone, Copy, Debug)]
+enum Candidate {
+    /// A point light, by its index in `Restir::lights`.
+    Light(usize),
+    /// A point on the surface of an emissive object, facing along
+    /// `normal`.
+    Surface {
+        position: Point,
+        normal: Vector,
+        emission: Radiance,
+    },
+}
+
+struct Hit {
+    ray: Ray,
+    hit: ShadedIntersection,
+}
+
+fn offset_ray(hit: &Hit, position: Point) -> Ray {
+    let intersection = &hit.hit.intersection;
+    let direction = position - intersection.position;
+    Ray::from_endpoints(intersection.offset_origin(direction), position).at_time(hit.ray.time)
+}
+
+fn emission(hit: &Hit) -> Radiance {
+    let cos_t_view = -hit.ray.direction.dot(hit.hit.intersection.normal);
+
+    let mut radiance = Radiance::none();
+    for effect in &hit.hit.bsdf.effects {
+        if let Effect::Emission(emission, dist) = *effect {
+            radiance += emission * dist.eval(clamp(cos_t_view, 0.0, 1.0));
+        }
+    }
+
+    radiance
+}
+
+/// The distribution of candidate lights, proportional to their
+/// emitted power.  The illumination of a `Light` falls off with
+/// `radius / distance`.
+fn distribution(lights: &[Light]) -> Discrete {
+    let powers: Vec<Float> = lights
+        .iter()
+        .map(|l| l.emission.luma() * l.radius)
+        .collect();
+    Discrete::new(&powers)
+}
+
+impl Restir {
+    pub fn new(lights: Vec<Light>, candidates: u32) -> Restir {
+        Restir {
+            distribution: OnceLock::from(distribution(&lights)),
+            lights,
+            candidates,
+            spatial_samples: 4,
+            spatial_radius: 16,
+            temporal: true,
+            history: None,
+        }
+    }
+
+    /// The point lights, in addition to the emissive objects of the
+    /// scene.
+    pub fn lights(&self) -> &[Light] {
+        &self.lights
+    }
+
+    /// Replaces the point lights, forgetting the reservoirs of the
+    /// previous frame, which refer to the old ones.
+    pub fn set_lights(&mut self, lights: Vec<Light>) {
+        self.distribution = OnceLock::from(distribution(&lights));
+        self.lights = lights;
+        self.reset();
+    }
+
+    /// Forgets the reservoirs of the previous frame, e.g. after the
+    /// camera or scene changed.
+    pub fn reset(&mut self) {
+        self.history = None;
+    }
+
+    fn distribution(&self) -> &Discrete {
+        self.distribution.get_or_init(|| distribution(&self.lights))
+    }
+
+    /// The unshadowed light reflected at `hit` from `candidate`; for
+    /// surface points per unit area of the emitter.
+    fn contribution(&self, hit: &Hit, candidate: Candidate) -> Radiance {
+        let (ray, intersection) = (&hit.ray, &hit.hit.intersection);
+        match candidate {
+            Candidate::Light(light) => {
+                self.lights[light].illuminate(ray, intersection, &hit.hit.bsdf)
+            }
+            Candidate::Surface {
+                position,
+                normal,
+                emission,
+            } => {
+                let offset = position - intersection.position;
+                let distance2 = offset.magnitude2();
+                let incidence = offset / distance2.sqrt();
+                let cos_t_light = -incidence.dot(normal);
+                if cos_t_light <= 0.0 {
+                    return Radiance::none();
+                }
+
+                let mut radiance = Radiance::none();
+                for effect in &hit.hit.bsdf.effects {
+                    let normal = intersection.normal;
+                    if let Some((factor, _)) = reflection(effect, ray.direction, normal, incidence)
+                    {
+                        radiance += factor * emission;
+                    }
+                }
+                radiance * (cos_t_light / distance2)
+            }
+        }
+    }
+
+    fn target(&self, hit: &Hit, candidate: Candidate) -> Float {
+        self.contribution(hit, candidate).luma()
+    }
+
+    /// Draws a candidate light, returning it and its probability
+    /// density; for surface points per unit area of the emitter.
+    /// Point lights and emissive objects get half of the candidates
+    /// each if there are both.
+    fn sample(&self, context: &mut Context, scene: &Scene) -> Option<(Candidate, Float)> {
+        let distribution = self.distribution();
+        let (points, surfaces) = (distribution.total() > 0.0, !scene.emitters().is_empty());
+        let (point, share) = match (points, surfaces) {
+            (true, true) => (context.rng.gen::<Float>() < 0.5, 0.5),
+            (true, false) => (true, 1.0),
+            (false, true) => (false, 1.0),
+            (false, false) => return None,
+        };
+
+        if point {
+            let (light, pdf) = distribution.sample(context.rng.gen())?;
+            return Some((Candidate::Light(light), share * pdf));
+        }
+
+        let (emitter, pdf) = scene.sample_emitter(context.rng.gen())?;
+        let (u, v) = (context.rng.gen(), context.rng.gen());
+        let (position, normal) = scene.sample_emitter_surface(emitter, u, v)?;
+        let candidate = Candidate::Surface {
+            position,
+            normal,
+            emission: emitter.emission,
+        };
+        Some((candidate, share * pdf / emitter.area))
+    }
+
+    fn initial(&self, context: &mut Context, scene: &Scene, hit: &Hit) -> Reservoir<Candidate> {
+        let mut reservoir = Reservoir::new();
+
+        for _ in 0..self.candidates {
+            let (candidate, pdf) = match self.sample(context, scene) {
+                Some(sample) => sample,
+                None => break,
+            };
+            let target = self.target(hit, candidate);
+            reservoir.update(candidate, target / pdf, target, &mut context.rng);
+        }
+
+        reservoir
+    }
+
+    fn intersect(&self, context: &mut Context, scene: &Scene, ray: Ray) -> Option<Hit> {
+        context.statistics.paths += 1;
+        context.statistics.rays += 1;
+
+        let hit = scene.intersect(&ray).map(|hit| Hit { ray, hit });
+        if hit.is_some() {
+            context.statistics.path_vertices += 1;
+        }
+
+        hit
+    }
+
+    fn occluded(
+        &self,
+        context: &mut Context,
+        scene: &Scene,
+        hit: &Hit,
+        candidate: Candidate,
+    ) -> bool {
+        context.statistics.shadow_rays += 1;
+        match candidate {
+            Candidate::Light(light) => scene.occlude(&offset_ray(hit, self.lights[light].position)),
+            Candidate::Surface { position, .. } => {
+                // Stop just short of the emitter itself.
+                let mut ray = offset_ray(hit, position);
+                ray.length *= 1.0 - 1e-3;
+                scene.occlude(&ray)
+            }
+        }
+    }
+
+    fn shade(
+        &self,
+        context: &mut Context,
+        scene: &Scene,
+        hit: &Hit,
+        reservoir: &mut Reservoir<Candidate>,
+    ) -> Radiance {
+        let mut radiance = emission(hit);
+
+        if let Some(&candidate) = reservoir.sample() {
+            if self.occluded(context, scene, hit, candidate) {
+                reservoir.discard();
+            } else {
+                radiance += self.contribution(hit, candidate) * reservoir.contribution_weight();
+            }
+        }
+
+        radiance
+    }
+
+    fn similar(a: &Hit, b: &Hit) -> bool {
+        let (ia, ib) = (&a.hit.intersection, &b.hit.intersection);
+        ia.normal.dot(ib.normal) > 0.9 && (ia.lambda - ib.lambda).abs() < 0.1 * ia.lambda
+    }
+
+    /// Renders a full frame, reusing reservoirs spatially and, if
+    /// `temporal` is set and the previous frame had the same
+    /// `Resolution`, temporally.  Returns the pixels in row-major
+    /// order.
+    pub fn render_frame(
+        &mut self,
+        context: &mut Context,
+        scene: &Scene,
+        camera: &dyn Camera,
+        resolution: Resolution,
+    ) -> Vec<Radiance> {
+        let (width, height) = (resolution.width, resolution.height);
+
+        let mut hits = Vec::with_capacity((width * height) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                let ray = camera.primary(resolution, Target::new(x, y));
+                let hit = self.intersect(context, scene, ray);
+                hits.push(hit);
+            }
+        }
+
+        let mut reservoirs: Vec<Reservoir<Candidate>> = Vec::with_capacity(hits.len());
+        for hit in &hits {
+            reservoirs.push(match *hit {
+                Some(ref hit) => {
+                    let mut reservoir = self.initial(context, scene, hit);
+                    if let Some(&candidate) = reservoir.sample() {
+                        if self.occluded(context, scene, hit, candidate) {
+                            reservoir.discard();
+                        }
+                    }
+                    reservoir
+                }
+                None => Reservoir::new(),
+            });
+        }
+
+        let history = self.history.take();
+        if let Some((res, previous)) = history {
+            if self.temporal && res.width == width && res.height == height {
+                let max_count = 20 * self.candidates.max(1);
+                for (i, mut old) in previous.into_iter().enumerate() {
+                    if let Some(ref hit) = hits[i] {
+                        old.clamp(max_count);
+                        let target = old.sample().map_or(0.0, |&c| self.target(hit, c));
+                        reservoirs[i].merge(old, target, &mut context.rng);
+                    }
+                }
+            }
+        }
+
+        let mut combined = reservoirs.clone();
+        let radius = self.spatial_radius as i64;
+        for y in 0..height {
+            for x in 0..width {
+                let i = (y * width + x) as usize;
+                let hit = match hits[i] {
+                    Some(ref hit) => hit,
+                    None => continue,
+                };
+
+                for _ in 0..self.spatial_samples {
+                    let nx = x as i64 + context.rng.gen_range(-radius, radius + 1);
+                    let ny = y as i64 + context.rng.gen_range(-radius, radius + 1);
+                    if nx < 0 || ny < 0 || nx >= width as i64 || ny >= height as i64 {
+                        continue;
+                    }
+
+                    let j = (ny as u32 * width + nx as u32) as usize;
+                    if i == j {
+                        continue;
+                    }
+                    if let Some(ref neighbor) = hits[j] {
+                        if Self::similar(hit, neighbor) {
+                            let other = reservoirs[j];
+                            let target = other.sample().map_or(0.0, |&c| self.target(hit, c));
+                            combined[i].merge(other, target, &mut context.rng);
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut image = Vec::with_capacity(hits.len());
+        for (i, (hit, reservoir)) in hits.iter().zip(combined.iter_mut()).enumerate() {
+            image.push(match *hit {
+                Some(ref hit) => self.shade(context, scene, hit, reservoir),
+                None => {
+                    let target = Target::new(i as u32 % width, i as u32 / width);
+                    scene.background(camera.primary(resolution, target).direction)
+                }
+            });
+        }
+
+        self.history = Some((resolution, combined));
+        image
+    }
+
+    /// Renders `frames` frames of the `film`'s resolution, adding one
+    /// sample per pixel each, reusing reservoirs from frame to frame
+    /// if `temporal` is set.  Calls `progress` after every frame.
+    pub fn render_with_progress<F>(
+        &mut self,
+        frames: u32,
+        context: &mut Context,
+        scene: &Scene,
+        camera: &dyn Camera,
+        film: &mut Film,
+        mut progress: F,
+    ) -> Report
+    where
+        F: FnMut(&Progress, &Film),
+    {
+        let start = Stopwatch::start();
+        let before = context.take_statistics();
+        let resolution = film.resolution();
+
+        for frame in 0..frames {
+            let image = self.render_frame(context, scene, camera, resolution);
+            for (i, radiance) in image.into_iter().enumerate() {
+                let i = i as u32;
+                film.add(
+                    Target::new(i % resolution.width, i / resolution.width),
+                    radiance,
+                );
+            }
+
+            let report = Progress {
+                done: frame as usize + 1,
+                total: frames as usize,
+                elapsed: start.elapsed(),
+                statistics: context.statistics,
+            };
+            progress(&report, film);
+        }
+
+        let statistics = context.take_statistics();
+        context.statistics = before;
+        context.statistics += statistics;
+
+        Report {
+            elapsed: start.elapsed(),
+            passes: frames,
+            statistics,
+            samples: SampleDistribution::from_counts(film.sample_counts()),
+        }
+    }
+}
+
+impl Renderer for Restir {
+    fn render(
+        &self,
+        context: &mut Context,
+        scene: &Scene,
+        camera: &dyn Camera,
+        resolution: Resolution,
+        target: Target,
+    ) -> Radiance {
+        let ray = camera.primary(resolution, target);
+        let direction = ray.direction;
+        match self.intersect(context, scene, ray) {
+            None => scene.background(direction),
+            Some(hit) => {
+                let mut reservoir = self.initial(context, scene, &hit);
+                self.shade(context, scene, &hit, &mut reservoir)
+            }
+        }
+    }
+}
@@ -0,0 +1,420 @@
+use std::fmt;
+use std::ops::{Add, AddAssign};
+use std::time::Duration;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+
+use lighting::Radiance;
+use Float;
+
+/// Counters collected by a `Renderer` while rendering.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Statistics {
+    /// The number of camera paths started.
+    pub paths: u64,
+    /// The number of rays tested for their nearest intersection.
+    pub rays: u64,
+    /// The number of rays only tested for occlusion.
+    pub shadow_rays: u64,
+    /// The number of surface interactions along all paths.
+    pub path_vertices: u64,
+}
+
+impl Statistics {
+    /// Creates a new `Statistics` with all counters set to zero.
+    pub fn new() -> Statistics {
+        Statistics::default()
+    }
+
+    /// The total number of rays of either kind.
+    pub fn total_rays(&self) -> u64 {
+        self.rays + self.shadow_rays
+    }
+
+    /// The mean number of surface interactions per camera path.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::render::Statistics;
+    ///
+    /// let stats = Statistics {
+    ///     paths: 4,
+    ///     rays: 10,
+    ///     shadow_rays: 2,
+    ///     path_vertices: 6,
+    /// };
+    ///
+    /// assert_eq!(stats.average_path_depth(), 1.5);
+    /// assert_eq!(stats.shadow_ray_ratio(), 2.0 / 12.0);
+    /// ```
+    pub fn average_path_depth(&self) -> f64 {
+        ratio(self.path_vertices, self.paths)
+    }
+
+    /// The fraction of shadow rays among all rays.
+    pub fn shadow_ray_ratio(&self) -> f64 {
+        ratio(self.shadow_rays, self.total_rays())
+    }
+}
+
+fn ratio(a: u64, b: u64) -> f64 {
+    if b == 0 {
+        0.0
+    } else {
+        a as f64 / b as f64
+    }
+}
+
+impl Add for Statistics {
+    type Output = Statistics;
+    fn add(self, rhs: Statistics) -> Statistics {
+        Statistics {
+            paths: self.paths + rhs.paths,
+            rays: self.rays + rhs.rays,
+            shadow_rays: self.shadow_rays + rhs.shadow_rays,
+            path_vertices: self.path_vertices + rhs.path_vertices,
+        }
+    }
+}
+
+impl AddAssign for Statistics {
+    fn add_assign(&mut self, rhs: Statistics) {
+        *self = *self + rhs;
+    }
+}
+
+/// The distribution of the number of samples over all pixels.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SampleDistribution {
+    pub min: u32,
+    pub max: u32,
+    pub mean: f64,
+}
+
+impl SampleDistribution {
+    /// Computes the distribution of a list of per-pixel sample
+    /// counts.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::render::SampleDistribution;
+    ///
+    /// let dist = SampleDistribution::from_counts(&[1, 2, 3, 6]);
+    ///
+    /// assert_eq!(dist.min, 1);
+    /// assert_eq!(dist.max, 6);
+    /// assert_eq!(dist.mean, 3.0);
+    /// ```
+    pub fn from_counts(counts: &[u32]) -> SampleDistribution {
+        if counts.is_empty() {
+            return SampleDistribution::default();
+        }
+
+        let sum: u64 = counts.iter().map(|&c| u64::from(c)).sum();
+        SampleDistribution {
+            min: *counts.iter().min().unwrap(),
+            max: *counts.iter().max().unwrap(),
+            mean: sum as f64 / counts.len() as f64,
+        }
+    }
+}
+
+/// A summary of the pixels of an image.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ImageStatistics {
+    /// The number of pixels.
+    pub pixels: usize,
+    /// The mean radiance of the valid pixels.
+    pub mean: Radiance,
+    /// The largest value of every channel of the valid pixels.
+    pub max: Radiance,
+    /// The number of pixels without any radiance.
+    pub black: usize,
+    /// The number of pixels with a channel that is negative, NaN, or
+    /// infinite, left out of `mean` and `max`.
+    pub invalid: usize,
+}
+
+impl ImageStatistics {
+    /// Computes the statistics of a list of pixels.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::lighting::Radiance;
+    /// use lucifer::render::ImageStatistics;
+    /// use lucifer::Float;
+    ///
+    /// let stats = ImageStatistics::from_pixels(&[
+    ///     Radiance::new(1.0, 0.0, 2.0),
+    ///     Radiance::none(),
+    ///     Radiance::gray(Float::NAN),
+    /// ]);
+    ///
+    /// assert_eq!(stats.mean, Radiance::new(0.5, 0.0, 1.0));
+    /// assert_eq!(stats.max, Radiance::new(1.0, 0.0, 2.0));
+    /// assert_eq!((stats.black, stats.invalid), (1, 1));
+    /// ```
+    pub fn from_pixels(pixels: &[Radiance]) -> ImageStatistics {
+        let mut stats = ImageStatistics {
+            pixels: pixels.len(),
+            ..ImageStatistics::default()
+        };
+
+        let mut sum = Radiance::none();
+        for &pixel in pixels {
+            if (0..3).any(|c| !(pixel[c] >= 0.0 && pixel[c].is_finite())) {
+                stats.invalid += 1;
+                continue;
+            }
+            if pixel == Radiance::none() {
+                stats.black += 1;
+            }
+            sum += pixel;
+            for c in 0..3 {
+                stats.max[c] = stats.max[c].max(pixel[c]);
+            }
+        }
+
+        let valid = stats.pixels - stats.invalid;
+        if valid > 0 {
+            stats.mean = sum / valid as Float;
+        }
+        stats
+    }
+}
+
+impl fmt::Display for ImageStatistics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "mean radiance:     {:.4} / {:.4} / {:.4}",
+            self.mean[0], self.mean[1], self.mean[2]
+        )?;
+        writeln!(
+            f,
+            "max radiance:      {:.4} / {:.4} / {:.4}",
+            self.max[0], self.max[1], self.max[2]
+        )?;
+        write!(
+            f,
+            "black/invalid:     {} / {} of {} pixels",
+            self.black, self.invalid, self.pixels
+        )
+    }
+}
+
+/// A histogram of the luma of the pixels of an image, over bins of
+/// equal width in stops, i.e. powers of two.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Histogram {
+    /// The base-two logarithm of the luma at the start of the first
+    /// bin.
+    pub min: Float,
+    /// The base-two logarithm of the luma at the end of the last
+    /// bin.
+    pub max: Float,
+    /// The number of pixels in each bin.  Pixels outside the range
+    /// are counted in the first or last bin.
+    pub counts: Vec<usize>,
+}
+
+impl Histogram {
+    /// Sorts the `lumas` of pixels into `bins` bins spanning the
+    /// stops `min` to `max`.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::render::Histogram;
+    ///
+    /// let histogram = Histogram::from_lumas(&[0.0, 0.3, 0.6, 1.5, 100.0], 4, -2.0, 2.0);
+    ///
+    /// assert_eq!(histogram.counts, vec![2, 1, 1, 1]);
+    /// assert_eq!(histogram.bin(2), (1.0, 2.0));
+    /// ```
+    pub fn from_lumas(lumas: &[Float], bins: usize, min: Float, max: Float) -> Histogram {
+        let mut counts = vec![0; bins.max(1)];
+        let last = counts.len() - 1;
+        let scale = counts.len() as Float / (max - min);
+
+        for &luma in lumas {
+            let bin = ((luma.log2() - min) * scale).floor();
+            // NaN and the logarithm of zero land in the first bin.
+            let bin = if bin >= 0.0 {
+                (bin as usize).min(last)
+            } else {
+                0
+            };
+            counts[bin] += 1;
+        }
+
+        Histogram { min, max, counts }
+    }
+
+    /// The range of luma of the bin with `index`.
+    pub fn bin(&self, index: usize) -> (Float, Float) {
+        let width = (self.max - self.min) / self.counts.len() as Float;
+        let start = self.min + width * index as Float;
+        (start.exp2(), (start + width).exp2())
+    }
+}
+
+/// A summary of a completed render.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Report {
+    /// The wall-clock time spent rendering.
+    pub elapsed: Duration,
+    /// The number of passes over the image.
+    pub passes: u32,
+    /// The counters collected by the renderer.
+    pub statistics: Statistics,
+    /// The distribution of samples per pixel.
+    pub samples: SampleDistribution,
+}
+
+impl Report {
+    /// The number of rays of either kind traced per second.
+    pub fn rays_per_second(&self) -> f64 {
+        per_second(self.statistics.total_rays(), self.elapsed)
+    }
+}
+
+fn per_second(count: u64, elapsed: Duration) -> f64 {
+    let seconds = elapsed.as_secs_f64();
+    if seconds > 0.0 {
+        count as f64 / seconds
+    } else {
+        0.0
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "time:              {}.{:03}s",
+            self.elapsed.as_secs(),
+            self.elapsed.subsec_millis()
+        )?;
+        writeln!(f, "passes:            {}", self.passes)?;
+        writeln!(f, "rays:              {}", self.statistics.total_rays())?;
+        writeln!(f, "rays/sec:          {:.0}", self.rays_per_second())?;
+        writeln!(
+            f,
+            "avg. path depth:   {:.3}",
+            self.statistics.average_path_depth()
+        )?;
+        writeln!(
+            f,
+            "shadow-ray ratio:  {:.3}",
+            self.statistics.shadow_ray_ratio()
+        )?;
+        write!(
+            f,
+            "samples/pixel:     min {} / mean {:.2} / max {}",
+            self.samples.min, self.samples.mean, self.samples.max
+        )
+    }
+}
+
+/// The state of a render in progress, reported after every tile.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Progress {
+    /// The number of tiles completed, over all passes.
+    pub done: usize,
+    /// The number of tiles of all passes.
+    pub total: usize,
+    /// The wall-clock time spent rendering so far.
+    pub elapsed: Duration,
+    /// The counters collected so far.
+    pub statistics: Statistics,
+}
+
+impl Progress {
+    /// The completed fraction of the render, between `0` and `1`.
+    pub fn fraction(&self) -> f64 {
+        if self.total == 0 {
+            1.0
+        } else {
+            self.done as f64 / self.total as f64
+        }
+    }
+
+    /// The estimated time until the render completes, assuming the
+    /// remaining tiles take as long as the completed ones.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::time::Duration;
+    ///
+    /// use lucifer::render::Progress;
+    ///
+    /// let progress = Progress {
+    ///     done: 10,
+    ///     total: 40,
+    ///     elapsed: Duration::from_secs(5),
+    ///     ..Progress::default()
+    /// };
+    ///
+    /// assert_eq!(progress.fraction(), 0.25);
+    /// assert_eq!(progress.remaining(), Some(Duration::from_secs(15)));
+    /// ```
+    pub fn remaining(&self) -> Option<Duration> {
+        if self.done == 0 {
+            None
+        } else {
+            let left = (self.total - self.done) as f64 / self.done as f64;
+            Some(self.elapsed.mul_f64(left))
+        }
+    }
+
+    /// The number of rays of either kind traced per second so far.
+    pub fn rays_per_second(&self) -> f64 {
+        per_second(self.statistics.total_rays(), self.elapsed)
+    }
+}
+
+/// Shows a progress bar, the elapsed and remaining time, and the
+/// current ray throughput on one line.
+impl fmt::Display for Progress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        const WIDTH: usize = 30;
+        let filled = (self.fraction() * WIDTH as f64).round() as usize;
+
+        write!(
+            f,
+            "[{}{}] {:3.0}% {:>6.1}s elapsed",
+            "#".repeat(filled),
+            "-".repeat(WIDTH - filled),
+            self.fraction() * 100.0,
+            self.elapsed.as_secs_f64()
+        )?;
+        match self.remaining() {
+            Some(remaining) => write!(f, ", {:>6.1}s left", remaining.as_secs_f64())?,
+            None => write!(f, ",       ?s left")?,
+        }
+        write!(f, ", {:.2}M rays/s", self.rays_per_second() * 1e-6)
+    }
+}
+
+/// Measures the time elapsed since it was started.
+///
+/// `wasm32-unknown-unknown` has no clock, so no time ever passes
+/// there.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Stopwatch(#[cfg(not(target_arch = "wasm32"))] Instant);
+
+impl Stopwatch {
+    pub(crate) fn start() -> Stopwatch {
+        #[cfg(not(target_arch = "wasm32"))]
+        return Stopwatch(Instant::now());
+        #[cfg(target_arch = "wasm32")]
+        return Stopwatch();
+    }
+
+    pub(crate) fn elapsed(&self) -> Duration {
+        #[cfg(not(target_arch = "wasm32"))]
+        return self.0.elapsed();
+        #[cfg(target_arch = "wasm32")]
+        return Duration::from_secs(0);
+    }
+}
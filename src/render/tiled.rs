@@ -0,0 +1,592 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+
+use camera::{Camera, Resolution, Target};
+use lighting::Radiance;
+use montecarlo::Convergence;
+use rand::RngCore;
+
+use render::stats::Stopwatch;
+use render::{Context, Film, Progress, Renderer, Report, SampleDistribution, Statistics};
+use scene::Scene;
+use trace::Span;
+use Float;
+
+/// A rectangular region of an image.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct Tile {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Tile {
+    /// Iterates over all pixels within the tile, in row-major order.
+    pub fn targets(self) -> impl Iterator<Item = Target> {
+        let Tile {
+            x,
+            y,
+            width,
+            height,
+        } = self;
+        (y..y + height).flat_map(move |ty| (x..x + width).map(move |tx| Target::new(tx, ty)))
+    }
+
+    /// Splits the tile into quarters, or halves or nothing if it is
+    /// only one pixel wide or high.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::render::Tile;
+    ///
+    /// let tile = Tile { x: 16, y: 0, width: 5, height: 1 };
+    /// let halves = tile.split();
+    ///
+    /// assert_eq!(halves.len(), 2);
+    /// assert_eq!(halves[1], Tile { x: 18, y: 0, width: 3, height: 1 });
+    /// ```
+    pub fn split(self) -> Vec<Tile> {
+        let halve = |start: u32, size: u32| match size {
+            0 | 1 => vec![(start, size)],
+            _ => vec![(start, size / 2), (start + size / 2, size - size / 2)],
+        };
+
+        let columns = halve(self.x, self.width);
+        halve(self.y, self.height)
+            .into_iter()
+            .flat_map(|(y, height)| {
+                columns.iter().map(move |&(x, width)| Tile {
+                    x,
+                    y,
+                    width,
+                    height,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Drives a `Renderer` over a whole image, tile by tile, in several
+/// progressive passes.
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct TiledRenderer {
+    /// The edge length of the square tiles.
+    pub tile_size: u32,
+    /// The number of passes over the image.
+    pub passes: u32,
+    /// The number of threads rendering tiles in parallel.
+    pub threads: usize,
+    /// The criterion for skipping tiles in later passes, once all of
+    /// their pixels have converged.  `passes` then is the maximum.
+    #[cfg_attr(feature = "serialize", serde(default))]
+    pub convergence: Option<Convergence>,
+    /// The cost of a tile, relative to the mean of a pass, above which
+    /// it is split into smaller tiles in the next pass.
+    #[cfg_attr(feature = "serialize", serde(default))]
+    pub subdivision: Option<Float>,
+}
+
+impl TiledRenderer {
+    /// Creates a new, single-threaded `TiledRenderer`.
+    pub fn new(tile_size: u32, passes: u32) -> TiledRenderer {
+        TiledRenderer {
+            tile_size,
+            passes,
+            threads: 1,
+            convergence: None,
+            subdivision: None,
+        }
+    }
+
+    /// Sets the number of threads rendering tiles in parallel.
+    pub fn threads(self, threads: usize) -> Self {
+        let mut tiler = self;
+        tiler.threads = threads.max(1);
+        tiler
+    }
+
+    /// Stops rendering tiles whose pixels all satisfy `criterion`.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::camera::{AffineTransformCamera, Resolution};
+    /// use lucifer::cgmath::{Matrix4, SquareMatrix};
+    /// use lucifer::lighting::Radiance;
+    /// use lucifer::montecarlo::Convergence;
+    /// use lucifer::rand::{SeedableRng, XorShiftRng};
+    /// use lucifer::render::{Context, DebugRenderer, Film, TiledRenderer};
+    /// use lucifer::scene::Scene;
+    ///
+    /// let scene = Scene::new(Radiance::gray(0.5));
+    /// let camera = AffineTransformCamera::new(Matrix4::identity());
+    /// let mut context = Context::new(XorShiftRng::from_seed([0; 16]));
+    /// let mut film = Film::new(Resolution::new(8, 8));
+    ///
+    /// // A constant image converges as soon as it may.
+    /// let tiler = TiledRenderer::new(4, 100).convergence(Convergence::new(0.95, 0.01));
+    /// let report = tiler.render(&DebugRenderer::new(), &mut context, &scene, &camera, &mut film);
+    ///
+    /// assert_eq!(report.passes, 16);
+    /// ```
+    pub fn convergence(self, criterion: Convergence) -> Self {
+        let mut tiler = self;
+        tiler.convergence = Some(criterion);
+        tiler
+    }
+
+    /// Splits tiles costing more than `factor` times the mean of a
+    /// pass into smaller tiles in the next pass, so that threads do
+    /// not idle while one of them is stuck with a difficult tile.
+    ///
+    /// The cost of a tile is the number of rays and surface
+    /// interactions it took, which depends on the scene alone, so the
+    /// image still does not depend on the number of `threads`.  The
+    /// smaller tiles use random number streams of their own, however,
+    /// so the image differs from one rendered without subdivision.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::camera::{AffineTransformCamera, Resolution, Target};
+    /// use lucifer::cgmath::{Matrix4, SquareMatrix};
+    /// use lucifer::lighting::Radiance;
+    /// use lucifer::rand::{SeedableRng, XorShiftRng};
+    /// use lucifer::render::{Context, DebugRenderer, Film, TiledRenderer};
+    /// use lucifer::scene::Scene;
+    ///
+    /// let scene = Scene::new(Radiance::gray(0.5));
+    /// let camera = AffineTransformCamera::new(Matrix4::identity());
+    /// let render = |threads| {
+    ///     let mut context = Context::new(XorShiftRng::from_seed([0; 16]));
+    ///     let mut film = Film::new(Resolution::new(40, 30));
+    ///     let mut parts = 0;
+    ///     let tiler = TiledRenderer::new(16, 2).threads(threads).subdivision(1.0);
+    ///     let renderer = DebugRenderer::new();
+    ///     tiler.render_with_progress(&renderer, &mut context, &scene, &camera, &mut film, |_, _| {
+    ///         parts += 1
+    ///     });
+    ///     (film.pixels(), parts)
+    /// };
+    ///
+    /// // The four full tiles are split into quarters in the second pass.
+    /// assert_eq!(render(1).1, 6 + 4 * 4 + 2);
+    /// assert_eq!(render(1), render(4));
+    /// ```
+    pub fn subdivision(self, factor: Float) -> Self {
+        let mut tiler = self;
+        tiler.subdivision = Some(factor);
+        tiler
+    }
+
+    /// Splits an image of the given `Resolution` into tiles.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::camera::Resolution;
+    /// use lucifer::render::TiledRenderer;
+    ///
+    /// let tiles = TiledRenderer::new(16, 1).tiles(Resolution::new(40, 16));
+    ///
+    /// assert_eq!(tiles.len(), 3);
+    /// assert_eq!(tiles[2].x, 32);
+    /// assert_eq!(tiles[2].width, 8);
+    /// ```
+    pub fn tiles(&self, resolution: Resolution) -> Vec<Tile> {
+        let size = self.tile_size.max(1);
+        let mut tiles = Vec::new();
+
+        for y in (0..resolution.height).step_by(size as usize) {
+            for x in (0..resolution.width).step_by(size as usize) {
+                tiles.push(Tile {
+                    x,
+                    y,
+                    width: size.min(resolution.width - x),
+                    height: size.min(resolution.height - y),
+                });
+            }
+        }
+
+        tiles
+    }
+
+    /// Renders `passes` estimates for every pixel of `film`, or fewer
+    /// for tiles that converged earlier, and reports on the work done.
+    ///
+    /// Every tile of every pass is rendered with its own random
+    /// number stream, derived from a seed drawn from `context`, so the
+    /// result only depends on the state of `context` and not on the
+    /// order in which tiles are processed, or on the number of
+    /// `threads`.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::camera::{AffineTransformCamera, Resolution, Target};
+    /// use lucifer::cgmath::{Matrix4, SquareMatrix};
+    /// use lucifer::lighting::Radiance;
+    /// use lucifer::rand::{SeedableRng, XorShiftRng};
+    /// use lucifer::render::{Context, DebugRenderer, Film, TiledRenderer};
+    /// use lucifer::scene::Scene;
+    ///
+    /// let scene = Scene::new(Radiance::gray(0.5));
+    /// let camera = AffineTransformCamera::new(Matrix4::identity());
+    /// let mut context = Context::new(XorShiftRng::from_seed([0; 16]));
+    /// let mut film = Film::new(Resolution::new(40, 30));
+    ///
+    /// let tiler = TiledRenderer::new(16, 2).threads(4);
+    /// let report = tiler.render(&DebugRenderer::new(), &mut context, &scene, &camera, &mut film);
+    ///
+    /// assert_eq!(report.statistics.paths, 2 * 40 * 30);
+    /// assert_eq!(film.get(Target::new(39, 29)), Radiance::gray(0.5));
+    /// ```
+    pub fn render<R>(
+        &self,
+        renderer: &R,
+        context: &mut Context,
+        scene: &Scene,
+        camera: &dyn Camera,
+        film: &mut Film,
+    ) -> Report
+    where
+        R: Renderer + Sync + ?Sized,
+    {
+        self.render_with_progress(renderer, context, scene, camera, film, |_, _| {})
+    }
+
+    /// Like `render`, but calls `progress` after every completed tile,
+    /// with the film rendered so far.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::camera::{AffineTransformCamera, Resolution};
+    /// use lucifer::cgmath::{Matrix4, SquareMatrix};
+    /// use lucifer::lighting::Radiance;
+    /// use lucifer::rand::{SeedableRng, XorShiftRng};
+    /// use lucifer::render::{Context, DebugRenderer, Film, TiledRenderer};
+    /// use lucifer::scene::Scene;
+    ///
+    /// let scene = Scene::new(Radiance::gray(0.5));
+    /// let camera = AffineTransformCamera::new(Matrix4::identity());
+    /// let mut context = Context::new(XorShiftRng::from_seed([0; 16]));
+    /// let mut film = Film::new(Resolution::new(40, 30));
+    ///
+    /// let mut updates = Vec::new();
+    /// let tiler = TiledRenderer::new(16, 2).threads(4);
+    /// tiler.render_with_progress(&DebugRenderer::new(), &mut context, &scene, &camera, &mut film, |p, _| {
+    ///     updates.push((p.done, p.total))
+    /// });
+    ///
+    /// assert_eq!(updates.len(), 12);
+    /// assert_eq!(updates.last(), Some(&(12, 12)));
+    /// ```
+    pub fn render_with_progress<R, F>(
+        &self,
+        renderer: &R,
+        context: &mut Context,
+        scene: &Scene,
+        camera: &dyn Camera,
+        film: &mut Film,
+        mut progress: F,
+    ) -> Report
+    where
+        R: Renderer + Sync + ?Sized,
+        F: FnMut(&Progress, &Film),
+    {
+        let films = ::std::slice::from_mut(film);
+        self.render_all(renderer, context, scene, &[camera], films, |p, f| {
+            progress(p, &f[0])
+        })
+    }
+
+    /// Renders several views of `scene` at once, the film at each
+    /// index seen through the camera at the same index, and reports
+    /// on the work done for all of them.
+    ///
+    /// Views of the same resolution, e.g. the two eyes of a stereo
+    /// pair, are rendered together, pixel by pixel, with
+    /// `Renderer::render_views`: a `PathTracer` traces the camera rays
+    /// of all of them through the scene in one traversal, and samples
+    /// their lights from the one distribution of the scene.  The tiles
+    /// of all views share one pool of threads, which keeps them busy
+    /// until the last view is done.  Every view is rendered exactly as
+    /// with `render`, except for the random number streams.
+    ///
+    /// # Panics
+    /// Panics if the numbers of cameras and films differ.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::camera::{AffineTransformCamera, Camera, Resolution, Target};
+    /// use lucifer::cgmath::{Matrix4, SquareMatrix};
+    /// use lucifer::lighting::Radiance;
+    /// use lucifer::rand::{SeedableRng, XorShiftRng};
+    /// use lucifer::render::{Context, DebugRenderer, Film, TiledRenderer};
+    /// use lucifer::scene::Scene;
+    ///
+    /// let scene = Scene::new(Radiance::gray(0.5));
+    /// let (left, right) = AffineTransformCamera::new(Matrix4::identity()).stereo(0.065);
+    /// let cameras: [&dyn Camera; 3] = [&left, &right, &left];
+    /// let mut films = vec![
+    ///     Film::new(Resolution::new(40, 30)),
+    ///     Film::new(Resolution::new(40, 30)),
+    ///     Film::new(Resolution::new(20, 10)),
+    /// ];
+    /// let mut context = Context::new(XorShiftRng::from_seed([0; 16]));
+    ///
+    /// let tiler = TiledRenderer::new(16, 2).threads(4);
+    /// let renderer = DebugRenderer::new();
+    /// let report = tiler.render_views(&renderer, &mut context, &scene, &cameras, &mut films);
+    ///
+    /// assert_eq!(report.statistics.paths, 2 * (2 * 40 * 30 + 20 * 10));
+    /// assert_eq!(films[1].get(Target::new(39, 29)), Radiance::gray(0.5));
+    /// assert_eq!(films[2].get(Target::new(19, 9)), Radiance::gray(0.5));
+    /// ```
+    pub fn render_views<R>(
+        &self,
+        renderer: &R,
+        context: &mut Context,
+        scene: &Scene,
+        cameras: &[&dyn Camera],
+        films: &mut [Film],
+    ) -> Report
+    where
+        R: Renderer + Sync + ?Sized,
+    {
+        self.render_all(renderer, context, scene, cameras, films, |_, _| {})
+    }
+
+    /// Renders the views of `cameras` into `films`, calling `progress`
+    /// after every completed tile with the films rendered so far.
+    fn render_all<R, F>(
+        &self,
+        renderer: &R,
+        context: &mut Context,
+        scene: &Scene,
+        cameras: &[&dyn Camera],
+        films: &mut [Film],
+        mut progress: F,
+    ) -> Report
+    where
+        R: Renderer + Sync + ?Sized,
+        F: FnMut(&Progress, &[Film]),
+    {
+        assert!(
+            cameras.len() == films.len(),
+            "every view needs one camera and one film"
+        );
+
+        let _span = Span::enter("render");
+        let start = Stopwatch::start();
+        // Views of the same resolution are rendered together, tile by
+        // tile, so renderers can share work between them.
+        let mut groups: Vec<(Resolution, Vec<usize>)> = Vec::new();
+        for (view, film) in films.iter().enumerate() {
+            let resolution = film.resolution();
+            match groups.iter_mut().find(|g| g.0 == resolution) {
+                Some(group) => group.1.push(view),
+                None => groups.push((resolution, vec![view])),
+            }
+        }
+        let group_cameras: Vec<Vec<&dyn Camera>> = groups
+            .iter()
+            .map(|(_, views)| views.iter().map(|&view| cameras[view]).collect())
+            .collect();
+        // The tiles of all groups of views, each with its group.
+        let tiles: Vec<(usize, Tile)> = groups
+            .iter()
+            .enumerate()
+            .flat_map(|(group, &(resolution, _))| {
+                self.tiles(resolution).into_iter().map(move |t| (group, t))
+            })
+            .collect();
+        let seed = context.rng.next_u64();
+        let mut statistics = Statistics::new();
+        let mut active: Vec<usize> = (0..tiles.len()).collect();
+        let mut passes = 0;
+        let total = tiles.len() * self.passes as usize;
+        let mut done = 0;
+        // The cost of every tile in the last pass it was rendered in.
+        let mut costs: Vec<u64> = vec![0; tiles.len()];
+
+        // A part of the tile `i`, rendered with the random number
+        // stream of the whole tile for the first part.  The pixels of
+        // the group's views are interleaved.
+        let render_tile = |pass: u32, (i, part, tile): (usize, u64, Tile)| {
+            let stream = (u64::from(pass) * tiles.len() as u64 + i as u64) ^ (part << 48);
+            let mut local = Context::seeded(seed, stream);
+            let group = tiles[i].0;
+            let (cameras, resolution) = (&group_cameras[group], groups[group].0);
+
+            let mut pixels: Vec<Radiance> =
+                Vec::with_capacity((tile.width * tile.height) as usize * cameras.len());
+            for target in tile.targets() {
+                match cameras[..] {
+                    [camera] => {
+                        pixels.push(renderer.render(&mut local, scene, camera, resolution, target))
+                    }
+                    _ => pixels.extend(
+                        renderer.render_views(&mut local, scene, cameras, resolution, target),
+                    ),
+                }
+            }
+
+            trace!("rendered tile {} of pass {}", i, pass);
+            (i, tile, pixels, local.statistics)
+        };
+
+        for pass in 0..self.passes {
+            if active.is_empty() {
+                break;
+            }
+            passes += 1;
+            let work = self.schedule(&tiles, &active, &costs);
+            debug!(
+                "pass {} renders {} of {} tiles in {} parts",
+                pass,
+                active.len(),
+                tiles.len(),
+                work.len()
+            );
+            for &i in &active {
+                costs[i] = 0;
+            }
+            let mut parts: Vec<usize> = vec![0; tiles.len()];
+            for &(i, _, _) in &work {
+                parts[i] += 1;
+            }
+
+            // Tiles of a pass never overlap, so the order in which
+            // their results are added does not matter.
+            let mut finish =
+                |(i, tile, pixels, local): (usize, Tile, Vec<Radiance>, Statistics)| {
+                    let views = &groups[tiles[i].0].1;
+                    for (k, &view) in views.iter().enumerate() {
+                        let radiances = pixels.iter().skip(k).step_by(views.len());
+                        for (target, &radiance) in tile.targets().zip(radiances) {
+                            films[view].add(target, radiance);
+                        }
+                    }
+                    statistics += local;
+                    costs[i] += local.total_rays() + local.path_vertices;
+                    parts[i] -= 1;
+                    if parts[i] == 0 {
+                        done += 1;
+                    }
+
+                    let report = Progress {
+                        done,
+                        total,
+                        elapsed: start.elapsed(),
+                        statistics,
+                    };
+                    progress(&report, films);
+                };
+
+            if self.threads <= 1 {
+                for &part in &work {
+                    finish(render_tile(pass, part));
+                }
+            } else {
+                // Workers pull tiles from a shared counter, the most
+                // expensive first, and send their results back as soon
+                // as they are done.
+                let next = &AtomicUsize::new(0);
+                let (work, render_tile) = (&work, &render_tile);
+                let (sender, receiver) = mpsc::channel();
+
+                thread::scope(|scope| {
+                    for _ in 0..self.threads {
+                        let sender = sender.clone();
+                        scope.spawn(move || loop {
+                            let i = next.fetch_add(1, Ordering::Relaxed);
+                            if i >= work.len() || sender.send(render_tile(pass, work[i])).is_err() {
+                                break;
+                            }
+                        });
+                    }
+                    drop(sender);
+
+                    for result in receiver {
+                        finish(result);
+                    }
+                });
+            }
+
+            if let Some(criterion) = self.convergence {
+                let before = active.len();
+                active.retain(|&i| {
+                    let (group, tile) = tiles[i];
+                    !groups[group]
+                        .1
+                        .iter()
+                        .all(|&view| tile.targets().all(|t| films[view].converged(t, &criterion)))
+                });
+
+                // Converged tiles skip all remaining passes.
+                let remaining = (self.passes - pass - 1) as usize;
+                done += (before - active.len()) * remaining;
+            }
+        }
+
+        context.statistics += statistics;
+
+        Report {
+            elapsed: start.elapsed(),
+            passes,
+            statistics,
+            samples: SampleDistribution::from_counts(
+                &films
+                    .iter()
+                    .flat_map(|f| f.sample_counts().iter().cloned())
+                    .collect::<Vec<_>>(),
+            ),
+        }
+    }
+
+    /// The parts of the `active` tiles to render in a pass, each with
+    /// the index of its tile and its number within the tile, ordered
+    /// by their `costs` in the previous pass, the most expensive first.
+    fn schedule(
+        &self,
+        tiles: &[(usize, Tile)],
+        active: &[usize],
+        costs: &[u64],
+    ) -> Vec<(usize, u64, Tile)> {
+        let mean = active.iter().map(|&i| costs[i]).sum::<u64>() as Float / active.len() as Float;
+        let mut work: Vec<(Float, (usize, u64, Tile))> = Vec::new();
+
+        for &i in active {
+            let tile = tiles[i].1;
+            let cost = costs[i] as Float;
+            let mut parts = vec![tile];
+            if let Some(factor) = self.subdivision.filter(|_| mean > 0.0) {
+                // Split until the parts are cheap enough, assuming the
+                // cost is spread evenly over the tile, but at most
+                // twice.
+                let mut share = cost;
+                for _ in 0..2 {
+                    if share <= factor * mean {
+                        break;
+                    }
+                    let split: Vec<Tile> = parts.iter().flat_map(|t| t.split()).collect();
+                    share *= parts.len() as Float / split.len() as Float;
+                    parts = split;
+                }
+            }
+
+            let share = cost / parts.len() as Float;
+            work.extend(
+                parts
+                    .into_iter()
+                    .enumerate()
+                    .map(|(part, t)| (share, (i, part as u64, t))),
+            );
+        }
+
+        // A stable sort keeps the order of the tiles in the first pass.
+        work.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(::std::cmp::Ordering::Equal));
+        work.into_iter().map(|(_, part)| part).collect()
+    }
+}
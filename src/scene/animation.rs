@@ -0,0 +1,158 @@
+use cgmath::{Decomposed, EuclideanSpace, InnerSpace, Matrix4, Point3, Quaternion, Vector3};
+use Float;
+
+/// A position, orientation, and uniform scale, which can be
+/// interpolated smoothly (unlike a `Matrix4`).
+pub type Pose = Decomposed<Vector3<Float>, Quaternion<Float>>;
+
+/// Values that can be blended between keyframes.
+pub trait Interpolate: Clone {
+    /// Blends from `self` (at `t = 0`) to `other` (at `t = 1`).
+    fn interpolate(&self, other: &Self, t: Float) -> Self;
+}
+
+impl Interpolate for Float {
+    fn interpolate(&self, other: &Float, t: Float) -> Float {
+        self + (other - self) * t
+    }
+}
+
+impl Interpolate for Vector3<Float> {
+    fn interpolate(&self, other: &Vector3<Float>, t: Float) -> Vector3<Float> {
+        self.lerp(*other, t)
+    }
+}
+
+impl Interpolate for Point3<Float> {
+    fn interpolate(&self, other: &Point3<Float>, t: Float) -> Point3<Float> {
+        Point3::from_vec(self.to_vec().lerp(other.to_vec(), t))
+    }
+}
+
+impl Interpolate for Quaternion<Float> {
+    fn interpolate(&self, other: &Quaternion<Float>, t: Float) -> Quaternion<Float> {
+        self.slerp(*other, t)
+    }
+}
+
+impl Interpolate for Pose {
+    fn interpolate(&self, other: &Pose, t: Float) -> Pose {
+        Decomposed {
+            scale: self.scale.interpolate(&other.scale, t),
+            rot: self.rot.interpolate(&other.rot, t),
+            disp: self.disp.interpolate(&other.disp, t),
+        }
+    }
+}
+
+/// How a `Track` blends between keyframes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Interpolation {
+    /// Holds the value of the previous keyframe.
+    Step,
+    /// Blends linearly.
+    Linear,
+    /// Blends with a smooth start and stop (smoothstep).
+    Smooth,
+}
+
+/// A keyframed value over time.  Before the first and after the last
+/// keyframe, the value of the respective keyframe holds.
+///
+/// Tracks can animate anything that implements `Interpolate`, e.g. a
+/// camera's `Pose` or field of view, which are then evaluated per
+/// frame.
+///
+/// # Examples
+/// ```
+/// use lucifer::scene::{Interpolation, Track};
+///
+/// let track = Track::new(Interpolation::Linear)
+///     .key(0.0, 1.0)
+///     .key(2.0, 3.0);
+///
+/// assert_eq!(track.at(-1.0), Some(1.0));
+/// assert_eq!(track.at(1.0), Some(2.0));
+/// assert_eq!(track.at(5.0), Some(3.0));
+/// ```
+#[derive(Clone, Debug)]
+pub struct Track<T> {
+    keys: Vec<(Float, T)>,
+    pub interpolation: Interpolation,
+}
+
+impl<T: Interpolate> Track<T> {
+    /// Creates a new `Track` without keyframes.
+    pub fn new(interpolation: Interpolation) -> Track<T> {
+        Track {
+            keys: Vec::new(),
+            interpolation,
+        }
+    }
+
+    /// Creates a new `Track` with a single, constant value.
+    pub fn constant(value: T) -> Track<T> {
+        Track::new(Interpolation::Step).key(0.0, value)
+    }
+
+    /// Adds a keyframe, builder style.
+    pub fn key(self, time: Float, value: T) -> Self {
+        let mut track = self;
+        track.add_key(time, value);
+        track
+    }
+
+    /// Adds a keyframe, replacing any keyframe at the same `time`.
+    pub fn add_key(&mut self, time: Float, value: T) {
+        match self.keys.binary_search_by(|&(t, _)| t.total_cmp(&time)) {
+            Ok(i) => self.keys[i] = (time, value),
+            Err(i) => self.keys.insert(i, (time, value)),
+        }
+    }
+
+    /// The keyframes, sorted by time.
+    pub fn keys(&self) -> &[(Float, T)] {
+        &self.keys
+    }
+
+    /// Evaluates the track at `time`.  Returns `None` if the track
+    /// has no keyframes.
+    pub fn at(&self, time: Float) -> Option<T> {
+        let next = self.keys.iter().position(|&(t, _)| t > time);
+
+        match next {
+            None => self.keys.last().map(|k| k.1.clone()),
+            Some(0) => Some(self.keys[0].1.clone()),
+            Some(i) => {
+                let (t0, ref v0) = self.keys[i - 1];
+                let (t1, ref v1) = self.keys[i];
+                let t = (time - t0) / (t1 - t0);
+
+                Some(match self.interpolation {
+                    Interpolation::Step => v0.clone(),
+                    Interpolation::Linear => v0.interpolate(v1, t),
+                    Interpolation::Smooth => v0.interpolate(v1, t * t * (3.0 - 2.0 * t)),
+                })
+            }
+        }
+    }
+}
+
+/// The keyframed motion of an `Object`.
+///
+/// The track gives the object's transformation relative to its
+/// `parent`, which is the transformation of the enclosing `Group`s.
+#[derive(Clone, Debug)]
+pub struct Animation {
+    pub parent: Matrix4<Float>,
+    pub track: Track<Pose>,
+}
+
+impl Animation {
+    /// The world transformation of the object at `time`.
+    pub fn transform(&self, time: Float) -> Option<Matrix4<Float>> {
+        self.track
+            .at(time)
+            .map(|pose| self.parent * Matrix4::from(pose))
+    }
+}
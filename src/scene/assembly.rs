@@ -0,0 +1,256 @@
+use std::mem;
+
+use geometry::bvh::Split;
+use geometry::{Bounds, Bvh, Geometry, Intersection, KdTree, Ray};
+use lighting::{Bsdf, Material};
+use scene::{Group, Hit, Object};
+use Float;
+
+/// A reusable set of objects, placed as a whole by instances (see
+/// `Object::instance`).
+///
+/// All instances share the objects and their `Bvh` (or `KdTree`, see
+/// `with_kdtree`), so placing an assembly a thousand times costs a
+/// thousand transformations, not a thousand copies.  Assemblies may
+/// contain instances of further assemblies, e.g. a forest of trees
+/// made of instanced leaves, and every level only tests the objects
+/// its `Bvh` finds along the ray.
+///
+/// Hits are shaded with the materials of the assembly's objects.
+/// Their emission is visible, but emissive objects inside assemblies
+/// are not registered as light sources, and their animations are not
+/// evaluated by `Scene::set_time`.
+///
+/// # Examples
+/// ```
+/// use std::sync::Arc;
+///
+/// use lucifer::cgmath::{vec3, Matrix4, SquareMatrix};
+/// use lucifer::geometry::{Geometry, Point, Ray, Sphere, Vector};
+/// use lucifer::lighting::{Albedo, Lambert, Material, Radiance};
+/// use lucifer::scene::{Assembly, Object, Scene};
+/// use lucifer::Float;
+///
+/// let sphere: Arc<dyn Geometry> = Arc::new(Sphere::new(Point::new(0.0, 0.0, 0.0), 0.4));
+/// let material: Arc<dyn Material> = Arc::new(Lambert::new(Albedo::gray(0.8)));
+/// let ball = |x: i32| {
+///     let transform = Matrix4::from_translation(vec3(x as Float, 0.0, 0.0));
+///     Object::shared(sphere.clone(), material.clone(), transform)
+/// };
+///
+/// // A row of ten balls, and a grid of ten rows.
+/// let row = Arc::new(Assembly::new((0..10).map(ball).collect()));
+/// let grid = Arc::new(Assembly::new(
+///     (0..10).map(|y| Object::instance(row.clone(), Matrix4::from_translation(vec3(0.0, y as Float, 0.0)))).collect(),
+/// ));
+///
+/// let mut scene = Scene::new(Radiance::none());
+/// scene.add(Object::instance(grid, Matrix4::from_translation(vec3(0.0, 0.0, -10.0))));
+///
+/// let ray = Ray::new(Point::new(3.0, 7.0, 0.0), Vector::new(0.0, 0.0, -1.0));
+/// let hit = scene.intersect(&ray).unwrap();
+///
+/// assert!((hit.intersection.lambda - 9.6).abs() < 1e-4);
+/// assert_eq!(scene.objects().len(), 1);
+/// ```
+pub struct Assembly {
+    objects: Vec<Object>,
+    index: Index,
+}
+
+/// The structure finding the objects along a ray.
+enum Index {
+    Bvh(Bvh),
+    KdTree(KdTree),
+}
+
+impl Index {
+    fn traverse<F>(&self, ray: &Ray, visit: F)
+    where
+        F: FnMut(usize) -> Option<Float>,
+    {
+        match self {
+            Index::Bvh(bvh) => bvh.traverse(ray, visit),
+            Index::KdTree(tree) => tree.traverse(ray, visit),
+        }
+    }
+
+    fn any<F>(&self, ray: &Ray, test: F) -> bool
+    where
+        F: FnMut(usize) -> bool,
+    {
+        match self {
+            Index::Bvh(bvh) => bvh.any(ray, test),
+            Index::KdTree(tree) => tree.any(ray, test),
+        }
+    }
+
+    fn bounds(&self) -> Bounds {
+        match self {
+            Index::Bvh(bvh) => bvh.bounds(),
+            Index::KdTree(tree) => tree.bounds(),
+        }
+    }
+
+    fn memory(&self) -> usize {
+        match self {
+            Index::Bvh(bvh) => bvh.memory(),
+            Index::KdTree(tree) => tree.memory(),
+        }
+    }
+}
+
+impl Assembly {
+    /// Creates a new `Assembly` of `objects`, whose transformations
+    /// place them relative to the assembly.
+    pub fn new(objects: Vec<Object>) -> Assembly {
+        Assembly::with_split(objects, Split::default())
+    }
+
+    /// Creates a new `Assembly` of `objects` like `new`, but divides
+    /// the objects in its `Bvh` by `split`.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::cgmath::{vec3, Matrix4};
+    /// use lucifer::geometry::bvh::Split;
+    /// use lucifer::geometry::{Geometry, Point, Ray, Sphere, Vector};
+    /// use lucifer::lighting::{Albedo, Lambert};
+    /// use lucifer::scene::{Assembly, Object};
+    /// use lucifer::Float;
+    ///
+    /// let balls = (0..10)
+    ///     .map(|i| {
+    ///         let ball = Sphere::new(Point::new(0.0, 0.0, 0.0), 0.4);
+    ///         let place = Matrix4::from_translation(vec3(i as Float, 0.0, 0.0));
+    ///         Object::new(ball, Lambert::new(Albedo::gray(0.8)), place)
+    ///     })
+    ///     .collect();
+    /// let row = Assembly::with_split(balls, Split::Median);
+    ///
+    /// let ray = Ray::new(Point::new(3.0, 0.0, 5.0), Vector::new(0.0, 0.0, -1.0));
+    /// assert!((row.intersect(&ray).unwrap().lambda - 4.6).abs() < 1e-4);
+    /// ```
+    pub fn with_split(objects: Vec<Object>, split: Split) -> Assembly {
+        Assembly {
+            index: Index::Bvh(Bvh::with_split(&object_bounds(&objects), split)),
+            objects,
+        }
+    }
+
+    /// Creates a new `Assembly` of `objects` like `new`, but finds
+    /// the objects along a ray with a `KdTree` instead of a `Bvh`.
+    /// This may trace faster through large static sets of objects,
+    /// like the walls and furniture of a building.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::cgmath::{vec3, Matrix4};
+    /// use lucifer::geometry::{Cube, Geometry, Point, Ray, Vector};
+    /// use lucifer::lighting::{Albedo, Lambert};
+    /// use lucifer::scene::{Assembly, Object};
+    /// use lucifer::Float;
+    ///
+    /// // A colonnade of fifty pillars.
+    /// let pillars = (0..50)
+    ///     .map(|i| {
+    ///         let pillar = Cube::new(Point::new(0.0, 0.0, 0.0), Vector::new(1.0, 4.0, 1.0));
+    ///         let white = Lambert::new(Albedo::gray(0.8));
+    ///         let place = Matrix4::from_translation(vec3(i as Float * 3.0, 0.0, 0.0));
+    ///         Object::new(pillar, white, place)
+    ///     })
+    ///     .collect();
+    /// let colonnade = Assembly::with_kdtree(pillars);
+    ///
+    /// let ray = Ray::new(Point::new(-5.0, 0.0, 0.0), Vector::new(1.0, 0.0, 0.0));
+    /// let hit = colonnade.intersect(&ray).unwrap();
+    /// assert_eq!(hit.lambda, 4.5);
+    /// assert!(colonnade.occlude(&ray));
+    /// ```
+    pub fn with_kdtree(objects: Vec<Object>) -> Assembly {
+        Assembly {
+            index: Index::KdTree(KdTree::new(&object_bounds(&objects))),
+            objects,
+        }
+    }
+
+    /// The objects of the assembly.
+    pub fn objects(&self) -> &[Object] {
+        &self.objects
+    }
+
+    /// The nearest hit, in the space of the assembly.
+    pub(crate) fn hit(&self, ray: &Ray, cull: bool) -> Option<Hit<'_>> {
+        let mut nearest: Option<Hit> = None;
+
+        self.index.traverse(ray, |i| {
+            let hit = self.objects[i].intersect(ray, cull)?;
+            let lambda = hit.world.lambda;
+            if nearest.as_ref().is_none_or(|n| lambda < n.world.lambda) {
+                nearest = Some(hit);
+            }
+            Some(lambda)
+        });
+
+        nearest
+    }
+}
+
+impl From<Group> for Assembly {
+    fn from(group: Group) -> Assembly {
+        Assembly::new(group.flatten())
+    }
+}
+
+impl Geometry for Assembly {
+    fn intersect(&self, ray: &Ray) -> Option<Intersection> {
+        self.hit(ray, false).map(|hit| hit.world)
+    }
+
+    fn occlude(&self, ray: &Ray) -> bool {
+        self.index.any(ray, |i| self.objects[i].occlude(ray, false))
+    }
+
+    fn intersect_front(&self, ray: &Ray) -> Option<Intersection> {
+        self.hit(ray, true).map(|hit| hit.world)
+    }
+
+    fn occlude_front(&self, ray: &Ray) -> bool {
+        self.index.any(ray, |i| self.objects[i].occlude(ray, true))
+    }
+
+    fn bounds(&self) -> Bounds {
+        self.index.bounds()
+    }
+
+    /// The number of triangles of all objects, counting every
+    /// instance.
+    fn triangle_count(&self) -> usize {
+        self.objects
+            .iter()
+            .map(|o| o.geometry.triangle_count())
+            .sum()
+    }
+
+    /// The memory of the `Bvh` or `KdTree` and all objects, counting
+    /// every instance.
+    fn memory(&self) -> usize {
+        let objects: usize = self.objects.iter().map(Object::memory).sum();
+        mem::size_of::<Assembly>() + self.index.memory() + objects
+    }
+}
+
+/// The material of an instance, which is never used, as hits are
+/// shaded with the materials of the assembly's objects.
+pub(crate) struct Inherited;
+
+impl Material for Inherited {
+    fn shade(&self, _: &Intersection) -> Bsdf {
+        Bsdf::new()
+    }
+}
+
+/// The bounds of `objects`, in the space of the assembly.
+fn object_bounds(objects: &[Object]) -> Vec<Bounds> {
+    objects.iter().map(Object::bounds).collect()
+}
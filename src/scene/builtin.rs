@@ -0,0 +1,216 @@
+use cgmath::{Deg, Matrix4, SquareMatrix, Transform};
+
+use geometry::{Cube, Plane, Point, Quad, Sphere, Vector};
+use lighting::{Albedo, Blackbody, Dielectric, Gradient, Ior, Lambert, Phong, Radiance};
+use render::ray::Light;
+use scene::{Object, Scene};
+use Float;
+
+/// A procedural scene shipped with lucifer, e.g. for first renders
+/// and regression tests that need no asset files.
+pub struct Builtin {
+    pub scene: Scene,
+    /// The position of the camera.
+    pub position: Point,
+    /// The point the camera looks at.
+    pub look_at: Point,
+    /// The vertical field of view, in degrees.
+    pub fov: Float,
+    /// The point lights illuminating the scene for the `RayTracer`.
+    pub lights: Vec<Light>,
+}
+
+impl Builtin {
+    /// The names of all built-in scenes.
+    pub const NAMES: [&'static str; 4] = ["cornell", "spheres", "caustic", "furnace"];
+
+    /// The built-in scene called `name`, if any.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::scene::Builtin;
+    ///
+    /// for name in Builtin::NAMES.iter() {
+    ///     assert!(Builtin::by_name(name).is_some());
+    /// }
+    /// assert!(Builtin::by_name("teapot").is_none());
+    /// ```
+    pub fn by_name(name: &str) -> Option<Builtin> {
+        match name {
+            "cornell" => Some(Builtin::cornell_box()),
+            "spheres" => Some(Builtin::spheres()),
+            "caustic" => Some(Builtin::caustic()),
+            "furnace" => Some(Builtin::furnace()),
+            _ => None,
+        }
+    }
+
+    /// The Cornell box, with a tall and a short block.
+    pub fn cornell_box() -> Builtin {
+        let white = Lambert::new(Albedo::new(0.725, 0.71, 0.68));
+        let red = Lambert::new(Albedo::new(0.63, 0.065, 0.05));
+        let green = Lambert::new(Albedo::new(0.14, 0.45, 0.091));
+        let glow = Blackbody::new(Radiance::new(17.0, 12.0, 4.0));
+
+        let mut scene = Scene::new(Radiance::none());
+        scene.add(Object::new(
+            Cube::new(Point::new(0.0, 2.0, 0.0), Vector::new(4.0, 0.02, 4.0)),
+            white.clone(),
+            Matrix4::identity(),
+        ));
+        scene.add(Object::new(
+            Cube::new(Point::new(0.0, -2.0, 0.0), Vector::new(4.0, 0.02, 4.0)),
+            white.clone(),
+            Matrix4::identity(),
+        ));
+        scene.add(Object::new(
+            Cube::new(Point::new(0.0, 0.0, -2.0), Vector::new(4.0, 4.0, 0.02)),
+            white.clone(),
+            Matrix4::identity(),
+        ));
+        scene.add(Object::new(
+            Cube::new(Point::new(-2.0, 0.0, 0.0), Vector::new(0.02, 4.0, 4.0)),
+            red,
+            Matrix4::identity(),
+        ));
+        scene.add(Object::new(
+            Cube::new(Point::new(2.0, 0.0, 0.0), Vector::new(0.02, 4.0, 4.0)),
+            green,
+            Matrix4::identity(),
+        ));
+        scene.add(Object::new(
+            Cube::new(Point::new(0.0, 0.0, 0.0), Vector::new(1.2, 2.4, 1.2)),
+            white.clone(),
+            Matrix4::from_translation(Vector::new(-0.7, -0.8, -0.5))
+                .concat(&Matrix4::from_angle_y(Deg(-160.0))),
+        ));
+        scene.add(Object::new(
+            Cube::new(Point::new(0.0, 0.0, 0.0), Vector::new(1.2, 1.2, 1.2)),
+            white,
+            Matrix4::from_translation(Vector::new(0.7, -1.4, 0.4))
+                .concat(&Matrix4::from_angle_y(Deg(160.0))),
+        ));
+        scene.add(Object::new(
+            Quad::from_corner(
+                Point::new(-0.52, 1.97, -0.35),
+                Vector::new(0.94, 0.0, 0.0),
+                Vector::new(0.0, 0.0, 0.76),
+            ),
+            glow,
+            Matrix4::identity(),
+        ));
+
+        Builtin {
+            scene,
+            position: Point::new(0.0, 0.0, 6.8),
+            look_at: Point::new(0.0, 0.0, 0.0),
+            fov: 40.0,
+            lights: vec![Light {
+                position: Point::new(0.0, 1.9, 0.0),
+                emission: Radiance::new(17.0, 12.0, 4.0),
+                radius: 0.1,
+            }],
+        }
+    }
+
+    /// A row of spheres of different materials on a gray floor,
+    /// under a sky gradient: diffuse, glossy, mirror, glass, and
+    /// emissive.
+    pub fn spheres() -> Builtin {
+        let sky = Gradient::new(
+            Radiance::gray(0.2),
+            Radiance::new(0.9, 0.9, 1.0),
+            Radiance::new(0.3, 0.5, 1.0),
+        );
+        let mut scene = Scene::new(sky);
+        scene.add(Object::new(
+            Plane::new(Vector::new(0.0, 1.0, 0.0), 0.0),
+            Lambert::new(Albedo::gray(0.5)),
+            Matrix4::identity(),
+        ));
+
+        let glossy = Phong::new()
+            .color(Albedo::new(0.1, 0.2, 0.5))
+            .highlight(Albedo::gray(0.4), 50.0);
+        let mirror = Phong::new().highlight(Albedo::gray(0.9), 1000.0);
+        let glass = Dielectric::new(Albedo::white(), Ior(1.5));
+        let glow = Blackbody::new(Radiance::new(4.0, 3.0, 1.5));
+
+        let sphere = |x: Float| Sphere::new(Point::new(x, 0.5, 0.0), 0.5);
+        scene.add(Object::new(
+            sphere(-2.4),
+            Lambert::new(Albedo::new(0.8, 0.3, 0.2)),
+            Matrix4::identity(),
+        ));
+        scene.add(Object::new(sphere(-1.2), glossy, Matrix4::identity()));
+        scene.add(Object::new(sphere(0.0), mirror, Matrix4::identity()));
+        scene.add(Object::new(sphere(1.2), glass, Matrix4::identity()));
+        scene.add(Object::new(sphere(2.4), glow, Matrix4::identity()));
+
+        Builtin {
+            scene,
+            position: Point::new(0.0, 2.0, 6.0),
+            look_at: Point::new(0.0, 0.4, 0.0),
+            fov: 40.0,
+            lights: vec![Light {
+                position: Point::new(-3.0, 5.0, 4.0),
+                emission: Radiance::gray(30.0),
+                radius: 0.1,
+            }],
+        }
+    }
+
+    /// A glass sphere on a white floor, lit by a small, bright light,
+    /// focusing a caustic onto the floor.
+    pub fn caustic() -> Builtin {
+        let mut scene = Scene::new(Radiance::gray(0.02));
+        scene.add(Object::new(
+            Plane::new(Vector::new(0.0, 1.0, 0.0), 0.0),
+            Lambert::new(Albedo::gray(0.8)),
+            Matrix4::identity(),
+        ));
+        scene.add(Object::new(
+            Sphere::new(Point::new(0.0, 1.0, 0.0), 1.0),
+            Dielectric::new(Albedo::white(), Ior(1.5)),
+            Matrix4::identity(),
+        ));
+        scene.add(Object::new(
+            Sphere::new(Point::new(-1.5, 4.0, -1.0), 0.25),
+            Blackbody::new(Radiance::gray(200.0)),
+            Matrix4::identity(),
+        ));
+
+        Builtin {
+            scene,
+            position: Point::new(0.0, 4.0, 6.0),
+            look_at: Point::new(0.5, 0.5, 0.5),
+            fov: 40.0,
+            lights: vec![Light {
+                position: Point::new(-1.5, 4.0, -1.0),
+                emission: Radiance::gray(200.0),
+                radius: 0.25,
+            }],
+        }
+    }
+
+    /// The white furnace test: a white, diffuse sphere in a uniform
+    /// white environment.  An energy conserving renderer makes the
+    /// sphere disappear, apart from the darkening caused by its depth
+    /// limit.
+    pub fn furnace() -> Builtin {
+        let mut scene = Scene::new(Radiance::gray(1.0));
+        scene.add(Object::new(
+            Sphere::new(Point::new(0.0, 0.0, 0.0), 1.0),
+            Lambert::new(Albedo::white()),
+            Matrix4::identity(),
+        ));
+
+        Builtin {
+            scene,
+            position: Point::new(0.0, 0.0, 4.0),
+            look_at: Point::new(0.0, 0.0, 0.0),
+            fov: 40.0,
+            lights: Vec::new(),
+        }
+    }
+}
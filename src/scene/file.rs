@@ -0,0 +1,884 @@
+//! A declarative scene description format
+//!
+//! Scenes can be described in [RON](https://github.com/ron-rs/ron) or
+//! JSON, listing the objects (geometry, material, transformation, and
+//! interior medium), the camera, point lights, and render settings.
+//! With the `usd` feature, USD files are imported as well.
+
+use std::error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use cgmath::{
+    Deg, EuclideanSpace, Matrix4, PerspectiveFov, Quaternion, Rad, Rotation3, SquareMatrix,
+    Transform,
+};
+use ron;
+use serde_json;
+
+use camera::{AffineTransformCamera, Resolution};
+use geometry::bvh::Split;
+use geometry::{
+    Cone, Cube, Cylinder, Disc, Geometry, InfiniteCylinder, Mesh, Plane, Point, Quad, Sphere,
+    Torus, Triangle, Vector,
+};
+use lighting::{
+    Albedo, Blackbody, Dielectric, Gradient, Ior, Lambert, Material, Medium, Phong, Radiance, Sky,
+};
+use render::ray::Light;
+#[cfg(feature = "usd")]
+use scene::usd::{self, UsdError};
+use scene::{Interpolation, Object, ObjectError, Pose, Scene, SceneError, Track};
+use trace::Span;
+use Error;
+use Float;
+
+fn vector(v: [Float; 3]) -> Vector {
+    Vector::new(v[0], v[1], v[2])
+}
+
+fn point(v: [Float; 3]) -> Point {
+    Point::new(v[0], v[1], v[2])
+}
+
+fn albedo(v: [Float; 3]) -> Albedo {
+    Albedo::new(v[0], v[1], v[2])
+}
+
+fn radiance(v: [Float; 3]) -> Radiance {
+    Radiance::new(v[0], v[1], v[2])
+}
+
+fn one() -> Float {
+    1.0
+}
+
+fn fps() -> Float {
+    24.0
+}
+
+fn white() -> [Float; 3] {
+    [1.0, 1.0, 1.0]
+}
+
+fn up() -> [Float; 3] {
+    [0.0, 1.0, 0.0]
+}
+
+/// The shape of an object.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum GeometryDescription {
+    Sphere {
+        center: [Float; 3],
+        radius: Float,
+    },
+    Cube {
+        center: [Float; 3],
+        size: [Float; 3],
+    },
+    Plane {
+        normal: [Float; 3],
+        distance: Float,
+    },
+    Disc {
+        center: [Float; 3],
+        normal: [Float; 3],
+        radius: Float,
+    },
+    Triangle {
+        vertices: [[Float; 3]; 3],
+    },
+    Cylinder {
+        center: [Float; 3],
+        axis: [Float; 3],
+        radius: Float,
+        height: Float,
+    },
+    /// A cylinder without caps, infinitely long.
+    InfiniteCylinder {
+        origin: [Float; 3],
+        axis: [Float; 3],
+        radius: Float,
+    },
+    Cone {
+        apex: [Float; 3],
+        axis: [Float; 3],
+        /// The angle between the axis and the side, in degrees.
+        angle: Float,
+        height: Float,
+    },
+    Torus {
+        center: [Float; 3],
+        axis: [Float; 3],
+        major: Float,
+        minor: Float,
+    },
+    Quad {
+        center: [Float; 3],
+        edges: [[Float; 3]; 2],
+    },
+    /// A mesh of triangles, given by the indices of their vertices;
+    /// see `Mesh`.  Normals and surface coordinates are optional, one
+    /// per position if given.  The triangles are divided in the
+    /// mesh's hierarchy by `split`; see `Mesh::with_split`.
+    Mesh {
+        positions: Vec<[Float; 3]>,
+        #[serde(default)]
+        normals: Vec<[Float; 3]>,
+        #[serde(default)]
+        uvs: Vec<[Float; 2]>,
+        indices: Vec<[u32; 3]>,
+        #[serde(default)]
+        split: Split,
+    },
+}
+
+/// The appearance of an object.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum MaterialDescription {
+    Lambert {
+        albedo: [Float; 3],
+    },
+    Blackbody {
+        radiance: [Float; 3],
+    },
+    Phong {
+        #[serde(default)]
+        emission: [Float; 3],
+        #[serde(default)]
+        diffuse: [Float; 3],
+        #[serde(default)]
+        specular: [Float; 3],
+        #[serde(default)]
+        shininess: Float,
+    },
+    Dielectric {
+        #[serde(default = "white")]
+        albedo: [Float; 3],
+        ior: Float,
+    },
+}
+
+/// A participating medium filling an object.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MediumDescription {
+    pub absorption: Float,
+    pub scattering: Float,
+    #[serde(default = "white")]
+    pub albedo: [Float; 3],
+    #[serde(default)]
+    pub emission: [Float; 3],
+    #[serde(default)]
+    pub anisotropy: Float,
+}
+
+/// A single step of an object's transformation.  Steps are applied
+/// in order, i.e. the first step is applied to the geometry first.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum TransformDescription {
+    Translate([Float; 3]),
+    Scale([Float; 3]),
+    /// Rotation around the x axis, in degrees.
+    RotateX(Float),
+    /// Rotation around the y axis, in degrees.
+    RotateY(Float),
+    /// Rotation around the z axis, in degrees.
+    RotateZ(Float),
+    /// An arbitrary matrix, given column by column.
+    Matrix([[Float; 4]; 4]),
+}
+
+impl TransformDescription {
+    fn matrix(&self) -> Matrix4<Float> {
+        match *self {
+            TransformDescription::Translate(v) => Matrix4::from_translation(vector(v)),
+            TransformDescription::Scale(v) => Matrix4::from_nonuniform_scale(v[0], v[1], v[2]),
+            TransformDescription::RotateX(a) => Matrix4::from_angle_x(Deg(a)),
+            TransformDescription::RotateY(a) => Matrix4::from_angle_y(Deg(a)),
+            TransformDescription::RotateZ(a) => Matrix4::from_angle_z(Deg(a)),
+            TransformDescription::Matrix(m) => Matrix4::from(m),
+        }
+    }
+}
+
+/// An object in the scene.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ObjectDescription {
+    pub geometry: GeometryDescription,
+    pub material: MaterialDescription,
+    #[serde(default)]
+    pub transform: Vec<TransformDescription>,
+    #[serde(default)]
+    pub medium: Option<MediumDescription>,
+    /// The object's motion.  Once the scene's time is set, the
+    /// keyframes replace `transform`.
+    #[serde(default)]
+    pub keyframes: Vec<KeyframeDescription>,
+    /// Whether rays only hit the front of the object's surface.
+    #[serde(default)]
+    pub one_sided: bool,
+    /// Which interior wins where the object overlaps others.
+    #[serde(default)]
+    pub priority: u32,
+}
+
+/// The placement of an animated object at a point in time, blended
+/// linearly between keyframes.  The object is scaled first, then
+/// rotated around the x, y, and z axes, then translated.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct KeyframeDescription {
+    /// The time of the keyframe, in seconds.
+    pub time: Float,
+    #[serde(default)]
+    pub translate: [Float; 3],
+    /// Rotations around the x, y, and z axes, in degrees.
+    #[serde(default)]
+    pub rotate: [Float; 3],
+    #[serde(default = "one")]
+    pub scale: Float,
+}
+
+impl KeyframeDescription {
+    fn pose(&self) -> Pose {
+        let [x, y, z] = self.rotate;
+        Pose {
+            scale: self.scale,
+            rot: Quaternion::from_angle_z(Deg(z))
+                * Quaternion::from_angle_y(Deg(y))
+                * Quaternion::from_angle_x(Deg(x)),
+            disp: vector(self.translate),
+        }
+    }
+}
+
+/// A perspective camera at a point in time, blended linearly between
+/// keyframes.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CameraKeyframeDescription {
+    /// The time of the keyframe, in seconds.
+    pub time: Float,
+    pub position: [Float; 3],
+    pub look_at: [Float; 3],
+    #[serde(default = "up")]
+    pub up: [Float; 3],
+    pub fov: Float,
+}
+
+/// A point light, used by the ray tracing renderers.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LightDescription {
+    pub position: [Float; 3],
+    pub emission: [Float; 3],
+    #[serde(default)]
+    pub radius: Float,
+}
+
+/// The camera's position and projection.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum CameraDescription {
+    /// A perspective camera at `position` looking at `look_at`, with
+    /// a vertical field of view of `fov` degrees.
+    Perspective {
+        position: [Float; 3],
+        look_at: [Float; 3],
+        #[serde(default = "up")]
+        up: [Float; 3],
+        fov: Float,
+    },
+    /// A camera given by the inverse view-projection matrix, column
+    /// by column.
+    Matrix([[Float; 4]; 4]),
+}
+
+/// Parameters for rendering the scene.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RenderSettings {
+    pub width: u32,
+    pub height: u32,
+    pub samples: u32,
+    pub depth_limit: u8,
+    pub contribution_limit: Float,
+    pub exposure: Float,
+    /// The number of animation frames per second.
+    #[serde(default = "fps")]
+    pub fps: Float,
+}
+
+impl Default for RenderSettings {
+    fn default() -> RenderSettings {
+        RenderSettings {
+            width: 256,
+            height: 256,
+            samples: 512,
+            depth_limit: 8,
+            contribution_limit: 0.01,
+            exposure: 1.0,
+            fps: fps(),
+        }
+    }
+}
+
+/// The light arriving from outside the scene, replacing the constant
+/// `background`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum EnvironmentDescription {
+    Gradient {
+        ground: [Float; 3],
+        horizon: [Float; 3],
+        zenith: [Float; 3],
+    },
+    /// A procedural daylight sky with the sun in direction `sun`.
+    Sky { sun: [Float; 3] },
+}
+
+/// A complete scene description.
+///
+/// # Examples
+/// ```
+/// use lucifer::scene::file::SceneFile;
+///
+/// let file = SceneFile::from_ron(r#"(
+///     camera: Perspective(position: (0, 0, 5), look_at: (0, 0, 0), fov: 40),
+///     objects: [
+///         (
+///             geometry: Sphere(center: (0, 0, 0), radius: 1),
+///             material: Lambert(albedo: (0.8, 0.8, 0.8)),
+///             transform: [Translate((0, 1, 0))],
+///         ),
+///     ],
+/// )"#).unwrap();
+///
+/// assert_eq!(file.objects.len(), 1);
+/// assert_eq!(file.settings.width, 256);
+///
+/// let scene = file.scene().unwrap();
+/// let camera = file.camera();
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SceneFile {
+    #[serde(default)]
+    pub background: [Float; 3],
+    #[serde(default)]
+    pub environment: Option<EnvironmentDescription>,
+    pub camera: CameraDescription,
+    #[serde(default)]
+    pub settings: RenderSettings,
+    #[serde(default)]
+    pub objects: Vec<ObjectDescription>,
+    #[serde(default)]
+    pub lights: Vec<LightDescription>,
+    #[serde(default)]
+    pub includes: Vec<IncludeDescription>,
+    /// The medium filling the space outside of all objects.
+    #[serde(default)]
+    pub medium: Option<MediumDescription>,
+    /// The medium surrounding the camera, if it differs from
+    /// `medium`, e.g. when the camera is inside an object.
+    #[serde(default)]
+    pub camera_medium: Option<MediumDescription>,
+    /// The camera's motion, replacing `camera` when given.
+    #[serde(default)]
+    pub camera_keyframes: Vec<CameraKeyframeDescription>,
+    /// How the hierarchy over the objects divides them; see
+    /// `Scene::set_split`.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::geometry::bvh::Split;
+    /// use lucifer::scene::file::SceneFile;
+    ///
+    /// let file = SceneFile::from_ron(r#"(
+    ///     camera: Perspective(position: (0, 0, 5), look_at: (0, 0, 0), fov: 40),
+    ///     split: Sah(bins: 32),
+    /// )"#).unwrap();
+    ///
+    /// assert_eq!(file.split, Split::Sah { bins: 32 });
+    /// ```
+    #[serde(default)]
+    pub split: Split,
+}
+
+/// Another scene file, whose objects are placed in the scene as a
+/// unit.  Only the objects (and their own includes) are used; camera,
+/// lights, and settings of the included file are ignored.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct IncludeDescription {
+    /// The path of the file, relative to the including file.
+    pub path: String,
+    #[serde(default)]
+    pub transform: Vec<TransformDescription>,
+}
+
+/// The maximum nesting depth of includes, to catch cycles.
+const MAX_INCLUDE_DEPTH: u32 = 16;
+
+/// An error while loading a scene description.
+#[derive(Debug)]
+pub enum LoadError {
+    Io(io::Error),
+    Ron(ron::error::SpannedError),
+    Json(serde_json::Error),
+    /// The file name has no known extension.
+    UnknownFormat(String),
+    /// The scene contains invalid objects.
+    Scene(SceneError),
+    /// An included file has an invalid transformation.
+    Include(String, ObjectError),
+    /// Includes are nested too deeply, probably because a file
+    /// includes itself.
+    IncludeDepth(String),
+    #[cfg(feature = "usd")]
+    Usd(UsdError),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LoadError::Io(ref e) => write!(f, "could not read scene: {}", e),
+            LoadError::Ron(ref e) => write!(f, "invalid RON scene: {}", e),
+            LoadError::Json(ref e) => write!(f, "invalid JSON scene: {}", e),
+            LoadError::UnknownFormat(ref name) => {
+                write!(
+                    f,
+                    "unknown scene format (expected .ron, .json, or .usda): {}",
+                    name
+                )
+            }
+            LoadError::Scene(ref e) => write!(f, "{}", e),
+            LoadError::Include(ref name, ref e) => write!(f, "invalid include {}: {}", name, e),
+            LoadError::IncludeDepth(ref name) => {
+                write!(f, "includes nested too deeply (cyclic?): {}", name)
+            }
+            #[cfg(feature = "usd")]
+            LoadError::Usd(ref e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl error::Error for LoadError {}
+
+impl From<io::Error> for LoadError {
+    fn from(e: io::Error) -> LoadError {
+        LoadError::Io(e)
+    }
+}
+
+impl From<ron::error::SpannedError> for LoadError {
+    fn from(e: ron::error::SpannedError) -> LoadError {
+        LoadError::Ron(e)
+    }
+}
+
+#[cfg(feature = "usd")]
+impl From<UsdError> for LoadError {
+    fn from(e: UsdError) -> LoadError {
+        LoadError::Usd(e)
+    }
+}
+
+impl From<SceneError> for LoadError {
+    fn from(e: SceneError) -> LoadError {
+        LoadError::Scene(e)
+    }
+}
+
+impl From<serde_json::Error> for LoadError {
+    fn from(e: serde_json::Error) -> LoadError {
+        LoadError::Json(e)
+    }
+}
+
+impl SceneFile {
+    /// Parses a scene description in RON format.
+    pub fn from_ron(s: &str) -> Result<SceneFile, LoadError> {
+        Ok(ron::de::from_str(s)?)
+    }
+
+    /// Parses a scene description in JSON format.
+    pub fn from_json(s: &str) -> Result<SceneFile, LoadError> {
+        Ok(serde_json::from_str(s)?)
+    }
+
+    /// Formats the scene description as pretty-printed RON.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::scene::file::SceneFile;
+    ///
+    /// let file = SceneFile::from_ron(r#"(
+    ///     camera: Perspective(position: (0, 0, 5), look_at: (0, 0, 0), fov: 40),
+    /// )"#).unwrap();
+    ///
+    /// let ron = file.to_ron().unwrap();
+    /// assert_eq!(SceneFile::from_ron(&ron).unwrap(), file);
+    ///
+    /// let json = file.to_json().unwrap();
+    /// assert_eq!(SceneFile::from_json(&json).unwrap(), file);
+    /// ```
+    pub fn to_ron(&self) -> Result<String, ron::Error> {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+    }
+
+    /// Formats the scene description as pretty-printed JSON.
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string_pretty(self)
+    }
+
+    /// Loads a scene description from a `.ron` or `.json` file, or,
+    /// with the `usd` feature, imports a `.usda`, `.usd`, or `.usdz`
+    /// file.  Unsupported parts of USD files are silently skipped;
+    /// use `usd::load` to find out which.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<SceneFile, LoadError> {
+        let _span = Span::enter("load scene file");
+        let path = path.as_ref();
+        debug!("loading {}", path.display());
+
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("ron") => SceneFile::from_ron(&fs::read_to_string(path)?),
+            Some("json") => SceneFile::from_json(&fs::read_to_string(path)?),
+            #[cfg(feature = "usd")]
+            Some("usda") | Some("usd") | Some("usdz") => Ok(usd::load(path)?.file),
+            _ => Err(LoadError::UnknownFormat(path.display().to_string())),
+        }
+    }
+
+    /// The `Resolution` given in the render settings.
+    pub fn resolution(&self) -> Resolution {
+        Resolution::new(self.settings.width, self.settings.height)
+    }
+
+    /// Builds the described camera, failing if its view is
+    /// degenerate.
+    pub fn camera(&self) -> Result<AffineTransformCamera, Error> {
+        match self.camera {
+            CameraDescription::Perspective {
+                position,
+                look_at,
+                up,
+                fov,
+            } => self.perspective(point(position), point(look_at), vector(up), fov),
+            CameraDescription::Matrix(m) => Ok(AffineTransformCamera::new(Matrix4::from(m))),
+        }
+    }
+
+    /// Builds the camera at `time`, following `camera_keyframes`, if
+    /// any, failing if its view is degenerate.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::camera::{Camera, Resolution, Target};
+    /// use lucifer::scene::file::SceneFile;
+    ///
+    /// let file = SceneFile::from_ron(
+    ///     r#"(
+    ///         camera: Perspective(position: (0, 0, 5), look_at: (0, 0, 0), fov: 40),
+    ///         camera_keyframes: [
+    ///             (time: 0, position: (0, 0, 5), look_at: (0, 0, 0), fov: 40),
+    ///             (time: 2, position: (4, 0, 5), look_at: (4, 0, 0), fov: 40),
+    ///         ],
+    ///     )"#,
+    /// )
+    /// .unwrap();
+    ///
+    /// let origin = |time| {
+    ///     let resolution = Resolution::new(2, 2);
+    ///     let ray = file.camera_at(time).unwrap().primary(resolution, Target::new(1, 1));
+    ///     ray.origin
+    /// };
+    ///
+    /// // Halfway between the keyframes, the camera moved by 2.
+    /// assert!((origin(1.0).x - origin(0.0).x - 2.0).abs() < 1e-3);
+    /// ```
+    pub fn camera_at(&self, time: Float) -> Result<AffineTransformCamera, Error> {
+        if self.camera_keyframes.is_empty() {
+            return self.camera();
+        }
+
+        let track = |value: &dyn Fn(&CameraKeyframeDescription) -> [Float; 3]| {
+            self.camera_keyframes
+                .iter()
+                .fold(Track::new(Interpolation::Linear), |t, k| {
+                    t.key(k.time, vector(value(k)))
+                })
+                .at(time)
+                .unwrap()
+        };
+        let fov = self
+            .camera_keyframes
+            .iter()
+            .fold(Track::new(Interpolation::Linear), |t, k| {
+                t.key(k.time, k.fov)
+            })
+            .at(time)
+            .unwrap();
+
+        self.perspective(
+            Point::from_vec(track(&|k| k.position)),
+            Point::from_vec(track(&|k| k.look_at)),
+            track(&|k| k.up),
+            fov,
+        )
+    }
+
+    fn perspective(
+        &self,
+        position: Point,
+        look_at: Point,
+        up: Vector,
+        fov: Float,
+    ) -> Result<AffineTransformCamera, Error> {
+        let projection = PerspectiveFov {
+            fovy: Rad::from(Deg(fov)),
+            aspect: self.settings.width as Float / self.settings.height as Float,
+            near: 1.0,
+            far: 100.0,
+        };
+
+        AffineTransformCamera::try_look_at(position, look_at, up, projection)
+    }
+
+    /// Builds the described point lights.
+    pub fn lights(&self) -> Vec<Light> {
+        self.lights
+            .iter()
+            .map(|l| Light {
+                position: point(l.position),
+                emission: radiance(l.emission),
+                radius: l.radius,
+            })
+            .collect()
+    }
+
+    /// Loads a scene description from `path` and builds the
+    /// `Scene`, including all referenced files.
+    pub fn load_scene<P: AsRef<Path>>(path: P) -> Result<(SceneFile, Scene), LoadError> {
+        let path = path.as_ref();
+        let file = SceneFile::load(path)?;
+        let base = path.parent().unwrap_or_else(|| Path::new(""));
+        let scene = file.resolve(base)?;
+        Ok((file, scene))
+    }
+
+    /// Builds the described `Scene`, including all referenced files.
+    /// Include paths are relative to `base`.
+    pub fn resolve<P: AsRef<Path>>(&self, base: P) -> Result<Scene, LoadError> {
+        let _span = Span::enter("build scene");
+        let scene = self.resolve_nested(base.as_ref(), 0)?;
+
+        debug!(
+            "scene has {} objects and {} emitters",
+            scene.objects().len(),
+            scene.emitters().len()
+        );
+        Ok(scene)
+    }
+
+    fn resolve_nested(&self, base: &Path, depth: u32) -> Result<Scene, LoadError> {
+        let mut scene = self.scene()?;
+
+        for include in &self.includes {
+            let path = base.join(&include.path);
+            if depth >= MAX_INCLUDE_DEPTH {
+                return Err(LoadError::IncludeDepth(path.display().to_string()));
+            }
+
+            let file = SceneFile::load(&path)?;
+            let parent = path.parent().unwrap_or(base);
+            let asset = file.resolve_nested(parent, depth + 1)?;
+
+            scene
+                .add_scene(&asset, compose(&include.transform))
+                .map_err(|e| LoadError::Include(path.display().to_string(), e))?;
+        }
+
+        Ok(scene)
+    }
+
+    /// Builds the described `Scene`, reporting all invalid objects
+    /// by their index in `objects`.  Includes are not resolved; see
+    /// `resolve`.
+    pub fn scene(&self) -> Result<Scene, SceneError> {
+        let mut scene = match self.environment {
+            None => Scene::new(radiance(self.background)),
+            Some(EnvironmentDescription::Gradient {
+                ground,
+                horizon,
+                zenith,
+            }) => Scene::new(Gradient::new(
+                radiance(ground),
+                radiance(horizon),
+                radiance(zenith),
+            )),
+            Some(EnvironmentDescription::Sky { sun }) => Scene::new(Sky::new(vector(sun))),
+        };
+
+        scene.set_split(self.split);
+        scene.set_medium(self.medium.as_ref().map(MediumDescription::medium));
+        if let Some(ref medium) = self.camera_medium {
+            scene.set_camera_medium(Some(medium.medium()));
+        }
+
+        let mut errors = Vec::new();
+        for (i, desc) in self.objects.iter().enumerate() {
+            match desc.object() {
+                Ok(object) => {
+                    scene.add(object);
+                }
+                Err(e) => errors.push((i, e)),
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(scene)
+        } else {
+            Err(SceneError { objects: errors })
+        }
+    }
+}
+
+/// Composes transformation steps, applying the first step first.
+fn compose(steps: &[TransformDescription]) -> Matrix4<Float> {
+    steps
+        .iter()
+        .fold(Matrix4::identity(), |acc, t| t.matrix().concat(&acc))
+}
+
+impl ObjectDescription {
+    fn transform(&self) -> Matrix4<Float> {
+        compose(&self.transform)
+    }
+
+    fn geometry(&self) -> Result<Arc<dyn Geometry>, ObjectError> {
+        Ok(match self.geometry {
+            GeometryDescription::Sphere { center, radius } => {
+                Arc::new(Sphere::new(point(center), radius))
+            }
+            GeometryDescription::Cube { center, size } => {
+                Arc::new(Cube::new(point(center), vector(size)))
+            }
+            GeometryDescription::Plane { normal, distance } => {
+                Arc::new(Plane::new(vector(normal), distance))
+            }
+            GeometryDescription::Disc {
+                center,
+                normal,
+                radius,
+            } => Arc::new(Disc::new(point(center), vector(normal), radius)),
+            GeometryDescription::Triangle {
+                vertices: [a, b, c],
+            } => Arc::new(Triangle::new(point(a), point(b), point(c))),
+            GeometryDescription::Cylinder {
+                center,
+                axis,
+                radius,
+                height,
+            } => Arc::new(Cylinder::new(point(center), vector(axis), radius, height)),
+            GeometryDescription::InfiniteCylinder {
+                origin,
+                axis,
+                radius,
+            } => Arc::new(InfiniteCylinder::new(point(origin), vector(axis), radius)),
+            GeometryDescription::Cone {
+                apex,
+                axis,
+                angle,
+                height,
+            } => Arc::new(Cone::new(
+                point(apex),
+                vector(axis),
+                angle.to_radians(),
+                height,
+            )),
+            GeometryDescription::Torus {
+                center,
+                axis,
+                major,
+                minor,
+            } => Arc::new(Torus::new(point(center), vector(axis), major, minor)),
+            GeometryDescription::Quad {
+                center,
+                edges: [u, v],
+            } => Arc::new(Quad::new(point(center), vector(u), vector(v))),
+            GeometryDescription::Mesh {
+                ref positions,
+                ref normals,
+                ref uvs,
+                ref indices,
+                split,
+            } => {
+                let positions = positions.iter().map(|&p| point(p)).collect();
+                let normals = if normals.is_empty() {
+                    None
+                } else {
+                    Some(normals.iter().map(|&n| vector(n)).collect())
+                };
+                let mut mesh = Mesh::try_new(positions, normals, indices.clone())
+                    .map_err(|_| ObjectError::InvalidGeometry)?;
+                if split != Split::default() {
+                    mesh = mesh.with_split(split);
+                }
+                if !uvs.is_empty() {
+                    mesh = mesh
+                        .try_with_uvs(uvs.iter().map(|&[u, v]| (u, v)).collect())
+                        .map_err(|_| ObjectError::InvalidGeometry)?;
+                }
+                Arc::new(mesh)
+            }
+        })
+    }
+
+    fn material(&self) -> Arc<dyn Material> {
+        match self.material {
+            MaterialDescription::Lambert { albedo: a } => Arc::new(Lambert::new(albedo(a))),
+            MaterialDescription::Blackbody { radiance: r } => Arc::new(Blackbody::new(radiance(r))),
+            MaterialDescription::Phong {
+                emission,
+                diffuse,
+                specular,
+                shininess,
+            } => Arc::new(
+                Phong::new()
+                    .glow(radiance(emission))
+                    .color(albedo(diffuse))
+                    .highlight(albedo(specular), shininess),
+            ),
+            MaterialDescription::Dielectric { albedo: a, ior } => {
+                Arc::new(Dielectric::new(albedo(a), Ior(ior)))
+            }
+        }
+    }
+
+    fn object(&self) -> Result<Object, ObjectError> {
+        let mut object = Object::try_shared(self.geometry()?, self.material(), self.transform())?;
+
+        if !self.keyframes.is_empty() {
+            let track = self
+                .keyframes
+                .iter()
+                .fold(Track::new(Interpolation::Linear), |t, k| {
+                    t.key(k.time, k.pose())
+                });
+            object = object.animate(track);
+        }
+        object.one_sided = self.one_sided;
+        object.priority = self.priority;
+
+        Ok(match self.medium {
+            Some(ref m) => object.with_medium(m.medium()),
+            None => object,
+        })
+    }
+}
+
+impl MediumDescription {
+    fn medium(&self) -> Medium {
+        Medium::new(self.absorption, self.scattering)
+            .color(albedo(self.albedo))
+            .glow(radiance(self.emission))
+            .anisotropy(self.anisotropy)
+    }
+}
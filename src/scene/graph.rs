@@ -0,0 +1,135 @@
+use cgmath::{Matrix4, SquareMatrix};
+
+use scene::{invert, Object, ObjectError};
+use Float;
+
+/// A node in a scene graph, either a single `Object` or a `Group` of
+/// further nodes.
+#[cfg_attr(feature = "f64", allow(clippy::large_enum_variant))]
+pub enum Node {
+    Object(Object),
+    Group(Group),
+}
+
+impl Node {
+    /// Moves all objects below this node into `objects`, composing
+    /// the transformations along the way with `transform` and its
+    /// inverse `inv_transform`.
+    pub fn flatten_into(
+        self,
+        transform: Matrix4<Float>,
+        inv_transform: Matrix4<Float>,
+        objects: &mut Vec<Object>,
+    ) {
+        match self {
+            Node::Object(object) => {
+                let mut object = object;
+                object.transform = transform * object.transform;
+                object.inv_transform = object.inv_transform * inv_transform;
+                if let Some(ref mut animation) = object.animation {
+                    animation.parent = transform * animation.parent;
+                }
+                objects.push(object);
+            }
+            Node::Group(group) => {
+                let transform = transform * group.transform;
+                let inv_transform = group.inv_transform * inv_transform;
+                for child in group.children {
+                    child.flatten_into(transform, inv_transform, objects);
+                }
+            }
+        }
+    }
+}
+
+impl From<Object> for Node {
+    fn from(object: Object) -> Node {
+        Node::Object(object)
+    }
+}
+
+impl From<Group> for Node {
+    fn from(group: Group) -> Node {
+        Node::Group(group)
+    }
+}
+
+/// A group of nodes sharing a common transformation, which is
+/// applied after the transformations of the children.
+///
+/// Groups only exist while building a scene; when added to a `Scene`,
+/// the transformations are composed once and every object stores its
+/// world transformation and inverse.
+///
+/// # Examples
+/// ```
+/// use lucifer::cgmath::{vec3, Matrix4};
+/// use lucifer::geometry::{Point, Sphere};
+/// use lucifer::lighting::{Albedo, Lambert, Radiance};
+/// use lucifer::scene::{Group, Object, Scene};
+///
+/// let sphere = |x| {
+///     let material = Lambert::new(Albedo::gray(0.8));
+///     let transform = Matrix4::from_translation(vec3(x, 0.0, 0.0));
+///     Object::new(Sphere::new(Point::new(0.0, 0.0, 0.0), 0.5), material, transform)
+/// };
+///
+/// let dumbbell = Group::new(Matrix4::from_translation(vec3(0.0, 0.0, -5.0)))
+///     .with(sphere(-1.0))
+///     .with(sphere(1.0));
+///
+/// let mut scene = Scene::new(Radiance::none());
+/// scene.add_group(dumbbell);
+///
+/// assert_eq!(scene.objects().len(), 2);
+/// assert_eq!(
+///     scene.objects()[1].transform,
+///     Matrix4::from_translation(vec3(1.0, 0.0, -5.0))
+/// );
+/// ```
+pub struct Group {
+    pub transform: Matrix4<Float>,
+    pub inv_transform: Matrix4<Float>,
+    pub children: Vec<Node>,
+}
+
+impl Group {
+    /// Creates a new, empty `Group`.
+    ///
+    /// # Panics
+    /// Panics if `transform` is not invertible; see `try_new`.
+    pub fn new(transform: Matrix4<Float>) -> Self {
+        Group::try_new(transform).unwrap_or_else(|e| panic!("invalid group: {}", e))
+    }
+
+    /// Creates a new, empty `Group`, failing if `transform` is not
+    /// invertible.
+    pub fn try_new(transform: Matrix4<Float>) -> Result<Self, ObjectError> {
+        Ok(Group {
+            transform,
+            inv_transform: invert(transform)?,
+            children: Vec::new(),
+        })
+    }
+
+    /// Adds an `Object` or another `Group` to the group.
+    pub fn add<N: Into<Node>>(&mut self, child: N) {
+        self.children.push(child.into())
+    }
+
+    /// Adds an `Object` or another `Group` to the group, builder
+    /// style.
+    pub fn with<N: Into<Node>>(self, child: N) -> Self {
+        let mut group = self;
+        group.add(child);
+        group
+    }
+
+    /// Moves all objects of the group into a flat list, composing
+    /// their transformations with the group's.
+    pub fn flatten(self) -> Vec<Object> {
+        let mut objects = Vec::new();
+        Node::Group(self).flatten_into(Matrix4::identity(), Matrix4::identity(), &mut objects);
+        objects
+    }
+}
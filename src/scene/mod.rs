@@ -1,115 +1,1673 @@
+use consts::PI;
+use std::collections::HashSet;
+use std::error;
+use std::fmt;
+use std::mem;
+use std::sync::{Arc, OnceLock};
+
 use cgmath::{InnerSpace, Matrix, Matrix4, SquareMatrix, Transform};
 
+use geometry::bvh::Split;
 use geometry::*;
 use lighting::*;
+use montecarlo::Discrete1D;
+
+mod animation;
+mod assembly;
+mod builtin;
+#[cfg(feature = "scene-file")]
+pub mod file;
+mod graph;
+mod stats;
+#[cfg(feature = "usd")]
+pub mod usd;
+
+pub use self::animation::{Animation, Interpolate, Interpolation, Pose, Track};
+pub use self::assembly::Assembly;
+use self::assembly::Inherited;
+pub use self::builtin::Builtin;
+pub use self::graph::{Group, Node};
+pub use self::stats::SceneStatistics;
+use Error;
+use Float;
+
+/// An error in the definition of an `Object`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ObjectError {
+    /// The transformation contains infinite or NaN entries.
+    NonFiniteTransform,
+    /// The transformation cannot be inverted, e.g. because it scales
+    /// by zero.
+    SingularTransform,
+    /// The geometry is malformed, e.g. a mesh refers to vertices it
+    /// does not have.
+    InvalidGeometry,
+}
+
+impl fmt::Display for ObjectError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ObjectError::NonFiniteTransform => write!(f, "transformation is not finite"),
+            ObjectError::SingularTransform => write!(f, "transformation is not invertible"),
+            ObjectError::InvalidGeometry => write!(f, "geometry is malformed"),
+        }
+    }
+}
+
+impl error::Error for ObjectError {}
+
+/// The invalid objects of a `Scene`, given by their index.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SceneError {
+    pub objects: Vec<(usize, ObjectError)>,
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid scene")?;
+        for (i, &(index, ref e)) in self.objects.iter().enumerate() {
+            let separator = if i == 0 { ": " } else { ", " };
+            write!(f, "{}object {}: {}", separator, index, e)?;
+        }
+        Ok(())
+    }
+}
+
+impl error::Error for SceneError {}
+
+fn is_finite(m: &Matrix4<Float>) -> bool {
+    let columns: &[[Float; 4]; 4] = m.as_ref();
+    columns.iter().all(|c| c.iter().all(|v| v.is_finite()))
+}
+
+/// Inverts `transform`, checking it for validity.
+pub(crate) fn invert(transform: Matrix4<Float>) -> Result<Matrix4<Float>, ObjectError> {
+    if !is_finite(&transform) {
+        return Err(ObjectError::NonFiniteTransform);
+    }
+
+    match transform.invert() {
+        Some(inverse) if is_finite(&inverse) => Ok(inverse),
+        _ => Err(ObjectError::SingularTransform),
+    }
+}
+
+/// The number of times an animated object's motion is sampled over
+/// the open shutter to bound it.
+const MOTION_SAMPLES: usize = 16;
+
+/// The motion of an animated object while the shutter is open; see
+/// `Scene::set_shutter`.
+#[derive(Clone, Copy, Debug)]
+struct Motion {
+    /// The time of the frame, which the times of rays are relative to.
+    time: Float,
+    open: Float,
+    close: Float,
+}
+
+/// A callback deciding whether a world-space intersection of a ray
+/// with an object counts as a hit, e.g. to cut out transparent parts
+/// of a surface.  The callback may also modify the intersection.
+pub type IntersectionFilter = Arc<dyn Fn(&Ray, &mut Intersection) -> bool + Send + Sync>;
 
 /// An object in the scene, given by a `Geometry` with a specific
 /// `Material` and positioned using a transformation defined by a
 /// `Matrix4<f32>`.
-pub struct Object<'a> {
-    pub geometry: Box<Geometry + 'a>,
-    pub material: Box<Material + 'a>,
-    pub transform: Matrix4<f32>,
-    pub inv_transform: Matrix4<f32>,
+///
+/// Geometry and material are reference counted, so cloning an
+/// `Object` is cheap and several objects can share them.
+#[derive(Clone)]
+pub struct Object {
+    pub geometry: Arc<dyn Geometry>,
+    pub transform: Matrix4<Float>,
+    pub inv_transform: Matrix4<Float>,
+    pub medium: Option<Medium>,
+    /// The keyframed motion of the object, applied by
+    /// `Scene::set_time`.
+    pub animation: Option<Animation>,
+    /// Decides which intersections with the object count as hits.
+    pub filter: Option<IntersectionFilter>,
+    /// Whether only the front (outside) of the object's surface is
+    /// hit, letting rays pass through it from behind.
+    pub one_sided: bool,
+    /// Which interior wins where the object overlaps others, e.g. the
+    /// glass of a vase over the water it holds; see `with_priority`.
+    pub priority: u32,
+    /// The material of the object.  Instances use the materials of
+    /// their assembly, unless overridden with `set_material`.
+    material: Arc<dyn Material>,
+    /// The assembly placed by an instance, whose objects shade hits.
+    assembly: Option<Arc<Assembly>>,
+    /// Whether hits are shaded with the materials of the assembly.
+    inherit_material: bool,
+    /// The motion blurred by the open shutter, set with the time of
+    /// the scene.
+    motion: Option<Motion>,
 }
 
-impl<'a> Object<'a> {
-    pub fn new<G, S>(geometry: G, material: S, transform: Matrix4<f32>) -> Self
+/// A hit on an object, in the space of the object's geometry (for
+/// shading) and in the space of the ray, with the material and medium
+/// of the innermost object of nested assemblies.
+pub(crate) struct Hit<'a> {
+    pub local: Intersection,
+    pub world: Intersection,
+    pub material: &'a dyn Material,
+    pub medium: Option<Medium>,
+    pub priority: u32,
+}
+
+impl Object {
+    /// Creates a new `Object`.
+    ///
+    /// # Panics
+    /// Panics if `transform` is not invertible; see `try_new`.
+    pub fn new<G, S>(geometry: G, material: S, transform: Matrix4<Float>) -> Self
+    where
+        G: Geometry + 'static,
+        S: Material + 'static,
+    {
+        Object::shared(Arc::new(geometry), Arc::new(material), transform)
+    }
+
+    /// Creates a new `Object`, failing if `transform` is not
+    /// invertible.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::cgmath::Matrix4;
+    /// use lucifer::geometry::{Point, Sphere};
+    /// use lucifer::lighting::{Albedo, Lambert};
+    /// use lucifer::scene::{Object, ObjectError};
+    ///
+    /// let sphere = Sphere::new(Point::new(0.0, 0.0, 0.0), 1.0);
+    /// let material = Lambert::new(Albedo::gray(0.8));
+    /// let object = Object::try_new(sphere, material, Matrix4::from_scale(0.0));
+    ///
+    /// assert_eq!(object.err(), Some(ObjectError::SingularTransform));
+    /// ```
+    pub fn try_new<G, S>(
+        geometry: G,
+        material: S,
+        transform: Matrix4<Float>,
+    ) -> Result<Self, ObjectError>
     where
-        G: Geometry + 'a,
-        S: Material + 'a,
+        G: Geometry + 'static,
+        S: Material + 'static,
     {
-        Object {
-            geometry: Box::new(geometry),
-            material: Box::new(material),
+        Object::try_shared(Arc::new(geometry), Arc::new(material), transform)
+    }
+
+    /// Creates a new `Object` from a shared `Geometry` and
+    /// `Material`, e.g. to place many instances of the same asset.
+    ///
+    /// # Panics
+    /// Panics if `transform` is not invertible; see `try_shared`.
+    pub fn shared(
+        geometry: Arc<dyn Geometry>,
+        material: Arc<dyn Material>,
+        transform: Matrix4<Float>,
+    ) -> Self {
+        Object::try_shared(geometry, material, transform)
+            .unwrap_or_else(|e| panic!("invalid object: {}", e))
+    }
+
+    /// Creates a new `Object` from a shared `Geometry` and
+    /// `Material`, failing if `transform` is not invertible.
+    pub fn try_shared(
+        geometry: Arc<dyn Geometry>,
+        material: Arc<dyn Material>,
+        transform: Matrix4<Float>,
+    ) -> Result<Self, ObjectError> {
+        Ok(Object {
+            geometry,
+            material,
             transform,
-            inv_transform: transform.invert().unwrap(),
+            inv_transform: invert(transform)?,
+            medium: None,
+            animation: None,
+            filter: None,
+            one_sided: false,
+            priority: 0,
+            assembly: None,
+            inherit_material: false,
+            motion: None,
+        })
+    }
+
+    /// Creates an instance of a shared `Assembly`, placing all of its
+    /// objects with `transform`.
+    ///
+    /// # Panics
+    /// Panics if `transform` is not invertible; see `try_instance`.
+    pub fn instance(assembly: Arc<Assembly>, transform: Matrix4<Float>) -> Self {
+        Object::try_instance(assembly, transform)
+            .unwrap_or_else(|e| panic!("invalid object: {}", e))
+    }
+
+    /// Creates an instance of a shared `Assembly`, failing if
+    /// `transform` is not invertible.
+    pub fn try_instance(
+        assembly: Arc<Assembly>,
+        transform: Matrix4<Float>,
+    ) -> Result<Self, ObjectError> {
+        let mut object = Object::try_shared(assembly.clone(), Arc::new(Inherited), transform)?;
+        object.assembly = Some(assembly);
+        object.inherit_material = true;
+        Ok(object)
+    }
+
+    /// The material of the object, which instances only use if it was
+    /// set with `set_material`.
+    pub fn material(&self) -> &dyn Material {
+        &*self.material
+    }
+
+    /// Replaces the material of the object.  For instances, the
+    /// material overrides the materials of all objects of the
+    /// assembly.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::sync::Arc;
+    ///
+    /// use lucifer::cgmath::{vec3, Matrix4, SquareMatrix};
+    /// use lucifer::geometry::{Point, Ray, Sphere, Vector};
+    /// use lucifer::lighting::{Albedo, Effect, Lambert, Radiance};
+    /// use lucifer::scene::{Assembly, Object, Scene};
+    ///
+    /// let sphere = Sphere::new(Point::new(0.0, 0.0, 0.0), 1.0);
+    /// let red = Lambert::new(Albedo::red(0.8));
+    /// let asset = Arc::new(Assembly::new(vec![Object::new(sphere, red, Matrix4::identity())]));
+    ///
+    /// let mut scene = Scene::new(Radiance::none());
+    /// scene.add(Object::instance(asset.clone(), Matrix4::from_translation(vec3(-2.0, 0.0, -5.0))));
+    /// scene.add(
+    ///     Object::instance(asset, Matrix4::from_translation(vec3(2.0, 0.0, -5.0)))
+    ///         .with_material(Arc::new(Lambert::new(Albedo::blue(0.8)))),
+    /// );
+    ///
+    /// let albedo = |x| {
+    ///     let ray = Ray::new(Point::new(x, 0.0, 0.0), Vector::new(0.0, 0.0, -1.0));
+    ///     match scene.intersect(&ray).unwrap().bsdf.effects[0] {
+    ///         Effect::DiffuseReflection(albedo, _) => albedo,
+    ///         _ => unreachable!(),
+    ///     }
+    /// };
+    ///
+    /// assert_eq!(albedo(-2.0), Albedo::red(0.8));
+    /// assert_eq!(albedo(2.0), Albedo::blue(0.8));
+    /// ```
+    pub fn set_material(&mut self, material: Arc<dyn Material>) {
+        self.material = material;
+        self.inherit_material = false;
+    }
+
+    /// Replaces the material of the object, builder style; see
+    /// `set_material`.
+    pub fn with_material(self, material: Arc<dyn Material>) -> Self {
+        let mut object = self;
+        object.set_material(material);
+        object
+    }
+
+    /// The assembly placed by the object, if it is an instance.
+    pub fn assembly(&self) -> Option<&Assembly> {
+        self.assembly.as_deref()
+    }
+
+    /// Fills the interior of the object with a participating
+    /// `Medium`.
+    pub fn with_medium(self, medium: Medium) -> Self {
+        let mut object = self;
+        object.medium = Some(medium);
+        object
+    }
+
+    /// Creates an invisible region filled with a participating
+    /// `Medium`, e.g. a patch of fog.  The surface of `geometry` only
+    /// marks the boundary of the medium and does not scatter light.
+    pub fn region<G>(geometry: G, medium: Medium, transform: Matrix4<Float>) -> Self
+    where
+        G: Geometry + 'static,
+    {
+        Object::new(
+            geometry,
+            Dielectric::new(Albedo::white(), Ior(1.0)),
+            transform,
+        )
+        .with_medium(medium)
+    }
+
+    /// Filters the intersections with the object; rejected
+    /// intersections are ignored by `Scene::intersect` and
+    /// `Scene::occlude`.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::cgmath::{Matrix4, SquareMatrix};
+    /// use lucifer::geometry::{Point, Ray, Sphere, Vector};
+    /// use lucifer::lighting::{Albedo, Lambert, Radiance};
+    /// use lucifer::scene::{Object, Scene};
+    ///
+    /// let sphere = Sphere::new(Point::new(0.0, 0.0, 0.0), 1.0);
+    /// let object = Object::new(sphere, Lambert::new(Albedo::gray(0.8)), Matrix4::identity())
+    ///     .with_filter(|_, intersection| intersection.position[1] < 0.5);
+    ///
+    /// let mut scene = Scene::new(Radiance::none());
+    /// scene.add(object);
+    ///
+    /// // The top of the sphere is cut off, so the ray hits the inside.
+    /// let ray = Ray::new(Point::new(0.0, 5.0, 0.0), Vector::new(0.0, -1.0, 0.0));
+    /// let hit = scene.intersect(&ray).unwrap();
+    /// assert!(hit.intersection.inside);
+    /// assert_eq!(hit.intersection.lambda, 6.0);
+    /// ```
+    pub fn with_filter<F>(self, filter: F) -> Self
+    where
+        F: Fn(&Ray, &mut Intersection) -> bool + Send + Sync + 'static,
+    {
+        let mut object = self;
+        object.filter = Some(Arc::new(filter));
+        object
+    }
+
+    /// Makes the surface of the object one-sided, so rays only hit it
+    /// from the front, e.g. for a wall seen from both sides or for
+    /// billboards.  Back faces neither show nor cast shadows.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::cgmath::{Matrix4, SquareMatrix};
+    /// use lucifer::geometry::{Point, Ray, Sphere, Vector};
+    /// use lucifer::lighting::{Albedo, Lambert, Radiance};
+    /// use lucifer::scene::{Object, Scene};
+    ///
+    /// let sphere = Sphere::new(Point::new(0.0, 0.0, 0.0), 1.0);
+    /// let object = Object::new(sphere, Lambert::new(Albedo::gray(0.8)), Matrix4::identity())
+    ///     .one_sided();
+    ///
+    /// let mut scene = Scene::new(Radiance::none());
+    /// scene.add(object);
+    ///
+    /// // From outside, the ray hits the front.
+    /// let ray = Ray::new(Point::new(0.0, 5.0, 0.0), Vector::new(0.0, -1.0, 0.0));
+    /// assert_eq!(scene.intersect(&ray).unwrap().intersection.lambda, 4.0);
+    ///
+    /// // From within, the ray leaves through the back.
+    /// let ray = Ray::new(Point::new(0.0, 0.0, 0.0), Vector::new(0.0, -1.0, 0.0));
+    /// assert!(scene.intersect(&ray).is_none());
+    /// assert!(!scene.occlude(&ray));
+    /// ```
+    pub fn one_sided(self) -> Self {
+        let mut object = self;
+        object.one_sided = true;
+        object
+    }
+
+    /// Sets the priority of the object's interior.  Where the
+    /// interiors of transparent objects overlap, e.g. to model the
+    /// water in a glass without leaving a gap between them, paths are
+    /// within the object of highest priority and pass the surfaces of
+    /// the others as if they were not there.  Equal priorities, the default,
+    /// leave the object last entered in charge.
+    pub fn with_priority(self, priority: u32) -> Self {
+        let mut object = self;
+        object.priority = priority;
+        object
+    }
+
+    /// Animates the object's transformation with a keyframed
+    /// `Track`, replacing its static transformation whenever
+    /// `Scene::set_time` is called.
+    pub fn animate(self, track: Track<Pose>) -> Self {
+        let mut object = self;
+        object.animation = Some(Animation {
+            parent: Matrix4::identity(),
+            track,
+        });
+        object
+    }
+
+    /// Moves the object, updating the cached inverse transformation.
+    ///
+    /// # Panics
+    /// Panics if `transform` is not invertible; see
+    /// `try_set_transform`.
+    pub fn set_transform(&mut self, transform: Matrix4<Float>) {
+        self.try_set_transform(transform)
+            .unwrap_or_else(|e| panic!("invalid object: {}", e))
+    }
+
+    /// Moves the object, updating the cached inverse transformation.
+    /// Leaves the object unchanged if `transform` is not invertible.
+    pub fn try_set_transform(&mut self, transform: Matrix4<Float>) -> Result<(), ObjectError> {
+        self.inv_transform = invert(transform)?;
+        self.transform = transform;
+        Ok(())
+    }
+
+    /// Checks that the (public) transformations of the object are
+    /// usable.
+    pub fn validate(&self) -> Result<(), ObjectError> {
+        invert(self.transform)?;
+        if is_finite(&self.inv_transform) {
+            Ok(())
+        } else {
+            Err(ObjectError::NonFiniteTransform)
         }
     }
 
-    fn transform_ray(&self, ray: &Ray) -> Ray {
-        ray.clone().transform(&self.inv_transform)
+    /// The bounds of the object in the space of its parent, covering
+    /// all of its motion while the shutter is open.
+    pub fn bounds(&self) -> Bounds {
+        let bounds = self.geometry.bounds();
+        let (motion, animation) = match (self.motion, &self.animation) {
+            (Some(motion), Some(animation)) if bounds.is_finite() => (motion, animation),
+            _ => return bounds.transform(&self.transform),
+        };
+
+        // Between samples, corners move at most along the chord for
+        // translation and scaling, and bulge out by less than half of
+        // it for rotation.
+        let start = motion.time + motion.open;
+        let end = motion.time + motion.close;
+        let keys = animation.track.keys().iter().map(|k| k.0);
+        let mut times: Vec<Float> = (0..=MOTION_SAMPLES)
+            .map(|i| start + (end - start) * i as Float / MOTION_SAMPLES as Float)
+            .chain(keys.filter(|&t| t > start && t < end))
+            .collect();
+        times.sort_by(Float::total_cmp);
+
+        let corners = bounds.corners();
+        let mut swept = Bounds::empty();
+        let mut chord: Float = 0.0;
+        let mut previous: Option<[Point; 8]> = None;
+        for transform in times.iter().filter_map(|&t| animation.transform(t)) {
+            let points = corners.map(|c| transform.transform_point(c));
+            for (i, &point) in points.iter().enumerate() {
+                swept = swept.include(point);
+                if let Some(ref previous) = previous {
+                    chord = chord.max((point - previous[i]).magnitude());
+                }
+            }
+            previous = Some(points);
+        }
+
+        if swept.is_empty() {
+            return bounds.transform(&self.transform);
+        }
+        let padding = Vector::new(1.0, 1.0, 1.0) * (0.5 * chord);
+        Bounds::new(swept.min - padding, swept.max + padding)
+    }
+
+    /// The transformation of the object and its inverse at the time
+    /// of `ray`, `None` for the identity (e.g. of a mesh with its
+    /// transformation baked into its vertices), which leaves rays and
+    /// intersections as they are.
+    fn transforms(&self, ray: &Ray) -> Option<(Matrix4<Float>, Matrix4<Float>)> {
+        if let (Some(motion), Some(animation)) = (self.motion, &self.animation) {
+            let time = motion.time + ray.time.clamp(motion.open, motion.close);
+            if let Some(transform) = animation.transform(time) {
+                if let Ok(inverse) = invert(transform) {
+                    return Some((transform, inverse));
+                }
+            }
+        }
+        if self.transform == Matrix4::identity() {
+            None
+        } else {
+            Some((self.transform, self.inv_transform))
+        }
+    }
+
+    /// The approximate memory used by the object, its geometry, and
+    /// its material, in bytes.
+    pub fn memory(&self) -> usize {
+        mem::size_of::<Object>() + self.geometry.memory() + mem::size_of_val(&*self.material)
+    }
+
+    /// Maps `u` and `v` in `[0, 1)` onto the surface of the geometry,
+    /// returning the position and the outward surface normal in the
+    /// space of the object's parent.
+    pub fn sample_surface(&self, u: Float, v: Float) -> Option<(Point, Vector)> {
+        let (position, normal) = self.geometry.sample_surface(u, v)?;
+
+        let inv_trans = self.inv_transform.transpose();
+        Some((
+            self.transform.transform_point(position),
+            Transform::<Point>::transform_vector(&inv_trans, normal).normalize(),
+        ))
+    }
+
+    /// The density, with respect to solid angle at the origin of
+    /// `ray`, of `sample_surface` choosing the point where `ray` first
+    /// hits the geometry; see `Geometry::pdf_from`.  Exact for
+    /// transformations preserving angles, an approximation otherwise.
+    pub fn pdf_from(&self, ray: &Ray) -> Float {
+        let local = match self.transforms(ray) {
+            Some((_, ref inv_transform)) => ray.clone().transform(inv_transform),
+            None => ray.clone(),
+        };
+        self.geometry.pdf_from(local.origin, local.direction)
     }
 
-    fn transform_intersection(&self, ray: &Ray, intersection: &Intersection) -> Intersection {
+    /// The position and outward normal of the surface point with the
+    /// surface coordinates `u` and `v`, in the space of the object's
+    /// parent; see `Geometry::surface_at`.
+    pub fn surface_at(&self, u: Float, v: Float) -> Result<Option<(Point, Vector)>, Error> {
+        let found = self.geometry.surface_at(u, v)?;
+
         let inv_trans = self.inv_transform.transpose();
-        let position = self.transform.transform_point(intersection.position);
-        let normal =
-            Transform::<Point>::transform_vector(&inv_trans, intersection.normal).normalize();
-        let lambda = (position - ray.origin).magnitude();
-        let inside = intersection.inside;
-        Intersection {
-            position,
-            normal,
-            lambda,
-            inside,
+        Ok(found.map(|(position, normal)| {
+            (
+                self.transform.transform_point(position),
+                Transform::<Point>::transform_vector(&inv_trans, normal).normalize(),
+            )
+        }))
+    }
+
+    /// Describes the object as a light source, if its material is
+    /// emissive and its surface finite.
+    fn emitter(&self, index: usize) -> Option<Emitter> {
+        let emission = self.material.emission();
+        let area = self.geometry.area();
+
+        if emission == Radiance::none() || area <= 0.0 {
+            return None;
+        }
+
+        // Exact for uniform scaling, an approximation otherwise.
+        let scale = self.transform.determinant().abs().powf(2.0 / 3.0);
+        Some(Emitter {
+            object: index,
+            emission,
+            area: area * scale,
+        })
+    }
+
+    /// Intersects the object with a `ray` in the space of its parent,
+    /// returning the nearest hit accepted by the filter.  Back faces
+    /// are culled if `cull`, e.g. within a one-sided instance, or if
+    /// the object is one-sided itself.
+    fn intersect(&self, ray: &Ray, cull: bool) -> Option<Hit<'_>> {
+        let cull = cull || self.one_sided;
+        let transforms = self.transforms(ray);
+        let mut local_ray = match transforms {
+            Some((_, ref inv_transform)) => ray.clone().transform(inv_transform),
+            None => ray.clone(),
+        };
+        let mut travelled = 0.0;
+
+        loop {
+            let hit = match self.assembly {
+                None => {
+                    let local = if cull {
+                        self.geometry.intersect_front(&local_ray)
+                    } else {
+                        self.geometry.intersect(&local_ray)
+                    };
+                    local.map(|local| Hit {
+                        world: local.clone(),
+                        local,
+                        material: &*self.material,
+                        medium: self.medium,
+                        priority: self.priority,
+                    })
+                }
+                Some(ref assembly) => assembly.hit(&local_ray, cull).map(|hit| {
+                    if self.inherit_material {
+                        hit
+                    } else {
+                        Hit {
+                            material: &*self.material,
+                            ..hit
+                        }
+                    }
+                }),
+            }?;
+
+            let mut world = match transforms {
+                Some((ref transform, ref inv_transform)) => {
+                    transform_intersection(transform, inv_transform, ray, &hit.world)
+                }
+                None => Intersection {
+                    lambda: travelled + hit.world.lambda,
+                    ..hit.world.clone()
+                },
+            };
+
+            let accepted = match self.filter {
+                None => true,
+                Some(ref filter) => filter(ray, &mut world),
+            };
+            if accepted {
+                return Some(Hit { world, ..hit });
+            }
+
+            // Rejected, so look for the next surface of the object.
+            let step = hit.world.lambda + hit.world.step(local_ray.direction);
+            travelled += step;
+            local_ray = Ray {
+                origin: local_ray.origin + local_ray.direction * step,
+                direction: local_ray.direction,
+                length: local_ray.length - step,
+                time: local_ray.time,
+            };
+        }
+    }
+
+    /// Whether the object blocks a `ray` in the space of its parent,
+    /// culling back faces like `intersect`.
+    fn occlude(&self, ray: &Ray, cull: bool) -> bool {
+        if self.filter.is_some() {
+            return self.intersect(ray, cull).is_some();
+        }
+
+        let local_ray = match self.transforms(ray) {
+            Some((_, inv_transform)) => ray.clone().transform(&inv_transform),
+            None => ray.clone(),
+        };
+        if cull || self.one_sided {
+            self.geometry.occlude_front(&local_ray)
+        } else {
+            self.geometry.occlude(&local_ray)
         }
     }
 }
 
+/// Maps an `intersection` in the space of an object's geometry to the
+/// space of its parent, where `ray` was cast.
+fn transform_intersection(
+    transform: &Matrix4<Float>,
+    inv_transform: &Matrix4<Float>,
+    ray: &Ray,
+    intersection: &Intersection,
+) -> Intersection {
+    let inv_trans = inv_transform.transpose();
+    let position = transform.transform_point(intersection.position);
+    let normal = Transform::<Point>::transform_vector(&inv_trans, intersection.normal).normalize();
+    let lambda = (position - ray.origin).magnitude();
+    Intersection {
+        position,
+        normal,
+        lambda,
+        inside: intersection.inside,
+        primitive: intersection.primitive,
+        uv: intersection.uv,
+    }
+}
+
 pub struct ShadedIntersection {
     pub intersection: Intersection,
     pub bsdf: Bsdf,
+    /// The medium filling the interior of the intersected object.
+    pub medium: Option<Medium>,
+    /// The priority of the interior of the intersected object; see
+    /// `Object::priority`.
+    pub priority: u32,
+    /// The index of the intersected object in the scene.
+    pub object: usize,
+}
+
+/// An emissive object in the scene, registered as a light source.
+#[derive(Clone, Copy, Debug)]
+pub struct Emitter {
+    /// The index of the object in the scene.
+    pub object: usize,
+    /// The radiance emitted by the object's material.
+    pub emission: Radiance,
+    /// The surface area of the object, in world space.
+    pub area: Float,
+}
+
+impl Emitter {
+    /// The total (luminous) power emitted by the object.
+    pub fn power(&self) -> Float {
+        PI * self.emission.luma() * self.area
+    }
 }
 
-pub struct Scene<'a> {
-    objects: Vec<Object<'a>>,
-    background: Radiance,
+/// A collection of objects, lit by emissive objects and a uniform
+/// background.
+///
+/// A `Scene` owns all of its objects, so it can be stored in
+/// long-lived structures and shared between threads.
+///
+/// # Examples
+/// ```
+/// use std::sync::Arc;
+/// use std::thread;
+///
+/// use lucifer::geometry::{Point, Ray, Vector};
+/// use lucifer::lighting::Radiance;
+/// use lucifer::scene::Scene;
+///
+/// let scene = Arc::new(Scene::new(Radiance::gray(0.5)));
+///
+/// let shared = scene.clone();
+/// let handle = thread::spawn(move || {
+///     let ray = Ray::new(Point::new(0.0, 0.0, 0.0), Vector::new(0.0, 0.0, 1.0));
+///     shared.intersect(&ray).is_none()
+/// });
+///
+/// assert!(handle.join().unwrap());
+/// ```
+#[derive(Clone)]
+pub struct Scene {
+    objects: Vec<Object>,
+    /// The hierarchy over the world-space bounds of all objects,
+    /// built by the first ray after objects were added or removed,
+    /// and refit when they move.
+    bvh: OnceLock<Bvh>,
+    /// How `bvh` divides the objects.
+    split: Split,
+    environment: Arc<dyn Environment>,
+    emitters: Vec<Emitter>,
+    emitter_distribution: Discrete1D,
+    medium: Option<Medium>,
+    camera_medium: Option<Option<Medium>>,
+    override_material: Option<Arc<dyn Material>>,
+    /// The time set by `set_time`, if any.
+    time: Option<Float>,
+    shutter: (Float, Float),
 }
 
-impl<'a> Scene<'a> {
-    pub fn new(background: Radiance) -> Scene<'a> {
+impl Scene {
+    /// Creates an empty `Scene`, lit by `environment`.  Any
+    /// `Radiance` can be used as a constant environment.
+    pub fn new<E>(environment: E) -> Scene
+    where
+        E: Environment + 'static,
+    {
         Scene {
             objects: Vec::new(),
-            background,
+            bvh: OnceLock::new(),
+            split: Split::default(),
+            environment: Arc::new(environment),
+            emitters: Vec::new(),
+            emitter_distribution: Discrete1D::default(),
+            medium: None,
+            camera_medium: None,
+            override_material: None,
+            time: None,
+            shutter: (0.0, 0.0),
         }
     }
 
-    pub fn background(&self) -> Radiance {
-        self.background
+    /// Divides the objects in the hierarchy over them by `split`,
+    /// which is rebuilt by the next ray.  The default builds with the
+    /// surface area heuristic; `Split::Median` builds faster for
+    /// scenes that change all the time.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::cgmath::{Matrix4, SquareMatrix};
+    /// use lucifer::geometry::bvh::Split;
+    /// use lucifer::geometry::{Point, Ray, Sphere, Vector};
+    /// use lucifer::lighting::{Albedo, Lambert, Radiance};
+    /// use lucifer::scene::{Object, Scene};
+    ///
+    /// let mut scene = Scene::new(Radiance::none());
+    /// let sphere = Sphere::new(Point::new(0.0, 0.0, 0.0), 1.0);
+    /// scene.add(Object::new(sphere, Lambert::new(Albedo::gray(0.8)), Matrix4::identity()));
+    /// scene.set_split(Split::Median);
+    ///
+    /// let ray = Ray::new(Point::new(0.0, 0.0, 5.0), Vector::new(0.0, 0.0, -1.0));
+    /// assert_eq!(scene.intersect(&ray).unwrap().intersection.lambda, 4.0);
+    /// ```
+    pub fn set_split(&mut self, split: Split) {
+        self.split = split;
+        self.bvh = OnceLock::new();
     }
 
-    pub fn add(&mut self, object: Object<'a>) {
-        self.objects.push(object)
+    /// The material replacing the materials of all objects, if any.
+    pub fn override_material(&self) -> Option<&dyn Material> {
+        self.override_material.as_deref()
     }
 
-    pub fn intersect(&self, ray: &Ray) -> Option<ShadedIntersection> {
-        let mut nearest: Option<(Intersection, &Object)> = None;
-
-        for obj in &self.objects {
-            if let Some(int) = obj.geometry.intersect(&obj.transform_ray(ray)) {
-                nearest = match nearest {
-                    None => Some((int, obj)),
-                    Some((i, o)) => {
-                        if int.lambda < i.lambda {
-                            Some((int, obj))
-                        } else {
-                            Some((i, o))
-                        }
-                    }
+    /// Shades all non-emissive surfaces with `material` instead of
+    /// their own, e.g. a gray `Lambert` for a "clay" render to check
+    /// the lighting.  Light sources keep their materials.  `None`
+    /// restores the objects' materials.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::sync::Arc;
+    ///
+    /// use lucifer::cgmath::{vec3, Matrix4};
+    /// use lucifer::geometry::{Point, Ray, Sphere, Vector};
+    /// use lucifer::lighting::{Albedo, Dielectric, Effect, Ior, Lambert, Radiance};
+    /// use lucifer::scene::{Object, Scene};
+    ///
+    /// let sphere = Sphere::new(Point::new(0.0, 0.0, 0.0), 1.0);
+    /// let glass = Dielectric::new(Albedo::white(), Ior(1.5));
+    ///
+    /// let mut scene = Scene::new(Radiance::none());
+    /// scene.add(Object::new(sphere, glass, Matrix4::from_translation(vec3(0.0, 0.0, -5.0))));
+    /// scene.set_override_material(Some(Arc::new(Lambert::new(Albedo::gray(0.5)))));
+    ///
+    /// let ray = Ray::new(Point::new(0.0, 0.0, 0.0), Vector::new(0.0, 0.0, -1.0));
+    /// match scene.intersect(&ray).unwrap().bsdf.effects[0] {
+    ///     Effect::DiffuseReflection(albedo, _) => assert_eq!(albedo, Albedo::gray(0.5)),
+    ///     _ => panic!("not overridden"),
+    /// }
+    /// ```
+    pub fn set_override_material(&mut self, material: Option<Arc<dyn Material>>) {
+        self.override_material = material;
+    }
+
+    /// The medium filling the space outside of all objects.
+    pub fn medium(&self) -> Option<Medium> {
+        self.medium
+    }
+
+    /// Fills the space outside of all objects with a participating
+    /// `Medium`, e.g. for a scene under water.
+    pub fn set_medium(&mut self, medium: Option<Medium>) {
+        self.medium = medium;
+    }
+
+    /// The medium surrounding the camera, by default the medium
+    /// outside of all objects.
+    pub fn camera_medium(&self) -> Option<Medium> {
+        self.camera_medium.unwrap_or(self.medium)
+    }
+
+    /// Sets the medium surrounding the camera, e.g. to
+    /// `medium_at(position)` for a camera inside an object.
+    pub fn set_camera_medium(&mut self, medium: Option<Medium>) {
+        self.camera_medium = Some(medium);
+    }
+
+    /// The medium at `point`, i.e. the medium of the innermost object
+    /// containing `point`, or the medium outside of all objects.  Only
+    /// closed objects reliably contain points.  For points on a
+    /// surface, which side is meant is ambiguous, so move them off it
+    /// first, e.g. with `Intersection::offset_origin`.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::cgmath::{Matrix4, SquareMatrix};
+    /// use lucifer::geometry::{Point, Sphere};
+    /// use lucifer::lighting::{Albedo, Lambert, Medium, Radiance};
+    /// use lucifer::scene::{Object, Scene};
+    ///
+    /// let air = Medium::new(0.0, 0.01);
+    /// let fog = Medium::new(0.0, 0.5);
+    /// let smoke = Medium::new(1.0, 0.5);
+    ///
+    /// let mut scene = Scene::new(Radiance::none());
+    /// scene.set_medium(Some(air));
+    /// scene.add(Object::region(Sphere::new(Point::new(0.0, 0.0, 0.0), 4.0), fog, Matrix4::identity()));
+    /// scene.add(Object::region(Sphere::new(Point::new(1.0, 0.0, 0.0), 1.0), smoke, Matrix4::identity()));
+    ///
+    /// // Solid objects in the fog do not hide it.
+    /// let ball = Sphere::new(Point::new(-2.0, 2.0, 0.0), 0.5);
+    /// scene.add(Object::new(ball, Lambert::new(Albedo::gray(0.8)), Matrix4::identity()));
+    ///
+    /// assert_eq!(scene.medium_at(Point::new(0.0, 0.0, 10.0)), Some(air));
+    /// assert_eq!(scene.medium_at(Point::new(-2.0, 0.0, 0.0)), Some(fog));
+    /// assert_eq!(scene.medium_at(Point::new(1.0, 0.0, 0.0)), Some(smoke));
+    ///
+    /// scene.set_camera_medium(scene.medium_at(Point::new(-2.0, 0.0, 0.0)));
+    /// assert_eq!(scene.camera_medium(), Some(fog));
+    /// ```
+    pub fn medium_at(&self, point: Point) -> Option<Medium> {
+        // Along any ray, the innermost of nested objects is left
+        // first.
+        let ray = Ray::new(point, Vector::new(0.0, 1.0, 0.0));
+        let mut innermost: Option<Hit> = None;
+
+        self.bvh().traverse(&ray, |index| {
+            let object = &self.objects[index];
+            if object.medium.is_none() && object.assembly.is_none() {
+                return None;
+            }
+
+            // Only the surfaces of objects containing the point, left
+            // with a medium, limit the search.
+            let hit = object
+                .intersect(&ray, false)
+                .filter(|hit| hit.world.inside && hit.medium.is_some())?;
+            let lambda = hit.world.lambda;
+            if innermost.as_ref().is_none_or(|i| lambda < i.world.lambda) {
+                innermost = Some(hit);
+            }
+            Some(lambda)
+        });
+
+        innermost.map_or(self.medium, |hit| hit.medium)
+    }
+
+    /// The environment surrounding the scene.
+    pub fn environment(&self) -> &dyn Environment {
+        &*self.environment
+    }
+
+    /// The radiance arriving from the environment for a ray leaving
+    /// the scene in `direction`.
+    pub fn background(&self, direction: Vector) -> Radiance {
+        self.environment.radiance(direction)
+    }
+
+    /// Adds an object to the scene.  Objects with an emissive
+    /// material and a finite surface are registered as light sources.
+    /// Returns the index of the object.
+    pub fn add(&mut self, object: Object) -> usize {
+        let index = self.objects.len();
+        let mut object = object;
+        if object.animation.is_some() {
+            object.motion = self.motion();
+        }
+
+        if let Some(emitter) = object.emitter(index) {
+            self.emitters.push(emitter);
+            self.update_emitter_distribution();
+        }
+
+        self.objects.push(object);
+        self.bvh = OnceLock::new();
+        index
+    }
+
+    /// Moves all animated objects to their position at `time`.
+    /// Objects whose animation yields an invalid transformation keep
+    /// their previous position and are reported.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::cgmath::{vec3, Decomposed, Matrix4, Quaternion, SquareMatrix};
+    /// use lucifer::geometry::{Point, Sphere};
+    /// use lucifer::lighting::{Albedo, Lambert, Radiance};
+    /// use lucifer::scene::{Interpolation, Object, Pose, Scene, Track};
+    ///
+    /// let pose = |x| Pose {
+    ///     scale: 1.0,
+    ///     rot: Quaternion::new(1.0, 0.0, 0.0, 0.0),
+    ///     disp: vec3(x, 0.0, 0.0),
+    /// };
+    /// let track = Track::new(Interpolation::Linear)
+    ///     .key(0.0, pose(0.0))
+    ///     .key(1.0, pose(2.0));
+    ///
+    /// let sphere = Sphere::new(Point::new(0.0, 0.0, 0.0), 1.0);
+    /// let object = Object::new(sphere, Lambert::new(Albedo::gray(0.8)), Matrix4::identity());
+    ///
+    /// let mut scene = Scene::new(Radiance::none());
+    /// scene.add(object.animate(track));
+    /// scene.set_time(0.5).unwrap();
+    ///
+    /// assert_eq!(
+    ///     scene.objects()[0].transform,
+    ///     Matrix4::from_translation(vec3(1.0, 0.0, 0.0))
+    /// );
+    /// ```
+    pub fn set_time(&mut self, time: Float) -> Result<(), SceneError> {
+        let mut errors = Vec::new();
+
+        for (i, object) in self.objects.iter_mut().enumerate() {
+            let transform = match object.animation {
+                Some(ref animation) => animation.transform(time),
+                None => None,
+            };
+
+            if let Some(transform) = transform {
+                if let Err(e) = object.try_set_transform(transform) {
+                    errors.push((i, e));
                 }
             }
         }
 
-        match nearest {
-            None => None,
-            Some((intersection, object)) => {
-                let bsdf = object.material.shade(&intersection);
-                Some(ShadedIntersection {
-                    intersection: object.transform_intersection(ray, &intersection),
-                    bsdf,
-                })
+        self.time = Some(time);
+        self.update_motion();
+        self.update_emitters();
+        self.refit_bvh();
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(SceneError { objects: errors })
+        }
+    }
+
+    /// The interval of time, relative to the time of the scene, the
+    /// shutter is open for; see `set_shutter`.
+    pub fn shutter(&self) -> (Float, Float) {
+        self.shutter
+    }
+
+    /// Opens the shutter from `open` to `close`, relative to the time
+    /// set by `set_time`.  Animated objects are then hit by each ray
+    /// where they are at its `time`, clamped to the interval, which
+    /// blurs their motion when the camera spreads the times of its
+    /// rays over the same interval.  An empty interval (the default)
+    /// freezes the objects at the time of the scene.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::cgmath::{vec3, Matrix4, Quaternion, SquareMatrix};
+    /// use lucifer::geometry::{Point, Ray, Sphere, Vector};
+    /// use lucifer::lighting::{Albedo, Lambert, Radiance};
+    /// use lucifer::scene::{Interpolation, Object, Pose, Scene, Track};
+    ///
+    /// let pose = |x| Pose {
+    ///     scale: 1.0,
+    ///     rot: Quaternion::new(1.0, 0.0, 0.0, 0.0),
+    ///     disp: vec3(x, 0.0, -5.0),
+    /// };
+    /// let track = Track::new(Interpolation::Linear)
+    ///     .key(0.0, pose(0.0))
+    ///     .key(1.0, pose(4.0));
+    ///
+    /// let sphere = Sphere::new(Point::new(0.0, 0.0, 0.0), 1.0);
+    /// let object = Object::new(sphere, Lambert::new(Albedo::gray(0.8)), Matrix4::identity());
+    ///
+    /// let mut scene = Scene::new(Radiance::none());
+    /// scene.add(object.animate(track));
+    /// scene.set_time(0.0).unwrap();
+    ///
+    /// let ray = |x, time| {
+    ///     Ray::new(Point::new(x, 0.0, 0.0), Vector::new(0.0, 0.0, -1.0)).at_time(time)
+    /// };
+    ///
+    /// // With the shutter closed, the time of rays does not matter.
+    /// assert!(scene.intersect(&ray(2.0, 0.5)).is_none());
+    ///
+    /// // Open for half a second, the sphere sweeps from 0 to 2.
+    /// scene.set_shutter(0.0, 0.5);
+    /// assert!(scene.intersect(&ray(0.0, 0.0)).is_some());
+    /// assert!(scene.intersect(&ray(2.0, 0.0)).is_none());
+    /// assert!(scene.intersect(&ray(2.0, 0.5)).is_some());
+    /// assert!(scene.occlude(&ray(2.0, 0.25)));
+    /// assert!(!scene.occlude(&ray(3.5, 1.0)));
+    /// ```
+    pub fn set_shutter(&mut self, open: Float, close: Float) {
+        self.shutter = (open, close);
+        self.update_motion();
+        self.refit_bvh();
+    }
+
+    /// Updates the motion of the animated objects after the time of
+    /// the scene or the shutter changed.
+    fn update_motion(&mut self) {
+        let motion = self.motion();
+        for object in &mut self.objects {
+            if object.animation.is_some() {
+                object.motion = motion;
             }
         }
     }
 
-    pub fn occlude(&self, ray: &Ray) -> bool {
-        for obj in &self.objects {
-            if obj.geometry.occlude(&obj.transform_ray(ray)) {
-                return true;
+    /// The motion of animated objects at the time of the scene, if
+    /// the shutter is open.
+    fn motion(&self) -> Option<Motion> {
+        let (open, close) = self.shutter;
+        match self.time {
+            Some(time) if close > open => Some(Motion { time, open, close }),
+            _ => None,
+        }
+    }
+
+    /// Checks all objects of the scene, reporting every invalid one.
+    pub fn validate(&self) -> Result<(), SceneError> {
+        let objects: Vec<(usize, ObjectError)> = self
+            .objects
+            .iter()
+            .enumerate()
+            .filter_map(|(i, object)| object.validate().err().map(|e| (i, e)))
+            .collect();
+
+        if objects.is_empty() {
+            Ok(())
+        } else {
+            Err(SceneError { objects })
+        }
+    }
+
+    /// Removes the object at `index` from the scene.  The indices of
+    /// all following objects shift down by one.
+    pub fn remove(&mut self, index: usize) -> Object {
+        let object = self.objects.remove(index);
+        self.update_emitters();
+        self.bvh = OnceLock::new();
+        object
+    }
+
+    /// Moves the object at `index`, updating its cached inverse
+    /// transformation.
+    pub fn set_transform(&mut self, index: usize, transform: Matrix4<Float>) {
+        self.objects[index].set_transform(transform);
+        self.update_emitters();
+        self.refit_bvh();
+    }
+
+    /// Replaces the material of the object at `index`.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::sync::Arc;
+    ///
+    /// use lucifer::cgmath::{Matrix4, SquareMatrix};
+    /// use lucifer::geometry::{Point, Sphere};
+    /// use lucifer::lighting::{Albedo, Blackbody, Lambert, Radiance};
+    /// use lucifer::scene::{Object, Scene};
+    ///
+    /// let sphere = Sphere::new(Point::new(0.0, 0.0, 0.0), 1.0);
+    /// let mut scene = Scene::new(Radiance::none());
+    /// let index = scene.add(Object::new(sphere, Lambert::new(Albedo::gray(0.8)), Matrix4::identity()));
+    /// assert!(scene.emitters().is_empty());
+    ///
+    /// scene.set_material(index, Arc::new(Blackbody::new(Radiance::gray(1.0))));
+    /// assert_eq!(scene.emitters().len(), 1);
+    ///
+    /// scene.remove(index);
+    /// assert!(scene.objects().is_empty());
+    /// assert!(scene.emitters().is_empty());
+    /// ```
+    pub fn set_material(&mut self, index: usize, material: Arc<dyn Material>) {
+        self.objects[index].set_material(material);
+        self.update_emitters();
+    }
+
+    fn update_emitters(&mut self) {
+        self.emitters = self
+            .objects
+            .iter()
+            .enumerate()
+            .filter_map(|(i, object)| object.emitter(i))
+            .collect();
+        self.update_emitter_distribution();
+    }
+
+    fn update_emitter_distribution(&mut self) {
+        let powers: Vec<Float> = self.emitters.iter().map(Emitter::power).collect();
+        self.emitter_distribution = Discrete1D::new(&powers);
+    }
+
+    /// Adds all objects of a `Group`, composing their transformations
+    /// with the group's.
+    pub fn add_group(&mut self, group: Group) {
+        for object in group.flatten() {
+            self.add(object);
+        }
+    }
+
+    /// Moves all objects of `other` into the scene.  The environment
+    /// of `other` is dropped.
+    pub fn merge(&mut self, other: Scene) {
+        for object in other.objects {
+            self.add(object);
+        }
+    }
+
+    /// Places all objects of `other` in the scene as a unit, moved by
+    /// `transform`.  Geometry and materials are shared, not copied.
+    /// Fails if `transform` is not invertible.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::cgmath::{vec3, Matrix4, SquareMatrix};
+    /// use lucifer::geometry::{Point, Sphere};
+    /// use lucifer::lighting::{Albedo, Lambert, Radiance};
+    /// use lucifer::scene::{Object, Scene};
+    ///
+    /// let mut asset = Scene::new(Radiance::none());
+    /// let sphere = Sphere::new(Point::new(0.0, 0.0, 0.0), 1.0);
+    /// asset.add(Object::new(sphere, Lambert::new(Albedo::gray(0.8)), Matrix4::identity()));
+    ///
+    /// let mut scene = Scene::new(Radiance::none());
+    /// scene.add_scene(&asset, Matrix4::from_translation(vec3(-2.0, 0.0, 0.0))).unwrap();
+    /// scene.add_scene(&asset, Matrix4::from_translation(vec3(2.0, 0.0, 0.0))).unwrap();
+    ///
+    /// assert_eq!(scene.objects().len(), 2);
+    /// assert_eq!(scene.bounds().max, Point::new(3.0, 1.0, 1.0));
+    /// ```
+    pub fn add_scene(
+        &mut self,
+        other: &Scene,
+        transform: Matrix4<Float>,
+    ) -> Result<(), ObjectError> {
+        let group = other
+            .objects
+            .iter()
+            .fold(Group::try_new(transform)?, |group, object| {
+                group.with(object.clone())
+            });
+        self.add_group(group);
+        Ok(())
+    }
+
+    /// The objects in the scene, with their world transformations.
+    pub fn objects(&self) -> &[Object] {
+        &self.objects
+    }
+
+    /// The world-space bounding box of all bounded objects.  Infinite
+    /// geometry, such as a `Plane`, is ignored, so the result is
+    /// suitable for framing the camera.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::cgmath::{vec3, Matrix4, SquareMatrix};
+    /// use lucifer::geometry::{Plane, Point, Sphere, Vector};
+    /// use lucifer::lighting::{Albedo, Lambert, Radiance};
+    /// use lucifer::scene::{Object, Scene};
+    ///
+    /// let material = Lambert::new(Albedo::gray(0.8));
+    /// let sphere = Sphere::new(Point::new(0.0, 0.0, 0.0), 1.0);
+    /// let floor = Plane::new(Vector::new(0.0, 1.0, 0.0), -1.0);
+    ///
+    /// let mut scene = Scene::new(Radiance::none());
+    /// scene.add(Object::new(sphere, material.clone(), Matrix4::from_translation(vec3(0.0, 0.0, -5.0))));
+    /// scene.add(Object::new(floor, material, Matrix4::identity()));
+    ///
+    /// let bounds = scene.bounds();
+    /// assert_eq!(bounds.min, Point::new(-1.0, -1.0, -6.0));
+    /// assert_eq!(bounds.max, Point::new(1.0, 1.0, -4.0));
+    ///
+    /// let statistics = scene.statistics();
+    /// assert_eq!(statistics.objects, 2);
+    /// assert_eq!(statistics.lights, 0);
+    /// ```
+    pub fn bounds(&self) -> Bounds {
+        self.objects
+            .iter()
+            .map(Object::bounds)
+            .filter(Bounds::is_finite)
+            .fold(Bounds::empty(), |acc, bounds| acc.union(&bounds))
+    }
+
+    /// Counts objects, triangles, and lights in the scene.
+    /// Triangles are counted for every object placing them, but the
+    /// memory of shared geometry only once.  The memory includes the
+    /// scene's `Bvh` once the first ray has built it.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::sync::Arc;
+    ///
+    /// use lucifer::cgmath::{vec3, Matrix4};
+    /// use lucifer::geometry::{Geometry, Mesh, Point, Ray, Vector};
+    /// use lucifer::lighting::{Albedo, Lambert, Material, Radiance};
+    /// use lucifer::scene::{Object, Scene};
+    /// use lucifer::Float;
+    ///
+    /// let positions: Vec<Point> = (0..1000)
+    ///     .map(|i| Point::new(i as Float, (i % 2) as Float, 0.0))
+    ///     .collect();
+    /// let indices = (0..998).map(|i| [i, i + 1, i + 2]).collect();
+    /// let mesh: Arc<dyn Geometry> = Arc::new(Mesh::new(positions, None, indices));
+    /// let material: Arc<dyn Material> = Arc::new(Lambert::new(Albedo::gray(0.8)));
+    ///
+    /// // A hundred copies of the mesh, sharing its vertices and BVH.
+    /// let mut scene = Scene::new(Radiance::none());
+    /// for z in 0..100 {
+    ///     let transform = Matrix4::from_translation(vec3(0.0, 0.0, z as Float));
+    ///     scene.add(Object::shared(mesh.clone(), material.clone(), transform));
+    /// }
+    ///
+    /// let statistics = scene.statistics();
+    /// assert_eq!(statistics.triangles, 99800);
+    /// assert_eq!(statistics.shared, 99);
+    /// assert!(statistics.memory < 2 * mesh.memory());
+    ///
+    /// // The first ray builds the hierarchy over the copies.
+    /// scene.intersect(&Ray::new(Point::new(0.0, 0.0, -1.0), Vector::new(0.0, 0.0, 1.0)));
+    /// assert!(scene.statistics().memory > statistics.memory);
+    /// ```
+    pub fn statistics(&self) -> SceneStatistics {
+        let mut statistics = SceneStatistics {
+            objects: self.objects.len(),
+            lights: self.emitters.len(),
+            ..SceneStatistics::default()
+        };
+
+        let mut geometries = HashSet::new();
+        let mut materials = HashSet::new();
+        for object in &self.objects {
+            statistics.triangles += object.geometry.triangle_count();
+            if object.animation.is_some() {
+                statistics.animated += 1;
+            }
+
+            statistics.memory += mem::size_of::<Object>();
+            if geometries.insert(Arc::as_ptr(&object.geometry) as *const u8) {
+                statistics.memory += object.geometry.memory();
+            } else {
+                statistics.shared += 1;
+            }
+            if materials.insert(Arc::as_ptr(&object.material) as *const u8) {
+                statistics.memory += mem::size_of_val(&*object.material);
+            }
+        }
+
+        if let Some(bvh) = self.bvh.get() {
+            statistics.memory += bvh.memory();
+        }
+        statistics.memory += self.emitters.capacity() * mem::size_of::<Emitter>()
+            + self.emitter_distribution.memory();
+
+        statistics
+    }
+
+    /// The light sources of the scene.
+    pub fn emitters(&self) -> &[Emitter] {
+        &self.emitters
+    }
+
+    /// Maps `u` in `[0, 1)` to a light source, chosen proportional to
+    /// its emitted power.  Returns the light source and the
+    /// probability of choosing it.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::cgmath::{Matrix4, SquareMatrix};
+    /// use lucifer::geometry::{Point, Sphere};
+    /// use lucifer::lighting::{Albedo, Blackbody, Lambert, Radiance};
+    /// use lucifer::scene::{Object, Scene};
+    ///
+    /// let sphere = Sphere::new(Point::new(0.0, 0.0, 0.0), 1.0);
+    /// let mut scene = Scene::new(Radiance::none());
+    /// scene.add(Object::new(sphere.clone(), Lambert::new(Albedo::gray(0.8)), Matrix4::identity()));
+    /// scene.add(Object::new(sphere, Blackbody::new(Radiance::gray(1.0)), Matrix4::identity()));
+    ///
+    /// let (emitter, probability) = scene.sample_emitter(0.5).unwrap();
+    /// assert_eq!(emitter.object, 1);
+    /// assert_eq!(probability, 1.0);
+    ///
+    /// let (position, normal) = scene.sample_emitter_surface(emitter, 0.5, 0.5).unwrap();
+    /// ```
+    pub fn sample_emitter(&self, u: Float) -> Option<(&Emitter, Float)> {
+        self.emitter_distribution
+            .sample(u)
+            .map(|(i, probability)| (&self.emitters[i], probability))
+    }
+
+    /// Maps `u` and `v` in `[0, 1)` onto the surface of `emitter`,
+    /// returning the world-space position and surface normal.
+    pub fn sample_emitter_surface(
+        &self,
+        emitter: &Emitter,
+        u: Float,
+        v: Float,
+    ) -> Option<(Point, Vector)> {
+        self.objects[emitter.object].sample_surface(u, v)
+    }
+
+    /// The density, with respect to solid angle at the origin of
+    /// `ray`, of `sample_emitter` and `sample_emitter_surface` choosing
+    /// the point where `ray` hits `object`, e.g. to weight light
+    /// sampling against BSDF sampling.  `0` if `object` is no light
+    /// source or `ray` misses it.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::cgmath::{Matrix4, SquareMatrix};
+    /// use lucifer::consts::PI;
+    /// use lucifer::geometry::{Point, Ray, Sphere, Vector};
+    /// use lucifer::lighting::{Blackbody, Radiance};
+    /// use lucifer::scene::{Object, Scene};
+    ///
+    /// let sphere = Sphere::new(Point::new(0.0, 0.0, 0.0), 1.0);
+    /// let mut scene = Scene::new(Radiance::none());
+    /// let light = scene.add(Object::new(sphere, Blackbody::new(Radiance::gray(1.0)), Matrix4::identity()));
+    ///
+    /// // The nearest point, of the `4π` area, seen head-on from a
+    /// // distance of 2.
+    /// let ray = Ray::new(Point::new(0.0, 0.0, 3.0), Vector::new(0.0, 0.0, -1.0));
+    /// let pdf = scene.emitter_pdf(light, &ray);
+    /// assert!((pdf - 1.0 / PI).abs() < 1e-4);
+    ///
+    /// let ray = Ray::new(Point::new(0.0, 0.0, 3.0), Vector::new(0.0, 0.0, 1.0));
+    /// assert_eq!(scene.emitter_pdf(light, &ray), 0.0);
+    /// ```
+    pub fn emitter_pdf(&self, object: usize, ray: &Ray) -> Float {
+        let index = match self.emitters.binary_search_by_key(&object, |e| e.object) {
+            Ok(index) => index,
+            Err(_) => return 0.0,
+        };
+
+        self.emitter_distribution.probability(index) * self.objects[object].pdf_from(ray)
+    }
+
+    /// The hierarchy over the objects, built if they changed since
+    /// the last ray.
+    fn bvh(&self) -> &Bvh {
+        self.bvh.get_or_init(|| {
+            let bounds: Vec<Bounds> = self.objects.iter().map(Object::bounds).collect();
+            Bvh::with_split(&bounds, self.split)
+        })
+    }
+
+    /// Updates the hierarchy after objects moved, or drops it to be
+    /// rebuilt by the next ray if it cannot be refit.
+    fn refit_bvh(&mut self) {
+        if self.bvh.get().is_none() {
+            return;
+        }
+
+        let bounds: Vec<Bounds> = self.objects.iter().map(Object::bounds).collect();
+        if let Some(bvh) = self.bvh.get_mut() {
+            if !bvh.refit(&bounds) {
+                self.bvh = OnceLock::new();
             }
         }
+    }
+
+    /// The nearest surface along `ray`, shaded by its material.
+    ///
+    /// Only objects whose bounds the ray passes through are tested,
+    /// found by a `Bvh` that is rebuilt by the first ray after objects
+    /// were added or removed, and refit to objects that moved.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::cgmath::{vec3, Matrix4};
+    /// use lucifer::geometry::{Point, Ray, Sphere, Vector};
+    /// use lucifer::lighting::{Albedo, Lambert, Radiance};
+    /// use lucifer::scene::{Object, Scene};
+    /// use lucifer::Float;
+    ///
+    /// let material = Lambert::new(Albedo::gray(0.8));
+    /// let sphere = Sphere::new(Point::new(0.0, 0.0, 0.0), 0.4);
+    ///
+    /// let mut scene = Scene::new(Radiance::none());
+    /// for i in 0..100 {
+    ///     let (x, y) = ((i % 10) as Float, (i / 10) as Float);
+    ///     let transform = Matrix4::from_translation(vec3(x, y, -5.0));
+    ///     scene.add(Object::new(sphere.clone(), material.clone(), transform));
+    /// }
+    ///
+    /// let ray = Ray::new(Point::new(3.0, 7.0, 0.0), Vector::new(0.0, 0.0, -1.0));
+    /// assert_eq!(scene.intersect(&ray).unwrap().object, 73);
+    ///
+    /// scene.set_transform(12, Matrix4::from_translation(vec3(3.0, 7.0, -2.0)));
+    /// assert_eq!(scene.intersect(&ray).unwrap().object, 12);
+    /// assert!(scene.occlude(&ray));
+    /// ```
+    pub fn intersect(&self, ray: &Ray) -> Option<ShadedIntersection> {
+        let mut nearest: Option<(Hit, usize)> = None;
+
+        self.bvh().traverse(ray, |index| {
+            let hit = self.objects[index].intersect(ray, false)?;
+            let lambda = hit.world.lambda;
+            // Ties go to the first object, whatever order the `Bvh`
+            // visits them in.
+            let closer = nearest.as_ref().is_none_or(|&(ref n, i)| {
+                lambda < n.world.lambda || (lambda == n.world.lambda && index < i)
+            });
+            if closer {
+                nearest = Some((hit, index));
+            }
+            Some(lambda)
+        });
+
+        nearest.map(|(hit, index)| self.shade(hit, index))
+    }
+
+    /// The nearest surface along each of `rays`, like `intersect`,
+    /// found in one traversal of the `Bvh` for all of them, e.g. for
+    /// the camera rays of several views.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::cgmath::{vec3, Matrix4};
+    /// use lucifer::geometry::{Point, Ray, Sphere, Vector};
+    /// use lucifer::lighting::{Albedo, Lambert, Radiance};
+    /// use lucifer::scene::{Object, Scene};
+    /// use lucifer::Float;
+    ///
+    /// let material = Lambert::new(Albedo::gray(0.8));
+    /// let sphere = Sphere::new(Point::new(0.0, 0.0, 0.0), 0.4);
+    ///
+    /// let mut scene = Scene::new(Radiance::none());
+    /// for i in 0..100 {
+    ///     let (x, y) = ((i % 10) as Float, (i / 10) as Float);
+    ///     let transform = Matrix4::from_translation(vec3(x, y, -5.0));
+    ///     scene.add(Object::new(sphere.clone(), material.clone(), transform));
+    /// }
+    ///
+    /// let rays: Vec<Ray> = (0..12)
+    ///     .map(|i| Ray::new(Point::new(i as Float, 7.0, 0.0), Vector::new(0.0, 0.0, -1.0)))
+    ///     .collect();
+    /// let hits = scene.intersect_batch(&rays);
+    ///
+    /// assert_eq!(hits[3].as_ref().unwrap().object, 73);
+    /// assert!(hits[11].is_none());
+    /// for (ray, hit) in rays.iter().zip(&hits) {
+    ///     let alone = scene.intersect(ray);
+    ///     assert_eq!(hit.as_ref().map(|h| h.object), alone.as_ref().map(|h| h.object));
+    /// }
+    /// ```
+    pub fn intersect_batch(&self, rays: &[Ray]) -> Vec<Option<ShadedIntersection>> {
+        let mut nearest: Vec<Option<(Hit, usize)>> = rays.iter().map(|_| None).collect();
+
+        self.bvh().traverse_batch(rays, |ray, index| {
+            let hit = self.objects[index].intersect(&rays[ray], false)?;
+            let lambda = hit.world.lambda;
+            let closer = nearest[ray].as_ref().is_none_or(|&(ref n, i)| {
+                lambda < n.world.lambda || (lambda == n.world.lambda && index < i)
+            });
+            if closer {
+                nearest[ray] = Some((hit, index));
+            }
+            Some(lambda)
+        });
+
+        nearest
+            .into_iter()
+            .map(|nearest| nearest.map(|(hit, index)| self.shade(hit, index)))
+            .collect()
+    }
+
+    /// Shades the `hit` on the object at `index` by its material, or
+    /// the override material.
+    fn shade(&self, hit: Hit, index: usize) -> ShadedIntersection {
+        let material = match self.override_material {
+            Some(ref material) if hit.material.emission() == Radiance::none() => &**material,
+            _ => hit.material,
+        };
 
-        false
+        ShadedIntersection {
+            bsdf: material.shade(&hit.local),
+            intersection: hit.world,
+            medium: hit.medium,
+            priority: hit.priority,
+            object: index,
+        }
+    }
+
+    /// Invokes `callback` for every surface along `ray`, front to
+    /// back, until it returns `false`.  Unlike `intersect`, this also
+    /// reports surfaces behind the nearest one, e.g. to look through
+    /// transparent objects.  Distances are measured from the origin of
+    /// `ray`.
+    pub fn intersect_each<F>(&self, ray: &Ray, mut callback: F)
+    where
+        F: FnMut(ShadedIntersection) -> bool,
+    {
+        let mut current = ray.clone();
+        let mut distance = 0.0;
+
+        while let Some(mut hit) = self.intersect(&current) {
+            let step = hit.intersection.lambda + hit.intersection.step(current.direction);
+            hit.intersection.lambda += distance;
+
+            if hit.intersection.lambda > ray.length || !callback(hit) {
+                break;
+            }
+
+            // Step past the surface to find the next one.
+            distance += step;
+            current = Ray {
+                origin: current.origin + current.direction * step,
+                direction: current.direction,
+                length: current.length - step,
+                time: current.time,
+            };
+        }
+    }
+
+    /// All surfaces along `ray`, sorted front to back.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::cgmath::{vec3, Matrix4};
+    /// use lucifer::geometry::{Point, Ray, Sphere, Vector};
+    /// use lucifer::lighting::{Albedo, Lambert, Radiance};
+    /// use lucifer::scene::{Object, Scene};
+    /// use lucifer::Float;
+    ///
+    /// let material = Lambert::new(Albedo::gray(0.8));
+    /// let sphere = Sphere::new(Point::new(0.0, 0.0, 0.0), 1.0);
+    ///
+    /// let mut scene = Scene::new(Radiance::none());
+    /// scene.add(Object::new(sphere.clone(), material.clone(), Matrix4::from_translation(vec3(0.0, 0.0, -3.0))));
+    /// scene.add(Object::new(sphere, material, Matrix4::from_translation(vec3(0.0, 0.0, -6.0))));
+    ///
+    /// let ray = Ray::new(Point::new(0.0, 0.0, 0.0), Vector::new(0.0, 0.0, -1.0));
+    /// let hits: Vec<Float> = scene
+    ///     .intersect_all(&ray)
+    ///     .iter()
+    ///     .map(|hit| hit.intersection.lambda.round())
+    ///     .collect();
+    ///
+    /// assert_eq!(hits, vec![2.0, 4.0, 5.0, 7.0]);
+    /// ```
+    pub fn intersect_all(&self, ray: &Ray) -> Vec<ShadedIntersection> {
+        let mut hits = Vec::new();
+        self.intersect_each(ray, |hit| {
+            hits.push(hit);
+            true
+        });
+        hits
+    }
+
+    /// Whether anything blocks `ray` within its length, e.g. between
+    /// a point and a light.
+    ///
+    /// # Examples
+    /// ```
+    /// use lucifer::cgmath::{vec3, Matrix4, SquareMatrix};
+    /// use lucifer::geometry::{Point, Ray, Sphere};
+    /// use lucifer::lighting::{Albedo, Lambert, Radiance};
+    /// use lucifer::scene::{Object, Scene};
+    ///
+    /// let material = Lambert::new(Albedo::gray(0.8));
+    /// let sphere = Sphere::new(Point::new(0.0, 0.0, 0.0), 1.0);
+    ///
+    /// let mut scene = Scene::new(Radiance::none());
+    /// scene.add(Object::new(sphere, material, Matrix4::from_translation(vec3(0.0, 0.0, -5.0))));
+    ///
+    /// // The sphere lies beyond a light 3 units away, but blocks one
+    /// // 6 units away.
+    /// let origin = Point::new(0.0, 0.0, 0.0);
+    /// assert!(!scene.occlude(&Ray::from_endpoints(origin, Point::new(0.0, 0.0, -3.0))));
+    /// assert!(scene.occlude(&Ray::from_endpoints(origin, Point::new(0.0, 0.0, -6.0))));
+    /// ```
+    pub fn occlude(&self, ray: &Ray) -> bool {
+        self.bvh()
+            .any(ray, |index| self.objects[index].occlude(ray, false))
     }
 }
@@ -0,0 +1,37 @@
+use std::fmt;
+
+/// Measures of the complexity of a `Scene`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SceneStatistics {
+    /// The number of objects.
+    pub objects: usize,
+    /// The number of triangles over all objects.
+    pub triangles: usize,
+    /// The number of emissive objects.
+    pub lights: usize,
+    /// The number of animated objects.
+    pub animated: usize,
+    /// The number of objects sharing their geometry with an earlier
+    /// object, e.g. the instances of a mesh.
+    pub shared: usize,
+    /// The approximate memory used by the objects, including the
+    /// `Bvh`s of assemblies, the scene's own `Bvh` once built, and its
+    /// light sources, in bytes.  Geometry and materials shared between
+    /// objects are counted once.
+    pub memory: usize,
+}
+
+impl fmt::Display for SceneStatistics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} objects ({} animated, {} shared), {} triangles, {} lights, {:.1} KiB",
+            self.objects,
+            self.animated,
+            self.shared,
+            self.triangles,
+            self.lights,
+            self.memory as f64 / 1024.0
+        )
+    }
+}
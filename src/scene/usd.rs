@@ -0,0 +1,1105 @@
+//! Import of scenes in Universal Scene Description (USD) format
+//!
+//! Only text layers are read, either as `.usda` files or as the root
+//! layer of a `.usdz` package, and only a small subset of the schemas
+//! is understood: `Xform` transformations, the `Sphere` and `Cube`
+//! primitives, polygon `Mesh` prims, `UsdPreviewSurface` materials,
+//! the first `Camera`, and `SphereLight`, `DiskLight`, `RectLight`,
+//! and `DomeLight` lights.  Meshes are split into triangles, and keep
+//! their normals and `st` coordinates if given per point; subdivision
+//! surfaces render as their control cage.
+//!
+//! Everything else, e.g. curves, textures, and composition arcs
+//! (references, payloads, variants), is skipped and reported in
+//! `UsdScene::skipped`, so that partially supported assets can still
+//! be rendered.
+
+use std::collections::HashMap;
+use std::error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use cgmath::{Deg, Matrix4, Point3, Quaternion, Rad, SquareMatrix, Transform, Vector3};
+
+use scene::file::{
+    CameraDescription, GeometryDescription, MaterialDescription, ObjectDescription, SceneFile,
+    TransformDescription,
+};
+use Float;
+
+/// An error while importing a USD file.
+#[derive(Debug)]
+pub enum UsdError {
+    Io(io::Error),
+    /// A syntax error, at the given line.
+    Parse(usize, String),
+    /// The file uses a format that cannot be read, e.g. binary
+    /// (`.usdc`) layers.
+    Unsupported(String),
+}
+
+impl fmt::Display for UsdError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            UsdError::Io(ref e) => write!(f, "could not read USD file: {}", e),
+            UsdError::Parse(line, ref message) => {
+                write!(f, "invalid USD file, line {}: {}", line, message)
+            }
+            UsdError::Unsupported(ref what) => write!(f, "unsupported USD file: {}", what),
+        }
+    }
+}
+
+impl error::Error for UsdError {}
+
+impl From<io::Error> for UsdError {
+    fn from(e: io::Error) -> UsdError {
+        UsdError::Io(e)
+    }
+}
+
+/// A scene imported from USD.
+#[derive(Clone, Debug)]
+pub struct UsdScene {
+    /// The imported scene description.
+    pub file: SceneFile,
+    /// The paths of prims that were skipped, or only partially
+    /// imported.
+    pub skipped: Vec<String>,
+}
+
+/// Imports a scene from a `.usda`, `.usd` (text), or `.usdz` file.
+pub fn load<P: AsRef<Path>>(path: P) -> Result<UsdScene, UsdError> {
+    let path = path.as_ref();
+    let bytes = fs::read(path)?;
+
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("usdz") => from_usdz(&bytes),
+        _ => from_layer(&bytes),
+    }
+}
+
+/// Imports a scene from the root layer of a `.usdz` package.
+pub fn from_usdz(bytes: &[u8]) -> Result<UsdScene, UsdError> {
+    // A usdz package is an uncompressed zip archive, whose first file
+    // is the root layer.
+    let u16_at = |i: usize| u16::from(bytes[i]) | u16::from(bytes[i + 1]) << 8;
+    let u32_at = |i: usize| u32::from(u16_at(i)) | u32::from(u16_at(i + 2)) << 16;
+
+    if bytes.len() < 30 || u32_at(0) != 0x0403_4b50 {
+        return Err(UsdError::Unsupported("not a usdz package".into()));
+    }
+    if u16_at(8) != 0 {
+        return Err(UsdError::Unsupported("compressed usdz package".into()));
+    }
+
+    let size = u32_at(18) as usize;
+    let name_length = u16_at(26) as usize;
+    let start = 30 + name_length + u16_at(28) as usize;
+
+    match bytes.get(start..start + size) {
+        Some(layer) => from_layer(layer),
+        None => Err(UsdError::Unsupported("truncated usdz package".into())),
+    }
+}
+
+fn from_layer(bytes: &[u8]) -> Result<UsdScene, UsdError> {
+    if bytes.starts_with(b"PXR-USDC") {
+        return Err(UsdError::Unsupported("binary (usdc) layer".into()));
+    }
+
+    match ::std::str::from_utf8(bytes) {
+        Ok(s) => from_usda(s),
+        Err(_) => Err(UsdError::Parse(1, "invalid UTF-8".into())),
+    }
+}
+
+/// Imports a scene from a text (`.usda`) layer.
+///
+/// # Examples
+/// ```
+/// use lucifer::scene::file::{GeometryDescription, MaterialDescription};
+/// use lucifer::scene::usd;
+///
+/// let usd = usd::from_usda(r#"#usda 1.0
+/// def Xform "World" {
+///     def Sphere "Ball" {
+///         double radius = 2
+///         double3 xformOp:translate = (0, 2, 0)
+///         uniform token[] xformOpOrder = ["xformOp:translate"]
+///         rel material:binding = </World/Looks/Red>
+///     }
+///     def Mesh "Tile" {
+///         point3f[] points = [(0, 0, 0), (1, 0, 0), (1, 1, 0), (0, 1, 0)]
+///         int[] faceVertexCounts = [4]
+///         int[] faceVertexIndices = [0, 1, 2, 3]
+///     }
+///     def BasisCurves "Hair" {
+///         point3f[] points = [(0, 0, 0), (0, 1, 0)]
+///     }
+///     def SphereLight "Lamp" {
+///         float inputs:intensity = 10
+///     }
+///     def Scope "Looks" {
+///         def Material "Red" {
+///             def Shader "Surface" {
+///                 uniform token info:id = "UsdPreviewSurface"
+///                 color3f inputs:diffuseColor = (0.8, 0.1, 0.1)
+///             }
+///         }
+///     }
+/// }
+/// "#).unwrap();
+///
+/// assert_eq!(usd.file.objects.len(), 3);
+/// assert_eq!(usd.file.objects[0].material, MaterialDescription::Lambert { albedo: [0.8, 0.1, 0.1] });
+/// match usd.file.objects[1].geometry {
+///     GeometryDescription::Mesh { ref indices, .. } => assert_eq!(indices, &[[0, 1, 2], [0, 2, 3]]),
+///     ref geometry => panic!("not a mesh: {:?}", geometry),
+/// }
+/// assert_eq!(usd.skipped, vec!["/World/Hair"]);
+///
+/// let scene = usd.file.scene().unwrap();
+/// assert_eq!(scene.emitters().len(), 1);
+/// ```
+pub fn from_usda(s: &str) -> Result<UsdScene, UsdError> {
+    if !s.starts_with("#usda") {
+        return Err(UsdError::Parse(1, "missing #usda header".into()));
+    }
+
+    let layer = Parser::new(tokenize(s)?).layer()?;
+
+    let up = match layer.metadata.get("upAxis").and_then(Value::text) {
+        Some("Z") => Matrix4::from_angle_x(Deg(-90.0)),
+        _ => Matrix4::identity(),
+    };
+
+    let mut importer = Importer {
+        prims: &layer.prims,
+        file: SceneFile {
+            background: [0.0; 3],
+            environment: None,
+            camera: CameraDescription::Perspective {
+                position: [0.0, 0.0, 0.0],
+                look_at: [0.0, 0.0, -1.0],
+                up: [0.0, 1.0, 0.0],
+                fov: fov(DEFAULT_FOCAL_LENGTH, DEFAULT_APERTURE.1),
+            },
+            settings: Default::default(),
+            objects: Vec::new(),
+            lights: Vec::new(),
+            includes: Vec::new(),
+            medium: None,
+            camera_medium: None,
+            camera_keyframes: Vec::new(),
+            split: Default::default(),
+        },
+        camera: false,
+        skipped: Vec::new(),
+    };
+
+    for prim in &layer.prims {
+        importer.visit(prim, "", up, None);
+    }
+
+    Ok(UsdScene {
+        file: importer.file,
+        skipped: importer.skipped,
+    })
+}
+
+/// The defaults of the `Camera` schema, in tenths of scene units.
+const DEFAULT_FOCAL_LENGTH: Float = 50.0;
+const DEFAULT_APERTURE: (Float, Float) = (20.955, 15.2908);
+
+/// The vertical field of view, in degrees.
+fn fov(focal_length: Float, vertical_aperture: Float) -> Float {
+    Deg::from(Rad(2.0 * (vertical_aperture / (2.0 * focal_length)).atan())).0
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Ident(String),
+    Number(f64),
+    String(String),
+    Path(String),
+    Asset(String),
+    Punct(char),
+}
+
+fn tokenize(s: &str) -> Result<Vec<(Token, usize)>, UsdError> {
+    let chars: Vec<char> = s.chars().collect();
+    let mut tokens = Vec::new();
+    let mut line = 1;
+    let mut i = 0;
+
+    // Reads up to (and skips) `end`, counting lines.
+    let until = |i: &mut usize, line: &mut usize, end: &str| -> Result<String, UsdError> {
+        let end: Vec<char> = end.chars().collect();
+        let mut text = String::new();
+        loop {
+            if *i >= chars.len() {
+                return Err(UsdError::Parse(*line, "unterminated literal".into()));
+            }
+            if chars[*i..].starts_with(&end) {
+                *i += end.len();
+                return Ok(text);
+            }
+            if chars[*i] == '\\' && end.len() == 1 && *i + 1 < chars.len() {
+                *i += 1;
+            }
+            if chars[*i] == '\n' {
+                *line += 1;
+            }
+            text.push(chars[*i]);
+            *i += 1;
+        }
+    };
+
+    while i < chars.len() {
+        let c = chars[i];
+        let start = line;
+
+        if c == '\n' {
+            line += 1;
+            i += 1;
+        } else if c.is_whitespace() {
+            i += 1;
+        } else if c == '#' {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if c == '"' || c == '\'' {
+            let quote: String = if chars[i..].starts_with(&[c, c, c]) {
+                i += 3;
+                (0..3).map(|_| c).collect()
+            } else {
+                i += 1;
+                c.to_string()
+            };
+            tokens.push((Token::String(until(&mut i, &mut line, &quote)?), start));
+        } else if c == '<' {
+            i += 1;
+            tokens.push((Token::Path(until(&mut i, &mut line, ">")?), start));
+        } else if c == '@' {
+            let quote = if chars[i..].starts_with(&['@', '@', '@']) {
+                "@@@"
+            } else {
+                "@"
+            };
+            i += quote.len();
+            tokens.push((Token::Asset(until(&mut i, &mut line, quote)?), start));
+        } else if c.is_ascii_digit()
+            || ((c == '-' || c == '+' || c == '.')
+                && chars
+                    .get(i + 1)
+                    .is_some_and(|d| d.is_ascii_digit() || *d == '.'))
+        {
+            let begin = i;
+            i += 1;
+            while i < chars.len()
+                && (chars[i].is_ascii_digit()
+                    || chars[i] == '.'
+                    || chars[i] == 'e'
+                    || chars[i] == 'E'
+                    || ((chars[i] == '-' || chars[i] == '+')
+                        && (chars[i - 1] == 'e' || chars[i - 1] == 'E')))
+            {
+                i += 1;
+            }
+            let text: String = chars[begin..i].iter().collect();
+            match text.parse() {
+                Ok(n) => tokens.push((Token::Number(n), start)),
+                Err(_) => return Err(UsdError::Parse(line, format!("invalid number {}", text))),
+            }
+        } else if c.is_alphabetic() || c == '_' {
+            let begin = i;
+            while i < chars.len()
+                && (chars[i].is_alphanumeric()
+                    || chars[i] == '_'
+                    || chars[i] == ':'
+                    || chars[i] == '.')
+            {
+                i += 1;
+            }
+            tokens.push((Token::Ident(chars[begin..i].iter().collect()), start));
+        } else if "()[]{}=,;:".contains(c) {
+            tokens.push((Token::Punct(c), start));
+            i += 1;
+        } else {
+            return Err(UsdError::Parse(
+                line,
+                format!("unexpected character {:?}", c),
+            ));
+        }
+    }
+
+    Ok(tokens)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Value {
+    None,
+    Number(f64),
+    /// Strings, tokens, and keywords such as `true`.
+    Text(String),
+    Path(String),
+    Asset(String),
+    /// Tuples and arrays.
+    List(Vec<Value>),
+    TimeSamples(Vec<(f64, Value)>),
+    /// A dictionary, whose contents are ignored.
+    Dictionary,
+}
+
+impl Value {
+    fn number(&self) -> Option<Float> {
+        match *self {
+            Value::Number(n) => Some(n as Float),
+            Value::Text(ref t) if t == "true" => Some(1.0),
+            Value::Text(ref t) if t == "false" => Some(0.0),
+            _ => None,
+        }
+    }
+
+    fn text(&self) -> Option<&str> {
+        match *self {
+            Value::Text(ref t) => Some(t),
+            _ => None,
+        }
+    }
+
+    /// All numbers in the value, in order, e.g. the entries of a
+    /// matrix.
+    fn numbers(&self) -> Vec<Float> {
+        match *self {
+            Value::List(ref values) => values.iter().flat_map(Value::numbers).collect(),
+            _ => self.number().into_iter().collect(),
+        }
+    }
+
+    fn triple(&self) -> Option<[Float; 3]> {
+        match self.numbers()[..] {
+            [x, y, z] => Some([x, y, z]),
+            _ => None,
+        }
+    }
+
+    /// The entries of an array of pairs, e.g. texture coordinates.
+    fn pairs(&self) -> Option<Vec<[Float; 2]>> {
+        let numbers = self.numbers();
+        let tuples = numbers.chunks_exact(2);
+        if !tuples.remainder().is_empty() {
+            return None;
+        }
+        Some(tuples.map(|c| [c[0], c[1]]).collect())
+    }
+
+    /// The entries of an array of triples, e.g. points.
+    fn triples(&self) -> Option<Vec<[Float; 3]>> {
+        let numbers = self.numbers();
+        let tuples = numbers.chunks_exact(3);
+        if !tuples.remainder().is_empty() {
+            return None;
+        }
+        Some(tuples.map(|c| [c[0], c[1], c[2]]).collect())
+    }
+
+    /// The entries of an array of indices, or `None` if any is not a
+    /// non-negative integer.
+    fn indices(&self) -> Option<Vec<usize>> {
+        match *self {
+            Value::List(ref values) => values
+                .iter()
+                .map(|v| match *v {
+                    Value::Number(n) if n >= 0.0 && n.fract() == 0.0 => Some(n as usize),
+                    _ => None,
+                })
+                .collect(),
+            _ => None,
+        }
+    }
+}
+
+struct Prim {
+    specifier: String,
+    kind: String,
+    name: String,
+    metadata: HashMap<String, Value>,
+    properties: HashMap<String, Value>,
+    children: Vec<Prim>,
+}
+
+impl Prim {
+    /// The default value of a property, or its earliest time sample.
+    fn property(&self, name: &str) -> Option<&Value> {
+        match self.properties.get(name) {
+            Some(&Value::None) | None => {
+                match self.properties.get(&format!("{}.timeSamples", name)) {
+                    Some(Value::TimeSamples(samples)) => samples.first().map(|s| &s.1),
+                    _ => None,
+                }
+            }
+            value => value,
+        }
+    }
+
+    fn number(&self, name: &str, default: Float) -> Float {
+        self.property(name)
+            .and_then(Value::number)
+            .unwrap_or(default)
+    }
+
+    fn triple(&self, name: &str, default: [Float; 3]) -> [Float; 3] {
+        self.property(name)
+            .and_then(Value::triple)
+            .unwrap_or(default)
+    }
+
+    /// A light input, which older files give without the `inputs:`
+    /// namespace.
+    fn input(&self, name: &str) -> Option<&Value> {
+        self.property(&format!("inputs:{}", name))
+            .or_else(|| self.property(name))
+    }
+
+    fn child(&self, name: &str) -> Option<&Prim> {
+        self.children.iter().find(|c| c.name == name)
+    }
+
+    /// Whether the prim uses composition arcs, which are not
+    /// resolved.
+    fn composes(&self) -> bool {
+        [
+            "references",
+            "payload",
+            "inherits",
+            "specializes",
+            "variantSets",
+        ]
+        .iter()
+        .any(|k| self.metadata.contains_key(*k))
+    }
+
+    /// The transformation relative to the parent, and whether it
+    /// ignores the parent's transformation.
+    fn transform(&self) -> (Matrix4<Float>, bool) {
+        let order = match self.property("xformOpOrder") {
+            Some(Value::List(ops)) => ops.iter().filter_map(Value::text).collect(),
+            _ => Vec::new(),
+        };
+
+        let reset = order.first() == Some(&"!resetXformStack!");
+        let transform = order.iter().fold(Matrix4::identity(), |acc, op| {
+            acc * self.operation(op).unwrap_or_else(Matrix4::identity)
+        });
+
+        (transform, reset)
+    }
+
+    /// The matrix of a single `xformOp`.
+    fn operation(&self, op: &str) -> Option<Matrix4<Float>> {
+        if let Some(op) = op.strip_prefix("!invert!") {
+            return self.operation(op).and_then(|m| m.invert());
+        }
+
+        let v = self.property(op)?.numbers();
+        let kind = op.split(':').nth(1)?;
+
+        Some(match (kind, &v[..]) {
+            ("translate", &[x, y, z]) => Matrix4::from_translation(Vector3::new(x, y, z)),
+            ("scale", &[x, y, z]) => Matrix4::from_nonuniform_scale(x, y, z),
+            ("rotateX", &[a]) => Matrix4::from_angle_x(Deg(a)),
+            ("rotateY", &[a]) => Matrix4::from_angle_y(Deg(a)),
+            ("rotateZ", &[a]) => Matrix4::from_angle_z(Deg(a)),
+            ("orient", &[w, x, y, z]) => Matrix4::from(Quaternion::new(w, x, y, z)),
+            ("transform", m) if m.len() == 16 => {
+                // USD matrices transform row vectors, so their rows
+                // are our columns.
+                let mut columns = [[0.0; 4]; 4];
+                for (i, x) in m.iter().enumerate() {
+                    columns[i / 4][i % 4] = *x;
+                }
+                Matrix4::from(columns)
+            }
+            (rotate, &[a, b, c]) if rotate.starts_with("rotate") && rotate.len() == 9 => {
+                // E.g. `rotateXYZ` rotates around x first.
+                rotate[6..].chars().zip(&[a, b, c]).fold(
+                    Matrix4::identity(),
+                    |acc, (axis, &angle)| {
+                        let r = match axis {
+                            'X' => Matrix4::from_angle_x(Deg(angle)),
+                            'Y' => Matrix4::from_angle_y(Deg(angle)),
+                            _ => Matrix4::from_angle_z(Deg(angle)),
+                        };
+                        r * acc
+                    },
+                )
+            }
+            _ => return None,
+        })
+    }
+}
+
+struct Layer {
+    metadata: HashMap<String, Value>,
+    prims: Vec<Prim>,
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+}
+
+impl Parser {
+    fn new(tokens: Vec<(Token, usize)>) -> Parser {
+        Parser {
+            tokens,
+            position: 0,
+        }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|t| &t.0)
+    }
+
+    fn error<T>(&self, message: &str) -> Result<T, UsdError> {
+        let line = self
+            .tokens
+            .get(self.position)
+            .or_else(|| self.tokens.last())
+            .map_or(1, |t| t.1);
+        Err(UsdError::Parse(line, message.into()))
+    }
+
+    fn next(&mut self) -> Result<Token, UsdError> {
+        match self.tokens.get(self.position) {
+            Some((token, _)) => {
+                self.position += 1;
+                Ok(token.clone())
+            }
+            None => self.error("unexpected end of file"),
+        }
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(&Token::Punct(c)) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), UsdError> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            self.error(&format!("expected '{}'", c))
+        }
+    }
+
+    fn ident(&mut self) -> Result<String, UsdError> {
+        match self.peek() {
+            Some(&Token::Ident(_)) => match self.next()? {
+                Token::Ident(name) => Ok(name),
+                _ => unreachable!(),
+            },
+            _ => self.error("expected identifier"),
+        }
+    }
+
+    fn is_ident(&self, name: &str) -> bool {
+        match self.peek() {
+            Some(Token::Ident(i)) => i == name,
+            _ => false,
+        }
+    }
+
+    /// Skips a bracketed block, whose opening bracket has been read.
+    fn skip_block(&mut self) -> Result<(), UsdError> {
+        let mut depth = 1;
+        while depth > 0 {
+            match self.next()? {
+                Token::Punct('(') | Token::Punct('[') | Token::Punct('{') => depth += 1,
+                Token::Punct(')') | Token::Punct(']') | Token::Punct('}') => depth -= 1,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn layer(&mut self) -> Result<Layer, UsdError> {
+        let metadata = self.metadata()?;
+
+        let mut prims = Vec::new();
+        while self.peek().is_some() {
+            prims.push(self.prim()?);
+        }
+
+        Ok(Layer { metadata, prims })
+    }
+
+    /// Parses an optional, parenthesized metadata block.
+    fn metadata(&mut self) -> Result<HashMap<String, Value>, UsdError> {
+        let mut metadata = HashMap::new();
+        if !self.eat('(') {
+            return Ok(metadata);
+        }
+
+        while !self.eat(')') {
+            if let Some(&Token::String(_)) = self.peek() {
+                // The documentation string.
+                self.next()?;
+                continue;
+            }
+
+            let mut key = self.ident()?;
+            if ["prepend", "append", "add", "delete", "reorder"].contains(&key.as_str()) {
+                key = self.ident()?;
+            }
+
+            let value = if self.eat('=') {
+                self.value()?
+            } else {
+                Value::None
+            };
+            metadata.insert(key, value);
+            self.eat(';');
+        }
+
+        Ok(metadata)
+    }
+
+    fn prim(&mut self) -> Result<Prim, UsdError> {
+        let specifier = self.ident()?;
+        if !["def", "over", "class"].contains(&specifier.as_str()) {
+            return self.error("expected prim");
+        }
+
+        let kind = match self.peek() {
+            Some(&Token::Ident(_)) => self.ident()?,
+            _ => String::new(),
+        };
+        let name = match self.next()? {
+            Token::String(name) => name,
+            _ => return self.error("expected prim name"),
+        };
+
+        let mut prim = Prim {
+            specifier,
+            kind,
+            name,
+            metadata: self.metadata()?,
+            properties: HashMap::new(),
+            children: Vec::new(),
+        };
+
+        self.expect('{')?;
+        while !self.eat('}') {
+            if self.is_ident("def") || self.is_ident("over") || self.is_ident("class") {
+                prim.children.push(self.prim()?);
+            } else if self.is_ident("variantSet") {
+                self.next()?;
+                self.next()?;
+                self.expect('=')?;
+                self.expect('{')?;
+                self.skip_block()?;
+                prim.metadata.insert("variantSets".into(), Value::None);
+            } else if self.is_ident("reorder") {
+                self.next()?;
+                self.ident()?;
+                self.expect('=')?;
+                self.value()?;
+            } else {
+                let (name, value) = self.property()?;
+                prim.properties.insert(name, value);
+            }
+            self.eat(';');
+        }
+
+        Ok(prim)
+    }
+
+    fn property(&mut self) -> Result<(String, Value), UsdError> {
+        const QUALIFIERS: &[&str] = &[
+            "custom", "uniform", "varying", "config", "prepend", "append", "add", "delete",
+        ];
+
+        let mut first = self.ident()?;
+        while QUALIFIERS.contains(&first.as_str()) {
+            first = self.ident()?;
+        }
+        if self.eat('[') {
+            self.expect(']')?;
+        }
+
+        // Attributes and relationships are typed; the type is not
+        // needed, though.
+        let name = match self.peek() {
+            Some(&Token::Ident(_)) => self.ident()?,
+            _ => first,
+        };
+
+        let value = if self.eat('=') {
+            self.value()?
+        } else {
+            Value::None
+        };
+        self.metadata()?;
+
+        Ok((name, value))
+    }
+
+    fn value(&mut self) -> Result<Value, UsdError> {
+        let value = match self.next()? {
+            Token::Number(n) => Value::Number(n),
+            Token::String(s) => Value::Text(s),
+            Token::Ident(ref i) if i == "None" => Value::None,
+            Token::Ident(i) => Value::Text(i),
+            Token::Path(p) => Value::Path(p),
+            Token::Asset(a) => {
+                // A reference may name a prim in the referenced
+                // layer.
+                if let Some(&Token::Path(_)) = self.peek() {
+                    self.next()?;
+                }
+                Value::Asset(a)
+            }
+            Token::Punct('(') => Value::List(self.list(')')?),
+            Token::Punct('[') => Value::List(self.list(']')?),
+            Token::Punct('{') => self.dictionary()?,
+            _ => return self.error("expected value"),
+        };
+
+        Ok(value)
+    }
+
+    fn list(&mut self, end: char) -> Result<Vec<Value>, UsdError> {
+        let mut values = Vec::new();
+        while !self.eat(end) {
+            values.push(self.value()?);
+            if !self.eat(',') {
+                self.expect(end)?;
+                break;
+            }
+        }
+        Ok(values)
+    }
+
+    fn dictionary(&mut self) -> Result<Value, UsdError> {
+        match self.peek() {
+            Some(&Token::Number(_)) => {}
+            _ => {
+                self.skip_block()?;
+                return Ok(Value::Dictionary);
+            }
+        }
+
+        let mut samples = Vec::new();
+        while !self.eat('}') {
+            let time = match self.next()? {
+                Token::Number(n) => n,
+                _ => return self.error("expected time"),
+            };
+            self.expect(':')?;
+            samples.push((time, self.value()?));
+            self.eat(',');
+        }
+        samples.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        Ok(Value::TimeSamples(samples))
+    }
+}
+
+struct Importer<'a> {
+    prims: &'a [Prim],
+    file: SceneFile,
+    camera: bool,
+    skipped: Vec<String>,
+}
+
+impl<'a> Importer<'a> {
+    fn visit(
+        &mut self,
+        prim: &'a Prim,
+        parent: &str,
+        transform: Matrix4<Float>,
+        binding: Option<&'a str>,
+    ) {
+        if prim.specifier == "class" {
+            return;
+        }
+        if prim.property("visibility").and_then(Value::text) == Some("invisible") {
+            return;
+        }
+
+        let path = format!("{}/{}", parent, prim.name);
+        if prim.composes() {
+            self.skipped.push(path.clone());
+        }
+
+        let (local, reset) = prim.transform();
+        let transform = if reset { local } else { transform * local };
+        let binding = match prim.property("material:binding") {
+            Some(Value::Path(p)) => Some(p.as_str()),
+            _ => binding,
+        };
+
+        match prim.kind.as_str() {
+            "" | "Xform" | "Scope" => {}
+            "Material" | "Shader" => return,
+            "Sphere" => {
+                let geometry = GeometryDescription::Sphere {
+                    center: [0.0; 3],
+                    radius: prim.number("radius", 1.0),
+                };
+                let material = self.material(prim, binding);
+                self.add(geometry, material, transform);
+            }
+            "Cube" => {
+                let size = prim.number("size", 2.0);
+                let geometry = GeometryDescription::Cube {
+                    center: [0.0; 3],
+                    size: [size; 3],
+                };
+                let material = self.material(prim, binding);
+                self.add(geometry, material, transform);
+            }
+            "Mesh" => match mesh(prim) {
+                Some(geometry) => {
+                    let material = self.material(prim, binding);
+                    self.add(geometry, material, transform);
+                }
+                None => self.skipped.push(path.clone()),
+            },
+            "SphereLight" | "DiskLight" | "RectLight" => {
+                let radius = prim.input("radius").and_then(Value::number).unwrap_or(0.5);
+                let geometry = match prim.kind.as_str() {
+                    "SphereLight" => GeometryDescription::Sphere {
+                        center: [0.0; 3],
+                        radius,
+                    },
+                    "DiskLight" => GeometryDescription::Disc {
+                        center: [0.0; 3],
+                        normal: [0.0, 0.0, -1.0],
+                        radius,
+                    },
+                    // Facing `-z`, like the disk.
+                    _ => GeometryDescription::Quad {
+                        center: [0.0; 3],
+                        edges: [
+                            [
+                                prim.input("width").and_then(Value::number).unwrap_or(1.0),
+                                0.0,
+                                0.0,
+                            ],
+                            [
+                                0.0,
+                                -prim.input("height").and_then(Value::number).unwrap_or(1.0),
+                                0.0,
+                            ],
+                        ],
+                    },
+                };
+                let material = MaterialDescription::Blackbody {
+                    radiance: light(prim),
+                };
+                self.add(geometry, material, transform);
+            }
+            "DomeLight" => {
+                if prim.input("texture:file").is_some() {
+                    self.skipped.push(path.clone());
+                }
+                self.file.background = light(prim);
+            }
+            "Camera" if !self.camera => {
+                self.camera = true;
+                self.set_camera(prim, transform);
+            }
+            _ => {
+                self.skipped.push(path);
+                return;
+            }
+        }
+
+        for child in &prim.children {
+            self.visit(child, &path, transform, binding);
+        }
+    }
+
+    fn add(
+        &mut self,
+        geometry: GeometryDescription,
+        material: MaterialDescription,
+        transform: Matrix4<Float>,
+    ) {
+        self.file.objects.push(ObjectDescription {
+            geometry,
+            material,
+            transform: vec![TransformDescription::Matrix(transform.into())],
+            medium: None,
+            keyframes: Vec::new(),
+            one_sided: false,
+            priority: 0,
+        });
+    }
+
+    fn find(&self, path: &str) -> Option<&'a Prim> {
+        let mut names = path.split('/').filter(|n| !n.is_empty());
+        let root = names.next()?;
+        let first = self.prims.iter().find(|p| p.name == root)?;
+        names.try_fold(first, |prim, name| prim.child(name))
+    }
+
+    /// The bound material, or the display color.
+    fn material(&self, prim: &Prim, binding: Option<&str>) -> MaterialDescription {
+        let surface = binding
+            .and_then(|b| self.find(b))
+            .and_then(|material| preview_surface(material));
+
+        match surface {
+            Some(shader) => surface_material(shader),
+            None => MaterialDescription::Lambert {
+                albedo: match prim.property("primvars:displayColor") {
+                    Some(Value::List(colors)) => colors.first().and_then(Value::triple),
+                    Some(color) => color.triple(),
+                    None => None,
+                }
+                .unwrap_or([0.18; 3]),
+            },
+        }
+    }
+
+    fn set_camera(&mut self, prim: &Prim, transform: Matrix4<Float>) {
+        let focal_length = prim.number("focalLength", DEFAULT_FOCAL_LENGTH);
+        let horizontal = prim.number("horizontalAperture", DEFAULT_APERTURE.0);
+        let vertical = prim.number("verticalAperture", DEFAULT_APERTURE.1);
+
+        let position = transform.transform_point(Point3::new(0.0, 0.0, 0.0));
+        let look_at = transform.transform_point(Point3::new(0.0, 0.0, -1.0));
+        let up = transform.transform_vector(Vector3::new(0.0, 1.0, 0.0));
+
+        self.file.camera = CameraDescription::Perspective {
+            position: position.into(),
+            look_at: look_at.into(),
+            up: up.into(),
+            fov: fov(focal_length, vertical),
+        };
+
+        let width = self.file.settings.width as Float;
+        self.file.settings.height = ((width * vertical / horizontal).round() as u32).max(1);
+    }
+}
+
+/// The `UsdPreviewSurface` shader of a material.
+fn preview_surface(prim: &Prim) -> Option<&Prim> {
+    if prim.kind == "Shader"
+        && prim.property("info:id").and_then(Value::text) == Some("UsdPreviewSurface")
+    {
+        return Some(prim);
+    }
+
+    prim.children.iter().filter_map(preview_surface).next()
+}
+
+/// Approximates a `UsdPreviewSurface` by the available materials.
+/// Textures are ignored.
+fn surface_material(shader: &Prim) -> MaterialDescription {
+    let diffuse = shader.triple("inputs:diffuseColor", [0.18; 3]);
+    let emission = shader.triple("inputs:emissiveColor", [0.0; 3]);
+    let metallic = shader.number("inputs:metallic", 0.0);
+    let roughness = shader.number("inputs:roughness", 0.5);
+
+    if shader.number("inputs:opacity", 1.0) < 1.0 {
+        MaterialDescription::Dielectric {
+            albedo: [1.0; 3],
+            ior: shader.number("inputs:ior", 1.5),
+        }
+    } else if metallic <= 0.0 && emission == [0.0; 3] {
+        MaterialDescription::Lambert { albedo: diffuse }
+    } else {
+        // Metals reflect their base color, dielectrics about 4%.
+        let alpha = (roughness * roughness).max(0.01);
+        let mix = |c: Float| 0.04 + (c - 0.04) * metallic;
+
+        MaterialDescription::Phong {
+            emission,
+            diffuse: [
+                diffuse[0] * (1.0 - metallic),
+                diffuse[1] * (1.0 - metallic),
+                diffuse[2] * (1.0 - metallic),
+            ],
+            specular: [mix(diffuse[0]), mix(diffuse[1]), mix(diffuse[2])],
+            shininess: 2.0 / (alpha * alpha) - 2.0,
+        }
+    }
+}
+
+/// The triangles of a `Mesh` prim, splitting its polygons into fans,
+/// or `None` if it is malformed.  Normals and `st` coordinates are
+/// only kept if there is one per point, as attribute metadata, e.g.
+/// their interpolation, is not read.
+fn mesh(prim: &Prim) -> Option<GeometryDescription> {
+    let positions = prim.property("points")?.triples()?;
+    let counts = prim.property("faceVertexCounts")?.indices()?;
+    let vertices = prim.property("faceVertexIndices")?.indices()?;
+    if counts.iter().sum::<usize>() != vertices.len()
+        || vertices.iter().any(|&i| i >= positions.len())
+    {
+        return None;
+    }
+
+    let left_handed = prim.property("orientation").and_then(Value::text) == Some("leftHanded");
+    let mut indices = Vec::new();
+    let mut faces = vertices.as_slice();
+    for &count in &counts {
+        let (face, rest) = faces.split_at(count);
+        faces = rest;
+        for i in 1..count.saturating_sub(1) {
+            let (a, b, c) = (face[0] as u32, face[i] as u32, face[i + 1] as u32);
+            indices.push(if left_handed { [a, c, b] } else { [a, b, c] });
+        }
+    }
+
+    let points = positions.len();
+    let normals = prim
+        .property("normals")
+        .and_then(Value::triples)
+        .filter(|normals| normals.len() == points)
+        .unwrap_or_default();
+    let uvs = prim
+        .property("primvars:st")
+        .and_then(Value::pairs)
+        .filter(|uvs| uvs.len() == points)
+        .unwrap_or_default();
+
+    Some(GeometryDescription::Mesh {
+        positions,
+        normals,
+        uvs,
+        indices,
+        split: Default::default(),
+    })
+}
+
+/// The emitted radiance of a light.
+fn light(prim: &Prim) -> [Float; 3] {
+    let intensity = prim
+        .input("intensity")
+        .and_then(Value::number)
+        .unwrap_or(1.0);
+    let exposure = prim
+        .input("exposure")
+        .and_then(Value::number)
+        .unwrap_or(0.0);
+    let color = prim
+        .input("color")
+        .and_then(Value::triple)
+        .unwrap_or([1.0; 3]);
+
+    let scale = intensity * exposure.exp2();
+    [color[0] * scale, color[1] * scale, color[2] * scale]
+}
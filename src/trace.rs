@@ -0,0 +1,74 @@
+//! Diagnostic events and timed spans, reported through the `log`
+//! crate with the `log` feature.
+//!
+//! Without the feature, the macros and `Span` compile to nothing,
+//! but their arguments are still type checked.
+
+#[cfg(feature = "log")]
+use render::stats::Stopwatch;
+
+/// Reports an event at debug level, e.g. the size of a scene.
+macro_rules! debug {
+    ($($arg:tt)+) => {{
+        #[cfg(feature = "log")]
+        ::log::debug!($($arg)+);
+        #[cfg(not(feature = "log"))]
+        {
+            if false {
+                let _ = format_args!($($arg)+);
+            }
+        }
+    }};
+}
+
+/// Reports an event at trace level, e.g. for every rendered tile.
+macro_rules! trace {
+    ($($arg:tt)+) => {{
+        #[cfg(feature = "log")]
+        ::log::trace!($($arg)+);
+        #[cfg(not(feature = "log"))]
+        {
+            if false {
+                let _ = format_args!($($arg)+);
+            }
+        }
+    }};
+}
+
+/// A stage of work, like building a `Bvh`, whose duration is
+/// reported at debug level when the span is dropped.
+///
+/// The clock only runs if debug events are enabled.
+pub(crate) struct Span {
+    #[cfg(feature = "log")]
+    name: &'static str,
+    #[cfg(feature = "log")]
+    start: Option<Stopwatch>,
+}
+
+impl Span {
+    pub(crate) fn enter(name: &'static str) -> Span {
+        trace!("{}", name);
+
+        #[cfg(feature = "log")]
+        return Span {
+            name,
+            start: if ::log::log_enabled!(::log::Level::Debug) {
+                Some(Stopwatch::start())
+            } else {
+                None
+            },
+        };
+        #[cfg(not(feature = "log"))]
+        return Span {};
+    }
+}
+
+#[cfg(feature = "log")]
+impl Drop for Span {
+    fn drop(&mut self) {
+        if let Some(start) = self.start {
+            debug!("{} took {:?}", self.name, start.elapsed());
+        }
+    }
+}
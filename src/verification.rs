@@ -0,0 +1,255 @@
+//! Scenes with known solutions, to check renderers for energy
+//! conservation and bias.
+//!
+//! In a *furnace*, a convex object is lit by a uniform, white
+//! environment.  It never sees itself, so every surface of albedo
+//! `ρ` reflects exactly `ρ` of the environment's radiance towards the
+//! camera, whatever its shape.  In an *enclosure*, the camera sits
+//! inside a closed sphere glowing with `E` and reflecting `ρ`, which
+//! converges to `E / (1 - ρ)`; after `d` vertices, a path has
+//! gathered `E (1 - ρ^d) / (1 - ρ)`.
+//!
+//! # Examples
+//! ```
+//! use lucifer::render::{Context, PathTracer};
+//! use lucifer::verification::references;
+//!
+//! // Sampling the environment as well as the BSDF makes even the
+//! // furnace noisy, so a few more samples are needed.
+//! let tracer = PathTracer::new(0.0, 8, 16);
+//! let mut context = Context::seeded(0, 0);
+//!
+//! for reference in references(tracer.depth_limit) {
+//!     let outcome = reference.check(&tracer, &mut context);
+//!     assert!(outcome.passed(0.02), "{}", outcome);
+//! }
+//! ```
+
+use std::fmt;
+
+use cgmath::{Deg, Matrix4, PerspectiveFov, Rad, SquareMatrix, Vector3};
+
+use camera::{AffineTransformCamera, Resolution, Target};
+use geometry::{Point, Sphere, Vector};
+use lighting::{Albedo, Dielectric, Ior, Lambert, Material, Phong, Radiance};
+use render::{Context, Renderer};
+use scene::{Object, Scene};
+use Float;
+
+/// The resolution of the images rendered by `Reference::check`.
+const SIZE: u32 = 8;
+
+/// A scene, and the radiance all pixels of its image converge to.
+pub struct Reference {
+    pub name: String,
+    pub scene: Scene,
+    pub camera: AffineTransformCamera,
+    pub expected: Radiance,
+    /// Whether `expected` is only an upper bound, for materials that
+    /// may lose, but not create energy.
+    pub upper_bound: bool,
+}
+
+impl Reference {
+    /// A unit sphere of `material`, reflecting `albedo`, in a white
+    /// furnace.  Reflecting light takes paths of two vertices.
+    pub fn furnace<M>(name: &str, material: M, albedo: Albedo) -> Reference
+    where
+        M: Material + 'static,
+    {
+        let mut scene = Scene::new(Radiance::gray(1.0));
+        scene.add(Object::new(
+            Sphere::new(Point::new(0.0, 0.0, 0.0), 1.0),
+            material,
+            Matrix4::identity(),
+        ));
+
+        Reference {
+            name: format!("furnace, {}", name),
+            scene,
+            camera: camera(Point::new(0.0, 0.0, 5.0), Point::new(0.0, 0.0, 0.0), 10.0),
+            expected: Radiance::gray(1.0) * albedo,
+            upper_bound: false,
+        }
+    }
+
+    /// A closed sphere glowing with `emission` and diffusely
+    /// reflecting `albedo`, seen from within by paths of at most
+    /// `depth_limit` vertices.
+    pub fn enclosure(emission: Float, albedo: Float, depth_limit: u8) -> Reference {
+        let mut scene = Scene::new(Radiance::none());
+        scene.add(Object::new(
+            Sphere::new(Point::new(0.0, 0.0, 0.0), 10.0),
+            Phong::new()
+                .glow(Radiance::gray(emission))
+                .color(Albedo::gray(albedo)),
+            Matrix4::identity(),
+        ));
+
+        let gathered = (0..depth_limit)
+            .map(|d| albedo.powi(i32::from(d)))
+            .sum::<Float>();
+
+        Reference {
+            name: format!("enclosure, emission {}, albedo {}", emission, albedo),
+            scene,
+            camera: camera(Point::new(0.0, 0.0, 0.0), Point::new(0.0, 0.0, -1.0), 40.0),
+            expected: Radiance::gray(emission * gathered),
+            upper_bound: false,
+        }
+    }
+
+    /// Makes `expected` an upper bound only.
+    pub fn bound(self) -> Self {
+        let mut reference = self;
+        reference.upper_bound = true;
+        reference
+    }
+
+    /// Renders a small image of the scene with `renderer`, and
+    /// compares its mean to the expected radiance.
+    pub fn check<R>(&self, renderer: &R, context: &mut Context) -> Outcome
+    where
+        R: Renderer + ?Sized,
+    {
+        let resolution = Resolution::new(SIZE, SIZE);
+        let mut sum = Vector3::new(0.0, 0.0, 0.0);
+
+        for y in 0..SIZE {
+            for x in 0..SIZE {
+                let target = Target::new(x, y);
+                sum += Vector3::from(renderer.render(
+                    context,
+                    &self.scene,
+                    &self.camera,
+                    resolution,
+                    target,
+                ));
+            }
+        }
+        let mean = sum / (SIZE * SIZE) as Float;
+
+        Outcome {
+            name: self.name.clone(),
+            expected: self.expected,
+            measured: Radiance::new(mean.x, mean.y, mean.z),
+            upper_bound: self.upper_bound,
+        }
+    }
+}
+
+fn camera(eye: Point, center: Point, fov: Float) -> AffineTransformCamera {
+    let projection = PerspectiveFov {
+        fovy: Rad::from(Deg(fov)),
+        aspect: 1.0,
+        near: 1.0,
+        far: 100.0,
+    };
+    AffineTransformCamera::look_at(eye, center, Vector::new(0.0, 1.0, 0.0), projection)
+}
+
+/// The references for a renderer tracing paths of at most
+/// `depth_limit` vertices: furnaces of diffuse, glossy, and glass
+/// spheres, and an enclosure.  The glass sphere needs about eight
+/// vertices to pass.
+pub fn references(depth_limit: u8) -> Vec<Reference> {
+    // Without a second vertex, furnaces reflect nothing.
+    let reflected = |albedo: Albedo| {
+        if depth_limit >= 2 {
+            albedo
+        } else {
+            Albedo::black()
+        }
+    };
+    let orange = Albedo::new(0.8, 0.5, 0.2);
+
+    vec![
+        Reference::furnace(
+            "white diffuse",
+            Lambert::new(Albedo::white()),
+            reflected(Albedo::white()),
+        ),
+        Reference::furnace(
+            "gray diffuse",
+            Lambert::new(Albedo::gray(0.5)),
+            reflected(Albedo::gray(0.5)),
+        ),
+        Reference::furnace(
+            "orange Phong",
+            Phong::new().color(orange),
+            reflected(orange),
+        ),
+        Reference::furnace(
+            "glossy Phong",
+            Phong::new().highlight(Albedo::gray(0.5), 10.0),
+            reflected(Albedo::gray(0.5)),
+        )
+        .bound(),
+        Reference::furnace(
+            "glass",
+            Dielectric::new(Albedo::white(), Ior(1.5)),
+            Albedo::white(),
+        ),
+        Reference::enclosure(1.0, 0.5, depth_limit),
+    ]
+}
+
+/// The result of checking a renderer against a `Reference`.
+#[derive(Clone, Debug)]
+pub struct Outcome {
+    pub name: String,
+    pub expected: Radiance,
+    pub measured: Radiance,
+    pub upper_bound: bool,
+}
+
+impl Outcome {
+    /// The largest difference of the color channels, relative to the
+    /// expected radiance, or absolute where it is `0`.  Measurements
+    /// below an upper bound have no error.
+    pub fn error(&self) -> Float {
+        let expected = Vector3::from(self.expected);
+        let measured = Vector3::from(self.measured);
+
+        (0..3)
+            .map(|c| {
+                let difference = if self.upper_bound {
+                    (measured[c] - expected[c]).max(0.0)
+                } else {
+                    (measured[c] - expected[c]).abs()
+                };
+                if expected[c] > 0.0 {
+                    difference / expected[c]
+                } else {
+                    difference
+                }
+            })
+            .fold(0.0, Float::max)
+    }
+
+    /// Whether the error is at most `tolerance`.
+    pub fn passed(&self, tolerance: Float) -> bool {
+        self.error() <= tolerance
+    }
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let expected = Vector3::from(self.expected);
+        let measured = Vector3::from(self.measured);
+
+        write!(
+            f,
+            "{}: expected {}({:.3}, {:.3}, {:.3}), measured ({:.3}, {:.3}, {:.3}), error {:.1}%",
+            self.name,
+            if self.upper_bound { "at most " } else { "" },
+            expected.x,
+            expected.y,
+            expected.z,
+            measured.x,
+            measured.y,
+            measured.z,
+            100.0 * self.error()
+        )
+    }
+}
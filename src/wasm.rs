@@ -0,0 +1,102 @@
+//! Entry points for rendering in a web browser.
+//!
+//! Build the library as a WebAssembly module with
+//!
+//! ```text
+//! cargo rustc --lib --release --no-default-features \
+//!     --target wasm32-unknown-unknown --crate-type cdylib
+//! ```
+//!
+//! and render one of the built-in scenes into the module's memory,
+//! without any file I/O or threads:
+//!
+//! ```js
+//! const { instance } = await WebAssembly.instantiateStreaming(fetch("lucifer.wasm"));
+//! const { memory, lucifer_render, lucifer_free } = instance.exports;
+//!
+//! const pointer = lucifer_render(0, 256, 256, 16, 0);
+//! const pixels = new Uint8ClampedArray(memory.buffer, pointer, 256 * 256 * 4);
+//! canvas.getContext("2d").putImageData(new ImageData(pixels.slice(), 256, 256), 0, 0);
+//! lucifer_free(pointer, 256 * 256 * 4);
+//! ```
+
+use std::ptr;
+
+use cgmath::{Deg, PerspectiveFov, Rad};
+
+use camera::{AffineTransformCamera, Resolution, Tonemap};
+use geometry::Vector;
+use render::preview::Control;
+use render::{Context, PathTracer, Preview};
+use scene::Builtin;
+use Float;
+use {Error, Result};
+
+/// Renders the built-in scene at index `scene` of `Builtin::NAMES`
+/// with `samples` paths per pixel, and returns a pointer to its
+/// `width * height` RGBA pixels, or null if there is no such scene
+/// or the settings are invalid.  The pixels must be released with
+/// `lucifer_free`.
+#[no_mangle]
+pub extern "C" fn lucifer_render(
+    scene: u32,
+    width: u32,
+    height: u32,
+    samples: u32,
+    seed: u32,
+) -> *mut u8 {
+    match render(scene, width, height, samples, seed) {
+        Ok(pixels) => Box::into_raw(pixels.into_boxed_slice()) as *mut u8,
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+fn render(scene: u32, width: u32, height: u32, samples: u32, seed: u32) -> Result<Vec<u8>> {
+    let builtin = match Builtin::NAMES.get(scene as usize) {
+        Some(name) => Builtin::by_name(name).unwrap(),
+        None => {
+            return Err(Error::InvalidSettings(format!(
+                "no built-in scene {}",
+                scene
+            )))
+        }
+    };
+    let resolution = Resolution::try_new(width, height)?;
+
+    let projection = PerspectiveFov {
+        fovy: Rad::from(Deg(builtin.fov)),
+        aspect: width as Float / height as Float,
+        near: 1.0,
+        far: 100.0,
+    };
+    let camera = AffineTransformCamera::try_look_at(
+        builtin.position,
+        builtin.look_at,
+        Vector::new(0.0, 1.0, 0.0),
+        projection,
+    )?;
+    let renderer = PathTracer::try_new(0.01, 8, samples)?;
+
+    // A single pass of a `Preview` renders on the calling thread.
+    let mut preview = Preview::new(resolution, 16);
+    let mut context = Context::seeded(u64::from(seed), 0);
+    preview.refine(&renderer, &mut context, &builtin.scene, &camera, |_| {
+        Control::Continue
+    });
+
+    Ok(preview.film().to_rgba8(1.0, &Tonemap::Filmic))
+}
+
+/// Releases the `length` bytes of pixels returned by
+/// `lucifer_render`.
+///
+/// # Safety
+///
+/// `pixels` must have been returned by `lucifer_render`, and `length`
+/// must be the size of its image in bytes.
+#[no_mangle]
+pub unsafe extern "C" fn lucifer_free(pixels: *mut u8, length: usize) {
+    if !pixels.is_null() {
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(pixels, length)));
+    }
+}